@@ -0,0 +1,450 @@
+use crate::{Error, Result};
+use crate::error::context;
+use crate::fs::ops::OperationProgress;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+const PROGRESS_INTERVAL: u64 = 1024 * 1024;
+// Entries from this size on get ZIP64 headers. Deflate can grow incompressible
+// data slightly, so this stays a little below the 4 GiB limit of plain zip.
+const ZIP64_THRESHOLD: u64 = zip::ZIP64_BYTES_THR - 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    TarGz,
+    TarZst,
+}
+
+impl ArchiveFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Zip => "zip",
+            Self::TarGz => "tar.gz",
+            Self::TarZst => "tar.zst",
+        }
+    }
+}
+
+enum ArchiveEntry {
+    Directory { source: PathBuf, name: PathBuf },
+    File { source: PathBuf, name: PathBuf, size: u64, mode: u32 },
+    Symlink { source: PathBuf, name: PathBuf, target: PathBuf },
+}
+
+pub async fn create(
+    sources: Vec<PathBuf>,
+    dest: &Path,
+    format: ArchiveFormat,
+    progress: mpsc::Sender<OperationProgress>,
+    cancel: CancellationToken,
+) -> Result<()> {
+    if sources.is_empty() {
+        return Err(Error::InvalidOperation("No files to archive".to_string()));
+    }
+
+    if dest.exists() {
+        return Err(Error::AlreadyExists { path: dest.to_path_buf() });
+    }
+
+    let archive_path = dest.to_path_buf();
+    let task_cancel = cancel.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        create_blocking(&sources, &archive_path, format, &progress, &task_cancel)
+    })
+    .await
+    .map_err(|e| Error::Runtime(context("Archive task failed", e)))?;
+
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(dest);
+        if cancel.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        return Err(e);
+    }
+
+    Ok(())
+}
+
+fn create_blocking(
+    sources: &[PathBuf],
+    dest: &Path,
+    format: ArchiveFormat,
+    progress: &mpsc::Sender<OperationProgress>,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let base = common_parent(sources)?;
+    let mut entries = Vec::new();
+    for source in sources {
+        collect_entries(source, &base, &mut entries)?;
+    }
+
+    let mut tracker = ProgressTracker {
+        progress,
+        cancel,
+        current_bytes: 0,
+        last_reported: 0,
+        total_bytes: entries.iter().map(|e| match e {
+            ArchiveEntry::File { size, .. } => *size,
+            _ => 0,
+        }).sum(),
+        files_processed: 0,
+        total_files: entries.len(),
+    };
+
+    let file = File::create(dest)?;
+
+    match format {
+        ArchiveFormat::Zip => write_zip(file, &entries, &mut tracker),
+        ArchiveFormat::TarGz => {
+            let encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+            write_tar(encoder, &entries, &mut tracker)?.finish()?;
+            Ok(())
+        }
+        ArchiveFormat::TarZst => {
+            let encoder = zstd::stream::write::Encoder::new(file, 0)?;
+            write_tar(encoder, &entries, &mut tracker)?.finish()?;
+            Ok(())
+        }
+    }
+}
+
+fn write_zip(file: File, entries: &[ArchiveEntry], tracker: &mut ProgressTracker<'_>) -> Result<()> {
+    use zip::write::SimpleFileOptions;
+
+    let mut writer = zip::ZipWriter::new(file);
+    let options = SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+
+    for entry in entries {
+        tracker.check_cancelled()?;
+
+        match entry {
+            ArchiveEntry::Directory { name, .. } => {
+                writer.add_directory(archive_name(name), options)
+                    .map_err(|e| Error::Archive(e.to_string()))?;
+            }
+            ArchiveEntry::File { source, name, size, mode } => {
+                writer.start_file(archive_name(name), zip_file_options(options, *size, *mode))
+                    .map_err(|e| Error::Archive(e.to_string()))?;
+                let mut reader = tracker.reader(source)?;
+                io::copy(&mut reader, &mut writer).map_err(|e| tracker.map_io_error(e))?;
+            }
+            ArchiveEntry::Symlink { name, target, .. } => {
+                writer.add_symlink(archive_name(name), target.to_string_lossy(), options)
+                    .map_err(|e| Error::Archive(e.to_string()))?;
+            }
+        }
+
+        tracker.file_done(entry)?;
+    }
+
+    writer.finish().map_err(|e| Error::Archive(e.to_string()))?;
+    Ok(())
+}
+
+fn zip_file_options(
+    options: zip::write::SimpleFileOptions,
+    size: u64,
+    mode: u32,
+) -> zip::write::SimpleFileOptions {
+    options.unix_permissions(mode).large_file(size >= ZIP64_THRESHOLD)
+}
+
+fn write_tar<W: Write>(writer: W, entries: &[ArchiveEntry], tracker: &mut ProgressTracker<'_>) -> Result<W> {
+    let mut builder = tar::Builder::new(writer);
+    builder.follow_symlinks(false);
+
+    for entry in entries {
+        tracker.check_cancelled()?;
+
+        match entry {
+            ArchiveEntry::Directory { source, name } | ArchiveEntry::Symlink { source, name, .. } => {
+                builder.append_path_with_name(source, name)?;
+            }
+            ArchiveEntry::File { source, name, .. } => {
+                let metadata = std::fs::metadata(source)?;
+                let mut header = tar::Header::new_gnu();
+                header.set_metadata(&metadata);
+
+                let reader = tracker.reader(source)?;
+                builder.append_data(&mut header, name, reader)
+                    .map_err(|e| tracker.map_io_error(e))?;
+            }
+        }
+
+        tracker.file_done(entry)?;
+    }
+
+    Ok(builder.into_inner()?)
+}
+
+fn archive_name(name: &Path) -> String {
+    name.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn common_parent(sources: &[PathBuf]) -> Result<PathBuf> {
+    let mut common: Option<PathBuf> = None;
+
+    for source in sources {
+        let parent = source.parent()
+            .ok_or_else(|| Error::InvalidPath { path: source.clone() })?;
+
+        common = Some(match common {
+            None => parent.to_path_buf(),
+            Some(current) => current
+                .components()
+                .zip(parent.components())
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect(),
+        });
+    }
+
+    common.ok_or_else(|| Error::InvalidOperation("No files to archive".to_string()))
+}
+
+fn collect_entries(path: &Path, base: &Path, entries: &mut Vec<ArchiveEntry>) -> Result<()> {
+    let metadata = std::fs::symlink_metadata(path)?;
+    let name = path.strip_prefix(base)
+        .map_err(|_| Error::InvalidPath { path: path.to_path_buf() })?
+        .to_path_buf();
+
+    if metadata.is_symlink() {
+        entries.push(ArchiveEntry::Symlink {
+            source: path.to_path_buf(),
+            name,
+            target: std::fs::read_link(path)?,
+        });
+    } else if metadata.is_dir() {
+        entries.push(ArchiveEntry::Directory {
+            source: path.to_path_buf(),
+            name,
+        });
+
+        for entry in std::fs::read_dir(path)? {
+            collect_entries(&entry?.path(), base, entries)?;
+        }
+    } else {
+        entries.push(ArchiveEntry::File {
+            source: path.to_path_buf(),
+            name,
+            size: metadata.len(),
+            mode: file_mode(&metadata),
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn file_mode(metadata: &std::fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+fn file_mode(_metadata: &std::fs::Metadata) -> u32 {
+    0o644
+}
+
+struct ProgressTracker<'a> {
+    progress: &'a mpsc::Sender<OperationProgress>,
+    cancel: &'a CancellationToken,
+    current_bytes: u64,
+    last_reported: u64,
+    total_bytes: u64,
+    files_processed: usize,
+    total_files: usize,
+}
+
+impl<'a> ProgressTracker<'a> {
+    fn check_cancelled(&self) -> Result<()> {
+        if self.cancel.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        Ok(())
+    }
+
+    fn reader<'t>(&'t mut self, path: &'t Path) -> Result<ProgressReader<'t, 'a>> {
+        Ok(ProgressReader {
+            file: File::open(path)?,
+            path,
+            tracker: self,
+        })
+    }
+
+    fn advance(&mut self, path: &Path, bytes: u64) -> Result<()> {
+        self.check_cancelled()?;
+        self.current_bytes += bytes;
+
+        if self.current_bytes - self.last_reported >= PROGRESS_INTERVAL {
+            self.report(path)?;
+        }
+
+        Ok(())
+    }
+
+    fn file_done(&mut self, entry: &ArchiveEntry) -> Result<()> {
+        self.files_processed += 1;
+
+        let path = match entry {
+            ArchiveEntry::Directory { source, .. }
+            | ArchiveEntry::File { source, .. }
+            | ArchiveEntry::Symlink { source, .. } => source,
+        };
+        self.report(path)
+    }
+
+    fn report(&mut self, path: &Path) -> Result<()> {
+        self.last_reported = self.current_bytes;
+
+        self.progress.blocking_send(OperationProgress {
+            current_bytes: self.current_bytes,
+            total_bytes: self.total_bytes,
+            current_file: path.to_path_buf(),
+            files_processed: self.files_processed,
+            total_files: self.total_files,
+            up_to_date: false,
+        }).map_err(|_| Error::Cancelled)
+    }
+
+    fn map_io_error(&self, err: io::Error) -> Error {
+        if self.cancel.is_cancelled() {
+            Error::Cancelled
+        } else {
+            Error::Io(err)
+        }
+    }
+}
+
+struct ProgressReader<'t, 'a> {
+    file: File,
+    path: &'t Path,
+    tracker: &'t mut ProgressTracker<'a>,
+}
+
+impl Read for ProgressReader<'_, '_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.file.read(buf)?;
+        self.tracker
+            .advance(self.path, n as u64)
+            .map_err(|e| io::Error::other(e.to_string()))?;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_fixture(root: &Path) -> PathBuf {
+        let dir = root.join("project");
+        std::fs::create_dir_all(dir.join("src/nested")).unwrap();
+        std::fs::create_dir_all(dir.join("empty")).unwrap();
+        std::fs::write(dir.join("README.md"), b"# Project\n").unwrap();
+        std::fs::write(dir.join("src/main.rs"), b"fn main() {}\n").unwrap();
+        std::fs::write(dir.join("src/nested/data.bin"), (0..=255u8).cycle().take(300_000).collect::<Vec<_>>()).unwrap();
+        dir
+    }
+
+    fn assert_same_tree(expected: &Path, actual: &Path) {
+        let mut expected_entries = Vec::new();
+        collect_entries(expected, expected.parent().unwrap(), &mut expected_entries).unwrap();
+
+        for entry in expected_entries {
+            match entry {
+                ArchiveEntry::Directory { name, .. } => {
+                    assert!(actual.join(&name).is_dir(), "missing directory {:?}", name);
+                }
+                ArchiveEntry::File { source, name, .. } => {
+                    let original = std::fs::read(&source).unwrap();
+                    let extracted = std::fs::read(actual.join(&name)).unwrap();
+                    assert_eq!(original, extracted, "content mismatch for {:?}", name);
+                }
+                ArchiveEntry::Symlink { .. } => {}
+            }
+        }
+    }
+
+    async fn create_archive(sources: Vec<PathBuf>, dest: &Path, format: ArchiveFormat) -> Result<()> {
+        let (tx, mut rx) = mpsc::channel(64);
+        let drain = tokio::spawn(async move { while rx.recv().await.is_some() {} });
+        let result = create(sources, dest, format, tx, CancellationToken::new()).await;
+        drain.await.unwrap();
+        result
+    }
+
+    #[tokio::test]
+    async fn test_zip_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = create_fixture(temp_dir.path());
+        let archive = temp_dir.path().join("project.zip");
+
+        create_archive(vec![source.clone()], &archive, ArchiveFormat::Zip).await.unwrap();
+
+        let out = temp_dir.path().join("out");
+        zip::ZipArchive::new(File::open(&archive).unwrap()).unwrap().extract(&out).unwrap();
+        assert_same_tree(&source, &out);
+    }
+
+    #[test]
+    fn test_zip_large_files_use_zip64() {
+        let options = zip::write::SimpleFileOptions::default();
+        let four_gib = 4 * 1024 * 1024 * 1024;
+
+        assert_eq!(zip_file_options(options, four_gib, 0o644), options.unix_permissions(0o644).large_file(true));
+        assert_eq!(zip_file_options(options, four_gib + 1, 0o644), options.unix_permissions(0o644).large_file(true));
+        assert_eq!(zip_file_options(options, 1024, 0o600), options.unix_permissions(0o600));
+    }
+
+    #[tokio::test]
+    async fn test_tar_gz_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = create_fixture(temp_dir.path());
+        let archive = temp_dir.path().join("project.tar.gz");
+
+        create_archive(vec![source.clone()], &archive, ArchiveFormat::TarGz).await.unwrap();
+
+        let out = temp_dir.path().join("out");
+        let decoder = flate2::read::GzDecoder::new(File::open(&archive).unwrap());
+        tar::Archive::new(decoder).unpack(&out).unwrap();
+        assert_same_tree(&source, &out);
+    }
+
+    #[tokio::test]
+    async fn test_tar_zst_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = create_fixture(temp_dir.path());
+        let archive = temp_dir.path().join("project.tar.zst");
+
+        create_archive(vec![source.clone()], &archive, ArchiveFormat::TarZst).await.unwrap();
+
+        let out = temp_dir.path().join("out");
+        let decoder = zstd::stream::read::Decoder::new(File::open(&archive).unwrap()).unwrap();
+        tar::Archive::new(decoder).unpack(&out).unwrap();
+        assert_same_tree(&source, &out);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_archive_is_removed() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = create_fixture(temp_dir.path());
+        let archive = temp_dir.path().join("project.zip");
+
+        let (tx, _rx) = mpsc::channel(64);
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+
+        let result = create(vec![source], &archive, ArchiveFormat::Zip, tx, cancel).await;
+        assert!(matches!(result, Err(Error::Cancelled)));
+        assert!(!archive.exists());
+    }
+}
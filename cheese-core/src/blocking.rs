@@ -0,0 +1,116 @@
+use crate::{Error, Result};
+use crate::error::context;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+
+pub const DEFAULT_BLOCKING_THREADS: usize = 8;
+
+/// Threads for synchronous filesystem work, kept apart from the async runtime
+/// so a deep directory walk cannot starve scans, watchers or the UI bridge.
+///
+/// Belongs here: recursive size computation, bulk metadata collection and
+/// other loops over `std::fs` calls. Stays on the core runtime: async scans,
+/// file copies (already chunked and cancellable) and short one-off
+/// `spawn_blocking` calls such as a single reflink or plugin call.
+#[derive(Clone)]
+pub struct BlockingPool {
+    inner: Arc<PoolRuntime>,
+}
+
+struct PoolRuntime(Option<Runtime>);
+
+impl Drop for PoolRuntime {
+    // The last clone may be dropped from inside an async task, where a
+    // blocking runtime shutdown would panic.
+    fn drop(&mut self) {
+        if let Some(runtime) = self.0.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+impl BlockingPool {
+    pub fn new(threads: usize) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .max_blocking_threads(threads.max(1))
+            .thread_name("cheese-blocking")
+            .build()?;
+
+        Ok(Self {
+            inner: Arc::new(PoolRuntime(Some(runtime))),
+        })
+    }
+
+    /// Starts `f` on the pool right away. The returned future resolves to its
+    /// result and can be awaited from any runtime.
+    pub fn spawn<F, R>(&self, f: F) -> impl Future<Output = Result<R>> + Send + 'static
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        spawn_on(Some(self), f)
+    }
+
+    fn runtime(&self) -> &Runtime {
+        self.inner.0.as_ref().expect("pool runtime is only taken on drop")
+    }
+}
+
+/// Like [`BlockingPool::spawn`], falling back to the current runtime's blocking
+/// threads for components constructed without a pool.
+pub fn spawn_on<F, R>(pool: Option<&BlockingPool>, f: F) -> impl Future<Output = Result<R>> + Send + 'static
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    let handle = match pool {
+        Some(pool) => pool.runtime().spawn_blocking(f),
+        None => tokio::task::spawn_blocking(f),
+    };
+    async move {
+        handle.await.map_err(|e| Error::Runtime(context("Blocking task failed", e)))
+    }
+}
+
+impl Default for BlockingPool {
+    fn default() -> Self {
+        Self::new(DEFAULT_BLOCKING_THREADS).expect("Failed to create blocking pool")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[tokio::test]
+    async fn test_heavy_task_does_not_stall_timer() {
+        let pool = BlockingPool::new(2).unwrap();
+
+        let heavy = pool.spawn(|| {
+            std::thread::sleep(Duration::from_millis(500));
+            42
+        });
+        let timer = async {
+            let start = Instant::now();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            start.elapsed()
+        };
+
+        let (result, elapsed) = tokio::join!(heavy, timer);
+        assert_eq!(result.unwrap(), 42);
+        assert!(elapsed < Duration::from_millis(250), "timer stalled for {:?}", elapsed);
+    }
+
+    #[tokio::test]
+    async fn test_panicking_task_reports_error() {
+        let pool = BlockingPool::new(1).unwrap();
+        let result = pool.spawn(|| panic!("boom")).await;
+        assert!(matches!(result, Err(Error::Runtime(_))));
+
+        // The pool stays usable after a task panics.
+        assert_eq!(pool.spawn(|| 1 + 1).await.unwrap(), 2);
+    }
+}
@@ -0,0 +1,313 @@
+use crate::{Error, Result};
+use crate::error::context;
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::os::unix::ffi::OsStringExt;
+use std::path::{Path, PathBuf};
+use xdg::BaseDirectories;
+
+const BOOKMARKS_FILE: &str = "bookmarks.toml";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub path: PathBuf,
+    pub label: Option<String>,
+}
+
+impl Bookmark {
+    pub fn display_name(&self) -> String {
+        if let Some(label) = &self.label {
+            return label.clone();
+        }
+
+        self.path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.path.display().to_string())
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct BookmarksFile {
+    #[serde(default)]
+    bookmarks: Vec<Bookmark>,
+}
+
+pub struct Bookmarks {
+    items: Vec<Bookmark>,
+    file_path: PathBuf,
+    hide_missing: bool,
+}
+
+impl Bookmarks {
+    pub fn load() -> Result<Self> {
+        let xdg_dirs = BaseDirectories::with_prefix("cheese")
+            .map_err(|e| Error::Config(context("Failed to get XDG directories", e)))?;
+
+        let file_path = xdg_dirs.get_config_home().join(BOOKMARKS_FILE);
+
+        if file_path.exists() {
+            return Self::load_from(file_path);
+        }
+
+        let mut bookmarks = Self::empty(file_path);
+        if let Some(gtk_path) = gtk_bookmarks_path() {
+            if gtk_path.exists() {
+                let contents = std::fs::read_to_string(&gtk_path)?;
+                bookmarks.items = parse_gtk_bookmarks(&contents);
+                tracing::info!(
+                    "Imported {} bookmarks from {}",
+                    bookmarks.items.len(),
+                    gtk_path.display()
+                );
+            }
+        }
+
+        bookmarks.save()?;
+        Ok(bookmarks)
+    }
+
+    pub fn load_from(file_path: PathBuf) -> Result<Self> {
+        if !file_path.exists() {
+            return Ok(Self::empty(file_path));
+        }
+
+        let contents = std::fs::read_to_string(&file_path)?;
+        let file: BookmarksFile = toml::from_str(&contents)?;
+
+        Ok(Self {
+            items: file.bookmarks,
+            file_path,
+            hide_missing: true,
+        })
+    }
+
+    fn empty(file_path: PathBuf) -> Self {
+        Self {
+            items: Vec::new(),
+            file_path,
+            hide_missing: true,
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.file_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let file = BookmarksFile {
+            bookmarks: self.items.clone(),
+        };
+        let toml_str = toml::to_string_pretty(&file)
+            .map_err(|e| Error::Config(context("Failed to serialize bookmarks", e)))?;
+
+        std::fs::write(&self.file_path, toml_str)?;
+        Ok(())
+    }
+
+    pub fn add(&mut self, path: PathBuf, label: Option<String>) -> Result<()> {
+        if self.contains(&path) {
+            return Err(Error::AlreadyExists { path });
+        }
+
+        self.items.push(Bookmark { path, label });
+        self.save()
+    }
+
+    pub fn remove(&mut self, path: &Path) -> Result<()> {
+        let index = self.position(path)
+            .ok_or_else(|| Error::NotFound { path: path.to_path_buf() })?;
+
+        self.items.remove(index);
+        self.save()
+    }
+
+    pub fn rename(&mut self, path: &Path, label: Option<String>) -> Result<()> {
+        let index = self.position(path)
+            .ok_or_else(|| Error::NotFound { path: path.to_path_buf() })?;
+
+        self.items[index].label = label;
+        self.save()
+    }
+
+    pub fn reorder(&mut self, from: usize, to: usize) -> Result<()> {
+        if from >= self.items.len() || to >= self.items.len() {
+            return Err(Error::InvalidOperation(format!(
+                "Bookmark index out of range: {} -> {}",
+                from, to
+            )));
+        }
+
+        let bookmark = self.items.remove(from);
+        self.items.insert(to, bookmark);
+        self.save()
+    }
+
+    pub fn list(&self) -> Vec<Bookmark> {
+        self.items
+            .iter()
+            .filter(|b| !self.hide_missing || b.path.exists())
+            .cloned()
+            .collect()
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.position(path).is_some()
+    }
+
+    pub fn set_hide_missing(&mut self, hide_missing: bool) {
+        self.hide_missing = hide_missing;
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    fn position(&self, path: &Path) -> Option<usize> {
+        self.items.iter().position(|b| b.path == path)
+    }
+}
+
+fn gtk_bookmarks_path() -> Option<PathBuf> {
+    let xdg_dirs = BaseDirectories::new().ok()?;
+    Some(xdg_dirs.get_config_home().join("gtk-3.0").join("bookmarks"))
+}
+
+fn parse_gtk_bookmarks(contents: &str) -> Vec<Bookmark> {
+    let mut bookmarks = Vec::new();
+
+    for line in contents.lines() {
+        let line = line.trim();
+        let (uri, label) = match line.split_once(' ') {
+            Some((uri, label)) => (uri, Some(label.trim().to_string())),
+            None => (line, None),
+        };
+
+        let Some(encoded) = uri.strip_prefix("file://") else {
+            continue;
+        };
+
+        let path = percent_decode(encoded);
+        if bookmarks.iter().any(|b: &Bookmark| b.path == path) {
+            continue;
+        }
+
+        bookmarks.push(Bookmark {
+            path,
+            label: label.filter(|l| !l.is_empty()),
+        });
+    }
+
+    bookmarks
+}
+
+// Decodes to raw bytes, since the escapes can spell a path that is not UTF-8.
+fn percent_decode(input: &str) -> PathBuf {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 3 <= bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+            if let Some(byte) = hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    PathBuf::from(OsString::from_vec(decoded))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_bookmarks_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join(BOOKMARKS_FILE);
+
+        let mut bookmarks = Bookmarks::load_from(file_path.clone()).unwrap();
+        bookmarks.add(temp_dir.path().to_path_buf(), Some("Temp".to_string())).unwrap();
+        bookmarks.add(PathBuf::from("/tmp"), None).unwrap();
+
+        let reloaded = Bookmarks::load_from(file_path).unwrap();
+        assert_eq!(reloaded.len(), 2);
+        assert_eq!(reloaded.items[0].label.as_deref(), Some("Temp"));
+        assert_eq!(reloaded.items[1].path, PathBuf::from("/tmp"));
+        assert!(reloaded.items[1].label.is_none());
+    }
+
+    #[test]
+    fn test_bookmarks_add_remove_reorder() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut bookmarks = Bookmarks::load_from(temp_dir.path().join(BOOKMARKS_FILE)).unwrap();
+
+        bookmarks.add(PathBuf::from("/a"), None).unwrap();
+        bookmarks.add(PathBuf::from("/b"), None).unwrap();
+        bookmarks.add(PathBuf::from("/c"), None).unwrap();
+        assert!(bookmarks.add(PathBuf::from("/a"), None).is_err());
+
+        bookmarks.reorder(2, 0).unwrap();
+        let order: Vec<_> = bookmarks.items.iter().map(|b| b.path.clone()).collect();
+        assert_eq!(order, vec![PathBuf::from("/c"), PathBuf::from("/a"), PathBuf::from("/b")]);
+        assert!(bookmarks.reorder(0, 3).is_err());
+
+        bookmarks.remove(Path::new("/a")).unwrap();
+        assert_eq!(bookmarks.len(), 2);
+        assert!(bookmarks.remove(Path::new("/a")).is_err());
+    }
+
+    #[test]
+    fn test_bookmarks_hide_missing() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut bookmarks = Bookmarks::load_from(temp_dir.path().join(BOOKMARKS_FILE)).unwrap();
+
+        bookmarks.add(temp_dir.path().to_path_buf(), None).unwrap();
+        bookmarks.add(temp_dir.path().join("missing"), None).unwrap();
+        assert_eq!(bookmarks.list().len(), 1);
+
+        bookmarks.set_hide_missing(false);
+        assert_eq!(bookmarks.list().len(), 2);
+    }
+
+    #[test]
+    fn test_parse_gtk_bookmarks() {
+        let contents = "file:///home/user/My%20Documents Docs\nfile:///tmp\nsftp://host/path Remote\n";
+        let bookmarks = parse_gtk_bookmarks(contents);
+
+        assert_eq!(bookmarks.len(), 2);
+        assert_eq!(bookmarks[0].path, PathBuf::from("/home/user/My Documents"));
+        assert_eq!(bookmarks[0].label.as_deref(), Some("Docs"));
+        assert_eq!(bookmarks[1].path, PathBuf::from("/tmp"));
+        assert!(bookmarks[1].label.is_none());
+    }
+
+    #[test]
+    fn test_parse_gtk_bookmarks_trailing_escape() {
+        let bookmarks = parse_gtk_bookmarks("file:///srv/music%20\nfile:///srv/odd%2\n");
+
+        assert_eq!(bookmarks[0].path, PathBuf::from("/srv/music "));
+        assert_eq!(bookmarks[1].path, PathBuf::from("/srv/odd%2"));
+    }
+
+    #[test]
+    fn test_parse_gtk_bookmarks_non_utf8_path() {
+        let bookmarks = parse_gtk_bookmarks("file:///home/user/caf%E9 Cafe\n");
+
+        assert_eq!(bookmarks.len(), 1);
+        assert_eq!(bookmarks[0].path, PathBuf::from(OsString::from_vec(b"/home/user/caf\xe9".to_vec())));
+        assert_eq!(bookmarks[0].label.as_deref(), Some("Cafe"));
+    }
+}
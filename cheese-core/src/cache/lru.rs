@@ -1 +1,175 @@
+use parking_lot::Mutex;
+use std::hash::Hash;
+use std::num::NonZeroUsize;
 
+pub type EvictCallback<K, V> = Box<dyn Fn(&K, &V) + Send + Sync>;
+
+/// Thread-safe LRU map with an optional hook invoked when an entry is pushed
+/// out by capacity. Explicit `remove`/`clear` calls do not trigger it.
+pub struct LruCache<K, V> {
+    inner: Mutex<::lru::LruCache<K, V>>,
+    on_evict: Option<EvictCallback<K, V>>,
+}
+
+impl<K: Hash + Eq, V: Clone> LruCache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(capacity.max(1)).expect("capacity is non-zero");
+        Self {
+            inner: Mutex::new(::lru::LruCache::new(capacity)),
+            on_evict: None,
+        }
+    }
+
+    pub fn with_on_evict(mut self, on_evict: impl Fn(&K, &V) + Send + Sync + 'static) -> Self {
+        self.on_evict = Some(Box::new(on_evict));
+        self
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.inner.lock().get(key).cloned()
+    }
+
+    pub fn insert(&self, key: K, value: V) {
+        let evicted = {
+            let mut inner = self.inner.lock();
+            let replacing = inner.contains(&key);
+            let pushed_out = inner.push(key, value);
+            if replacing { None } else { pushed_out }
+        };
+
+        // Run the callback outside the lock so it may touch the cache itself.
+        if let (Some(on_evict), Some((key, value))) = (&self.on_evict, evicted) {
+            on_evict(&key, &value);
+        }
+    }
+
+    /// Changes the capacity. When shrinking below the current length the
+    /// least recently used entries are pushed out through `on_evict`.
+    pub fn resize(&self, capacity: usize) {
+        let capacity = NonZeroUsize::new(capacity.max(1)).expect("capacity is non-zero");
+        let evicted = {
+            let mut inner = self.inner.lock();
+            let mut evicted = Vec::new();
+            while inner.len() > capacity.get() {
+                match inner.pop_lru() {
+                    Some(entry) => evicted.push(entry),
+                    None => break,
+                }
+            }
+            inner.resize(capacity);
+            evicted
+        };
+
+        if let Some(on_evict) = &self.on_evict {
+            for (key, value) in &evicted {
+                on_evict(key, value);
+            }
+        }
+    }
+
+    pub fn remove(&self, key: &K) -> Option<V> {
+        self.inner.lock().pop(key)
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.inner.lock().contains(key)
+    }
+
+    pub fn clear(&self) {
+        self.inner.lock().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.lock().is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.inner.lock().cap().get()
+    }
+
+    /// Sums `weight` over every entry without touching their recency.
+    pub fn sum_by(&self, weight: impl Fn(&K, &V) -> usize) -> usize {
+        self.inner.lock().iter().map(|(key, value)| weight(key, value)).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    type EvictionLog = Arc<Mutex<Vec<(String, u32)>>>;
+
+    fn recording_cache(capacity: usize) -> (LruCache<String, u32>, EvictionLog) {
+        let evicted = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&evicted);
+        let cache = LruCache::new(capacity)
+            .with_on_evict(move |key: &String, value: &u32| log.lock().push((key.clone(), *value)));
+        (cache, evicted)
+    }
+
+    #[test]
+    fn test_on_evict_fires_on_overflow() {
+        let (cache, evicted) = recording_cache(2);
+
+        cache.insert("a".to_string(), 1);
+        cache.insert("b".to_string(), 2);
+        assert!(evicted.lock().is_empty());
+
+        cache.get(&"a".to_string());
+        cache.insert("c".to_string(), 3);
+
+        assert_eq!(*evicted.lock(), vec![("b".to_string(), 2)]);
+        assert_eq!(cache.len(), 2);
+        assert!(cache.contains(&"a".to_string()));
+        assert!(cache.contains(&"c".to_string()));
+    }
+
+    #[test]
+    fn test_resize_shrink_evicts_least_recent() {
+        let (cache, evicted) = recording_cache(4);
+        for (i, key) in ["a", "b", "c", "d"].iter().enumerate() {
+            cache.insert(key.to_string(), i as u32);
+        }
+        cache.get(&"a".to_string());
+
+        cache.resize(2);
+
+        assert_eq!(cache.capacity(), 2);
+        assert_eq!(*evicted.lock(), vec![("b".to_string(), 1), ("c".to_string(), 2)]);
+        assert!(cache.contains(&"a".to_string()));
+        assert!(cache.contains(&"d".to_string()));
+    }
+
+    #[test]
+    fn test_resize_grow_keeps_entries() {
+        let (cache, evicted) = recording_cache(2);
+        cache.insert("a".to_string(), 1);
+        cache.insert("b".to_string(), 2);
+
+        cache.resize(3);
+        cache.insert("c".to_string(), 3);
+
+        assert_eq!(cache.capacity(), 3);
+        assert_eq!(cache.len(), 3);
+        assert!(evicted.lock().is_empty());
+    }
+
+    #[test]
+    fn test_on_evict_skips_replace_and_remove() {
+        let (cache, evicted) = recording_cache(2);
+
+        cache.insert("a".to_string(), 1);
+        cache.insert("a".to_string(), 10);
+        cache.remove(&"a".to_string());
+        cache.insert("b".to_string(), 2);
+        cache.clear();
+
+        assert!(evicted.lock().is_empty());
+        assert!(cache.is_empty());
+    }
+}
@@ -3,17 +3,22 @@ pub mod thumbnail;
 
 use crate::{Error, Result};
 use crate::fs::DirEntry;
+use crate::fs::watcher::WatchEvent;
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use parking_lot::RwLock;
-use lru::LruCache;
+use ::lru::LruCache;
 use std::num::NonZeroUsize;
+use std::time::Instant;
+use thumbnail::ThumbnailCache;
 
 const DEFAULT_CACHE_SIZE: usize = 10000;
+pub const DEFAULT_LISTING_CACHE_DIRS: usize = 32;
 
 #[derive(Clone)]
 pub struct MetadataCache {
-    cache: Arc<RwLock<LruCache<u64, CachedMetadata>>>,
+    state: Arc<RwLock<CacheState>>,
 }
 
 #[derive(Debug, Clone)]
@@ -22,32 +27,83 @@ pub struct CachedMetadata {
     pub cached_at: std::time::Instant,
 }
 
+struct CacheState {
+    entries: LruCache<u64, CachedMetadata>,
+    // Ordered by path, so a directory's descendants form one contiguous range
+    // starting at the directory itself.
+    by_parent: BTreeMap<PathBuf, Vec<u64>>,
+}
+
+impl CacheState {
+    fn put(&mut self, inode: u64, cached: CachedMetadata) {
+        let parent = parent_of(&cached.entry.path);
+
+        if let Some((old_inode, old)) = self.entries.push(inode, cached) {
+            self.unindex(old_inode, &old.entry.path);
+        }
+
+        self.by_parent.entry(parent).or_default().push(inode);
+    }
+
+    fn pop(&mut self, inode: u64) -> Option<CachedMetadata> {
+        let cached = self.entries.pop(&inode)?;
+        self.unindex(inode, &cached.entry.path);
+        Some(cached)
+    }
+
+    fn unindex(&mut self, inode: u64, path: &Path) {
+        let parent = parent_of(path);
+        if let Some(inodes) = self.by_parent.get_mut(&parent) {
+            inodes.retain(|i| *i != inode);
+            if inodes.is_empty() {
+                self.by_parent.remove(&parent);
+            }
+        }
+    }
+}
+
 impl MetadataCache {
     pub fn new(capacity_mb: usize) -> Self {
-        let size = (capacity_mb * 1024 * 1024) / std::mem::size_of::<CachedMetadata>();
-        let capacity = NonZeroUsize::new(size.max(DEFAULT_CACHE_SIZE)).unwrap();
-        
         Self {
-            cache: Arc::new(RwLock::new(LruCache::new(capacity))),
+            state: Arc::new(RwLock::new(CacheState {
+                entries: LruCache::new(entry_capacity(capacity_mb)),
+                by_parent: BTreeMap::new(),
+            })),
+        }
+    }
+
+    /// Applies a new `cache_size_mb` in place. Entries are kept unless the
+    /// cache shrinks below its length, in which case the least recently used
+    /// are dropped.
+    pub fn resize(&self, capacity_mb: usize) {
+        let capacity = entry_capacity(capacity_mb);
+        let mut state = self.state.write();
+
+        while state.entries.len() > capacity.get() {
+            let Some((inode, old)) = state.entries.pop_lru() else {
+                break;
+            };
+            state.unindex(inode, &old.entry.path);
         }
+        state.entries.resize(capacity);
     }
 
     pub fn get(&self, inode: u64) -> Option<DirEntry> {
-        let mut cache = self.cache.write();
-        cache.get(&inode).map(|cached| cached.entry.clone())
+        let mut state = self.state.write();
+        state.entries.get(&inode).map(|cached| cached.entry.clone())
     }
 
     pub fn insert(&self, inode: u64, entry: DirEntry) {
-        let mut cache = self.cache.write();
-        cache.put(inode, CachedMetadata {
+        let mut state = self.state.write();
+        state.put(inode, CachedMetadata {
             entry,
             cached_at: std::time::Instant::now(),
         });
     }
 
     pub fn remove(&self, inode: u64) {
-        let mut cache = self.cache.write();
-        cache.pop(&inode);
+        let mut state = self.state.write();
+        state.pop(inode);
     }
 
     pub fn get_or_fetch(&self, path: &Path) -> Result<DirEntry> {
@@ -76,30 +132,63 @@ impl MetadataCache {
         let mut to_remove = Vec::new();
         
         {
-            let cache = self.cache.read();
-            for (inode, cached) in cache.iter() {
+            let state = self.state.read();
+            for (inode, cached) in state.entries.iter() {
                 if cached.entry.path.starts_with(dir) {
                     to_remove.push(*inode);
                 }
             }
         }
 
-        let mut cache = self.cache.write();
+        let mut state = self.state.write();
         for inode in to_remove {
-            cache.pop(&inode);
+            state.pop(inode);
         }
 
         Ok(())
     }
 
+    pub fn invalidate_recursive(&self, root: &Path) -> usize {
+        let mut state = self.state.write();
+
+        let dirs: Vec<PathBuf> = state.by_parent
+            .range(root.to_path_buf()..)
+            .map(|(dir, _)| dir)
+            .take_while(|dir| dir.starts_with(root))
+            .cloned()
+            .collect();
+
+        let mut evicted = 0;
+        for dir in dirs {
+            for inode in state.by_parent.remove(&dir).unwrap_or_default() {
+                if state.entries.pop(&inode).is_some() {
+                    evicted += 1;
+                }
+            }
+        }
+
+        let root_inode = state.by_parent.get(&parent_of(root)).and_then(|inodes| {
+            inodes.iter().copied().find(|inode| {
+                state.entries.peek(inode).is_some_and(|cached| cached.entry.path == root)
+            })
+        });
+        if let Some(inode) = root_inode {
+            state.pop(inode);
+            evicted += 1;
+        }
+
+        evicted
+    }
+
     pub fn clear(&self) {
-        let mut cache = self.cache.write();
-        cache.clear();
+        let mut state = self.state.write();
+        state.entries.clear();
+        state.by_parent.clear();
     }
 
     pub fn len(&self) -> usize {
-        let cache = self.cache.read();
-        cache.len()
+        let state = self.state.read();
+        state.entries.len()
     }
 
     pub fn is_empty(&self) -> bool {
@@ -107,8 +196,8 @@ impl MetadataCache {
     }
 
     pub fn capacity(&self) -> usize {
-        let cache = self.cache.read();
-        cache.cap().get()
+        let state = self.state.read();
+        state.entries.cap().get()
     }
 }
 
@@ -118,6 +207,197 @@ impl Default for MetadataCache {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct CachedListing {
+    pub entries: Vec<DirEntry>,
+    pub scanned_at: Instant,
+    pub entry_count_at_scan: usize,
+}
+
+// Whole directory listings keyed by directory path, so revisiting a directory
+// does not need a rescan until a watch event touches it.
+#[derive(Clone)]
+pub struct DirectoryListingCache {
+    cache: Arc<RwLock<LruCache<PathBuf, CachedListing>>>,
+}
+
+impl DirectoryListingCache {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(capacity.max(1)).unwrap();
+        Self {
+            cache: Arc::new(RwLock::new(LruCache::new(capacity))),
+        }
+    }
+
+    pub fn get(&self, path: &Path) -> Option<Vec<DirEntry>> {
+        let mut cache = self.cache.write();
+        cache.get(path).map(|listing| listing.entries.clone())
+    }
+
+    pub fn insert(&self, path: &Path, entries: Vec<DirEntry>) {
+        let mut cache = self.cache.write();
+        cache.put(path.to_path_buf(), CachedListing {
+            entry_count_at_scan: entries.len(),
+            entries,
+            scanned_at: Instant::now(),
+        });
+    }
+
+    pub fn invalidate(&self, path: &Path) {
+        let mut cache = self.cache.write();
+        cache.pop(path);
+    }
+
+    pub fn invalidate_on_event(&self, event: &WatchEvent) {
+        match event {
+            WatchEvent::Created(path) | WatchEvent::Modified(path) | WatchEvent::AttributesChanged(path) => {
+                self.invalidate(&parent_of(path));
+                self.invalidate(path);
+            }
+            WatchEvent::Deleted(path) => {
+                self.invalidate(&parent_of(path));
+                self.invalidate_tree(path);
+            }
+            WatchEvent::Renamed { from, to } => {
+                self.invalidate(&parent_of(from));
+                self.invalidate(&parent_of(to));
+                self.invalidate_tree(from);
+                self.invalidate_tree(to);
+            }
+        }
+    }
+
+    fn invalidate_tree(&self, root: &Path) {
+        let mut cache = self.cache.write();
+        let stale: Vec<PathBuf> = cache
+            .iter()
+            .map(|(dir, _)| dir)
+            .filter(|dir| dir.starts_with(root))
+            .cloned()
+            .collect();
+
+        for dir in stale {
+            cache.pop(&dir);
+        }
+    }
+
+    pub fn clear(&self) {
+        self.cache.write().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.cache.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for DirectoryListingCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_LISTING_CACHE_DIRS)
+    }
+}
+
+/// Owns the metadata and thumbnail caches and keeps their combined size
+/// limits within one memory budget.
+pub struct CacheManager {
+    metadata: MetadataCache,
+    thumbnails: ThumbnailCache,
+    total_budget_mb: usize,
+    // Megabytes currently given to (metadata, thumbnails).
+    allocation: RwLock<(usize, usize)>,
+}
+
+impl CacheManager {
+    /// Splits `total_budget_mb` 2:1 between metadata and thumbnails.
+    pub fn new(total_budget_mb: usize) -> Result<Self> {
+        let (metadata_mb, thumbnail_mb) = default_split(total_budget_mb);
+        Ok(Self::with_caches(
+            MetadataCache::new(metadata_mb),
+            ThumbnailCache::new(thumbnail_mb)?,
+            total_budget_mb,
+        ))
+    }
+
+    pub fn with_thumbnail_dir(thumbnail_dir: PathBuf, total_budget_mb: usize) -> Result<Self> {
+        let (metadata_mb, thumbnail_mb) = default_split(total_budget_mb);
+        Ok(Self::with_caches(
+            MetadataCache::new(metadata_mb),
+            ThumbnailCache::with_dir(thumbnail_dir, thumbnail_mb)?,
+            total_budget_mb,
+        ))
+    }
+
+    /// Bounds concurrent thumbnail generation; see
+    /// `ThumbnailCache::with_max_concurrent`.
+    pub fn with_max_concurrent_thumbnails(mut self, max_concurrent: usize) -> Self {
+        self.thumbnails = self.thumbnails.with_max_concurrent(max_concurrent);
+        self
+    }
+
+    fn with_caches(metadata: MetadataCache, thumbnails: ThumbnailCache, total_budget_mb: usize) -> Self {
+        Self {
+            metadata,
+            thumbnails,
+            total_budget_mb,
+            allocation: RwLock::new(default_split(total_budget_mb)),
+        }
+    }
+
+    pub fn metadata(&self) -> &MetadataCache {
+        &self.metadata
+    }
+
+    pub fn thumbnails(&self) -> &ThumbnailCache {
+        &self.thumbnails
+    }
+
+    pub fn total_budget_mb(&self) -> usize {
+        self.total_budget_mb
+    }
+
+    /// Megabytes given to the metadata and the thumbnail cache.
+    pub fn allocation(&self) -> (usize, usize) {
+        *self.allocation.read()
+    }
+
+    /// Resizes both caches in place, evicting the least recently used
+    /// entries of a cache that shrinks. Fails, changing nothing, when the two
+    /// do not fit in the budget together.
+    pub fn reconfigure(&self, metadata_mb: usize, thumbnail_mb: usize) -> Result<()> {
+        if metadata_mb.saturating_add(thumbnail_mb) > self.total_budget_mb {
+            return Err(Error::Cache(format!(
+                "{} MB for metadata and {} MB for thumbnails exceed the cache budget of {} MB",
+                metadata_mb, thumbnail_mb, self.total_budget_mb
+            ).into()));
+        }
+
+        let mut allocation = self.allocation.write();
+        self.metadata.resize(metadata_mb);
+        self.thumbnails.resize(thumbnail_mb);
+        *allocation = (metadata_mb, thumbnail_mb);
+        Ok(())
+    }
+
+    pub fn total_entries(&self) -> usize {
+        self.metadata.len() + self.thumbnails.cache_size()
+    }
+
+    /// Rough memory held by both caches: the fixed size of each metadata
+    /// entry plus the thumbnail data.
+    pub fn memory_estimate_mb(&self) -> f64 {
+        let bytes = self.metadata.len() * std::mem::size_of::<CachedMetadata>() + self.thumbnails.memory_bytes();
+        bytes as f64 / (1024.0 * 1024.0)
+    }
+}
+
+fn default_split(total_budget_mb: usize) -> (usize, usize) {
+    let metadata_mb = total_budget_mb * 2 / 3;
+    (metadata_mb, total_budget_mb - metadata_mb)
+}
+
 #[cfg(unix)]
 fn get_inode(metadata: &std::fs::Metadata) -> u64 {
     use std::os::unix::fs::MetadataExt;
@@ -129,6 +409,15 @@ fn get_inode(_metadata: &std::fs::Metadata) -> u64 {
     0
 }
 
+fn parent_of(path: &Path) -> PathBuf {
+    path.parent().map(Path::to_path_buf).unwrap_or_default()
+}
+
+fn entry_capacity(capacity_mb: usize) -> NonZeroUsize {
+    let size = (capacity_mb * 1024 * 1024) / std::mem::size_of::<CachedMetadata>();
+    NonZeroUsize::new(size.max(DEFAULT_CACHE_SIZE)).unwrap()
+}
+
 fn is_valid(cached: &DirEntry, metadata: &std::fs::Metadata) -> bool {
     cached.size == metadata.len() &&
     cached.modified == metadata.modified().unwrap_or(std::time::UNIX_EPOCH)
@@ -137,6 +426,7 @@ fn is_valid(cached: &DirEntry, metadata: &std::fs::Metadata) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fs::EntryType;
     use std::fs;
     use tempfile::TempDir;
 
@@ -170,4 +460,240 @@ mod tests {
         cache.invalidate(&file_path).unwrap();
         assert_eq!(cache.len(), 0);
     }
+
+    fn synthetic_entry(path: PathBuf, is_dir: bool, inode: u64) -> DirEntry {
+        DirEntry {
+            name: path.file_name().unwrap().to_string_lossy().into_owned(),
+            path,
+            size: 0,
+            modified: std::time::UNIX_EPOCH,
+            is_dir,
+            is_symlink: false,
+            entry_type: if is_dir { EntryType::Directory } else { EntryType::File },
+            permissions: 0o644,
+            inode,
+            symlink_target: None,
+            symlink_is_broken: false,
+            target_type: None,
+        }
+    }
+
+    // Builds /root/d0/d1/.../d{depth-1}, each directory holding `files` entries.
+    fn populate_deep_tree(cache: &MetadataCache, depth: usize, files: usize, first_inode: u64) -> u64 {
+        let mut inode = first_inode;
+        let mut dir = PathBuf::from("/root");
+
+        for level in 0..depth {
+            dir = dir.join(format!("d{}", level));
+            cache.insert(inode, synthetic_entry(dir.clone(), true, inode));
+            inode += 1;
+
+            for i in 0..files {
+                cache.insert(inode, synthetic_entry(dir.join(format!("f{}", i)), false, inode));
+                inode += 1;
+            }
+        }
+
+        inode
+    }
+
+    #[test]
+    fn test_invalidate_recursive_counts() {
+        let cache = MetadataCache::default();
+        populate_deep_tree(&cache, 10, 5, 1);
+        assert_eq!(cache.len(), 60);
+
+        let deep = PathBuf::from("/root/d0/d1/d2/d3/d4/d5/d6");
+        let evicted = cache.invalidate_recursive(&deep);
+
+        // d6..d9 plus five files each.
+        assert_eq!(evicted, 24);
+        assert_eq!(cache.len(), 36);
+        assert!(cache.get(7 * 6 + 1).is_none());
+        assert!(cache.get(1).is_some());
+
+        assert_eq!(cache.invalidate_recursive(&deep), 0);
+        assert_eq!(cache.invalidate_recursive(Path::new("/root")), 36);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_index_survives_replacement_and_removal() {
+        let cache = MetadataCache::default();
+        cache.insert(1, synthetic_entry(PathBuf::from("/a/x"), false, 1));
+        cache.insert(1, synthetic_entry(PathBuf::from("/b/x"), false, 1));
+        cache.insert(2, synthetic_entry(PathBuf::from("/a/y"), false, 2));
+        cache.remove(2);
+
+        assert_eq!(cache.invalidate_recursive(Path::new("/a")), 0);
+        assert_eq!(cache.invalidate_recursive(Path::new("/b")), 1);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_invalidate_recursive_skips_name_prefixed_siblings() {
+        let cache = MetadataCache::default();
+        cache.insert(1, synthetic_entry(PathBuf::from("/a/d1/x"), false, 1));
+        cache.insert(2, synthetic_entry(PathBuf::from("/a/d10/x"), false, 2));
+        cache.insert(3, synthetic_entry(PathBuf::from("/a/d1-old/x"), false, 3));
+
+        assert_eq!(cache.invalidate_recursive(Path::new("/a/d1")), 1);
+        assert!(cache.get(2).is_some());
+        assert!(cache.get(3).is_some());
+    }
+
+    #[test]
+    fn test_resize_grow_and_shrink() {
+        let cache = MetadataCache::new(0);
+        assert_eq!(cache.capacity(), DEFAULT_CACHE_SIZE);
+
+        cache.resize(64);
+        let grown = cache.capacity();
+        assert!(grown > DEFAULT_CACHE_SIZE);
+
+        let count = DEFAULT_CACHE_SIZE as u64 + 500;
+        for inode in 1..=count {
+            cache.insert(inode, synthetic_entry(PathBuf::from(format!("/dir/f{}", inode)), false, inode));
+        }
+        cache.get(1);
+        assert_eq!(cache.len(), count as usize);
+
+        cache.resize(0);
+
+        assert_eq!(cache.capacity(), DEFAULT_CACHE_SIZE);
+        assert_eq!(cache.len(), DEFAULT_CACHE_SIZE);
+        assert!(cache.get(1).is_some());
+        assert!(cache.get(2).is_none());
+        assert!(cache.get(count).is_some());
+
+        // The parent index only refers to entries that are still cached.
+        assert_eq!(cache.invalidate_recursive(Path::new("/dir")), DEFAULT_CACHE_SIZE);
+        assert!(cache.is_empty());
+    }
+
+    fn listing(dir: &str, names: &[&str]) -> Vec<DirEntry> {
+        names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| synthetic_entry(Path::new(dir).join(name), false, i as u64 + 1))
+            .collect()
+    }
+
+    #[test]
+    fn test_listing_cache_hit_and_miss() {
+        let cache = DirectoryListingCache::new(2);
+        cache.insert(Path::new("/home/user"), listing("/home/user", &["a", "b"]));
+
+        let hit = cache.get(Path::new("/home/user")).unwrap();
+        assert_eq!(hit.len(), 2);
+        assert!(cache.get(Path::new("/home")).is_none());
+
+        // /home/user was just used, so /tmp is the one evicted.
+        cache.insert(Path::new("/tmp"), listing("/tmp", &["x"]));
+        cache.get(Path::new("/home/user"));
+        cache.insert(Path::new("/srv"), listing("/srv", &[]));
+        assert!(cache.get(Path::new("/tmp")).is_none());
+        assert!(cache.get(Path::new("/home/user")).is_some());
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_listing_cache_invalidated_on_delete() {
+        let cache = DirectoryListingCache::default();
+        cache.insert(Path::new("/data"), listing("/data", &["photos", "notes.txt"]));
+        cache.insert(Path::new("/data/photos"), listing("/data/photos", &["1.jpg"]));
+        cache.insert(Path::new("/data/photos/2024"), listing("/data/photos/2024", &["2.jpg"]));
+        cache.insert(Path::new("/other"), listing("/other", &["keep"]));
+
+        cache.invalidate_on_event(&WatchEvent::Deleted(PathBuf::from("/data/notes.txt")));
+        assert!(cache.get(Path::new("/data")).is_none());
+        assert!(cache.get(Path::new("/data/photos")).is_some());
+
+        cache.invalidate_on_event(&WatchEvent::Deleted(PathBuf::from("/data/photos")));
+        assert!(cache.get(Path::new("/data/photos")).is_none());
+        assert!(cache.get(Path::new("/data/photos/2024")).is_none());
+        assert!(cache.get(Path::new("/other")).is_some());
+    }
+
+    #[test]
+    fn test_listing_cache_invalidated_on_create_and_rename() {
+        let cache = DirectoryListingCache::default();
+        cache.insert(Path::new("/a"), listing("/a", &["old"]));
+        cache.insert(Path::new("/a/old"), listing("/a/old", &["f"]));
+        cache.insert(Path::new("/b"), listing("/b", &[]));
+
+        cache.invalidate_on_event(&WatchEvent::Renamed {
+            from: PathBuf::from("/a/old"),
+            to: PathBuf::from("/b/new"),
+        });
+        assert!(cache.is_empty());
+
+        cache.insert(Path::new("/c"), listing("/c", &[]));
+        cache.invalidate_on_event(&WatchEvent::Created(PathBuf::from("/c/file")));
+        assert!(cache.get(Path::new("/c")).is_none());
+    }
+
+    #[test]
+    fn test_cache_manager_budget() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = CacheManager::with_thumbnail_dir(temp_dir.path().join("thumbnails"), 192).unwrap();
+        let thumbnail_capacity = |mb| ThumbnailCache::with_dir(temp_dir.path().join("sizing"), mb).unwrap().cache_capacity();
+
+        assert_eq!(manager.allocation(), (128, 64));
+        assert_eq!(manager.metadata().capacity(), MetadataCache::new(128).capacity());
+        assert_eq!(manager.thumbnails().cache_capacity(), thumbnail_capacity(64));
+
+        assert!(manager.reconfigure(128, 65).is_err());
+        assert_eq!(manager.allocation(), (128, 64));
+
+        manager.reconfigure(32, 160).unwrap();
+        assert_eq!(manager.allocation(), (32, 160));
+        assert_eq!(manager.metadata().capacity(), MetadataCache::new(32).capacity());
+        assert_eq!(manager.thumbnails().cache_capacity(), thumbnail_capacity(160));
+    }
+
+    #[test]
+    fn test_cache_manager_entries_and_shrinking() {
+        use thumbnail::ThumbnailSize;
+
+        let temp_dir = TempDir::new().unwrap();
+        let manager = CacheManager::with_thumbnail_dir(temp_dir.path().join("thumbnails"), 192).unwrap();
+        assert_eq!(manager.total_entries(), 0);
+        assert_eq!(manager.memory_estimate_mb(), 0.0);
+
+        for inode in 1..=10 {
+            manager.metadata().insert(inode, synthetic_entry(PathBuf::from(format!("/dir/f{}", inode)), false, inode));
+        }
+        for i in 0..150 {
+            let path = PathBuf::from(format!("/photos/{}.jpg", i));
+            manager.thumbnails().insert(&path, ThumbnailSize::Normal, vec![0u8; 1024]).unwrap();
+        }
+        assert_eq!(manager.total_entries(), 160);
+        let expected = (10 * std::mem::size_of::<CachedMetadata>() + 150 * 1024) as f64 / (1024.0 * 1024.0);
+        assert_eq!(manager.memory_estimate_mb(), expected);
+
+        // Both caches keep their minimum entry counts when given nothing.
+        manager.reconfigure(0, 0).unwrap();
+        assert_eq!(manager.metadata().len(), 10);
+        assert_eq!(manager.thumbnails().cache_size(), 100);
+        assert_eq!(manager.total_entries(), 110);
+    }
+
+    #[test]
+    fn test_invalidate_recursive_matches_scan() {
+        let scanned = MetadataCache::new(256);
+        let indexed = MetadataCache::new(256);
+        for cache in [&scanned, &indexed] {
+            let next = populate_deep_tree(cache, 200, 250, 1);
+            assert_eq!(next, 1 + 200 * 251);
+        }
+
+        let deep = (0..195).fold(PathBuf::from("/root"), |p, i| p.join(format!("d{}", i)));
+
+        scanned.invalidate_directory(&deep).unwrap();
+        let evicted = indexed.invalidate_recursive(&deep);
+
+        assert_eq!(evicted, 6 * 251);
+        assert_eq!(scanned.len(), indexed.len());
+    }
 }
@@ -1,12 +1,36 @@
 use crate::{Error, Result};
+use crate::error::context;
 use crate::cache::lru::LruCache;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use sha2::{Sha256, Digest};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::io::Write;
+use std::os::unix::ffi::OsStrExt;
+use flate2::{write::ZlibEncoder, Compression, Crc};
+use md5::{Md5, Digest};
+use parking_lot::Mutex;
+use tokio::sync::{OnceCell, Semaphore};
+use tokio_util::sync::CancellationToken;
 use xdg::BaseDirectories;
 
 const THUMBNAIL_SIZE_NORMAL: u32 = 128;
 const THUMBNAIL_SIZE_LARGE: u32 = 256;
+const FAIL_DIR_APP_NAME: &str = "cheese";
+const DEFAULT_MAX_CONCURRENT_GENERATIONS: usize = 4;
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+type ThumbnailKey = (PathBuf, ThumbnailSize);
+type InFlight = Arc<OnceCell<std::result::Result<Vec<u8>, Arc<Error>>>>;
+type PathLock = Arc<tokio::sync::Mutex<()>>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CachedThumbnail {
+    Data(Vec<u8>),
+    /// Generation failed for the current version of the file; show a generic
+    /// icon instead of retrying.
+    Failed,
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ThumbnailSize {
@@ -31,36 +55,127 @@ impl ThumbnailSize {
 }
 
 pub struct ThumbnailCache {
-    cache: LruCache<(PathBuf, ThumbnailSize), Vec<u8>>,
+    cache: LruCache<ThumbnailKey, Vec<u8>>,
     cache_dir: PathBuf,
     size_limit_mb: usize,
+    in_flight: Mutex<HashMap<ThumbnailKey, InFlight>>,
+    path_locks: Mutex<HashMap<PathBuf, PathLock>>,
+    generation_limit: Arc<Semaphore>,
+    max_concurrent: usize,
+    #[cfg(test)]
+    generations: std::sync::atomic::AtomicUsize,
 }
 
 impl ThumbnailCache {
     pub fn new(size_limit_mb: usize) -> Result<Self> {
+        Self::with_dir(Self::default_dir()?, size_limit_mb)
+    }
+
+    /// The shared freedesktop thumbnail directory, `$XDG_CACHE_HOME/thumbnails`.
+    pub fn default_dir() -> Result<PathBuf> {
         let xdg_dirs = BaseDirectories::new()
-            .map_err(|e| Error::Cache(format!("Failed to get XDG directories: {}", e)))?;
-        
-        let cache_dir = xdg_dirs.get_cache_home().join("thumbnails");
+            .map_err(|e| Error::Cache(context("Failed to get XDG directories", e)))?;
+
+        Ok(xdg_dirs.get_cache_home().join("thumbnails"))
+    }
+
+    pub fn with_dir(cache_dir: PathBuf, size_limit_mb: usize) -> Result<Self> {
         std::fs::create_dir_all(&cache_dir)?;
 
-        let capacity = (size_limit_mb * 1024 * 1024) / (THUMBNAIL_SIZE_LARGE * THUMBNAIL_SIZE_LARGE * 4) as usize;
-        
+        // Keep the disk cache in step with memory: an evicted thumbnail is
+        // dropped from disk too, so the directory never outgrows the limit.
+        let evict_dir = cache_dir.clone();
+        let cache = LruCache::new(memory_capacity(size_limit_mb)).with_on_evict(
+            move |(path, size): &ThumbnailKey, _data: &Vec<u8>| {
+                let _ = std::fs::remove_file(thumbnail_path(&evict_dir, path, *size));
+            },
+        );
+
         Ok(Self {
-            cache: LruCache::new(capacity.max(100)),
+            cache,
             cache_dir,
             size_limit_mb,
+            in_flight: Mutex::new(HashMap::new()),
+            path_locks: Mutex::new(HashMap::new()),
+            generation_limit: Arc::new(Semaphore::new(DEFAULT_MAX_CONCURRENT_GENERATIONS)),
+            max_concurrent: DEFAULT_MAX_CONCURRENT_GENERATIONS,
+            #[cfg(test)]
+            generations: std::sync::atomic::AtomicUsize::new(0),
         })
     }
 
-    pub fn get(&self, path: &Path, size: ThumbnailSize) -> Option<Vec<u8>> {
+    /// Bounds how many thumbnails are generated at once, normally from
+    /// `PerformanceConfig::max_concurrent_ops`.
+    pub fn with_max_concurrent(mut self, max_concurrent: usize) -> Self {
+        self.max_concurrent = max_concurrent.max(1);
+        self.generation_limit = Arc::new(Semaphore::new(self.max_concurrent));
+        self
+    }
+
+    pub fn max_concurrent(&self) -> usize {
+        self.max_concurrent
+    }
+
+    pub fn get(&self, path: &Path, size: ThumbnailSize) -> Option<CachedThumbnail> {
         let key = (path.to_path_buf(), size);
         
         if let Some(data) = self.cache.get(&key) {
-            return Some(data);
+            return Some(CachedThumbnail::Data(data));
         }
 
-        self.load_from_disk(path, size)
+        if self.has_fail_marker(path) {
+            return Some(CachedThumbnail::Failed);
+        }
+
+        self.load_from_disk(path, size).map(CachedThumbnail::Data)
+    }
+
+    /// Returns the thumbnail from memory or disk, generating and storing it on
+    /// a miss. Callers for the same file are serialized, so a miss is only
+    /// generated once.
+    pub async fn get_or_generate(
+        &self,
+        path: &Path,
+        size: ThumbnailSize,
+        cancel: CancellationToken,
+    ) -> Result<Vec<u8>> {
+        let lock = Arc::clone(self.path_locks.lock().entry(path.to_path_buf()).or_default());
+
+        let result = tokio::select! {
+            biased;
+            _ = cancel.cancelled() => Err(Error::Cancelled),
+            result = async {
+                let _guard = lock.lock().await;
+                self.lookup_or_generate(path, size).await
+            } => result,
+        };
+
+        // Drop the entry once no other caller is waiting on it.
+        let mut locks = self.path_locks.lock();
+        if Arc::strong_count(&lock) == 2 {
+            locks.remove(path);
+        }
+
+        result
+    }
+
+    async fn lookup_or_generate(&self, path: &Path, size: ThumbnailSize) -> Result<Vec<u8>> {
+        let key = (path.to_path_buf(), size);
+
+        if let Some(data) = self.cache.get(&key) {
+            return Ok(data);
+        }
+
+        if self.has_fail_marker(path) {
+            return Err(Error::Cache(format!("Thumbnail generation previously failed for {}", path.display()).into()));
+        }
+
+        if let Some(data) = self.load_from_disk(path, size) {
+            self.cache.insert(key, data.clone());
+            return Ok(data);
+        }
+
+        self.generate_thumbnail(path, size).await
     }
 
     pub fn insert(&self, path: &Path, size: ThumbnailSize, data: Vec<u8>) -> Result<()> {
@@ -76,6 +191,7 @@ impl ThumbnailCache {
             self.cache.remove(&key);
             let _ = self.remove_from_disk(path, size);
         }
+        let _ = std::fs::remove_file(self.fail_marker_path(path));
     }
 
     pub fn clear(&self) {
@@ -87,6 +203,7 @@ impl ThumbnailCache {
                 let _ = std::fs::create_dir_all(&thumb_dir);
             }
         }
+        let _ = std::fs::remove_dir_all(self.fail_dir());
     }
 
     pub fn is_supported_format(path: &Path) -> bool {
@@ -102,13 +219,13 @@ impl ThumbnailCache {
     }
 
     fn load_from_disk(&self, path: &Path, size: ThumbnailSize) -> Option<Vec<u8>> {
-        let thumb_path = self.get_thumbnail_path(path, size)?;
+        let thumb_path = self.thumbnail_path(path, size)?;
         std::fs::read(&thumb_path).ok()
     }
 
     fn save_to_disk(&self, path: &Path, size: ThumbnailSize, data: &[u8]) -> Result<()> {
-        let thumb_path = self.get_thumbnail_path(path, size)
-            .ok_or_else(|| Error::Cache("Failed to get thumbnail path".to_string()))?;
+        let thumb_path = self.thumbnail_path(path, size)
+            .ok_or_else(|| Error::Cache("Failed to get thumbnail path".into()))?;
 
         if let Some(parent) = thumb_path.parent() {
             std::fs::create_dir_all(parent)?;
@@ -118,8 +235,46 @@ impl ThumbnailCache {
         Ok(())
     }
 
+    fn fail_dir(&self) -> PathBuf {
+        self.cache_dir.join("fail").join(FAIL_DIR_APP_NAME)
+    }
+
+    fn fail_marker_path(&self, path: &Path) -> PathBuf {
+        self.fail_dir().join(format!("{}.png", uri_hash(&thumbnail_uri(path))))
+    }
+
+    // A marker only counts while the source still has the mtime it was
+    // written for; stale markers are removed so the file gets another try.
+    fn has_fail_marker(&self, path: &Path) -> bool {
+        let marker = self.fail_marker_path(path);
+        let Some(text) = std::fs::read(&marker).ok().and_then(|png| png_text(&png)) else {
+            return false;
+        };
+
+        let recorded = text.get("Thumb::MTime");
+        if recorded.is_some() && recorded == source_mtime(path).map(|m| m.to_string()).as_ref() {
+            return true;
+        }
+
+        let _ = std::fs::remove_file(&marker);
+        false
+    }
+
+    fn write_fail_marker(&self, path: &Path) -> Result<()> {
+        let mtime = source_mtime(path)
+            .ok_or_else(|| Error::Cache(format!("Failed to read mtime of {}", path.display()).into()))?;
+        let marker = self.fail_marker_path(path);
+
+        if let Some(parent) = marker.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        std::fs::write(&marker, fail_marker_png(&thumbnail_uri(path), mtime)?)?;
+        Ok(())
+    }
+
     fn remove_from_disk(&self, path: &Path, size: ThumbnailSize) -> Result<()> {
-        if let Some(thumb_path) = self.get_thumbnail_path(path, size) {
+        if let Some(thumb_path) = self.thumbnail_path(path, size) {
             if thumb_path.exists() {
                 std::fs::remove_file(&thumb_path)?;
             }
@@ -127,19 +282,28 @@ impl ThumbnailCache {
         Ok(())
     }
 
-    fn get_thumbnail_path(&self, path: &Path, size: ThumbnailSize) -> Option<PathBuf> {
-        let uri = format!("file://{}", path.display());
-        let hash = self.compute_hash(&uri);
-        
-        Some(self.cache_dir
-            .join(size.directory_name())
-            .join(format!("{}.png", hash)))
+    /// Where the thumbnail of `path` is stored on disk, whether or not it
+    /// has been generated yet.
+    pub fn thumbnail_path(&self, path: &Path, size: ThumbnailSize) -> Option<PathBuf> {
+        Some(thumbnail_path(&self.cache_dir, path, size))
+    }
+
+    /// Checks the disk cache only; the thumbnail is not read.
+    pub fn thumbnail_exists(&self, path: &Path, size: ThumbnailSize) -> bool {
+        self.thumbnail_path(path, size).is_some_and(|thumb_path| thumb_path.is_file())
     }
 
-    fn compute_hash(&self, uri: &str) -> String {
-        let mut hasher = Sha256::new();
-        hasher.update(uri.as_bytes());
-        format!("{:x}", hasher.finalize())
+    /// When the thumbnail of `path` was written, or `None` if there is none.
+    pub fn thumbnail_mtime(&self, path: &Path, size: ThumbnailSize) -> Result<Option<SystemTime>> {
+        let Some(thumb_path) = self.thumbnail_path(path, size) else {
+            return Ok(None);
+        };
+
+        match std::fs::metadata(&thumb_path) {
+            Ok(metadata) => Ok(Some(metadata.modified()?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
     }
 
     pub fn cache_size(&self) -> usize {
@@ -150,6 +314,17 @@ impl ThumbnailCache {
         self.cache.capacity()
     }
 
+    /// Bytes of thumbnail data held in memory.
+    pub fn memory_bytes(&self) -> usize {
+        self.cache.sum_by(|_, data| data.len())
+    }
+
+    /// Applies a new size limit without dropping thumbnails that still fit.
+    /// Thumbnails evicted by shrinking are removed from disk as well.
+    pub fn resize(&self, size_limit_mb: usize) {
+        self.cache.resize(memory_capacity(size_limit_mb));
+    }
+
     pub fn disk_size(&self) -> Result<u64> {
         let mut total = 0u64;
         
@@ -190,23 +365,198 @@ impl ThumbnailCache {
         }
 
         if !Self::is_supported_format(path) {
-            return Err(Error::Cache("Unsupported format".to_string()));
+            return Err(Error::Cache("Unsupported format".into()));
         }
 
+        if self.has_fail_marker(path) {
+            return Err(Error::Cache(format!("Thumbnail generation previously failed for {}", path.display()).into()));
+        }
+
+        // Concurrent requests for the same key share one generation. The entry
+        // is dropped once it finishes so later calls pick up file changes.
+        let key = (path.to_path_buf(), size);
+        let cell = Arc::clone(self.in_flight.lock().entry(key.clone()).or_default());
+
+        cell.get_or_init(|| async {
+            let result = self.generate_uncached(path, size).await.map_err(Arc::new);
+            self.in_flight.lock().remove(&key);
+            result
+        })
+        .await
+        .clone()
+        .map_err(|e| shared_error(&e))
+    }
+
+    async fn generate_uncached(&self, path: &Path, size: ThumbnailSize) -> Result<Vec<u8>> {
+        let _permit = self.generation_limit.acquire().await
+            .map_err(|e| Error::Cache(context("Thumbnail generation unavailable", e)))?;
+
+        #[cfg(test)]
+        self.generations.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+
         let data = tokio::fs::read(path).await?;
-        let thumbnail = self.create_thumbnail_data(&data, size)?;
+        let thumbnail = match self.create_thumbnail_data(path, &data, size) {
+            Ok(thumbnail) => thumbnail,
+            Err(e) => {
+                for size in [ThumbnailSize::Normal, ThumbnailSize::Large] {
+                    self.cache.remove(&(path.to_path_buf(), size));
+                    let _ = self.remove_from_disk(path, size);
+                }
+                if let Err(marker_err) = self.write_fail_marker(path) {
+                    tracing::warn!("Failed to write thumbnail fail marker for {}: {}", path.display(), marker_err);
+                }
+                return Err(e);
+            }
+        };
         
         self.insert(path, size, thumbnail.clone())?;
         Ok(thumbnail)
     }
 
-    fn create_thumbnail_data(&self, _data: &[u8], size: ThumbnailSize) -> Result<Vec<u8>> {
+    fn create_thumbnail_data(&self, path: &Path, data: &[u8], size: ThumbnailSize) -> Result<Vec<u8>> {
+        // infer has no SVG matcher, so only binary formats are sniffed.
+        let is_svg = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("svg"));
+        if data.is_empty() || (!is_svg && !infer::is_image(data)) {
+            return Err(Error::Cache(format!("Failed to decode image: {}", path.display()).into()));
+        }
+
         let pixels = size.pixels();
         let placeholder = vec![0u8; (pixels * pixels * 4) as usize];
         Ok(placeholder)
     }
 }
 
+// Every caller waiting on one generation gets its own copy of the error, in
+// the variant the generator returned.
+fn shared_error(error: &Arc<Error>) -> Error {
+    match error.as_ref() {
+        Error::Io(e) => Error::Io(std::io::Error::new(e.kind(), e.to_string())),
+        Error::NotFound { path } => Error::NotFound { path: path.clone() },
+        Error::PermissionDenied { path } => Error::PermissionDenied { path: path.clone() },
+        Error::Cancelled => Error::Cancelled,
+        _ => Error::Cache(Box::new(SharedError(Arc::clone(error)))),
+    }
+}
+
+// Stands in for the boxed source of a shared `Error::Cache`, or for the whole
+// error of any other variant.
+#[derive(Debug)]
+struct SharedError(Arc<Error>);
+
+impl std::fmt::Display for SharedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0.as_ref() {
+            Error::Cache(source) => source.fmt(f),
+            other => other.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for SharedError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self.0.as_ref() {
+            Error::Cache(source) => source.source(),
+            other => Some(other),
+        }
+    }
+}
+
+// In-memory entries for `size_limit_mb`, sized for large RGBA thumbnails.
+fn memory_capacity(size_limit_mb: usize) -> usize {
+    let capacity = (size_limit_mb * 1024 * 1024) / (THUMBNAIL_SIZE_LARGE * THUMBNAIL_SIZE_LARGE * 4) as usize;
+    capacity.max(100)
+}
+
+fn thumbnail_path(cache_dir: &Path, path: &Path, size: ThumbnailSize) -> PathBuf {
+    cache_dir
+        .join(size.directory_name())
+        .join(format!("{}.png", uri_hash(&thumbnail_uri(path))))
+}
+
+/// The `file://` URI thumbnails of `path` are keyed by, escaped the way GLib
+/// escapes paths so other desktop apps find the same cache entries.
+fn thumbnail_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for &byte in path.as_os_str().as_bytes() {
+        if byte.is_ascii_alphanumeric() || b"!$&'()*+,-./:=@_~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
+
+fn source_mtime(path: &Path) -> Option<u64> {
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    modified.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs())
+}
+
+fn uri_hash(uri: &str) -> String {
+    let mut hasher = Md5::new();
+    hasher.update(uri.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+// The spec's fail marker: a transparent 1x1 PNG whose tEXt chunks record the
+// source URI and the mtime generation failed for.
+fn fail_marker_png(uri: &str, mtime: u64) -> Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    // One scanline: filter type 0 and a single RGBA pixel.
+    encoder.write_all(&[0; 5])?;
+    let idat = encoder.finish()?;
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&1u32.to_be_bytes());
+    ihdr.extend_from_slice(&1u32.to_be_bytes());
+    // 8-bit RGBA, deflate, adaptive filtering, no interlace.
+    ihdr.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let mut png = PNG_SIGNATURE.to_vec();
+    push_png_chunk(&mut png, b"IHDR", &ihdr);
+    push_png_chunk(&mut png, b"tEXt", format!("Thumb::URI\0{}", uri).as_bytes());
+    push_png_chunk(&mut png, b"tEXt", format!("Thumb::MTime\0{}", mtime).as_bytes());
+    push_png_chunk(&mut png, b"IDAT", &idat);
+    push_png_chunk(&mut png, b"IEND", &[]);
+    Ok(png)
+}
+
+fn push_png_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+
+    let mut crc = Crc::new();
+    crc.update(&png[start..]);
+    png.extend_from_slice(&crc.sum().to_be_bytes());
+}
+
+// The tEXt keyword/value pairs of a PNG, or `None` if `png` is not one.
+fn png_text(png: &[u8]) -> Option<HashMap<String, String>> {
+    let mut rest = png.strip_prefix(PNG_SIGNATURE.as_slice())?;
+    let mut text = HashMap::new();
+
+    while !rest.is_empty() {
+        let len = u32::from_be_bytes(rest.get(..4)?.try_into().ok()?) as usize;
+        let kind = rest.get(4..8)?;
+        let data = rest.get(8..8 + len)?;
+
+        if kind == b"tEXt" {
+            if let Some(nul) = data.iter().position(|&b| b == 0) {
+                text.insert(
+                    String::from_utf8_lossy(&data[..nul]).into_owned(),
+                    String::from_utf8_lossy(&data[nul + 1..]).into_owned(),
+                );
+            }
+        }
+
+        rest = rest.get(8 + len + 4..)?;
+    }
+
+    Some(text)
+}
+
 impl Default for ThumbnailCache {
     fn default() -> Self {
         Self::new(64).expect("Failed to create thumbnail cache")
@@ -246,6 +596,271 @@ mod tests {
         cache.insert(&path, ThumbnailSize::Normal, data.clone()).unwrap();
         let retrieved = cache.get(&path, ThumbnailSize::Normal);
         
-        assert_eq!(retrieved, Some(data));
+        assert_eq!(retrieved, Some(CachedThumbnail::Data(data)));
+    }
+
+    #[test]
+    fn test_eviction_removes_disk_thumbnail() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = ThumbnailCache::with_dir(temp_dir.path().join("thumbnails"), 0).unwrap();
+        let capacity = cache.cache_capacity();
+
+        let first = PathBuf::from("/photos/0.png");
+        cache.insert(&first, ThumbnailSize::Normal, vec![0]).unwrap();
+        let first_thumb = cache.thumbnail_path(&first, ThumbnailSize::Normal).unwrap();
+        assert!(first_thumb.exists());
+
+        for i in 1..=capacity {
+            let path = PathBuf::from(format!("/photos/{}.png", i));
+            cache.insert(&path, ThumbnailSize::Normal, vec![1]).unwrap();
+        }
+
+        assert_eq!(cache.cache_size(), capacity);
+        assert!(!first_thumb.exists());
+        assert_eq!(cache.get(&first, ThumbnailSize::Normal), None);
+    }
+
+    #[test]
+    fn test_thumbnail_path_exists_and_mtime() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_dir = temp_dir.path().join("thumbnails");
+        let cache = ThumbnailCache::with_dir(cache_dir.clone(), 64).unwrap();
+        let path = PathBuf::from("/photos/cat.png");
+
+        let thumb_path = cache.thumbnail_path(&path, ThumbnailSize::Large).unwrap();
+        assert_eq!(thumb_path, cache_dir.join("large").join(format!("{}.png", uri_hash("file:///photos/cat.png"))));
+        assert_ne!(cache.thumbnail_path(&path, ThumbnailSize::Normal), Some(thumb_path.clone()));
+        assert!(!cache.thumbnail_exists(&path, ThumbnailSize::Large));
+        assert_eq!(cache.thumbnail_mtime(&path, ThumbnailSize::Large).unwrap(), None);
+
+        let before = SystemTime::now() - std::time::Duration::from_secs(1);
+        cache.insert(&path, ThumbnailSize::Large, vec![1, 2, 3]).unwrap();
+
+        assert!(cache.thumbnail_exists(&path, ThumbnailSize::Large));
+        assert!(!cache.thumbnail_exists(&path, ThumbnailSize::Normal));
+        assert_eq!(std::fs::read(&thumb_path).unwrap(), vec![1, 2, 3]);
+        assert!(cache.thumbnail_mtime(&path, ThumbnailSize::Large).unwrap().unwrap() >= before);
+    }
+
+    #[test]
+    fn test_resize_grow_and_shrink() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = ThumbnailCache::with_dir(temp_dir.path().join("thumbnails"), 0).unwrap();
+        assert_eq!(cache.cache_capacity(), 100);
+
+        let paths: Vec<PathBuf> = (0..150).map(|i| PathBuf::from(format!("/photos/{}.png", i))).collect();
+        cache.resize(256);
+        assert_eq!(cache.cache_capacity(), memory_capacity(256));
+        for path in &paths {
+            cache.insert(path, ThumbnailSize::Normal, vec![1]).unwrap();
+        }
+        assert_eq!(cache.cache_size(), 150);
+
+        cache.resize(0);
+
+        assert_eq!(cache.cache_capacity(), 100);
+        assert_eq!(cache.cache_size(), 100);
+        let oldest = cache.thumbnail_path(&paths[0], ThumbnailSize::Normal).unwrap();
+        assert!(!oldest.exists());
+        assert_eq!(cache.get(&paths[0], ThumbnailSize::Normal), None);
+        assert_eq!(cache.get(&paths[149], ThumbnailSize::Normal), Some(CachedThumbnail::Data(vec![1])));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_requests_share_generation() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = Arc::new(
+            ThumbnailCache::with_dir(temp_dir.path().join("thumbnails"), 64).unwrap().with_max_concurrent(1),
+        );
+        let image = temp_dir.path().join("photo.png");
+        std::fs::write(&image, b"\x89PNG\r\n\x1a\n0000").unwrap();
+
+        // Hold the only permit so every request piles up before generation starts.
+        let permit = Arc::clone(&cache.generation_limit).acquire_owned().await.unwrap();
+
+        let mut tasks = tokio::task::JoinSet::new();
+        for _ in 0..32 {
+            let cache = Arc::clone(&cache);
+            let image = image.clone();
+            tasks.spawn(async move { cache.generate_thumbnail(&image, ThumbnailSize::Normal).await });
+        }
+
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert_eq!(cache.in_flight.lock().len(), 1);
+        drop(permit);
+
+        let mut results = Vec::new();
+        while let Some(result) = tasks.join_next().await {
+            results.push(result.unwrap().unwrap());
+        }
+
+        assert_eq!(results.len(), 32);
+        assert!(results.windows(2).all(|pair| pair[0] == pair[1]));
+        assert_eq!(cache.generations.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert!(cache.in_flight.lock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_shared_generation_keeps_error_variant() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = Arc::new(ThumbnailCache::with_dir(temp_dir.path().join("thumbnails"), 64).unwrap());
+        let unreadable = temp_dir.path().join("folder.png");
+        std::fs::create_dir(&unreadable).unwrap();
+        let garbage = temp_dir.path().join("garbage.png");
+        std::fs::write(&garbage, b"not an image").unwrap();
+
+        let mut tasks = tokio::task::JoinSet::new();
+        for _ in 0..4 {
+            let (cache, unreadable) = (Arc::clone(&cache), unreadable.clone());
+            tasks.spawn(async move { cache.generate_thumbnail(&unreadable, ThumbnailSize::Normal).await });
+        }
+        while let Some(result) = tasks.join_next().await {
+            assert!(matches!(result.unwrap(), Err(Error::Io(_))));
+        }
+
+        match cache.generate_thumbnail(&garbage, ThumbnailSize::Normal).await {
+            Err(Error::Cache(source)) => assert!(source.to_string().starts_with("Failed to decode image")),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_or_generate_single_generation() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = Arc::new(
+            ThumbnailCache::with_dir(temp_dir.path().join("thumbnails"), 64).unwrap().with_max_concurrent(1),
+        );
+        let image = temp_dir.path().join("photo.png");
+        std::fs::write(&image, b"\x89PNG\r\n\x1a\n0000").unwrap();
+
+        // Holding the only permit stalls the first generation, so every
+        // caller is inside get_or_generate before any of them can finish.
+        let permit = Arc::clone(&cache.generation_limit).acquire_owned().await.unwrap();
+        let callers = 8;
+        let barrier = Arc::new(tokio::sync::Barrier::new(callers + 1));
+
+        let mut tasks = tokio::task::JoinSet::new();
+        for _ in 0..callers {
+            let (cache, image, barrier) = (Arc::clone(&cache), image.clone(), Arc::clone(&barrier));
+            tasks.spawn(async move {
+                barrier.wait().await;
+                cache.get_or_generate(&image, ThumbnailSize::Normal, CancellationToken::new()).await
+            });
+        }
+        barrier.wait().await;
+
+        // The map holds one reference to the path lock and each caller another.
+        while cache.path_locks.lock().get(&image).map(Arc::strong_count) != Some(callers + 1) {
+            tokio::task::yield_now().await;
+        }
+        drop(permit);
+
+        let mut results = Vec::new();
+        while let Some(result) = tasks.join_next().await {
+            results.push(result.unwrap().unwrap());
+        }
+        let first = results[0].clone();
+
+        assert!(results.iter().all(|result| *result == first));
+        assert_eq!(cache.generations.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert!(cache.path_locks.lock().is_empty());
+
+        // A fresh cache over the same directory is served from disk.
+        let reopened = ThumbnailCache::with_dir(temp_dir.path().join("thumbnails"), 64).unwrap();
+        let from_disk = reopened.get_or_generate(&image, ThumbnailSize::Normal, CancellationToken::new()).await.unwrap();
+        assert_eq!(from_disk, first);
+        assert_eq!(reopened.generations.load(std::sync::atomic::Ordering::SeqCst), 0);
+        assert_eq!(reopened.cache_size(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_or_generate_cancelled() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = ThumbnailCache::with_dir(temp_dir.path().join("thumbnails"), 64).unwrap();
+        let image = temp_dir.path().join("photo.png");
+        std::fs::write(&image, b"\x89PNG\r\n\x1a\n0000").unwrap();
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let result = cache.get_or_generate(&image, ThumbnailSize::Normal, cancel).await;
+
+        assert!(matches!(result, Err(Error::Cancelled)));
+        assert!(cache.path_locks.lock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failed_thumbnail_is_not_retried() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = ThumbnailCache::with_dir(temp_dir.path().join("thumbnails"), 64).unwrap();
+        let image = temp_dir.path().join("corrupt.png");
+        std::fs::write(&image, b"not an image").unwrap();
+
+        assert!(cache.generate_thumbnail(&image, ThumbnailSize::Normal).await.is_err());
+        assert!(cache.fail_marker_path(&image).exists());
+        assert_eq!(cache.get(&image, ThumbnailSize::Normal), Some(CachedThumbnail::Failed));
+        assert_eq!(cache.get(&image, ThumbnailSize::Large), Some(CachedThumbnail::Failed));
+
+        // Even valid data is not looked at again until the file changes.
+        let mtime = std::fs::metadata(&image).unwrap().modified().unwrap();
+        std::fs::write(&image, b"\x89PNG\r\n\x1a\n0000").unwrap();
+        std::fs::File::options().write(true).open(&image).unwrap().set_modified(mtime).unwrap();
+
+        match cache.generate_thumbnail(&image, ThumbnailSize::Normal).await {
+            Err(Error::Cache(msg)) => assert!(msg.to_string().contains("previously failed")),
+            other => panic!("Unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fail_marker_invalidated_by_mtime_change() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = ThumbnailCache::with_dir(temp_dir.path().join("thumbnails"), 64).unwrap();
+        let image = temp_dir.path().join("fixed.png");
+        std::fs::write(&image, b"").unwrap();
+
+        assert!(cache.generate_thumbnail(&image, ThumbnailSize::Normal).await.is_err());
+        assert_eq!(cache.get(&image, ThumbnailSize::Normal), Some(CachedThumbnail::Failed));
+
+        std::fs::write(&image, b"\x89PNG\r\n\x1a\n0000").unwrap();
+        let later = std::time::SystemTime::now() + std::time::Duration::from_secs(10);
+        std::fs::File::options().write(true).open(&image).unwrap().set_modified(later).unwrap();
+
+        assert_eq!(cache.get(&image, ThumbnailSize::Normal), None);
+        assert!(!cache.fail_marker_path(&image).exists());
+
+        let thumbnail = cache.generate_thumbnail(&image, ThumbnailSize::Normal).await.unwrap();
+        assert_eq!(cache.get(&image, ThumbnailSize::Normal), Some(CachedThumbnail::Data(thumbnail)));
+    }
+
+    #[test]
+    fn test_thumbnail_uri_and_hash_follow_spec() {
+        let uri = thumbnail_uri(Path::new("/home/jens/photos/me.png"));
+        assert_eq!(uri, "file:///home/jens/photos/me.png");
+        assert_eq!(uri_hash(&uri), "c6ee772d9e49320e97ec29a7eb5b1697");
+
+        assert_eq!(
+            thumbnail_uri(Path::new("/photos/my cat#1 (é).png")),
+            "file:///photos/my%20cat%231%20(%C3%A9).png"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fail_marker_is_png_with_thumb_text() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_dir = temp_dir.path().join("thumbnails");
+        let cache = ThumbnailCache::with_dir(cache_dir.clone(), 64).unwrap();
+        let image = temp_dir.path().join("broken file.png");
+        std::fs::write(&image, b"not an image").unwrap();
+
+        assert!(cache.generate_thumbnail(&image, ThumbnailSize::Normal).await.is_err());
+
+        let uri = thumbnail_uri(&image);
+        let marker = cache_dir.join("fail").join("cheese").join(format!("{}.png", uri_hash(&uri)));
+        let png = std::fs::read(&marker).unwrap();
+        assert_eq!(infer::get(&png).map(|kind| kind.mime_type()), Some("image/png"));
+
+        let text = png_text(&png).unwrap();
+        assert_eq!(text.get("Thumb::URI"), Some(&uri));
+        assert_eq!(text.get("Thumb::MTime"), Some(&source_mtime(&image).unwrap().to_string()));
     }
 }
@@ -1,8 +1,17 @@
 use crate::{Error, Result};
+use crate::error::context;
+use crate::fs::metadata::ByteFormat;
+use crate::fs::ops::LocalFileOps;
+use crate::security::default_protected_paths;
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
 use xdg::BaseDirectories;
 
+// Used when the CPU count cannot be determined.
+const DEFAULT_WORKER_THREADS: usize = 4;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub ui: UiConfig,
@@ -11,6 +20,8 @@ pub struct Config {
     pub keyboard: KeyboardConfig,
     pub integrations: IntegrationsConfig,
     pub plugins: PluginsConfig,
+    #[serde(default)]
+    pub security: SecurityConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,6 +33,20 @@ pub struct UiConfig {
     pub font_size: u32,
     pub confirm_delete: bool,
     pub confirm_trash: bool,
+    #[serde(default = "default_restore_session")]
+    pub restore_session: bool,
+    #[serde(default)]
+    pub byte_format: ByteFormat,
+    #[serde(default = "default_search_history_size")]
+    pub search_history_size: usize,
+}
+
+fn default_restore_session() -> bool {
+    true
+}
+
+fn default_search_history_size() -> usize {
+    20
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,7 +66,7 @@ pub struct NavigationConfig {
     pub group_directories: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum SortBy {
     Name,
@@ -50,13 +75,40 @@ pub enum SortBy {
     Type,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum SortOrder {
     Ascending,
     Descending,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SortConfig {
+    pub sort_by: SortBy,
+    pub sort_order: SortOrder,
+    pub directories_first: bool,
+}
+
+impl Default for SortConfig {
+    fn default() -> Self {
+        Self {
+            sort_by: SortBy::Name,
+            sort_order: SortOrder::Ascending,
+            directories_first: true,
+        }
+    }
+}
+
+impl From<&NavigationConfig> for SortConfig {
+    fn from(navigation: &NavigationConfig) -> Self {
+        Self {
+            sort_by: navigation.sort_by,
+            sort_order: navigation.sort_order,
+            directories_first: navigation.group_directories,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PerformanceConfig {
     pub cache_size_mb: usize,
@@ -64,6 +116,38 @@ pub struct PerformanceConfig {
     pub max_concurrent_ops: usize,
     pub debounce_ms: u64,
     pub large_dir_threshold: usize,
+    #[serde(default = "default_listing_cache_dirs")]
+    pub listing_cache_dirs: usize,
+    /// Per-entry cost used to estimate recursive scan times.
+    #[serde(default = "default_scan_nanos_per_entry")]
+    pub scan_nanos_per_entry: u64,
+    /// Threads reserved for synchronous filesystem work, see `BlockingPool`.
+    #[serde(default = "default_blocking_threads")]
+    pub blocking_threads: usize,
+    /// Async runtime workers; `None` uses one per CPU.
+    #[serde(default)]
+    pub worker_threads: Option<usize>,
+}
+
+impl PerformanceConfig {
+    pub fn runtime_worker_threads(&self) -> usize {
+        match self.worker_threads {
+            Some(threads) if threads > 0 => threads,
+            _ => std::thread::available_parallelism().map_or(DEFAULT_WORKER_THREADS, |n| n.get()),
+        }
+    }
+}
+
+fn default_listing_cache_dirs() -> usize {
+    crate::cache::DEFAULT_LISTING_CACHE_DIRS
+}
+
+fn default_scan_nanos_per_entry() -> u64 {
+    crate::fs::scanner::DEFAULT_NANOS_PER_ENTRY
+}
+
+fn default_blocking_threads() -> usize {
+    crate::blocking::DEFAULT_BLOCKING_THREADS
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -89,6 +173,29 @@ pub struct IntegrationsConfig {
 pub struct PluginsConfig {
     pub enabled: Vec<String>,
     pub auto_update: bool,
+    #[serde(default)]
+    pub settings: HashMap<String, HashMap<String, toml::Value>>,
+    /// Largest plugin download accepted from a repository.
+    #[serde(default = "default_max_download_mb")]
+    pub max_download_mb: u64,
+}
+
+fn default_max_download_mb() -> u64 {
+    crate::plugins::DEFAULT_MAX_DOWNLOAD_MB
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecurityConfig {
+    #[serde(default = "default_protected_paths")]
+    pub protected_paths: Vec<PathBuf>,
+}
+
+impl Default for SecurityConfig {
+    fn default() -> Self {
+        Self {
+            protected_paths: default_protected_paths(),
+        }
+    }
 }
 
 impl Default for Config {
@@ -102,6 +209,9 @@ impl Default for Config {
                 font_size: 10,
                 confirm_delete: true,
                 confirm_trash: false,
+                restore_session: true,
+                byte_format: ByteFormat::Iec,
+                search_history_size: default_search_history_size(),
             },
             navigation: NavigationConfig {
                 follow_symlinks: true,
@@ -116,6 +226,10 @@ impl Default for Config {
                 max_concurrent_ops: 4,
                 debounce_ms: 150,
                 large_dir_threshold: 10000,
+                listing_cache_dirs: default_listing_cache_dirs(),
+                scan_nanos_per_entry: default_scan_nanos_per_entry(),
+                blocking_threads: default_blocking_threads(),
+                worker_threads: None,
             },
             keyboard: KeyboardConfig {
                 vim_mode: true,
@@ -135,15 +249,31 @@ impl Default for Config {
             plugins: PluginsConfig {
                 enabled: vec!["git-overlay".to_string(), "archive-preview".to_string()],
                 auto_update: false,
+                settings: HashMap::new(),
+                max_download_mb: default_max_download_mb(),
             },
+            security: SecurityConfig::default(),
         }
     }
 }
 
+/// A setting that differs between two configs. `field_path` is its dotted
+/// TOML key, such as `ui.show_hidden`, with keys that are not bare quoted.
+///
+/// The values are `Option<toml::Value>` rather than plain `toml::Value`: a
+/// setting can exist on one side only, like an unset `worker_threads` or a
+/// newly added plugin setting, and is `None` on the side where it is unset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigDiff {
+    pub field_path: String,
+    pub old_value: Option<toml::Value>,
+    pub new_value: Option<toml::Value>,
+}
+
 impl Config {
     pub fn load() -> Result<Self> {
         let xdg_dirs = BaseDirectories::with_prefix("cheese")
-            .map_err(|e| Error::Config(format!("Failed to get XDG directories: {}", e)))?;
+            .map_err(|e| Error::Config(context("Failed to get XDG directories", e)))?;
 
         let config_path = xdg_dirs
             .find_config_file("cheese.toml")
@@ -151,41 +281,492 @@ impl Config {
                 xdg_dirs.get_config_home().join("cheese.toml")
             });
 
-        if config_path.exists() {
-            let contents = std::fs::read_to_string(&config_path)?;
-            toml::from_str(&contents).map_err(Into::into)
-        } else {
+        Self::load_from(&config_path)
+    }
+
+    /// Loads `config_path`, writing defaults if it does not exist. Each
+    /// successful load refreshes `<file>.bak`; if the file no longer parses,
+    /// it is kept as `<file>.corrupt` and the backup is restored in its place.
+    pub fn load_from(config_path: &Path) -> Result<Self> {
+        if !config_path.exists() {
             let default_config = Self::default();
-            if let Some(parent) = config_path.parent() {
-                std::fs::create_dir_all(parent)?;
+            default_config.save_to(config_path)?;
+            return Ok(default_config);
+        }
+
+        let contents = std::fs::read_to_string(config_path)?;
+        let backup_path = backup_path(config_path);
+
+        match toml::from_str(&contents) {
+            Ok(config) => {
+                if std::fs::read_to_string(&backup_path).ok().as_deref() != Some(contents.as_str()) {
+                    LocalFileOps::atomic_write(&backup_path, contents.as_bytes())?;
+                }
+                Ok(config)
+            }
+            Err(e) => {
+                let Some((config, backup)) = std::fs::read_to_string(&backup_path)
+                    .ok()
+                    .and_then(|backup| Some((toml::from_str(&backup).ok()?, backup)))
+                else {
+                    return Err(e.into());
+                };
+
+                let corrupt_path = sibling_path(config_path, ".corrupt");
+                tracing::warn!(
+                    "{} is invalid ({}), moving it to {} and restoring {}",
+                    config_path.display(), e, corrupt_path.display(), backup_path.display()
+                );
+                std::fs::rename(config_path, &corrupt_path)?;
+                LocalFileOps::atomic_write(config_path, backup.as_bytes())?;
+                Ok(config)
             }
-            let toml_str = toml::to_string_pretty(&default_config)
-                .map_err(|e| Error::Config(format!("Failed to serialize config: {}", e)))?;
-            std::fs::write(&config_path, toml_str)?;
-            Ok(default_config)
         }
     }
 
     pub fn save(&self) -> Result<()> {
         let xdg_dirs = BaseDirectories::with_prefix("cheese")
-            .map_err(|e| Error::Config(format!("Failed to get XDG directories: {}", e)))?;
+            .map_err(|e| Error::Config(context("Failed to get XDG directories", e)))?;
+
+        self.save_to(&xdg_dirs.get_config_home().join("cheese.toml"))
+    }
 
-        let config_path = xdg_dirs.get_config_home().join("cheese.toml");
-        
+    pub fn save_to(&self, config_path: &Path) -> Result<()> {
         if let Some(parent) = config_path.parent() {
             std::fs::create_dir_all(parent)?;
         }
 
         let toml_str = toml::to_string_pretty(self)
-            .map_err(|e| Error::Config(format!("Failed to serialize config: {}", e)))?;
-        
-        std::fs::write(&config_path, toml_str)?;
+            .map_err(|e| Error::Config(context("Failed to serialize config", e)))?;
+
+        LocalFileOps::atomic_write(config_path, toml_str.as_bytes())
+    }
+
+    pub fn export_json(&self) -> Result<String> {
+        Self::export_json_section(self)
+    }
+
+    pub fn export_json_section<T: Serialize>(section: &T) -> Result<String> {
+        serde_json::to_string_pretty(section)
+            .map_err(|e| Error::Config(context("Failed to serialize config as JSON", e)))
+    }
+
+    pub fn import_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json)
+            .map_err(|e| Error::Config(context("Failed to parse JSON config", e)))
+    }
+
+    /// Settings whose value in `other` differs from `self`, ordered by path.
+    /// Tables are compared key by key, anything else, arrays included, as a
+    /// whole. A section that cannot be represented in TOML on either side,
+    /// such as `security` with a non-UTF-8 protected path, is skipped with a
+    /// warning.
+    pub fn diff(&self, other: &Config) -> Vec<ConfigDiff> {
+        let mut diffs = Vec::new();
+
+        for ((section, old), (_, new)) in self.sections().into_iter().zip(other.sections()) {
+            match (old, new) {
+                (Ok(old), Ok(new)) => diff_values(&mut vec![section.to_string()], Some(&old), Some(&new), &mut diffs),
+                (Err(e), _) | (_, Err(e)) => tracing::warn!("Cannot compare [{}] settings: {}", section, e),
+            }
+        }
+        diffs
+    }
+
+    // Each top-level table serialized on its own, so one that fails does not
+    // hide changes in the others. Sorted by name, the order `diff` reports in.
+    // Destructured so that a new section fails to compile until it is listed.
+    fn sections(&self) -> [(&'static str, std::result::Result<toml::Value, toml::ser::Error>); 7] {
+        let Config { ui, navigation, performance, keyboard, integrations, plugins, security } = self;
+
+        [
+            ("integrations", toml::Value::try_from(integrations)),
+            ("keyboard", toml::Value::try_from(keyboard)),
+            ("navigation", toml::Value::try_from(navigation)),
+            ("performance", toml::Value::try_from(performance)),
+            ("plugins", toml::Value::try_from(plugins)),
+            ("security", toml::Value::try_from(security)),
+            ("ui", toml::Value::try_from(ui)),
+        ]
+    }
+
+    /// Reverts `diffs` taken with `diff`, setting each path back to its
+    /// `old_value`. Nothing changes if the result is not a valid config.
+    pub fn apply_diff(&mut self, diffs: &[ConfigDiff]) -> Result<()> {
+        let mut value = toml::Value::try_from(&*self)?;
+        for diff in diffs {
+            set_value(&mut value, &split_key_path(&diff.field_path)?, diff.old_value.clone())?;
+        }
+
+        *self = value.try_into()?;
         Ok(())
     }
 
     pub fn config_path() -> Result<PathBuf> {
         let xdg_dirs = BaseDirectories::with_prefix("cheese")
-            .map_err(|e| Error::Config(format!("Failed to get XDG directories: {}", e)))?;
+            .map_err(|e| Error::Config(context("Failed to get XDG directories", e)))?;
         Ok(xdg_dirs.get_config_home().join("cheese.toml"))
     }
+
+    /// `(label, accelerator)` pairs for every configured shortcut, in the
+    /// order `KeyboardConfig` declares them.
+    pub fn keyboard_shortcuts(&self) -> Vec<(String, String)> {
+        keyboard_shortcut_map(&self.keyboard)
+            .into_iter()
+            .map(|(field, accelerator)| (shortcut_label(&field), accelerator))
+            .collect()
+    }
+}
+
+/// Accelerators keyed by `KeyboardConfig` field name, in declaration order.
+/// Settings that are not shortcuts, such as `vim_mode`, are left out.
+pub fn keyboard_shortcut_map(keyboard: &KeyboardConfig) -> IndexMap<String, String> {
+    // Destructured so that a new field fails to compile until it is listed here.
+    let KeyboardConfig {
+        vim_mode: _,
+        command_palette,
+        fuzzy_search,
+        new_tab,
+        close_tab,
+        toggle_hidden,
+        delete,
+        trash,
+    } = keyboard;
+
+    [
+        ("command_palette", command_palette),
+        ("fuzzy_search", fuzzy_search),
+        ("new_tab", new_tab),
+        ("close_tab", close_tab),
+        ("toggle_hidden", toggle_hidden),
+        ("delete", delete),
+        ("trash", trash),
+    ]
+    .into_iter()
+    .map(|(field, accelerator)| (field.to_string(), accelerator.clone()))
+    .collect()
+}
+
+fn backup_path(config_path: &Path) -> PathBuf {
+    sibling_path(config_path, ".bak")
+}
+
+fn sibling_path(config_path: &Path, suffix: &str) -> PathBuf {
+    let mut file_name = config_path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(suffix);
+    config_path.with_file_name(file_name)
+}
+
+fn diff_values(path: &mut Vec<String>, old: Option<&toml::Value>, new: Option<&toml::Value>, diffs: &mut Vec<ConfigDiff>) {
+    match (old, new) {
+        (Some(toml::Value::Table(old)), Some(toml::Value::Table(new))) => {
+            let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+            for key in keys {
+                path.push(key.clone());
+                diff_values(path, old.get(key), new.get(key), diffs);
+                path.pop();
+            }
+        }
+        _ if old == new => {}
+        _ => diffs.push(ConfigDiff {
+            field_path: join_key_path(path),
+            old_value: old.cloned(),
+            new_value: new.cloned(),
+        }),
+    }
+}
+
+// Sets the value at `path`, creating missing tables, or removes it for `None`.
+fn set_value(root: &mut toml::Value, path: &[String], value: Option<toml::Value>) -> Result<()> {
+    let Some((last, parents)) = path.split_last() else {
+        return Err(Error::Config("Empty setting path".into()));
+    };
+
+    let mut table = root;
+    for key in parents {
+        let toml::Value::Table(entries) = table else {
+            return Err(Error::Config(format!("{} is not a table", join_key_path(path)).into()));
+        };
+        table = entries.entry(key.clone()).or_insert_with(|| toml::Value::Table(toml::Table::new()));
+    }
+
+    let toml::Value::Table(entries) = table else {
+        return Err(Error::Config(format!("{} is not a table", join_key_path(path)).into()));
+    };
+    match value {
+        Some(value) => entries.insert(last.clone(), value),
+        None => entries.remove(last),
+    };
+    Ok(())
+}
+
+fn join_key_path(path: &[String]) -> String {
+    path.iter()
+        .map(|key| {
+            let bare = !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+            if bare {
+                key.clone()
+            } else {
+                format!("\"{}\"", key.replace('\\', "\\\\").replace('"', "\\\""))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+fn split_key_path(path: &str) -> Result<Vec<String>> {
+    let invalid = || Error::Config(format!("Invalid setting path: {}", path).into());
+    let mut keys = Vec::new();
+    let mut chars = path.chars();
+
+    loop {
+        let mut key = String::new();
+        let mut next = chars.next();
+        if next == Some('"') {
+            loop {
+                match chars.next().ok_or_else(invalid)? {
+                    '"' => break,
+                    '\\' => key.push(chars.next().ok_or_else(invalid)?),
+                    c => key.push(c),
+                }
+            }
+            next = chars.next();
+        } else {
+            while let Some(c) = next.filter(|&c| c != '.') {
+                key.push(c);
+                next = chars.next();
+            }
+            if key.is_empty() {
+                return Err(invalid());
+            }
+        }
+
+        keys.push(key);
+        match next {
+            None => return Ok(keys),
+            Some('.') => {}
+            Some(_) => return Err(invalid()),
+        }
+    }
+}
+
+// "toggle_hidden" -> "Toggle hidden"
+fn shortcut_label(field: &str) -> String {
+    let words = field.replace('_', " ");
+    let mut chars = words.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_json_round_trip() {
+        let mut config = Config::default();
+        config.ui.show_hidden = true;
+        config.keyboard.new_tab = "Ctrl+Shift+T".to_string();
+        config.plugins.settings.insert(
+            "git-overlay".to_string(),
+            HashMap::from([("depth".to_string(), toml::Value::Integer(3))]),
+        );
+
+        let json = config.export_json().unwrap();
+        let imported = Config::import_json(&json).unwrap();
+
+        assert_eq!(
+            toml::to_string(&imported).unwrap(),
+            toml::to_string(&config).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_diff_lists_changed_settings() {
+        let old = Config::default();
+        assert!(old.diff(&old).is_empty());
+
+        let mut new = old.clone();
+        new.ui.show_hidden = true;
+        new.performance.cache_size_mb = 256;
+        new.performance.worker_threads = Some(3);
+        new.keyboard.new_tab = "Ctrl+Shift+T".to_string();
+        new.plugins.settings.insert(
+            "git.overlay".to_string(),
+            HashMap::from([("depth".to_string(), toml::Value::Integer(3))]),
+        );
+
+        let diffs = old.diff(&new);
+        let paths: Vec<&str> = diffs.iter().map(|d| d.field_path.as_str()).collect();
+        assert_eq!(paths, vec![
+            "keyboard.new_tab",
+            "performance.cache_size_mb",
+            "performance.worker_threads",
+            "plugins.settings.\"git.overlay\"",
+            "ui.show_hidden",
+        ]);
+        assert_eq!(diffs[1].old_value, Some(toml::Value::Integer(128)));
+        assert_eq!(diffs[1].new_value, Some(toml::Value::Integer(256)));
+        assert_eq!(diffs[2].old_value, None);
+        assert_eq!(diffs[4].new_value, Some(toml::Value::Boolean(true)));
+
+        // A later change to a nested plugin setting is reported on its own.
+        let mut newer = new.clone();
+        newer.plugins.settings.get_mut("git.overlay").unwrap().insert("depth".to_string(), toml::Value::Integer(5));
+        let diffs = new.diff(&newer);
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].field_path, "plugins.settings.\"git.overlay\".depth");
+    }
+
+    #[test]
+    fn test_diff_skips_sections_that_are_not_toml() {
+        use std::os::unix::ffi::OsStringExt;
+
+        let old = Config::default();
+        let mut new = old.clone();
+        new.ui.show_hidden = true;
+        new.security.protected_paths.push(PathBuf::from(std::ffi::OsString::from_vec(b"/srv/\xff".to_vec())));
+
+        let diffs = old.diff(&new);
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].field_path, "ui.show_hidden");
+    }
+
+    #[test]
+    fn test_diff_sections_match_serialized_tables() {
+        let config = Config::default();
+        let serialized = toml::Value::try_from(&config).unwrap();
+        let tables: Vec<&str> = serialized.as_table().unwrap().keys().map(String::as_str).collect();
+        let sections: Vec<&str> = config.sections().iter().map(|(name, _)| *name).collect();
+
+        assert_eq!(sections, tables);
+    }
+
+    #[test]
+    fn test_apply_diff_reverts_changes() {
+        let old = Config::default();
+        let mut new = old.clone();
+        new.ui.show_hidden = true;
+        new.performance.worker_threads = Some(3);
+        new.security.protected_paths.push(PathBuf::from("/srv"));
+        new.plugins.settings.insert(
+            "git.overlay".to_string(),
+            HashMap::from([("depth".to_string(), toml::Value::Integer(3))]),
+        );
+
+        new.apply_diff(&old.diff(&new)).unwrap();
+        assert!(old.diff(&new).is_empty());
+
+        let invalid = ConfigDiff {
+            field_path: "ui.show_hidden".to_string(),
+            old_value: Some(toml::Value::String("yes".to_string())),
+            new_value: Some(toml::Value::Boolean(false)),
+        };
+        assert!(new.apply_diff(&[invalid]).is_err());
+        assert!(!new.ui.show_hidden);
+
+        let path = vec!["plugins".to_string(), "a.b \"c\"\\".to_string(), String::new()];
+        assert_eq!(split_key_path(&join_key_path(&path)).unwrap(), path);
+        assert!(split_key_path("ui..show_hidden").is_err());
+        assert!(split_key_path("\"unterminated").is_err());
+    }
+
+    #[test]
+    fn test_import_ignores_unknown_fields() {
+        let mut value: serde_json::Value = serde_json::from_str(&Config::default().export_json().unwrap()).unwrap();
+        value["ui"]["future_option"] = serde_json::Value::Bool(true);
+        value["unknown_section"] = serde_json::json!({ "key": "value" });
+
+        let imported = Config::import_json(&value.to_string()).unwrap();
+        assert_eq!(imported.ui.icon_size, 24);
+    }
+
+    #[test]
+    fn test_protected_paths_default_and_override() {
+        let mut value: serde_json::Value = serde_json::from_str(&Config::default().export_json().unwrap()).unwrap();
+        value.as_object_mut().unwrap().remove("security");
+        let imported = Config::import_json(&value.to_string()).unwrap();
+        assert!(imported.security.protected_paths.contains(&PathBuf::from("/etc")));
+
+        value["security"] = serde_json::json!({ "protected_paths": ["/nix/store"] });
+        let imported = Config::import_json(&value.to_string()).unwrap();
+        assert_eq!(imported.security.protected_paths, vec![PathBuf::from("/nix/store")]);
+    }
+
+    #[test]
+    fn test_load_recovers_truncated_config_from_backup() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("cheese").join("cheese.toml");
+
+        // First load writes defaults; the next one backs them up.
+        Config::load_from(&path).unwrap();
+        let mut config = Config::default();
+        config.ui.icon_size = 48;
+        config.save_to(&path).unwrap();
+        Config::load_from(&path).unwrap();
+        let saved = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(backup_path(&path)).unwrap(), saved);
+
+        let truncated = &saved[..saved.len() / 2];
+        std::fs::write(&path, truncated).unwrap();
+
+        let recovered = Config::load_from(&path).unwrap();
+        assert_eq!(recovered.ui.icon_size, 48);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), saved);
+        assert_eq!(std::fs::read_to_string(path.with_file_name("cheese.toml.corrupt")).unwrap(), truncated);
+    }
+
+    #[test]
+    fn test_load_corrupt_config_without_backup_fails() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("cheese.toml");
+        std::fs::write(&path, "[ui\nshow_hidden = ").unwrap();
+
+        assert!(matches!(Config::load_from(&path), Err(Error::Config(_))));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "[ui\nshow_hidden = ");
+    }
+
+    #[test]
+    fn test_keyboard_shortcut_map() {
+        let mut config = Config::default();
+        config.keyboard.trash = "Ctrl+Backspace".to_string();
+
+        let map = keyboard_shortcut_map(&config.keyboard);
+        let mut fields: Vec<&str> = map.keys().map(String::as_str).collect();
+        fields.sort_unstable();
+        let serialized = toml::Value::try_from(&config.keyboard).unwrap();
+        // Every string setting is a shortcut; `toml` tables iterate in key order.
+        let string_fields: Vec<&str> = serialized
+            .as_table()
+            .unwrap()
+            .iter()
+            .filter(|(_, value)| value.is_str())
+            .map(|(field, _)| field.as_str())
+            .collect();
+        assert_eq!(fields, string_fields);
+        assert_eq!(map["new_tab"], "Ctrl+T");
+        assert_eq!(map["trash"], "Ctrl+Backspace");
+        assert!(!map.contains_key("vim_mode"));
+    }
+
+    #[test]
+    fn test_keyboard_shortcuts_labels() {
+        let shortcuts = Config::default().keyboard_shortcuts();
+        assert_eq!(shortcuts.len(), 7);
+        assert_eq!(shortcuts[0], ("Command palette".to_string(), "Ctrl+P".to_string()));
+        assert!(shortcuts.contains(&("Toggle hidden".to_string(), "Ctrl+H".to_string())));
+    }
+
+    #[test]
+    fn test_export_section() {
+        let config = Config::default();
+        let json = Config::export_json_section(&config.keyboard).unwrap();
+
+        let keyboard: KeyboardConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(keyboard.command_palette, "Ctrl+P");
+        assert!(!json.contains("icon_size"));
+    }
 }
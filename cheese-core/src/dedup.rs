@@ -0,0 +1,139 @@
+use crate::{Error, Result};
+use crate::fs::scanner::{ScanResult, Scanner};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+const HASH_CHUNK_SIZE: usize = 64 * 1024;
+
+pub async fn find_duplicates(root: &Path, cancel: CancellationToken) -> Result<Vec<Vec<PathBuf>>> {
+    let by_size = group_by_size(root, cancel.clone()).await?;
+
+    let candidates: Vec<Vec<PathBuf>> = by_size
+        .into_values()
+        .filter(|paths| paths.len() > 1)
+        .collect();
+
+    tokio::task::spawn_blocking(move || confirm_duplicates(candidates, &cancel))
+        .await
+        .map_err(|e| Error::InvalidOperation(format!("Duplicate search failed: {}", e)))?
+}
+
+async fn group_by_size(root: &Path, cancel: CancellationToken) -> Result<HashMap<u64, Vec<PathBuf>>> {
+    let (sender, mut receiver) = mpsc::channel::<ScanResult>(16);
+    let root = root.to_path_buf();
+    let scan_cancel = cancel.clone();
+
+    let scan = tokio::spawn(async move {
+        Scanner::default().scan_recursive(root, sender, scan_cancel).await
+    });
+
+    let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+
+    while let Some(batch) = receiver.recv().await {
+        for entry in batch.entries {
+            if entry.is_dir || entry.is_symlink || entry.size == 0 {
+                continue;
+            }
+            by_size.entry(entry.size).or_default().push(entry.path);
+        }
+    }
+
+    scan.await
+        .map_err(|e| Error::InvalidOperation(format!("Duplicate search failed: {}", e)))??;
+
+    if cancel.is_cancelled() {
+        return Err(Error::Cancelled);
+    }
+
+    Ok(by_size)
+}
+
+fn confirm_duplicates(candidates: Vec<Vec<PathBuf>>, cancel: &CancellationToken) -> Result<Vec<Vec<PathBuf>>> {
+    let mut groups = Vec::new();
+
+    for paths in candidates {
+        let mut by_hash: HashMap<blake3::Hash, Vec<PathBuf>> = HashMap::new();
+
+        for path in paths {
+            match hash_file(&path, cancel) {
+                Ok(hash) => by_hash.entry(hash).or_default().push(path),
+                Err(Error::Cancelled) => return Err(Error::Cancelled),
+                Err(e) => tracing::warn!("Failed to hash {}: {}", path.display(), e),
+            }
+        }
+
+        groups.extend(by_hash.into_values().filter(|group| group.len() > 1).map(|mut group| {
+            group.sort();
+            group
+        }));
+    }
+
+    groups.sort();
+    Ok(groups)
+}
+
+pub(crate) fn hash_file(path: &Path, cancel: &CancellationToken) -> Result<blake3::Hash> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = blake3::Hasher::new();
+    let mut buffer = vec![0u8; HASH_CHUNK_SIZE];
+
+    loop {
+        if cancel.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+
+        let n = file.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+
+    Ok(hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_find_duplicates() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        std::fs::create_dir(root.join("nested")).unwrap();
+
+        std::fs::write(root.join("a.txt"), b"same contents").unwrap();
+        std::fs::write(root.join("nested/b.txt"), b"same contents").unwrap();
+        std::fs::write(root.join("c.txt"), b"same contentz").unwrap();
+        std::fs::write(root.join("d.txt"), b"other").unwrap();
+        std::fs::write(root.join("e.txt"), b"other").unwrap();
+        std::fs::write(root.join("f.txt"), b"unique file").unwrap();
+        std::fs::write(root.join(".hidden"), b"same contents").unwrap();
+        std::os::unix::fs::symlink(root.join("a.txt"), root.join("link.txt")).unwrap();
+
+        let groups = find_duplicates(root, CancellationToken::new()).await.unwrap();
+
+        assert_eq!(groups, vec![
+            vec![root.join("a.txt"), root.join("nested/b.txt")],
+            vec![root.join("d.txt"), root.join("e.txt")],
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_find_duplicates_cancelled() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("a"), b"x").unwrap();
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+
+        assert!(matches!(
+            find_duplicates(temp_dir.path(), cancel).await,
+            Err(Error::Cancelled)
+        ));
+    }
+}
@@ -3,6 +3,8 @@ use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("IO error: {0}")]
@@ -30,37 +32,43 @@ pub enum Error {
     Timeout(String),
 
     #[error("Configuration error: {0}")]
-    Config(String),
+    Config(#[source] BoxError),
 
     #[error("SELinux context error: {0}")]
     SelinuxContext(String),
 
+    #[error("AppArmor error: {0}")]
+    AppArmor(String),
+
     #[error("Polkit authorization failed: {0}")]
     PolkitDenied(String),
 
     #[error("Trash operation failed: {0}")]
-    TrashError(String),
+    TrashError(#[source] BoxError),
 
     #[error("Mount operation failed: {0}")]
-    MountError(String),
+    MountError(#[source] BoxError),
+
+    #[error("Archive error: {0}")]
+    Archive(String),
 
     #[error("Plugin error: {0}")]
-    Plugin(String),
+    Plugin(#[source] BoxError),
 
     #[error("Cache error: {0}")]
-    Cache(String),
+    Cache(#[source] BoxError),
 
     #[error("Watcher error: {0}")]
-    Watcher(String),
+    Watcher(#[source] BoxError),
 
     #[error("D-Bus error: {0}")]
-    DBus(String),
+    DBus(#[source] BoxError),
 
     #[error("Invalid operation: {0}")]
     InvalidOperation(String),
 
     #[error("Runtime error: {0}")]
-    Runtime(String),
+    Runtime(#[source] BoxError),
 }
 
 impl From<tokio::io::Error> for Error {
@@ -71,18 +79,103 @@ impl From<tokio::io::Error> for Error {
 
 impl From<notify::Error> for Error {
     fn from(err: notify::Error) -> Self {
-        Error::Watcher(err.to_string())
+        Error::Watcher(Box::new(err))
     }
 }
 
 impl From<toml::de::Error> for Error {
     fn from(err: toml::de::Error) -> Self {
-        Error::Config(err.to_string())
+        Error::Config(Box::new(err))
+    }
+}
+
+impl From<toml::ser::Error> for Error {
+    fn from(err: toml::ser::Error) -> Self {
+        Error::Config(Box::new(err))
     }
 }
 
 impl From<zbus::Error> for Error {
     fn from(err: zbus::Error) -> Self {
-        Error::DBus(err.to_string())
+        Error::DBus(Box::new(err))
+    }
+}
+
+impl From<zbus::fdo::Error> for Error {
+    fn from(err: zbus::fdo::Error) -> Self {
+        Error::DBus(Box::new(err))
+    }
+}
+
+impl From<tokio::task::JoinError> for Error {
+    fn from(err: tokio::task::JoinError) -> Self {
+        Error::Runtime(Box::new(err))
+    }
+}
+
+/// Prefixes `source` with `message` while keeping it reachable through
+/// `source()`. Displays as "message: source", the format wrapper variants
+/// used when they only stored a string.
+pub fn context(message: impl Into<String>, source: impl Into<BoxError>) -> BoxError {
+    Box::new(Context {
+        message: message.into(),
+        source: source.into(),
+    })
+}
+
+#[derive(Error, Debug)]
+#[error("{message}: {source}")]
+struct Context {
+    message: String,
+    source: BoxError,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as _;
+
+    fn chain(err: &(dyn std::error::Error + 'static)) -> Vec<String> {
+        let mut messages = Vec::new();
+        let mut current = err.source();
+        while let Some(source) = current {
+            messages.push(source.to_string());
+            current = source.source();
+        }
+        messages
+    }
+
+    #[test]
+    fn test_from_keeps_original_as_source() {
+        let toml_err = toml::from_str::<toml::Value>("key = ").unwrap_err();
+        let expected = toml_err.to_string();
+        let err = Error::from(toml_err);
+
+        assert_eq!(err.to_string(), format!("Configuration error: {}", expected));
+        let source = err.source().unwrap();
+        assert!(source.downcast_ref::<toml::de::Error>().is_some());
+    }
+
+    #[test]
+    fn test_context_chains_to_io_error() {
+        let io = std::io::Error::new(std::io::ErrorKind::NotFound, "no such file");
+        let err = Error::MountError(context("Failed to read /proc/mounts", io));
+
+        assert_eq!(err.to_string(), "Mount operation failed: Failed to read /proc/mounts: no such file");
+        assert_eq!(chain(&err), vec!["Failed to read /proc/mounts: no such file", "no such file"]);
+        let root = err.source().unwrap().source().unwrap();
+        assert_eq!(root.downcast_ref::<std::io::Error>().unwrap().kind(), std::io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_every_wrapper_variant_has_source() {
+        let variants: Vec<fn(BoxError) -> Error> = vec![
+            Error::Config, Error::Plugin, Error::Cache, Error::Watcher,
+            Error::DBus, Error::Runtime, Error::TrashError, Error::MountError,
+        ];
+        for variant in variants {
+            let err = variant("message only".into());
+            assert_eq!(err.source().unwrap().to_string(), "message only");
+        }
     }
 }
@@ -1,8 +1,29 @@
 use crate::{Error, Result};
-use crate::fs::DirEntry;
-use std::path::Path;
-use std::time::SystemTime;
+use crate::error::context;
+use crate::blocking::{self, BlockingPool};
+use crate::fs::{detect_mime, DirEntry};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 use std::collections::HashMap;
+use std::sync::OnceLock;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+
+const CHECKSUM_CHUNK_SIZE: usize = 1024 * 1024;
+const NAME_CACHE_CAPACITY: usize = 1024;
+// Paths per blocking task in AsyncMetadataCollector. Local stats take
+// microseconds, so one task per path would spend most of its time scheduling.
+const COLLECT_CHUNK_SIZE: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+    Md5,
+    Sha256,
+    Xxh3,
+}
 
 #[derive(Debug, Clone)]
 pub struct ExtendedMetadata {
@@ -16,21 +37,33 @@ pub struct ExtendedMetadata {
     pub is_writable: bool,
 }
 
+/// Attribute changes between two snapshots, each as `(old, new)`.
+/// `owner_changed` covers the group as well, formatted `user:group`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MetadataDiff {
+    pub size_changed: Option<(u64, u64)>,
+    pub permissions_changed: Option<(u32, u32)>,
+    pub owner_changed: Option<(String, String)>,
+    pub modified_changed: Option<(SystemTime, SystemTime)>,
+}
+
+impl MetadataDiff {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
 impl ExtendedMetadata {
     pub fn from_path(path: &Path) -> Result<Self> {
         let entry = DirEntry::from_path(path)?;
         let metadata = std::fs::symlink_metadata(path)?;
 
         let (owner, group) = get_owner_group(&metadata);
-        let link_target = if metadata.is_symlink() {
-            std::fs::read_link(path)
-                .ok()
-                .and_then(|p| p.to_str().map(String::from))
-        } else {
-            None
-        };
+        let link_target = entry.symlink_target
+            .as_ref()
+            .and_then(|p| p.to_str().map(String::from));
 
-        let mime_type = entry.mime_type();
+        let mime_type = detect_mime(path);
         let is_executable = is_executable(&metadata);
         let is_readable = is_readable(path);
         let is_writable = is_writable(path);
@@ -47,8 +80,22 @@ impl ExtendedMetadata {
         })
     }
 
-    pub fn format_size(&self) -> String {
-        format_bytes(self.entry.size)
+    pub fn diff(&self, other: &ExtendedMetadata) -> MetadataDiff {
+        MetadataDiff {
+            size_changed: changed(self.entry.size, other.entry.size),
+            permissions_changed: changed(self.entry.permissions, other.entry.permissions),
+            owner_changed: changed(self.owner_and_group(), other.owner_and_group()),
+            modified_changed: changed(self.entry.modified, other.entry.modified),
+        }
+    }
+
+    fn owner_and_group(&self) -> String {
+        format!("{}:{}", self.owner, self.group)
+    }
+
+    /// The size in `format`, normally `UiConfig::byte_format`.
+    pub fn format_size(&self, format: ByteFormat) -> String {
+        format_bytes_with(self.entry.size, format)
     }
 
     pub fn format_permissions(&self) -> String {
@@ -58,6 +105,101 @@ impl ExtendedMetadata {
     pub fn format_modified(&self) -> String {
         format_time(self.entry.modified)
     }
+
+    pub async fn checksum(&self, algorithm: ChecksumAlgorithm) -> Result<String> {
+        if self.entry.is_dir {
+            return Err(Error::InvalidOperation("Cannot checksum a directory".to_string()));
+        }
+
+        let path = self.entry.path.clone();
+        tokio::task::spawn_blocking(move || compute_checksum(&path, algorithm))
+            .await
+            .map_err(|e| Error::Runtime(context("Checksum task failed", e)))?
+    }
+}
+
+enum ChecksumHasher {
+    Md5(md5::Md5),
+    Sha256(Sha256),
+    Xxh3(Box<xxhash_rust::xxh3::Xxh3>),
+}
+
+impl ChecksumHasher {
+    fn new(algorithm: ChecksumAlgorithm) -> Self {
+        match algorithm {
+            ChecksumAlgorithm::Md5 => Self::Md5(md5::Md5::new()),
+            ChecksumAlgorithm::Sha256 => Self::Sha256(Sha256::new()),
+            ChecksumAlgorithm::Xxh3 => Self::Xxh3(Box::new(xxhash_rust::xxh3::Xxh3::new())),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Md5(hasher) => hasher.update(data),
+            Self::Sha256(hasher) => hasher.update(data),
+            Self::Xxh3(hasher) => hasher.update(data),
+        }
+    }
+
+    fn finalize(self) -> String {
+        match self {
+            Self::Md5(hasher) => format!("{:x}", hasher.finalize()),
+            Self::Sha256(hasher) => format!("{:x}", hasher.finalize()),
+            Self::Xxh3(hasher) => format!("{:016x}", hasher.digest()),
+        }
+    }
+}
+
+fn compute_checksum(path: &Path, algorithm: ChecksumAlgorithm) -> Result<String> {
+    use std::io::Read;
+
+    let mut file = std::fs::File::open(path)?;
+    let mut buffer = vec![0u8; CHECKSUM_CHUNK_SIZE];
+    let mut hasher = ChecksumHasher::new(algorithm);
+
+    loop {
+        let n = file.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+
+    Ok(hasher.finalize())
+}
+
+struct NameCache {
+    names: RwLock<HashMap<u32, String>>,
+    capacity: usize,
+}
+
+impl NameCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            names: RwLock::new(HashMap::new()),
+            capacity,
+        }
+    }
+
+    fn resolve(&self, id: u32, lookup: impl FnOnce(u32) -> Option<String>) -> String {
+        if let Some(name) = self.names.read().get(&id) {
+            return name.clone();
+        }
+
+        let name = lookup(id).unwrap_or_else(|| id.to_string());
+
+        let mut names = self.names.write();
+        if names.len() >= self.capacity {
+            names.clear();
+        }
+        names.insert(id, name.clone());
+
+        name
+    }
+}
+
+fn changed<T: PartialEq>(old: T, new: T) -> Option<(T, T)> {
+    (old != new).then_some((old, new))
 }
 
 #[cfg(unix)]
@@ -65,20 +207,20 @@ fn get_owner_group(metadata: &std::fs::Metadata) -> (String, String) {
     use std::os::unix::fs::MetadataExt;
     use nix::unistd::{Uid, Gid, User, Group};
 
-    let uid = Uid::from_raw(metadata.uid());
-    let gid = Gid::from_raw(metadata.gid());
+    static USER_NAMES: OnceLock<NameCache> = OnceLock::new();
+    static GROUP_NAMES: OnceLock<NameCache> = OnceLock::new();
 
-    let owner = User::from_uid(uid)
-        .ok()
-        .flatten()
-        .map(|u| u.name)
-        .unwrap_or_else(|| uid.to_string());
+    let owner = USER_NAMES
+        .get_or_init(|| NameCache::new(NAME_CACHE_CAPACITY))
+        .resolve(metadata.uid(), |uid| {
+            User::from_uid(Uid::from_raw(uid)).ok().flatten().map(|u| u.name)
+        });
 
-    let group = Group::from_gid(gid)
-        .ok()
-        .flatten()
-        .map(|g| g.name)
-        .unwrap_or_else(|| gid.to_string());
+    let group = GROUP_NAMES
+        .get_or_init(|| NameCache::new(NAME_CACHE_CAPACITY))
+        .resolve(metadata.gid(), |gid| {
+            Group::from_gid(Gid::from_raw(gid)).ok().flatten().map(|g| g.name)
+        });
 
     (owner, group)
 }
@@ -99,22 +241,47 @@ fn is_executable(_metadata: &std::fs::Metadata) -> bool {
     false
 }
 
+// access(2) rather than opening the file, which blocks on a FIFO.
 fn is_readable(path: &Path) -> bool {
-    std::fs::File::open(path).is_ok()
+    nix::unistd::access(path, nix::unistd::AccessFlags::R_OK).is_ok()
 }
 
 fn is_writable(path: &Path) -> bool {
-    use std::fs::OpenOptions;
-    OpenOptions::new()
-        .write(true)
-        .append(true)
-        .open(path)
-        .is_ok()
+    nix::unistd::access(path, nix::unistd::AccessFlags::W_OK).is_ok()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ByteFormat {
+    #[default]
+    Iec,
+    Si,
+}
+
+impl ByteFormat {
+    fn base(self) -> f64 {
+        match self {
+            Self::Iec => 1024.0,
+            Self::Si => 1000.0,
+        }
+    }
+
+    fn units(self) -> &'static [&'static str] {
+        match self {
+            Self::Iec => &["B", "KiB", "MiB", "GiB", "TiB", "PiB"],
+            Self::Si => &["B", "KB", "MB", "GB", "TB", "PB"],
+        }
+    }
 }
 
 pub fn format_bytes(bytes: u64) -> String {
-    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB", "PB"];
-    
+    format_bytes_with(bytes, ByteFormat::Iec)
+}
+
+pub fn format_bytes_with(bytes: u64, format: ByteFormat) -> String {
+    let units = format.units();
+    let base = format.base();
+
     if bytes == 0 {
         return "0 B".to_string();
     }
@@ -122,16 +289,22 @@ pub fn format_bytes(bytes: u64) -> String {
     let mut size = bytes as f64;
     let mut unit_index = 0;
 
-    while size >= 1024.0 && unit_index < UNITS.len() - 1 {
-        size /= 1024.0;
+    while size >= base && unit_index < units.len() - 1 {
+        size /= base;
         unit_index += 1;
     }
 
     if unit_index == 0 {
-        format!("{} {}", bytes, UNITS[unit_index])
-    } else {
-        format!("{:.2} {}", size, UNITS[unit_index])
+        return format!("{} {}", bytes, units[0]);
+    }
+
+    // Avoid displaying e.g. "1024.00 KiB" when rounding reaches the next unit.
+    if (size * 100.0).round() / 100.0 >= base && unit_index < units.len() - 1 {
+        size /= base;
+        unit_index += 1;
     }
+
+    format!("{:.2} {}", size, units[unit_index])
 }
 
 pub fn format_permissions(mode: u32) -> String {
@@ -173,23 +346,98 @@ pub fn format_time(time: SystemTime) -> String {
     datetime.format("%Y-%m-%d %H:%M:%S").to_string()
 }
 
+pub fn format_relative_time(time: SystemTime) -> String {
+    format_relative_time_at(time, SystemTime::now())
+}
+
+pub fn format_relative_time_at(time: SystemTime, now: SystemTime) -> String {
+    const MINUTE: u64 = 60;
+    const HOUR: u64 = 60 * MINUTE;
+    const DAY: u64 = 24 * HOUR;
+    const WEEK: u64 = 7 * DAY;
+    const MONTH: u64 = 30 * DAY;
+    const YEAR: u64 = 365 * DAY;
+
+    let secs = match now.duration_since(time) {
+        Ok(elapsed) => elapsed.as_secs(),
+        Err(_) => return "just now".to_string(),
+    };
+
+    match secs {
+        s if s < MINUTE => "just now".to_string(),
+        s if s < HOUR => plural(s / MINUTE, "minute"),
+        s if s < DAY => plural(s / HOUR, "hour"),
+        s if s < 2 * DAY => "yesterday".to_string(),
+        s if s < WEEK => plural(s / DAY, "day"),
+        s if s < MONTH => plural(s / WEEK, "week"),
+        s if s < YEAR => plural(s / MONTH, "month"),
+        s => plural(s / YEAR, "year"),
+    }
+}
+
+pub fn format_display_time(time: SystemTime) -> String {
+    format_display_time_at(time, SystemTime::now())
+}
+
+pub fn format_display_time_at(time: SystemTime, now: SystemTime) -> String {
+    const RELATIVE_LIMIT: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+    match now.duration_since(time) {
+        Ok(elapsed) if elapsed >= RELATIVE_LIMIT => format_time(time),
+        _ => format_relative_time_at(time, now),
+    }
+}
+
+fn plural(count: u64, unit: &str) -> String {
+    if count == 1 {
+        format!("1 {} ago", unit)
+    } else {
+        format!("{} {}s ago", count, unit)
+    }
+}
+
+#[deprecated(note = "use AsyncMetadataCollector::collect_all")]
 pub struct MetadataCollector {
     cache: HashMap<u64, ExtendedMetadata>,
+    blocking_pool: Option<BlockingPool>,
 }
 
+#[allow(deprecated)]
 impl MetadataCollector {
     pub fn new() -> Self {
         Self {
             cache: HashMap::new(),
+            blocking_pool: None,
         }
     }
 
+    pub fn with_blocking_pool(mut self, pool: BlockingPool) -> Self {
+        self.blocking_pool = Some(pool);
+        self
+    }
+
     pub fn collect(&mut self, path: &Path) -> Result<ExtendedMetadata> {
         let metadata = ExtendedMetadata::from_path(path)?;
         self.cache.insert(metadata.entry.inode, metadata.clone());
         Ok(metadata)
     }
 
+    pub async fn collect_many(&mut self, paths: Vec<PathBuf>) -> Result<Vec<ExtendedMetadata>> {
+        let tasks: Vec<_> = paths
+            .into_iter()
+            .map(|path| blocking::spawn_on(self.blocking_pool.as_ref(), move || ExtendedMetadata::from_path(&path)))
+            .collect();
+
+        let mut results = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            let metadata = task.await??;
+            self.cache.insert(metadata.entry.inode, metadata.clone());
+            results.push(metadata);
+        }
+
+        Ok(results)
+    }
+
     pub fn get(&self, inode: u64) -> Option<&ExtendedMetadata> {
         self.cache.get(&inode)
     }
@@ -207,22 +455,174 @@ impl MetadataCollector {
     }
 }
 
+#[allow(deprecated)]
 impl Default for MetadataCollector {
     fn default() -> Self {
         Self::new()
     }
 }
 
+/// Metadata for a batch of paths keyed by inode, gathered in parallel on the
+/// runtime's blocking threads.
+#[derive(Debug, Default)]
+pub struct AsyncMetadataCollector {
+    cache: HashMap<u64, ExtendedMetadata>,
+}
+
+impl AsyncMetadataCollector {
+    /// Fails with the first path whose metadata cannot be read; remaining
+    /// lookups are abandoned.
+    pub async fn collect_all(paths: &[PathBuf], cancel: CancellationToken) -> Result<Self> {
+        let mut tasks = JoinSet::new();
+        for chunk in paths.chunks(COLLECT_CHUNK_SIZE) {
+            let chunk = chunk.to_vec();
+            let cancel = cancel.clone();
+            tasks.spawn_blocking(move || {
+                chunk
+                    .iter()
+                    .take_while(|_| !cancel.is_cancelled())
+                    .map(|path| ExtendedMetadata::from_path(path))
+                    .collect::<Result<Vec<_>>>()
+            });
+        }
+
+        let mut cache = HashMap::with_capacity(paths.len());
+        loop {
+            let joined = tokio::select! {
+                biased;
+                _ = cancel.cancelled() => return Err(Error::Cancelled),
+                joined = tasks.join_next() => joined,
+            };
+            let Some(joined) = joined else {
+                break;
+            };
+
+            for metadata in joined?? {
+                cache.insert(metadata.entry.inode, metadata);
+            }
+        }
+
+        Ok(Self { cache })
+    }
+
+    pub fn get(&self, inode: u64) -> Option<&ExtendedMetadata> {
+        self.cache.get(&inode)
+    }
+
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn snapshot() -> (tempfile::TempDir, ExtendedMetadata) {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("file.txt");
+        std::fs::write(&path, "data").unwrap();
+        let metadata = ExtendedMetadata::from_path(&path).unwrap();
+        (temp_dir, metadata)
+    }
+
+    #[test]
+    fn test_diff_unchanged() {
+        let (_temp_dir, metadata) = snapshot();
+        assert!(metadata.diff(&metadata.clone()).is_empty());
+    }
+
+    #[test]
+    fn test_diff_size() {
+        let (_temp_dir, old) = snapshot();
+        let mut new = old.clone();
+        new.entry.size = 10;
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.size_changed, Some((4, 10)));
+        assert_eq!(diff, MetadataDiff { size_changed: Some((4, 10)), ..Default::default() });
+    }
+
+    #[test]
+    fn test_diff_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (_temp_dir, old) = snapshot();
+        std::fs::set_permissions(&old.entry.path, std::fs::Permissions::from_mode(0o600)).unwrap();
+        let new = ExtendedMetadata::from_path(&old.entry.path).unwrap();
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.permissions_changed, Some((old.entry.permissions, new.entry.permissions)));
+        assert_eq!(new.entry.permissions & 0o777, 0o600);
+        assert!(diff.size_changed.is_none());
+        assert!(diff.owner_changed.is_none());
+    }
+
+    #[test]
+    fn test_diff_owner_and_group() {
+        let (_temp_dir, old) = snapshot();
+        let mut new = old.clone();
+        new.owner = "alice".to_string();
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.owner_changed, Some((
+            format!("{}:{}", old.owner, old.group),
+            format!("alice:{}", old.group),
+        )));
+
+        let mut regrouped = old.clone();
+        regrouped.group = "staff".to_string();
+        assert!(old.diff(&regrouped).owner_changed.is_some());
+    }
+
+    #[test]
+    fn test_diff_modified() {
+        let (_temp_dir, old) = snapshot();
+        let mut new = old.clone();
+        new.entry.modified = old.entry.modified + Duration::from_secs(5);
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.modified_changed, Some((old.entry.modified, new.entry.modified)));
+        assert!(!diff.is_empty());
+    }
+
+    #[test]
+    fn test_format_size_uses_byte_format() {
+        let (_temp_dir, mut metadata) = snapshot();
+        metadata.entry.size = 1_500_000;
+
+        assert_eq!(metadata.format_size(ByteFormat::Iec), "1.43 MiB");
+        assert_eq!(metadata.format_size(ByteFormat::Si), "1.50 MB");
+    }
+
     #[test]
     fn test_format_bytes() {
         assert_eq!(format_bytes(0), "0 B");
-        assert_eq!(format_bytes(1024), "1.00 KB");
-        assert_eq!(format_bytes(1048576), "1.00 MB");
-        assert_eq!(format_bytes(1073741824), "1.00 GB");
+        assert_eq!(format_bytes(1024), "1.00 KiB");
+        assert_eq!(format_bytes(1048576), "1.00 MiB");
+        assert_eq!(format_bytes(1073741824), "1.00 GiB");
+    }
+
+    #[test]
+    fn test_format_bytes_formats() {
+        assert_eq!(format_bytes_with(999, ByteFormat::Iec), "999 B");
+        assert_eq!(format_bytes_with(1000, ByteFormat::Iec), "1000 B");
+        assert_eq!(format_bytes_with(1023, ByteFormat::Iec), "1023 B");
+        assert_eq!(format_bytes_with(1024, ByteFormat::Iec), "1.00 KiB");
+        assert_eq!(format_bytes_with(1536, ByteFormat::Iec), "1.50 KiB");
+        assert_eq!(format_bytes_with(1048575, ByteFormat::Iec), "1.00 MiB");
+
+        assert_eq!(format_bytes_with(999, ByteFormat::Si), "999 B");
+        assert_eq!(format_bytes_with(1000, ByteFormat::Si), "1.00 KB");
+        assert_eq!(format_bytes_with(1024, ByteFormat::Si), "1.02 KB");
+        assert_eq!(format_bytes_with(999_999, ByteFormat::Si), "1.00 MB");
+        assert_eq!(format_bytes_with(1_000_000_000, ByteFormat::Si), "1.00 GB");
+
+        assert_eq!(format_bytes(1000), format_bytes_with(1000, ByteFormat::default()));
     }
 
     #[test]
@@ -237,4 +637,193 @@ mod tests {
         let result = ExtendedMetadata::from_path(Path::new("/tmp"));
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_extended_metadata_of_fifo() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let fifo = temp_dir.path().join("pipe");
+        nix::unistd::mkfifo(&fifo, nix::sys::stat::Mode::S_IRUSR | nix::sys::stat::Mode::S_IWUSR).unwrap();
+
+        // Returns without waiting for the other end of the pipe.
+        let metadata = ExtendedMetadata::from_path(&fifo).unwrap();
+        assert_eq!(metadata.mime_type, "inode/fifo");
+        assert!(metadata.is_readable && metadata.is_writable);
+    }
+
+    #[tokio::test]
+    async fn test_checksum_known_values() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("abc.txt");
+        std::fs::write(&file_path, b"abc").unwrap();
+
+        let metadata = ExtendedMetadata::from_path(&file_path).unwrap();
+
+        assert_eq!(
+            metadata.checksum(ChecksumAlgorithm::Md5).await.unwrap(),
+            "900150983cd24fb0d6963f7d28e17f72"
+        );
+        assert_eq!(
+            metadata.checksum(ChecksumAlgorithm::Sha256).await.unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            metadata.checksum(ChecksumAlgorithm::Xxh3).await.unwrap(),
+            "78af5f94892f3950"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_checksum_directory() {
+        let metadata = ExtendedMetadata::from_path(Path::new("/tmp")).unwrap();
+        let result = metadata.checksum(ChecksumAlgorithm::Sha256).await;
+        assert!(matches!(result, Err(Error::InvalidOperation(_))));
+    }
+
+    #[test]
+    fn test_format_relative_time() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let ago = |secs: u64| now - Duration::from_secs(secs);
+
+        assert_eq!(format_relative_time_at(ago(0), now), "just now");
+        assert_eq!(format_relative_time_at(ago(59), now), "just now");
+        assert_eq!(format_relative_time_at(ago(60), now), "1 minute ago");
+        assert_eq!(format_relative_time_at(ago(5 * 60), now), "5 minutes ago");
+        assert_eq!(format_relative_time_at(ago(3 * 3600), now), "3 hours ago");
+        assert_eq!(format_relative_time_at(ago(30 * 3600), now), "yesterday");
+        assert_eq!(format_relative_time_at(ago(4 * 86400), now), "4 days ago");
+        assert_eq!(format_relative_time_at(ago(21 * 86400), now), "3 weeks ago");
+        assert_eq!(format_relative_time_at(ago(90 * 86400), now), "3 months ago");
+        assert_eq!(format_relative_time_at(ago(800 * 86400), now), "2 years ago");
+        assert_eq!(format_relative_time_at(now + Duration::from_secs(30), now), "just now");
+    }
+
+    #[test]
+    fn test_format_display_time() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let recent = now - Duration::from_secs(2 * 3600);
+        let old = now - Duration::from_secs(30 * 86400);
+
+        assert_eq!(format_display_time_at(recent, now), "2 hours ago");
+        assert_eq!(format_display_time_at(old, now), format_time(old));
+    }
+
+    fn write_files(dir: &Path, count: usize) -> Vec<PathBuf> {
+        (0..count)
+            .map(|i| {
+                let path = dir.join(format!("file{}.txt", i));
+                std::fs::write(&path, vec![b'x'; i * 10]).unwrap();
+                path
+            })
+            .collect()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_collect_all_by_inode() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let paths = write_files(temp_dir.path(), 200);
+
+        let collector = AsyncMetadataCollector::collect_all(&paths, CancellationToken::new()).await.unwrap();
+
+        assert_eq!(collector.len(), 200);
+        for (i, path) in paths.iter().enumerate() {
+            let inode = DirEntry::from_path(path).unwrap().inode;
+            let metadata = collector.get(inode).unwrap();
+            assert_eq!(&metadata.entry.path, path);
+            assert_eq!(metadata.entry.size, i as u64 * 10);
+        }
+        assert!(collector.get(u64::MAX).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_collect_all_errors_and_cancellation() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut paths = write_files(temp_dir.path(), 4);
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        assert!(matches!(
+            AsyncMetadataCollector::collect_all(&paths, cancel).await,
+            Err(Error::Cancelled)
+        ));
+
+        paths.push(temp_dir.path().join("missing"));
+        assert!(AsyncMetadataCollector::collect_all(&paths, CancellationToken::new()).await.is_err());
+
+        let empty = AsyncMetadataCollector::collect_all(&[], CancellationToken::new()).await.unwrap();
+        assert!(empty.is_empty());
+    }
+
+    // cargo test --lib bench_collect_all -- --ignored --nocapture
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore]
+    async fn bench_collect_all() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let paths = write_files(temp_dir.path(), 5000);
+
+        let start = std::time::Instant::now();
+        for path in &paths {
+            ExtendedMetadata::from_path(path).unwrap();
+        }
+        let sequential = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let collector = AsyncMetadataCollector::collect_all(&paths, CancellationToken::new()).await.unwrap();
+        let concurrent = start.elapsed();
+
+        assert_eq!(collector.len(), paths.len());
+        println!("{} paths: sequential {:?}, collect_all {:?}", paths.len(), sequential, concurrent);
+    }
+
+    #[tokio::test]
+    #[allow(deprecated)]
+    async fn test_collect_many_preserves_order() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let paths: Vec<PathBuf> = (0..8)
+            .map(|i| {
+                let path = temp_dir.path().join(format!("file{}.txt", i));
+                std::fs::write(&path, vec![b'x'; i * 10]).unwrap();
+                path
+            })
+            .collect();
+
+        let mut collector = MetadataCollector::new();
+        let results = collector.collect_many(paths.clone()).await.unwrap();
+
+        let collected: Vec<_> = results.iter().map(|m| m.entry.path.clone()).collect();
+        assert_eq!(collected, paths);
+        assert_eq!(results[3].entry.size, 30);
+        assert_eq!(collector.len(), 8);
+
+        let missing = vec![paths[0].clone(), temp_dir.path().join("missing")];
+        assert!(collector.collect_many(missing).await.is_err());
+    }
+
+    #[test]
+    fn test_name_cache_avoids_repeat_lookups() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let cache = NameCache::new(4);
+        let lookups = AtomicUsize::new(0);
+        let lookup = |id: u32| {
+            lookups.fetch_add(1, Ordering::SeqCst);
+            (id == 1000).then(|| "alice".to_string())
+        };
+
+        assert_eq!(cache.resolve(1000, lookup), "alice");
+        assert_eq!(cache.resolve(1000, lookup), "alice");
+        assert_eq!(lookups.load(Ordering::SeqCst), 1);
+
+        assert_eq!(cache.resolve(4242, lookup), "4242");
+        assert_eq!(cache.resolve(4242, lookup), "4242");
+        assert_eq!(lookups.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_name_cache_capacity() {
+        let cache = NameCache::new(2);
+        for id in 0..5 {
+            cache.resolve(id, |id| Some(format!("user{}", id)));
+        }
+        assert!(cache.names.read().len() <= 2);
+    }
 }
@@ -0,0 +1,159 @@
+use std::fs::FileType;
+use std::io::Read;
+use std::os::unix::fs::FileTypeExt;
+use std::path::Path;
+
+const SNIFF_BYTES: usize = 8192;
+
+pub fn detect_mime(path: &Path) -> String {
+    // Only regular files are sniffed; opening a FIFO would block until a
+    // writer shows up.
+    match std::fs::metadata(path) {
+        Ok(metadata) if !metadata.is_file() => return special_file_mime(metadata.file_type()).to_string(),
+        Ok(_) => {}
+        Err(_) => return guess_from_extension(path),
+    }
+
+    let header = match read_header(path) {
+        Ok(header) => header,
+        Err(_) => return guess_from_extension(path),
+    };
+
+    if let Some(mime) = shebang_mime(&header) {
+        return mime.to_string();
+    }
+
+    if let Some(kind) = infer::get(&header) {
+        return kind.mime_type().to_string();
+    }
+
+    if let Some(mime) = mime_guess::from_path(path).first() {
+        return mime.to_string();
+    }
+
+    if !header.contains(&0) && std::str::from_utf8(&header).is_ok() {
+        return "text/plain".to_string();
+    }
+
+    "application/octet-stream".to_string()
+}
+
+fn special_file_mime(file_type: FileType) -> &'static str {
+    if file_type.is_dir() {
+        "inode/directory"
+    } else if file_type.is_fifo() {
+        "inode/fifo"
+    } else if file_type.is_socket() {
+        "inode/socket"
+    } else if file_type.is_block_device() {
+        "inode/blockdevice"
+    } else if file_type.is_char_device() {
+        "inode/chardevice"
+    } else {
+        "application/octet-stream"
+    }
+}
+
+fn read_header(path: &Path) -> std::io::Result<Vec<u8>> {
+    let file = std::fs::File::open(path)?;
+    let mut header = Vec::with_capacity(SNIFF_BYTES);
+    file.take(SNIFF_BYTES as u64).read_to_end(&mut header)?;
+    Ok(header)
+}
+
+fn guess_from_extension(path: &Path) -> String {
+    mime_guess::from_path(path)
+        .first_or_octet_stream()
+        .to_string()
+}
+
+fn shebang_mime(header: &[u8]) -> Option<&'static str> {
+    let line = header.strip_prefix(b"#!")?;
+    let line = &line[..line.iter().position(|&b| b == b'\n').unwrap_or(line.len())];
+    let line = std::str::from_utf8(line).ok()?;
+
+    let mut words = line.split_whitespace();
+    let mut interpreter = words.next()?.rsplit('/').next()?;
+    if interpreter == "env" {
+        interpreter = words.find(|w| !w.starts_with('-'))?;
+    }
+
+    let mime = match interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.') {
+        "sh" | "bash" | "dash" | "zsh" | "ksh" | "fish" => "application/x-shellscript",
+        "python" => "text/x-python",
+        "perl" => "text/x-perl",
+        "ruby" => "text/x-ruby",
+        "node" | "nodejs" => "application/javascript",
+        "php" => "application/x-php",
+        "lua" => "text/x-lua",
+        _ => "text/x-script",
+    };
+
+    Some(mime)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const PNG_HEADER: &[u8] = &[0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, 0, 0, 0, 0x0d, b'I', b'H', b'D', b'R'];
+
+    #[test]
+    fn test_png_with_wrong_extension() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("picture.txt");
+        std::fs::write(&path, PNG_HEADER).unwrap();
+
+        assert_eq!(detect_mime(&path), "image/png");
+    }
+
+    #[test]
+    fn test_extensionless_elf() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("program");
+        let mut elf = vec![0x7f, b'E', b'L', b'F', 2, 1, 1, 0];
+        elf.resize(64, 0);
+        std::fs::write(&path, elf).unwrap();
+
+        assert_eq!(detect_mime(&path), "application/x-executable");
+    }
+
+    #[test]
+    fn test_shebang_scripts() {
+        let temp_dir = TempDir::new().unwrap();
+
+        let shell = temp_dir.path().join("deploy");
+        std::fs::write(&shell, "#!/bin/bash\necho hi\n").unwrap();
+        assert_eq!(detect_mime(&shell), "application/x-shellscript");
+
+        let python = temp_dir.path().join("tool");
+        std::fs::write(&python, "#!/usr/bin/env -S python3 -u\nprint('hi')\n").unwrap();
+        assert_eq!(detect_mime(&python), "text/x-python");
+    }
+
+    #[test]
+    fn test_extension_fallback() {
+        let temp_dir = TempDir::new().unwrap();
+
+        let css = temp_dir.path().join("style.css");
+        std::fs::write(&css, "body { margin: 0 }").unwrap();
+        assert_eq!(detect_mime(&css), "text/css");
+
+        let notes = temp_dir.path().join("NOTES");
+        std::fs::write(&notes, "plain words").unwrap();
+        assert_eq!(detect_mime(&notes), "text/plain");
+
+        assert_eq!(detect_mime(temp_dir.path()), "inode/directory");
+    }
+
+    #[test]
+    fn test_special_files_not_opened() {
+        let temp_dir = TempDir::new().unwrap();
+        let fifo = temp_dir.path().join("pipe");
+        nix::unistd::mkfifo(&fifo, nix::sys::stat::Mode::S_IRWXU).unwrap();
+
+        assert_eq!(detect_mime(&fifo), "inode/fifo");
+        assert_eq!(detect_mime(Path::new("/dev/null")), "inode/chardevice");
+    }
+}
@@ -0,0 +1,460 @@
+use crate::fs::ops::{
+    BatchJob, BatchProgress, BatchReport, ConflictResolution, ConflictResolutions, CopyOptions, DryRunReport, FileOps,
+    MirrorOptions, MirrorReport, OpFuture, OperationPlan, OperationProgress, OperationReport,
+};
+use crate::security::Security;
+use crate::Result;
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileOpCall {
+    CopyFiles {
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+        conflict: ConflictResolution,
+        options: CopyOptions,
+    },
+    CopyFilesWithResolutions {
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+        resolutions: ConflictResolutions,
+        options: CopyOptions,
+    },
+    CopyFilesReport {
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+        resolutions: ConflictResolutions,
+        options: CopyOptions,
+    },
+    BatchCopy {
+        labels: Vec<String>,
+        conflict: ConflictResolution,
+        options: CopyOptions,
+    },
+    CopyOnWriteClone {
+        src: PathBuf,
+        dest: PathBuf,
+    },
+    CopySparse {
+        src: PathBuf,
+        dest: PathBuf,
+    },
+    MoveFiles {
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+        conflict: ConflictResolution,
+    },
+    MoveFilesWithResolutions {
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+        resolutions: ConflictResolutions,
+    },
+    DeleteFiles {
+        paths: Vec<PathBuf>,
+    },
+    MirrorDirectory {
+        source: PathBuf,
+        dest: PathBuf,
+        options: MirrorOptions,
+    },
+    Symlink {
+        target: PathBuf,
+        link: PathBuf,
+        relative: bool,
+    },
+    Plan {
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+    },
+    DryRunCopy {
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+    },
+    DryRunMove {
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+    },
+    WriteAtomic {
+        path: PathBuf,
+        contents: Vec<u8>,
+    },
+}
+
+// Records every call and answers from queues of pre-programmed results. Once a
+// queue is empty, calls succeed (with an empty report for batch copies and
+// mirrors). Copy reports list every source as succeeded; plans and dry runs
+// are always empty.
+#[derive(Default)]
+pub struct MockFileOps {
+    calls: Mutex<Vec<FileOpCall>>,
+    results: Mutex<VecDeque<Result<()>>>,
+    batch_results: Mutex<VecDeque<Result<BatchReport>>>,
+}
+
+impl MockFileOps {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push_result(&self, result: Result<()>) {
+        self.results.lock().push_back(result);
+    }
+
+    pub fn push_batch_result(&self, result: Result<BatchReport>) {
+        self.batch_results.lock().push_back(result);
+    }
+
+    pub fn calls(&self) -> Vec<FileOpCall> {
+        self.calls.lock().clone()
+    }
+
+    fn record(&self, call: FileOpCall) -> Result<()> {
+        self.calls.lock().push(call);
+        self.results.lock().pop_front().unwrap_or(Ok(()))
+    }
+}
+
+impl FileOps for MockFileOps {
+    fn copy_files(
+        &self,
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+        conflict: ConflictResolution,
+        options: CopyOptions,
+        _progress: mpsc::Sender<OperationProgress>,
+        _cancel: CancellationToken,
+    ) -> OpFuture<'_, ()> {
+        let result = self.record(FileOpCall::CopyFiles { sources, dest_dir, conflict, options });
+        Box::pin(async move { result })
+    }
+
+    fn copy_files_with_resolutions<'a>(
+        &'a self,
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+        resolutions: &'a ConflictResolutions,
+        options: CopyOptions,
+        _progress: mpsc::Sender<OperationProgress>,
+        _cancel: CancellationToken,
+    ) -> OpFuture<'a, ()> {
+        let result = self.record(FileOpCall::CopyFilesWithResolutions {
+            sources,
+            dest_dir,
+            resolutions: resolutions.clone(),
+            options,
+        });
+        Box::pin(async move { result })
+    }
+
+    fn copy_files_report<'a>(
+        &'a self,
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+        resolutions: &'a ConflictResolutions,
+        options: CopyOptions,
+        _progress: mpsc::Sender<OperationProgress>,
+        _cancel: CancellationToken,
+    ) -> OpFuture<'a, OperationReport> {
+        let result = self.record(FileOpCall::CopyFilesReport {
+            sources: sources.clone(),
+            dest_dir,
+            resolutions: resolutions.clone(),
+            options,
+        });
+        Box::pin(async move {
+            result.map(|()| OperationReport { succeeded: sources, ..OperationReport::default() })
+        })
+    }
+
+    fn batch_copy(
+        &self,
+        jobs: Vec<BatchJob>,
+        conflict: ConflictResolution,
+        options: CopyOptions,
+        _progress: mpsc::Sender<BatchProgress>,
+        _cancel: CancellationToken,
+    ) -> OpFuture<'_, BatchReport> {
+        let labels = jobs.into_iter().map(|job| job.label).collect();
+        self.calls.lock().push(FileOpCall::BatchCopy { labels, conflict, options });
+        let result = self.batch_results.lock().pop_front().unwrap_or_else(|| Ok(BatchReport::default()));
+        Box::pin(async move { result })
+    }
+
+    #[cfg(target_os = "linux")]
+    fn copy_on_write_clone<'a>(&'a self, src: &'a Path, dest: &'a Path) -> OpFuture<'a, ()> {
+        let result = self.record(FileOpCall::CopyOnWriteClone {
+            src: src.to_path_buf(),
+            dest: dest.to_path_buf(),
+        });
+        Box::pin(async move { result })
+    }
+
+    fn copy_sparse<'a>(
+        &'a self,
+        src: &'a Path,
+        dest: &'a Path,
+        _progress: mpsc::Sender<OperationProgress>,
+        _cancel: CancellationToken,
+    ) -> OpFuture<'a, ()> {
+        let result = self.record(FileOpCall::CopySparse {
+            src: src.to_path_buf(),
+            dest: dest.to_path_buf(),
+        });
+        Box::pin(async move { result })
+    }
+
+    fn move_files(
+        &self,
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+        conflict: ConflictResolution,
+        _progress: mpsc::Sender<OperationProgress>,
+        _cancel: CancellationToken,
+    ) -> OpFuture<'_, ()> {
+        let result = self.record(FileOpCall::MoveFiles { sources, dest_dir, conflict });
+        Box::pin(async move { result })
+    }
+
+    fn move_files_with_resolutions<'a>(
+        &'a self,
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+        resolutions: &'a ConflictResolutions,
+        _progress: mpsc::Sender<OperationProgress>,
+        _cancel: CancellationToken,
+    ) -> OpFuture<'a, ()> {
+        let result = self.record(FileOpCall::MoveFilesWithResolutions {
+            sources,
+            dest_dir,
+            resolutions: resolutions.clone(),
+        });
+        Box::pin(async move { result })
+    }
+
+    fn delete_files<'a>(
+        &'a self,
+        paths: Vec<PathBuf>,
+        _security: Option<&'a Security>,
+        _progress: mpsc::Sender<OperationProgress>,
+        _cancel: CancellationToken,
+    ) -> OpFuture<'a, ()> {
+        let result = self.record(FileOpCall::DeleteFiles { paths });
+        Box::pin(async move { result })
+    }
+
+    fn mirror_directory<'a>(
+        &'a self,
+        source: &'a Path,
+        dest: &'a Path,
+        options: MirrorOptions,
+        _progress: mpsc::Sender<OperationProgress>,
+        _cancel: CancellationToken,
+    ) -> OpFuture<'a, MirrorReport> {
+        let result = self.record(FileOpCall::MirrorDirectory {
+            source: source.to_path_buf(),
+            dest: dest.to_path_buf(),
+            options,
+        });
+        Box::pin(async move { result.map(|()| MirrorReport::default()) })
+    }
+
+    fn symlink<'a>(&'a self, target: &'a Path, link: &'a Path, relative: bool) -> OpFuture<'a, ()> {
+        let result = self.record(FileOpCall::Symlink {
+            target: target.to_path_buf(),
+            link: link.to_path_buf(),
+            relative,
+        });
+        Box::pin(async move { result })
+    }
+
+    fn plan<'a>(&'a self, sources: &'a [PathBuf], dest_dir: &'a Path) -> OpFuture<'a, OperationPlan> {
+        let result = self.record(FileOpCall::Plan {
+            sources: sources.to_vec(),
+            dest_dir: dest_dir.to_path_buf(),
+        });
+        Box::pin(async move { result.map(|()| OperationPlan::default()) })
+    }
+
+    fn dry_run_copy<'a>(&'a self, sources: &'a [PathBuf], dest_dir: &'a Path) -> OpFuture<'a, DryRunReport> {
+        let result = self.record(FileOpCall::DryRunCopy {
+            sources: sources.to_vec(),
+            dest_dir: dest_dir.to_path_buf(),
+        });
+        Box::pin(async move { result.map(|()| DryRunReport::default()) })
+    }
+
+    fn dry_run_move<'a>(&'a self, sources: &'a [PathBuf], dest_dir: &'a Path) -> OpFuture<'a, DryRunReport> {
+        let result = self.record(FileOpCall::DryRunMove {
+            sources: sources.to_vec(),
+            dest_dir: dest_dir.to_path_buf(),
+        });
+        Box::pin(async move { result.map(|()| DryRunReport::default()) })
+    }
+
+    fn write_atomic<'a>(&'a self, path: &'a Path, contents: Vec<u8>) -> OpFuture<'a, ()> {
+        let result = self.record(FileOpCall::WriteAtomic { path: path.to_path_buf(), contents });
+        Box::pin(async move { result })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+    use std::sync::Arc;
+
+    fn channel<T>() -> mpsc::Sender<T> {
+        mpsc::channel(1).0
+    }
+
+    // Stands in for state logic that only sees the trait object.
+    async fn paste(ops: &dyn FileOps, sources: Vec<PathBuf>, dest: &Path) -> Result<()> {
+        ops.copy_files(
+            sources,
+            dest.to_path_buf(),
+            ConflictResolution::Rename,
+            CopyOptions::default(),
+            channel(),
+            CancellationToken::new(),
+        ).await
+    }
+
+    #[tokio::test]
+    async fn test_records_calls_in_order() {
+        let mock = Arc::new(MockFileOps::new());
+        let ops: Arc<dyn FileOps> = mock.clone();
+
+        paste(ops.as_ref(), vec![PathBuf::from("/a"), PathBuf::from("/b")], Path::new("/dest")).await.unwrap();
+        ops.move_files(
+            vec![PathBuf::from("/c")],
+            PathBuf::from("/elsewhere"),
+            ConflictResolution::Skip,
+            channel(),
+            CancellationToken::new(),
+        ).await.unwrap();
+        ops.delete_files(vec![PathBuf::from("/d")], None, channel(), CancellationToken::new()).await.unwrap();
+        ops.copy_sparse(Path::new("/disk.img"), Path::new("/copy.img"), channel(), CancellationToken::new())
+            .await
+            .unwrap();
+        let mirror = MirrorOptions { delete_extra: true, ..MirrorOptions::default() };
+        ops.mirror_directory(Path::new("/photos"), Path::new("/backup"), mirror, channel(), CancellationToken::new())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            mock.calls(),
+            vec![
+                FileOpCall::CopyFiles {
+                    sources: vec![PathBuf::from("/a"), PathBuf::from("/b")],
+                    dest_dir: PathBuf::from("/dest"),
+                    conflict: ConflictResolution::Rename,
+                    options: CopyOptions::default(),
+                },
+                FileOpCall::MoveFiles {
+                    sources: vec![PathBuf::from("/c")],
+                    dest_dir: PathBuf::from("/elsewhere"),
+                    conflict: ConflictResolution::Skip,
+                },
+                FileOpCall::DeleteFiles { paths: vec![PathBuf::from("/d")] },
+                FileOpCall::CopySparse {
+                    src: PathBuf::from("/disk.img"),
+                    dest: PathBuf::from("/copy.img"),
+                },
+                FileOpCall::MirrorDirectory {
+                    source: PathBuf::from("/photos"),
+                    dest: PathBuf::from("/backup"),
+                    options: mirror,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_records_look_ahead_and_write_calls() {
+        let mock = MockFileOps::new();
+        let sources = vec![PathBuf::from("/a")];
+        let dest = Path::new("/dest");
+
+        assert!(mock.plan(&sources, dest).await.unwrap().conflicts.is_empty());
+        assert!(mock.dry_run_copy(&sources, dest).await.unwrap().would_create.is_empty());
+        mock.dry_run_move(&sources, dest).await.unwrap();
+        mock.write_atomic(Path::new("/dest/state.json"), b"{}".to_vec()).await.unwrap();
+
+        assert_eq!(mock.calls(), vec![
+            FileOpCall::Plan { sources: sources.clone(), dest_dir: dest.to_path_buf() },
+            FileOpCall::DryRunCopy { sources: sources.clone(), dest_dir: dest.to_path_buf() },
+            FileOpCall::DryRunMove { sources, dest_dir: dest.to_path_buf() },
+            FileOpCall::WriteAtomic { path: PathBuf::from("/dest/state.json"), contents: b"{}".to_vec() },
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_injected_errors_are_returned_in_order() {
+        let mock = MockFileOps::new();
+        mock.push_result(Err(Error::PermissionDenied { path: PathBuf::from("/root") }));
+        mock.push_result(Err(Error::Cancelled));
+
+        let first = paste(&mock, vec![PathBuf::from("/x")], Path::new("/root")).await;
+        let second = mock.delete_files(vec![PathBuf::from("/y")], None, channel(), CancellationToken::new()).await;
+        let third = paste(&mock, vec![PathBuf::from("/z")], Path::new("/tmp")).await;
+
+        assert!(matches!(first, Err(Error::PermissionDenied { .. })));
+        assert!(matches!(second, Err(Error::Cancelled)));
+        assert!(third.is_ok());
+        assert_eq!(mock.calls().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_batch_copy_results() {
+        let mock = MockFileOps::new();
+        mock.push_batch_result(Ok(BatchReport {
+            completed: vec!["photos".to_string()],
+            failed: vec![("music".to_string(), Error::NotFound { path: PathBuf::from("/music") })],
+        }));
+
+        let jobs = ["photos", "music"]
+            .iter()
+            .map(|label| BatchJob {
+                sources: vec![],
+                dest_dir: PathBuf::from("/backup"),
+                label: label.to_string(),
+            })
+            .collect();
+        let options = CopyOptions { preserve_xattrs: true, ..CopyOptions::default() };
+
+        let report = mock.batch_copy(jobs, ConflictResolution::Skip, options.clone(), channel(), CancellationToken::new()).await.unwrap();
+        assert_eq!(report.completed, vec!["photos"]);
+        assert_eq!(report.failed[0].0, "music");
+
+        let empty = mock.batch_copy(vec![], ConflictResolution::Skip, options.clone(), channel(), CancellationToken::new()).await.unwrap();
+        assert!(empty.completed.is_empty() && empty.failed.is_empty());
+
+        assert_eq!(
+            mock.calls()[0],
+            FileOpCall::BatchCopy {
+                labels: vec!["photos".to_string(), "music".to_string()],
+                conflict: ConflictResolution::Skip,
+                options,
+            }
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_clone_is_recorded() {
+        let mock = MockFileOps::new();
+        mock.push_result(Err(Error::InvalidOperation("no reflink".to_string())));
+
+        let result = mock.copy_on_write_clone(Path::new("/src"), Path::new("/dst")).await;
+
+        assert!(matches!(result, Err(Error::InvalidOperation(_))));
+        assert_eq!(
+            mock.calls(),
+            vec![FileOpCall::CopyOnWriteClone { src: PathBuf::from("/src"), dest: PathBuf::from("/dst") }]
+        );
+    }
+}
@@ -2,8 +2,18 @@ pub mod scanner;
 pub mod metadata;
 pub mod watcher;
 pub mod ops;
+pub mod natural_sort;
+pub mod usage;
+pub mod mime;
+pub mod operation_manager;
+#[cfg(test)]
+pub mod mock_ops;
+
+pub use usage::{disk_usage, UsageNode};
+pub use mime::detect_mime;
 
 use crate::{Error, Result};
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 use serde::{Deserialize, Serialize};
@@ -16,31 +26,88 @@ pub struct DirEntry {
     pub modified: SystemTime,
     pub is_dir: bool,
     pub is_symlink: bool,
+    pub entry_type: EntryType,
     pub permissions: u32,
     pub inode: u64,
+    #[serde(default)]
+    pub symlink_target: Option<PathBuf>,
+    #[serde(default)]
+    pub symlink_is_broken: bool,
+    #[serde(default)]
+    pub target_type: Option<EntryType>,
 }
 
 impl DirEntry {
     pub fn from_path(path: &Path) -> Result<Self> {
         let metadata = std::fs::symlink_metadata(path)?;
+
+        let (symlink_target, target_metadata) = if metadata.file_type().is_symlink() {
+            (std::fs::read_link(path).ok(), std::fs::metadata(path).ok())
+        } else {
+            (None, None)
+        };
+
+        Self::from_parts(path, &metadata, symlink_target, target_metadata)
+    }
+
+    /// Async counterpart of [`DirEntry::from_path`]. The stat calls run on the
+    /// blocking pool, trading a thread handoff per entry for not stalling
+    /// runtime workers on slow filesystems.
+    pub async fn from_path_async(path: &Path) -> Result<Self> {
+        let metadata = tokio::fs::symlink_metadata(path).await?;
+
+        let (symlink_target, target_metadata) = if metadata.file_type().is_symlink() {
+            (tokio::fs::read_link(path).await.ok(), tokio::fs::metadata(path).await.ok())
+        } else {
+            (None, None)
+        };
+
+        Self::from_parts(path, &metadata, symlink_target, target_metadata)
+    }
+
+    fn from_parts(
+        path: &Path,
+        metadata: &std::fs::Metadata,
+        symlink_target: Option<PathBuf>,
+        target_metadata: Option<std::fs::Metadata>,
+    ) -> Result<Self> {
         let name = path
             .file_name()
             .ok_or_else(|| Error::InvalidPath { path: path.to_path_buf() })?
             .to_string_lossy()
             .into_owned();
 
+        let entry_type = EntryType::from_metadata(metadata);
+        let is_symlink = entry_type == EntryType::Symlink;
+
         Ok(Self {
             name,
             path: path.to_path_buf(),
             size: metadata.len(),
             modified: metadata.modified()?,
-            is_dir: metadata.is_dir(),
-            is_symlink: metadata.is_symlink(),
-            permissions: get_permissions(&metadata),
-            inode: get_inode(&metadata),
+            is_dir: entry_type == EntryType::Directory,
+            is_symlink,
+            entry_type,
+            permissions: get_permissions(metadata),
+            inode: get_inode(metadata),
+            symlink_target,
+            symlink_is_broken: is_symlink && target_metadata.is_none(),
+            target_type: target_metadata.as_ref().map(EntryType::from_metadata),
         })
     }
 
+    pub fn is_broken_symlink(&self) -> bool {
+        self.is_symlink && self.symlink_is_broken
+    }
+
+    pub fn resolved_type(&self) -> Option<EntryType> {
+        if self.is_symlink {
+            self.target_type
+        } else {
+            Some(self.entry_type)
+        }
+    }
+
     pub fn is_hidden(&self) -> bool {
         self.name.starts_with('.')
     }
@@ -52,14 +119,17 @@ impl DirEntry {
             .map(|s| s.to_lowercase())
     }
 
+    /// Sniffs the contents like `detect_mime`, so this reads from disk.
     pub fn mime_type(&self) -> String {
-        mime_guess::from_path(&self.path)
-            .first_or_octet_stream()
-            .to_string()
+        detect_mime(&self.path)
+    }
+
+    pub fn is_empty_dir(&self) -> Result<bool> {
+        is_empty_dir(&self.path)
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum EntryType {
     File,
     Directory,
@@ -118,7 +188,67 @@ fn get_inode(_metadata: &std::fs::Metadata) -> u64 {
     0
 }
 
+// Reads a single getdents64 batch at a time and stops at the first real entry,
+// so huge directories are not listed just to find out they are non-empty.
+#[cfg(target_os = "linux")]
+pub fn is_empty_dir(path: &Path) -> Result<bool> {
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::os::unix::io::AsRawFd;
+
+    // linux_dirent64: d_ino (8), d_off (8), d_reclen (2), d_type (1), d_name.
+    const RECLEN_OFFSET: usize = 16;
+    const NAME_OFFSET: usize = 19;
+
+    let dir = std::fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_DIRECTORY)
+        .open(path)?;
+    let mut buf = [0u8; 4096];
+
+    loop {
+        let read = unsafe {
+            libc::syscall(libc::SYS_getdents64, dir.as_raw_fd(), buf.as_mut_ptr(), buf.len())
+        };
+        if read < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        if read == 0 {
+            return Ok(true);
+        }
+
+        let mut offset = 0;
+        while offset < read as usize {
+            let reclen = u16::from_ne_bytes([buf[offset + RECLEN_OFFSET], buf[offset + RECLEN_OFFSET + 1]]) as usize;
+            let name = &buf[offset + NAME_OFFSET..offset + reclen];
+            let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+
+            if name != b"." && name != b".." {
+                return Ok(false);
+            }
+            offset += reclen;
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn is_empty_dir(path: &Path) -> Result<bool> {
+    Ok(std::fs::read_dir(path)?.next().is_none())
+}
+
+// Longest single path component most Linux filesystems accept, in bytes.
+const NAME_MAX: usize = 255;
+
+// A NUL would silently truncate the path at the syscall boundary, so it is
+// rejected before anything touches the filesystem.
 pub fn validate_path(path: &Path) -> Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let has_nul = path.as_os_str().as_bytes().contains(&0);
+    let overlong = path.components().any(|c| c.as_os_str().as_bytes().len() > NAME_MAX);
+    if has_nul || overlong {
+        return Err(Error::InvalidPath { path: path.to_path_buf() });
+    }
+
     if !path.exists() {
         return Err(Error::NotFound { path: path.to_path_buf() });
     }
@@ -130,18 +260,220 @@ pub fn validate_path(path: &Path) -> Result<()> {
     Ok(())
 }
 
+// A link seen twice means a cycle, however short; the depth cap still bounds
+// long acyclic chains. Relative targets are resolved against the link's parent.
 pub fn check_symlink_loop(path: &Path, max_depth: usize) -> Result<PathBuf> {
+    let mut visited = HashSet::new();
     let mut current = path.to_path_buf();
     let mut depth = 0;
 
     while current.is_symlink() {
-        if depth >= max_depth {
+        if !visited.insert(link_identity(&current)) || depth >= max_depth {
             return Err(Error::SymlinkLoop { path: path.to_path_buf() });
         }
 
-        current = std::fs::read_link(&current)?;
+        let target = std::fs::read_link(&current)?;
+        current = match current.parent() {
+            Some(parent) => parent.join(target),
+            None => target,
+        };
         depth += 1;
     }
 
     Ok(current)
 }
+
+// The link itself can't be canonicalized while it is part of a cycle, so only
+// its parent directory is.
+fn link_identity(link: &Path) -> PathBuf {
+    match (link.parent().and_then(|p| p.canonicalize().ok()), link.file_name()) {
+        (Some(parent), Some(name)) => parent.join(name),
+        _ => link.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_valid_symlink() {
+        let temp_dir = TempDir::new().unwrap();
+        let target = temp_dir.path().join("target_dir");
+        let link = temp_dir.path().join("link");
+        std::fs::create_dir(&target).unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        let entry = DirEntry::from_path(&link).unwrap();
+        assert!(entry.is_symlink);
+        assert!(!entry.is_broken_symlink());
+        assert_eq!(entry.symlink_target, Some(target));
+        assert_eq!(entry.resolved_type(), Some(EntryType::Directory));
+    }
+
+    #[tokio::test]
+    async fn test_from_path_async_matches_sync() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("file.txt");
+        std::fs::write(&file, "hello").unwrap();
+        let dir = temp_dir.path().join("dir");
+        std::fs::create_dir(&dir).unwrap();
+        let link = temp_dir.path().join("link");
+        std::os::unix::fs::symlink(&dir, &link).unwrap();
+        let dangling = temp_dir.path().join("dangling");
+        std::os::unix::fs::symlink("missing", &dangling).unwrap();
+
+        for path in [&file, &dir, &link, &dangling] {
+            let sync = DirEntry::from_path(path).unwrap();
+            let async_entry = DirEntry::from_path_async(path).await.unwrap();
+
+            assert_eq!(sync.name, async_entry.name);
+            assert_eq!(sync.size, async_entry.size);
+            assert_eq!(sync.modified, async_entry.modified);
+            assert_eq!(sync.entry_type, async_entry.entry_type);
+            assert_eq!(sync.is_dir, async_entry.is_dir);
+            assert_eq!(sync.permissions, async_entry.permissions);
+            assert_eq!(sync.inode, async_entry.inode);
+            assert_eq!(sync.symlink_target, async_entry.symlink_target);
+            assert_eq!(sync.is_broken_symlink(), async_entry.is_broken_symlink());
+            assert_eq!(sync.resolved_type(), async_entry.resolved_type());
+        }
+
+        assert!(DirEntry::from_path_async(&temp_dir.path().join("missing")).await.is_err());
+    }
+
+    #[test]
+    fn test_dangling_symlink() {
+        let temp_dir = TempDir::new().unwrap();
+        let link = temp_dir.path().join("dangling");
+        std::os::unix::fs::symlink("missing-file", &link).unwrap();
+
+        let entry = DirEntry::from_path(&link).unwrap();
+        assert!(entry.is_broken_symlink());
+        assert_eq!(entry.symlink_target, Some(PathBuf::from("missing-file")));
+        assert_eq!(entry.resolved_type(), None);
+    }
+
+    #[test]
+    fn test_regular_file_has_no_link_info() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("file.txt");
+        std::fs::write(&file, b"data").unwrap();
+
+        let entry = DirEntry::from_path(&file).unwrap();
+        assert!(!entry.is_symlink);
+        assert!(entry.symlink_target.is_none());
+        assert!(!entry.is_broken_symlink());
+        assert_eq!(entry.resolved_type(), Some(EntryType::File));
+    }
+
+    #[test]
+    fn test_fifo_entry_type() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let fifo = temp_dir.path().join("pipe");
+        let c_path = std::ffi::CString::new(fifo.as_os_str().as_bytes()).unwrap();
+        assert_eq!(unsafe { libc::mkfifo(c_path.as_ptr(), 0o644) }, 0);
+
+        let entry = DirEntry::from_path(&fifo).unwrap();
+        assert_eq!(entry.entry_type, EntryType::Fifo);
+        assert!(!entry.is_dir);
+        assert!(!entry.is_symlink);
+        assert_eq!(entry.resolved_type(), Some(EntryType::Fifo));
+
+        let dir = DirEntry::from_path(temp_dir.path()).unwrap();
+        assert_eq!(dir.entry_type, EntryType::Directory);
+        assert!(dir.is_dir);
+    }
+
+    #[test]
+    fn test_is_empty_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().join("empty");
+        std::fs::create_dir(&dir).unwrap();
+
+        let entry = DirEntry::from_path(&dir).unwrap();
+        assert!(entry.is_empty_dir().unwrap());
+
+        std::fs::write(dir.join("only.txt"), "x").unwrap();
+        assert!(!entry.is_empty_dir().unwrap());
+
+        std::fs::remove_file(dir.join("only.txt")).unwrap();
+        std::fs::create_dir(dir.join(".hidden")).unwrap();
+        assert!(!entry.is_empty_dir().unwrap());
+    }
+
+    #[test]
+    fn test_mime_type_sniffs_contents() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("photo.txt");
+        std::fs::write(&path, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+
+        assert_eq!(DirEntry::from_path(&path).unwrap().mime_type(), "image/png");
+    }
+
+    #[test]
+    fn test_is_empty_dir_rejects_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("file.txt");
+        std::fs::write(&file, "x").unwrap();
+
+        let entry = DirEntry::from_path(&file).unwrap();
+        assert!(entry.is_empty_dir().is_err());
+        assert!(is_empty_dir(&temp_dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_symlink_cycle_detected_within_depth() {
+        let temp_dir = TempDir::new().unwrap();
+        let a = temp_dir.path().join("a");
+        let b = temp_dir.path().join("b");
+        std::os::unix::fs::symlink("b", &a).unwrap();
+        std::os::unix::fs::symlink("a", &b).unwrap();
+
+        assert!(matches!(check_symlink_loop(&a, 1000), Err(Error::SymlinkLoop { .. })));
+
+        let self_link = temp_dir.path().join("self");
+        std::os::unix::fs::symlink(&self_link, &self_link).unwrap();
+        assert!(matches!(check_symlink_loop(&self_link, 1000), Err(Error::SymlinkLoop { .. })));
+    }
+
+    #[test]
+    fn test_long_symlink_chain() {
+        let temp_dir = TempDir::new().unwrap();
+        let target = temp_dir.path().join("target");
+        std::fs::create_dir(&target).unwrap();
+
+        // link0 -> link1 -> ... -> link19 -> target, mixing relative and absolute targets.
+        for i in 0..20 {
+            let link = temp_dir.path().join(format!("link{}", i));
+            let next = if i == 19 { PathBuf::from("target") } else { PathBuf::from(format!("link{}", i + 1)) };
+            let next = if i % 2 == 0 { temp_dir.path().join(next) } else { next };
+            std::os::unix::fs::symlink(next, link).unwrap();
+        }
+
+        let start = temp_dir.path().join("link0");
+        assert_eq!(check_symlink_loop(&start, 40).unwrap().canonicalize().unwrap(), target.canonicalize().unwrap());
+        assert!(matches!(check_symlink_loop(&start, 10), Err(Error::SymlinkLoop { .. })));
+    }
+
+    #[test]
+    fn test_validate_path_rejects_nul_and_long_names() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        assert!(validate_path(temp_dir.path()).is_ok());
+
+        let with_nul = temp_dir.path().join(OsStr::from_bytes(b"file\0.txt"));
+        assert!(matches!(validate_path(&with_nul), Err(Error::InvalidPath { .. })));
+
+        let overlong = temp_dir.path().join("x".repeat(NAME_MAX + 1));
+        assert!(matches!(validate_path(&overlong), Err(Error::InvalidPath { .. })));
+
+        let longest = temp_dir.path().join("x".repeat(NAME_MAX));
+        assert!(matches!(validate_path(&longest), Err(Error::NotFound { .. })));
+    }
+}
@@ -0,0 +1,108 @@
+use std::cmp::Ordering;
+use unicode_normalization::UnicodeNormalization;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Segment {
+    Number { significant_len: usize, digits: String },
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct NaturalKey {
+    primary: Vec<Segment>,
+    accents: String,
+    original: String,
+}
+
+pub fn sort_key(s: &str) -> NaturalKey {
+    NaturalKey {
+        primary: segments(&fold(s)),
+        accents: s.to_lowercase(),
+        original: s.to_string(),
+    }
+}
+
+pub fn cmp(a: &str, b: &str) -> Ordering {
+    sort_key(a).cmp(&sort_key(b))
+}
+
+fn fold(s: &str) -> String {
+    s.nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn is_combining_mark(c: char) -> bool {
+    matches!(c as u32, 0x0300..=0x036F | 0x1AB0..=0x1AFF | 0x1DC0..=0x1DFF | 0x20D0..=0x20FF | 0xFE20..=0xFE2F)
+}
+
+fn segments(s: &str) -> Vec<Segment> {
+    let mut result = Vec::new();
+    let mut chars = s.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        let is_digit = c.is_ascii_digit();
+        let mut run = String::new();
+
+        while let Some(&next) = chars.peek() {
+            if next.is_ascii_digit() != is_digit {
+                break;
+            }
+            run.push(next);
+            chars.next();
+        }
+
+        if is_digit {
+            let significant = run.trim_start_matches('0');
+            result.push(Segment::Number {
+                significant_len: significant.len(),
+                digits: significant.to_string(),
+            });
+        } else {
+            result.push(Segment::Text(run));
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(names: &[&str]) -> Vec<String> {
+        let mut names: Vec<String> = names.iter().map(|s| s.to_string()).collect();
+        names.sort_by(|a, b| cmp(a, b));
+        names
+    }
+
+    #[test]
+    fn test_numeric_ordering() {
+        assert_eq!(sorted(&["img10", "img2", "img1"]), vec!["img1", "img2", "img10"]);
+        assert_eq!(sorted(&["v1.10", "v1.9", "v1.1"]), vec!["v1.1", "v1.9", "v1.10"]);
+        assert_eq!(cmp("file007", "file7"), Ordering::Less);
+        assert_eq!(cmp("file99999999999999999999", "file100000000000000000000"), Ordering::Less);
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        assert_eq!(sorted(&["banana", "Apple", "cherry"]), vec!["Apple", "banana", "cherry"]);
+        assert_eq!(cmp("readme", "README"), Ordering::Greater);
+        assert_eq!(sorted(&["README", "readme"]), vec!["README", "readme"]);
+    }
+
+    #[test]
+    fn test_accents() {
+        assert_eq!(sorted(&["ezra", "école", "eagle"]), vec!["eagle", "école", "ezra"]);
+        assert_eq!(cmp("e", "é"), Ordering::Less);
+        assert_eq!(cmp("é", "f"), Ordering::Less);
+    }
+
+    #[test]
+    fn test_sort_key_matches_cmp() {
+        let mut names = vec!["img10", "Img2", "img1", "émile", "emma"];
+        names.sort_by_key(|n| sort_key(n));
+        assert_eq!(names, vec!["émile", "emma", "img1", "Img2", "img10"]);
+    }
+}
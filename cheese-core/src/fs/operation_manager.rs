@@ -0,0 +1,871 @@
+use crate::{Error, Result};
+use crate::fs::ops::{
+    ConflictResolutions, CopyOptions, DryRunReport, FileOps, OperationPlan, OperationProgress, OperationReport,
+};
+use crate::security::{polkit, Security};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::runtime::Handle;
+use tokio::sync::{broadcast, mpsc, watch};
+use tokio_util::sync::CancellationToken;
+
+const UPDATE_BUFFER: usize = 256;
+
+// A single slot means a paused operation stalls on its next progress report.
+const PROGRESS_BUFFER: usize = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct OperationId(u64);
+
+impl fmt::Display for OperationId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum OperationRequest {
+    /// A single policy for every conflict is `ConflictResolutions::new`;
+    /// answers to an `OperationPlan` go in per destination.
+    Copy {
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+        resolutions: ConflictResolutions,
+        options: CopyOptions,
+    },
+    /// Like `Copy`, but carries on past sources that fail and completes
+    /// with an `OperationOutcome::Report` saying what happened to each.
+    CopyReport {
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+        resolutions: ConflictResolutions,
+        options: CopyOptions,
+    },
+    Move {
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+        resolutions: ConflictResolutions,
+    },
+    Delete {
+        paths: Vec<PathBuf>,
+    },
+    /// Finds the destinations a copy would collide with, without writing
+    /// anything, so they can be answered before submitting the copy.
+    Plan {
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+    },
+    /// What `Copy` would create and collide with, from metadata alone.
+    DryRunCopy {
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+    },
+    /// What `Move` would create and collide with, from metadata alone.
+    DryRunMove {
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+    },
+    /// Replaces `path` with `contents` so readers never see a partial write.
+    WriteAtomic {
+        path: PathBuf,
+        contents: Vec<u8>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperationKind {
+    Copy,
+    Move,
+    Delete,
+    /// Plans and dry runs, which write nothing.
+    Plan,
+    Write,
+}
+
+/// What a completed request found, for requests that produce more than
+/// success or failure.
+#[derive(Debug)]
+pub enum OperationOutcome {
+    Plan(OperationPlan),
+    DryRun(DryRunReport),
+    Report(OperationReport),
+}
+
+impl OperationRequest {
+    pub fn kind(&self) -> OperationKind {
+        match self {
+            Self::Copy { .. } | Self::CopyReport { .. } => OperationKind::Copy,
+            Self::Move { .. } => OperationKind::Move,
+            Self::Delete { .. } => OperationKind::Delete,
+            Self::Plan { .. } | Self::DryRunCopy { .. } | Self::DryRunMove { .. } => OperationKind::Plan,
+            Self::WriteAtomic { .. } => OperationKind::Write,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OperationStatus {
+    Running,
+    Paused,
+    Completed,
+    Failed(String),
+    Cancelled,
+}
+
+impl OperationStatus {
+    pub fn is_active(&self) -> bool {
+        matches!(self, Self::Running | Self::Paused)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OperationInfo {
+    pub id: OperationId,
+    pub kind: OperationKind,
+    pub status: OperationStatus,
+    pub progress: Option<OperationProgress>,
+    /// Set before the operation is reported as completed.
+    pub outcome: Option<Arc<OperationOutcome>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct OperationUpdate {
+    pub id: OperationId,
+    pub status: OperationStatus,
+    pub progress: Option<OperationProgress>,
+}
+
+struct TrackedOperation {
+    info: OperationInfo,
+    cancel: CancellationToken,
+    paused: watch::Sender<bool>,
+}
+
+type Operations = Arc<Mutex<HashMap<OperationId, TrackedOperation>>>;
+
+/// Runs file operations in the background, one cancellation token and
+/// progress record per operation. Finished operations stay listed until
+/// `clear_finished` so a transfer list can show their outcome.
+pub struct OperationManager {
+    file_ops: Arc<dyn FileOps>,
+    security: Option<Arc<Security>>,
+    runtime: Handle,
+    operations: Operations,
+    next_id: AtomicU64,
+    updates: broadcast::Sender<OperationUpdate>,
+}
+
+impl OperationManager {
+    pub fn new(file_ops: Arc<dyn FileOps>, runtime: Handle) -> Self {
+        let (updates, _) = broadcast::channel(UPDATE_BUFFER);
+        Self {
+            file_ops,
+            security: None,
+            runtime,
+            operations: Arc::new(Mutex::new(HashMap::new())),
+            next_id: AtomicU64::new(1),
+            updates,
+        }
+    }
+
+    pub fn with_security(mut self, security: Arc<Security>) -> Self {
+        self.security = Some(security);
+        self
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<OperationUpdate> {
+        self.updates.subscribe()
+    }
+
+    pub fn submit(&self, request: OperationRequest) -> OperationId {
+        let id = OperationId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        let cancel = CancellationToken::new();
+        let (paused_tx, paused_rx) = watch::channel(false);
+
+        self.operations.lock().insert(id, TrackedOperation {
+            info: OperationInfo {
+                id,
+                kind: request.kind(),
+                status: OperationStatus::Running,
+                progress: None,
+                outcome: None,
+            },
+            cancel: cancel.clone(),
+            paused: paused_tx,
+        });
+        let _ = self.updates.send(OperationUpdate { id, status: OperationStatus::Running, progress: None });
+
+        let file_ops = Arc::clone(&self.file_ops);
+        let security = self.security.clone();
+        let operations = Arc::clone(&self.operations);
+        let updates = self.updates.clone();
+
+        self.runtime.spawn(async move {
+            let (progress_tx, progress_rx) = mpsc::channel(PROGRESS_BUFFER);
+            let run = run_request(file_ops.as_ref(), security.as_deref(), request, progress_tx, cancel.clone());
+            let forward = forward_progress(id, progress_rx, paused_rx, cancel.clone(), &operations, &updates);
+            let (result, ()) = tokio::join!(run, forward);
+
+            let status = match result {
+                Ok(outcome) => {
+                    if let Some(outcome) = outcome {
+                        set_outcome(id, outcome, &operations);
+                    }
+                    OperationStatus::Completed
+                }
+                Err(_) if cancel.is_cancelled() => OperationStatus::Cancelled,
+                Err(Error::Cancelled) => OperationStatus::Cancelled,
+                Err(e) => OperationStatus::Failed(e.to_string()),
+            };
+            set_status(id, status, &operations, &updates);
+        });
+
+        id
+    }
+
+    pub fn cancel(&self, id: OperationId) -> Result<()> {
+        self.with_active(id, |op| op.cancel.cancel())
+    }
+
+    pub fn cancel_all(&self) {
+        for op in self.operations.lock().values() {
+            if op.info.status.is_active() {
+                op.cancel.cancel();
+            }
+        }
+    }
+
+    /// Resolves once no operation is running or paused.
+    pub async fn wait_idle(&self) {
+        let mut updates = self.updates.subscribe();
+        while !self.list_active().is_empty() {
+            // Lagging only means missed updates; the active list is rechecked.
+            let _ = updates.recv().await;
+        }
+    }
+
+    pub fn pause(&self, id: OperationId) -> Result<()> {
+        self.with_active(id, |op| {
+            op.paused.send_replace(true);
+        })?;
+        set_status(id, OperationStatus::Paused, &self.operations, &self.updates);
+        Ok(())
+    }
+
+    pub fn resume(&self, id: OperationId) -> Result<()> {
+        self.with_active(id, |op| {
+            op.paused.send_replace(false);
+        })?;
+        set_status(id, OperationStatus::Running, &self.operations, &self.updates);
+        Ok(())
+    }
+
+    pub fn info(&self, id: OperationId) -> Option<OperationInfo> {
+        self.operations.lock().get(&id).map(|op| op.info.clone())
+    }
+
+    pub fn list_active(&self) -> Vec<OperationInfo> {
+        let mut active: Vec<_> = self.operations.lock()
+            .values()
+            .filter(|op| op.info.status.is_active())
+            .map(|op| op.info.clone())
+            .collect();
+        active.sort_by_key(|info| info.id);
+        active
+    }
+
+    pub fn clear_finished(&self) {
+        self.operations.lock().retain(|_, op| op.info.status.is_active());
+    }
+
+    fn with_active(&self, id: OperationId, action: impl FnOnce(&TrackedOperation)) -> Result<()> {
+        let operations = self.operations.lock();
+        match operations.get(&id) {
+            Some(op) if op.info.status.is_active() => {
+                action(op);
+                Ok(())
+            }
+            Some(_) => Err(Error::InvalidOperation(format!("Operation {} has already finished", id))),
+            None => Err(Error::InvalidOperation(format!("Unknown operation {}", id))),
+        }
+    }
+}
+
+async fn run_request(
+    file_ops: &dyn FileOps,
+    security: Option<&Security>,
+    request: OperationRequest,
+    progress: mpsc::Sender<OperationProgress>,
+    cancel: CancellationToken,
+) -> Result<Option<OperationOutcome>> {
+    match request {
+        OperationRequest::Copy { sources, dest_dir, resolutions, options } => {
+            authorize_modify(security, std::slice::from_ref(&dest_dir)).await?;
+            file_ops.copy_files_with_resolutions(sources, dest_dir, &resolutions, options, progress, cancel).await?;
+        }
+        OperationRequest::CopyReport { sources, dest_dir, resolutions, options } => {
+            authorize_modify(security, std::slice::from_ref(&dest_dir)).await?;
+            let report = file_ops.copy_files_report(sources, dest_dir, &resolutions, options, progress, cancel).await?;
+            return Ok(Some(OperationOutcome::Report(report)));
+        }
+        OperationRequest::Move { sources, dest_dir, resolutions } => {
+            let touched: Vec<PathBuf> = sources.iter().chain([&dest_dir]).cloned().collect();
+            authorize_modify(security, &touched).await?;
+            file_ops.move_files_with_resolutions(sources, dest_dir, &resolutions, progress, cancel).await?;
+        }
+        OperationRequest::Delete { paths } => {
+            file_ops.delete_files(paths, security, progress, cancel).await?;
+        }
+        OperationRequest::Plan { sources, dest_dir } => {
+            let plan = file_ops.plan(&sources, &dest_dir).await?;
+            return Ok(Some(OperationOutcome::Plan(plan)));
+        }
+        OperationRequest::DryRunCopy { sources, dest_dir } => {
+            let report = file_ops.dry_run_copy(&sources, &dest_dir).await?;
+            return Ok(Some(OperationOutcome::DryRun(report)));
+        }
+        OperationRequest::DryRunMove { sources, dest_dir } => {
+            let report = file_ops.dry_run_move(&sources, &dest_dir).await?;
+            return Ok(Some(OperationOutcome::DryRun(report)));
+        }
+        OperationRequest::WriteAtomic { path, contents } => {
+            authorize_modify(security, std::slice::from_ref(&path)).await?;
+            file_ops.write_atomic(&path, contents).await?;
+        }
+    }
+    Ok(None)
+}
+
+// Deletes are authorized by `FileOps::delete_files`, which audits each path.
+async fn authorize_modify(security: Option<&Security>, paths: &[PathBuf]) -> Result<()> {
+    match security {
+        Some(security) => security.authorize_protected(polkit::ACTION_MODIFY, paths).await,
+        None => Ok(()),
+    }
+}
+
+// Stops reading while paused so the operation blocks on its next progress send.
+// Returning drops the receiver, which also unblocks a cancelled operation.
+async fn forward_progress(
+    id: OperationId,
+    mut progress: mpsc::Receiver<OperationProgress>,
+    mut paused: watch::Receiver<bool>,
+    cancel: CancellationToken,
+    operations: &Operations,
+    updates: &broadcast::Sender<OperationUpdate>,
+) {
+    loop {
+        if *paused.borrow_and_update() {
+            tokio::select! {
+                _ = cancel.cancelled() => return,
+                changed = paused.changed() => if changed.is_err() { return },
+            }
+            continue;
+        }
+
+        tokio::select! {
+            _ = cancel.cancelled() => return,
+            changed = paused.changed() => if changed.is_err() { return },
+            next = progress.recv() => match next {
+                Some(update) => record_progress(id, update, operations, updates),
+                None => return,
+            },
+        }
+    }
+}
+
+fn record_progress(
+    id: OperationId,
+    progress: OperationProgress,
+    operations: &Operations,
+    updates: &broadcast::Sender<OperationUpdate>,
+) {
+    let status = match operations.lock().get_mut(&id) {
+        Some(op) => {
+            op.info.progress = Some(progress.clone());
+            op.info.status.clone()
+        }
+        None => return,
+    };
+    let _ = updates.send(OperationUpdate { id, status, progress: Some(progress) });
+}
+
+fn set_outcome(id: OperationId, outcome: OperationOutcome, operations: &Operations) {
+    if let Some(op) = operations.lock().get_mut(&id) {
+        op.info.outcome = Some(Arc::new(outcome));
+    }
+}
+
+fn set_status(
+    id: OperationId,
+    status: OperationStatus,
+    operations: &Operations,
+    updates: &broadcast::Sender<OperationUpdate>,
+) {
+    let progress = match operations.lock().get_mut(&id) {
+        // A finished operation keeps its final status even if a late
+        // pause/resume races with completion.
+        Some(op) if op.info.status.is_active() => {
+            op.info.status = status.clone();
+            op.info.progress.clone()
+        }
+        _ => return,
+    };
+    let _ = updates.send(OperationUpdate { id, status, progress });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::mock_ops::{FileOpCall, MockFileOps};
+    use crate::security::mock_authorizer::{AuthCall, MockAuthorizer};
+    use crate::fs::ops::{
+        BatchJob, BatchProgress, BatchReport, ConflictResolution, LocalFileOps, MirrorOptions, MirrorReport, OpFuture,
+    };
+    use std::path::Path;
+    use std::time::Duration;
+
+    // Reports one progress step per source. Copies into a directory named
+    // "hold" then wait for cancellation instead of finishing.
+    struct SteppingOps;
+
+    impl SteppingOps {
+        async fn step(
+            sources: Vec<PathBuf>,
+            dest_dir: PathBuf,
+            progress: mpsc::Sender<OperationProgress>,
+            cancel: CancellationToken,
+        ) -> Result<()> {
+            let total_files = sources.len();
+            for (i, source) in sources.into_iter().enumerate() {
+                if cancel.is_cancelled() {
+                    return Err(Error::Cancelled);
+                }
+                progress.send(OperationProgress {
+                    current_bytes: i as u64 + 1,
+                    total_bytes: total_files as u64,
+                    current_file: source,
+                    files_processed: i + 1,
+                    total_files,
+                    up_to_date: false,
+                }).await.map_err(|_| Error::Cancelled)?;
+            }
+
+            if dest_dir.ends_with("hold") {
+                cancel.cancelled().await;
+                return Err(Error::Cancelled);
+            }
+            Ok(())
+        }
+    }
+
+    impl FileOps for SteppingOps {
+        fn copy_files(
+            &self,
+            sources: Vec<PathBuf>,
+            dest_dir: PathBuf,
+            _conflict: ConflictResolution,
+            _options: CopyOptions,
+            progress: mpsc::Sender<OperationProgress>,
+            cancel: CancellationToken,
+        ) -> OpFuture<'_, ()> {
+            Box::pin(Self::step(sources, dest_dir, progress, cancel))
+        }
+
+        fn copy_files_with_resolutions<'a>(
+            &'a self,
+            sources: Vec<PathBuf>,
+            dest_dir: PathBuf,
+            _resolutions: &'a ConflictResolutions,
+            _options: CopyOptions,
+            progress: mpsc::Sender<OperationProgress>,
+            cancel: CancellationToken,
+        ) -> OpFuture<'a, ()> {
+            Box::pin(Self::step(sources, dest_dir, progress, cancel))
+        }
+
+        fn copy_files_report<'a>(
+            &'a self,
+            sources: Vec<PathBuf>,
+            dest_dir: PathBuf,
+            _resolutions: &'a ConflictResolutions,
+            _options: CopyOptions,
+            progress: mpsc::Sender<OperationProgress>,
+            cancel: CancellationToken,
+        ) -> OpFuture<'a, OperationReport> {
+            Box::pin(async move {
+                Self::step(sources.clone(), dest_dir, progress, cancel).await?;
+                Ok(OperationReport { succeeded: sources, ..OperationReport::default() })
+            })
+        }
+
+        fn batch_copy(
+            &self,
+            _jobs: Vec<BatchJob>,
+            _conflict: ConflictResolution,
+            _options: CopyOptions,
+            _progress: mpsc::Sender<BatchProgress>,
+            _cancel: CancellationToken,
+        ) -> OpFuture<'_, BatchReport> {
+            Box::pin(async { Ok(BatchReport::default()) })
+        }
+
+        #[cfg(target_os = "linux")]
+        fn copy_on_write_clone<'a>(&'a self, _src: &'a Path, _dest: &'a Path) -> OpFuture<'a, ()> {
+            Box::pin(async { Ok(()) })
+        }
+
+        fn copy_sparse<'a>(
+            &'a self,
+            _src: &'a Path,
+            _dest: &'a Path,
+            _progress: mpsc::Sender<OperationProgress>,
+            _cancel: CancellationToken,
+        ) -> OpFuture<'a, ()> {
+            Box::pin(async { Ok(()) })
+        }
+
+        fn move_files(
+            &self,
+            sources: Vec<PathBuf>,
+            dest_dir: PathBuf,
+            _conflict: ConflictResolution,
+            progress: mpsc::Sender<OperationProgress>,
+            cancel: CancellationToken,
+        ) -> OpFuture<'_, ()> {
+            Box::pin(Self::step(sources, dest_dir, progress, cancel))
+        }
+
+        fn move_files_with_resolutions<'a>(
+            &'a self,
+            sources: Vec<PathBuf>,
+            dest_dir: PathBuf,
+            _resolutions: &'a ConflictResolutions,
+            progress: mpsc::Sender<OperationProgress>,
+            cancel: CancellationToken,
+        ) -> OpFuture<'a, ()> {
+            Box::pin(Self::step(sources, dest_dir, progress, cancel))
+        }
+
+        fn delete_files<'a>(
+            &'a self,
+            paths: Vec<PathBuf>,
+            _security: Option<&'a Security>,
+            progress: mpsc::Sender<OperationProgress>,
+            cancel: CancellationToken,
+        ) -> OpFuture<'a, ()> {
+            Box::pin(Self::step(paths, PathBuf::new(), progress, cancel))
+        }
+
+        fn mirror_directory<'a>(
+            &'a self,
+            _source: &'a Path,
+            _dest: &'a Path,
+            _options: MirrorOptions,
+            _progress: mpsc::Sender<OperationProgress>,
+            _cancel: CancellationToken,
+        ) -> OpFuture<'a, MirrorReport> {
+            Box::pin(async { Ok(MirrorReport::default()) })
+        }
+
+        fn symlink<'a>(&'a self, _target: &'a Path, _link: &'a Path, _relative: bool) -> OpFuture<'a, ()> {
+            Box::pin(async { Ok(()) })
+        }
+
+        fn plan<'a>(&'a self, _sources: &'a [PathBuf], _dest_dir: &'a Path) -> OpFuture<'a, OperationPlan> {
+            Box::pin(async { Ok(OperationPlan::default()) })
+        }
+
+        fn dry_run_copy<'a>(&'a self, _sources: &'a [PathBuf], _dest_dir: &'a Path) -> OpFuture<'a, DryRunReport> {
+            Box::pin(async { Ok(DryRunReport::default()) })
+        }
+
+        fn dry_run_move<'a>(&'a self, _sources: &'a [PathBuf], _dest_dir: &'a Path) -> OpFuture<'a, DryRunReport> {
+            Box::pin(async { Ok(DryRunReport::default()) })
+        }
+
+        fn write_atomic<'a>(&'a self, _path: &'a Path, _contents: Vec<u8>) -> OpFuture<'a, ()> {
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    fn copy(names: &[&str], dest_dir: &str) -> OperationRequest {
+        OperationRequest::Copy {
+            sources: names.iter().map(PathBuf::from).collect(),
+            dest_dir: PathBuf::from(dest_dir),
+            resolutions: ConflictResolutions::new(ConflictResolution::Skip),
+            options: CopyOptions::default(),
+        }
+    }
+
+    async fn wait_for(
+        updates: &mut broadcast::Receiver<OperationUpdate>,
+        id: OperationId,
+        done: impl Fn(&OperationUpdate) -> bool,
+    ) -> OperationUpdate {
+        tokio::time::timeout(Duration::from_secs(2), async {
+            loop {
+                let update = updates.recv().await.expect("Update channel closed");
+                if update.id == id && done(&update) {
+                    return update;
+                }
+            }
+        })
+        .await
+        .expect("Timed out waiting for operation update")
+    }
+
+    #[tokio::test]
+    async fn test_independent_progress_and_cancel() {
+        let manager = OperationManager::new(Arc::new(SteppingOps), Handle::current());
+        let mut updates = manager.subscribe();
+
+        let first = manager.submit(copy(&["a1", "a2", "a3"], "/dest"));
+        let second = manager.submit(copy(&["b1", "b2"], "/hold"));
+        assert_ne!(first, second);
+        assert_eq!(manager.list_active().len(), 2);
+
+        wait_for(&mut updates, first, |u| u.status == OperationStatus::Completed).await;
+        // The second operation's updates may already have gone by while
+        // waiting on the first, so poll its recorded progress instead.
+        tokio::time::timeout(Duration::from_secs(2), async {
+            while manager.info(second).unwrap().progress.is_none_or(|p| p.files_processed < 2) {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        }).await.expect("Timed out waiting for progress");
+
+        let first_progress = manager.info(first).unwrap().progress.unwrap();
+        assert_eq!(first_progress.total_files, 3);
+        assert_eq!(first_progress.current_file, PathBuf::from("a3"));
+
+        let active = manager.list_active();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].id, second);
+        assert_eq!(active[0].progress.as_ref().unwrap().total_files, 2);
+
+        manager.cancel(second).unwrap();
+        wait_for(&mut updates, second, |u| u.status == OperationStatus::Cancelled).await;
+
+        assert_eq!(manager.info(first).unwrap().status, OperationStatus::Completed);
+        assert!(manager.list_active().is_empty());
+        assert!(manager.cancel(second).is_err());
+
+        manager.clear_finished();
+        assert!(manager.info(first).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_pause_and_resume() {
+        let manager = OperationManager::new(Arc::new(SteppingOps), Handle::current());
+        let mut updates = manager.subscribe();
+        let sources: Vec<String> = (0..20).map(|i| format!("f{}", i)).collect();
+        let sources: Vec<&str> = sources.iter().map(String::as_str).collect();
+
+        // The current-thread test runtime has not run the task yet, so the
+        // operation is paused before it reports anything.
+        let id = manager.submit(copy(&sources, "/dest"));
+        manager.pause(id).unwrap();
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let info = manager.info(id).unwrap();
+        assert_eq!(info.status, OperationStatus::Paused);
+        assert!(info.progress.is_none());
+
+        manager.resume(id).unwrap();
+        let done = wait_for(&mut updates, id, |u| u.status == OperationStatus::Completed).await;
+        assert_eq!(done.progress.unwrap().files_processed, 20);
+    }
+
+    #[tokio::test]
+    async fn test_routes_requests_to_file_ops() {
+        let mock = Arc::new(MockFileOps::new());
+        mock.push_result(Err(Error::PermissionDenied { path: PathBuf::from("/locked") }));
+        let manager = OperationManager::new(mock.clone(), Handle::current());
+        let mut updates = manager.subscribe();
+
+        let id = manager.submit(OperationRequest::Delete { paths: vec![PathBuf::from("/locked")] });
+        assert_eq!(manager.info(id).unwrap().kind, OperationKind::Delete);
+
+        let update = wait_for(&mut updates, id, |u| !u.status.is_active()).await;
+        assert!(matches!(update.status, OperationStatus::Failed(msg) if msg.contains("/locked")));
+        assert_eq!(mock.calls(), vec![FileOpCall::DeleteFiles { paths: vec![PathBuf::from("/locked")] }]);
+    }
+
+    #[tokio::test]
+    async fn test_plan_then_copy_with_answers() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let (src, dest) = (temp_dir.path().join("src"), temp_dir.path().join("dest"));
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::create_dir_all(&dest).unwrap();
+        for name in ["keep.txt", "replace.txt", "new.txt"] {
+            std::fs::write(src.join(name), "new").unwrap();
+        }
+        for name in ["keep.txt", "replace.txt"] {
+            std::fs::write(dest.join(name), "old").unwrap();
+        }
+        let sources: Vec<PathBuf> = ["keep.txt", "replace.txt", "new.txt"].iter().map(|n| src.join(n)).collect();
+
+        let manager = OperationManager::new(Arc::new(LocalFileOps::new(1)), Handle::current());
+        let mut updates = manager.subscribe();
+        let planned = manager.submit(OperationRequest::Plan { sources: sources.clone(), dest_dir: dest.clone() });
+        wait_for(&mut updates, planned, |u| u.status == OperationStatus::Completed).await;
+
+        let info = manager.info(planned).unwrap();
+        assert_eq!(info.kind, OperationKind::Plan);
+        let Some(OperationOutcome::Plan(plan)) = info.outcome.as_deref() else {
+            panic!("No plan in {:?}", info.outcome);
+        };
+        let collisions: Vec<&Path> = plan.collisions().map(|c| c.destination.as_path()).collect();
+        assert_eq!(collisions, vec![dest.join("keep.txt"), dest.join("replace.txt")]);
+
+        let resolutions = ConflictResolutions::new(ConflictResolution::Skip)
+            .with_resolution(dest.join("replace.txt"), ConflictResolution::Overwrite);
+        let copied = manager.submit(OperationRequest::Copy {
+            sources,
+            dest_dir: dest.clone(),
+            resolutions,
+            options: CopyOptions::default(),
+        });
+        wait_for(&mut updates, copied, |u| u.status == OperationStatus::Completed).await;
+
+        assert!(manager.info(copied).unwrap().outcome.is_none());
+        for (name, contents) in [("keep.txt", "old"), ("replace.txt", "new"), ("new.txt", "new")] {
+            assert_eq!(std::fs::read_to_string(dest.join(name)).unwrap(), contents, "{}", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_copy_report_request_keeps_going_past_failures() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dest = temp_dir.path().join("dest");
+        std::fs::create_dir(&dest).unwrap();
+        let present = temp_dir.path().join("present.txt");
+        std::fs::write(&present, "data").unwrap();
+        let missing = temp_dir.path().join("missing.txt");
+
+        let manager = OperationManager::new(Arc::new(LocalFileOps::new(1)), Handle::current());
+        let mut updates = manager.subscribe();
+        let id = manager.submit(OperationRequest::CopyReport {
+            sources: vec![missing.clone(), present.clone()],
+            dest_dir: dest.clone(),
+            resolutions: ConflictResolutions::new(ConflictResolution::Skip),
+            options: CopyOptions::default(),
+        });
+        wait_for(&mut updates, id, |u| u.status == OperationStatus::Completed).await;
+
+        let info = manager.info(id).unwrap();
+        assert_eq!(info.kind, OperationKind::Copy);
+        let Some(OperationOutcome::Report(report)) = info.outcome.as_deref() else {
+            panic!("No report in {:?}", info.outcome);
+        };
+        assert_eq!(report.succeeded, vec![present]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, missing);
+        assert!(dest.join("present.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_and_write_requests() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dest = temp_dir.path().join("dest");
+        std::fs::create_dir(&dest).unwrap();
+        let source = temp_dir.path().join("notes.txt");
+        std::fs::write(&source, "12345").unwrap();
+
+        let manager = OperationManager::new(Arc::new(LocalFileOps::new(1)), Handle::current());
+        let mut updates = manager.subscribe();
+
+        for request in [
+            OperationRequest::DryRunCopy { sources: vec![source.clone()], dest_dir: dest.clone() },
+            OperationRequest::DryRunMove { sources: vec![source.clone()], dest_dir: dest.clone() },
+        ] {
+            let id = manager.submit(request);
+            wait_for(&mut updates, id, |u| u.status == OperationStatus::Completed).await;
+            let info = manager.info(id).unwrap();
+            assert_eq!(info.kind, OperationKind::Plan);
+            let Some(OperationOutcome::DryRun(report)) = info.outcome.as_deref() else {
+                panic!("No dry run report in {:?}", info.outcome);
+            };
+            assert_eq!(report.would_create, vec![dest.join("notes.txt")]);
+        }
+        assert!(!dest.join("notes.txt").exists());
+
+        let written = dest.join("written.txt");
+        let id = manager.submit(OperationRequest::WriteAtomic { path: written.clone(), contents: b"saved".to_vec() });
+        wait_for(&mut updates, id, |u| u.status == OperationStatus::Completed).await;
+        assert_eq!(manager.info(id).unwrap().kind, OperationKind::Write);
+        assert_eq!(std::fs::read(&written).unwrap(), b"saved");
+    }
+
+    #[tokio::test]
+    async fn test_requests_carry_per_destination_resolutions() {
+        let mock = Arc::new(MockFileOps::new());
+        let manager = OperationManager::new(mock.clone(), Handle::current());
+        let mut updates = manager.subscribe();
+        let resolutions = ConflictResolutions::new(ConflictResolution::Skip)
+            .with_resolution("/dest/a", ConflictResolution::Overwrite)
+            .with_resolution("/dest/b", ConflictResolution::Rename);
+
+        let copied = manager.submit(OperationRequest::Copy {
+            sources: vec![PathBuf::from("/a"), PathBuf::from("/b")],
+            dest_dir: PathBuf::from("/dest"),
+            resolutions: resolutions.clone(),
+            options: CopyOptions::default(),
+        });
+        wait_for(&mut updates, copied, |u| !u.status.is_active()).await;
+        let moved = manager.submit(OperationRequest::Move {
+            sources: vec![PathBuf::from("/a")],
+            dest_dir: PathBuf::from("/dest"),
+            resolutions: resolutions.clone(),
+        });
+        wait_for(&mut updates, moved, |u| !u.status.is_active()).await;
+
+        assert_eq!(mock.calls(), vec![
+            FileOpCall::CopyFilesWithResolutions {
+                sources: vec![PathBuf::from("/a"), PathBuf::from("/b")],
+                dest_dir: PathBuf::from("/dest"),
+                resolutions: resolutions.clone(),
+                options: CopyOptions::default(),
+            },
+            FileOpCall::MoveFilesWithResolutions {
+                sources: vec![PathBuf::from("/a")],
+                dest_dir: PathBuf::from("/dest"),
+                resolutions,
+            },
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_protected_destinations_need_authorization() {
+        let authorizer = MockAuthorizer::denying();
+        let security = Security::with_authorizer(Box::new(authorizer.clone()))
+            .with_protected_paths(vec![PathBuf::from("/srv/protected")]);
+        let manager = OperationManager::new(Arc::new(SteppingOps), Handle::current())
+            .with_security(Arc::new(security));
+        let mut updates = manager.subscribe();
+
+        let allowed = manager.submit(copy(&["a"], "/dest"));
+        let update = wait_for(&mut updates, allowed, |u| !u.status.is_active()).await;
+        assert_eq!(update.status, OperationStatus::Completed);
+        assert!(authorizer.calls().is_empty());
+
+        let copied = manager.submit(copy(&["a"], "/srv/protected/in"));
+        let moved = manager.submit(OperationRequest::Move {
+            sources: vec![PathBuf::from("/srv/protected/out")],
+            dest_dir: PathBuf::from("/dest"),
+            resolutions: ConflictResolutions::new(ConflictResolution::Skip),
+        });
+        for id in [copied, moved] {
+            let update = wait_for(&mut updates, id, |u| !u.status.is_active()).await;
+            assert!(matches!(update.status, OperationStatus::Failed(_)));
+        }
+        assert_eq!(authorizer.calls(), vec![
+            AuthCall::Request(polkit::ACTION_MODIFY.to_string()),
+            AuthCall::Request(polkit::ACTION_MODIFY.to_string()),
+        ]);
+    }
+}
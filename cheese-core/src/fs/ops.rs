@@ -1,13 +1,30 @@
 use crate::{Error, Result};
+use crate::error::context;
+use crate::blocking::{self, BlockingPool};
+use crate::security::{polkit, selinux, Security};
+use nix::errno::Errno;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
 use std::path::{Path, PathBuf};
+use std::pin::Pin;
 use tokio::fs;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, Semaphore};
+use tokio::task::JoinSet;
 use tokio_util::sync::CancellationToken;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
+use parking_lot::Mutex;
 
 const BUFFER_SIZE: usize = 1024 * 1024;
+const TASK_PROGRESS_BUFFER: usize = 16;
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(100);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(5);
+
+/// Errors worth retrying on flaky network mounts, for
+/// `CopyOptions::retriable_errors`.
+pub const TRANSIENT_ERRORS: &[Errno] = &[Errno::EAGAIN, Errno::ETIMEDOUT];
 
 #[derive(Debug, Clone)]
 pub struct OperationProgress {
@@ -16,22 +33,326 @@ pub struct OperationProgress {
     pub current_file: PathBuf,
     pub files_processed: usize,
     pub total_files: usize,
+    /// `current_file` was skipped because its destination is already current.
+    pub up_to_date: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ConflictResolution {
     Skip,
+    /// Replaces the destination. A directory copied onto a directory is
+    /// merged into it, overwriting the entries both have and keeping those
+    /// only found in the destination.
     Overwrite,
     Rename,
+    /// Copies a directory into an existing one, resolving each entry that
+    /// already exists on its own. Overwrites when the two are not both
+    /// directories.
+    Merge,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SelinuxLabeling {
+    Ignore,
+    PreserveSource,
+    #[default]
+    PolicyDefault,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CopyOptions {
+    pub preserve_xattrs: bool,
+    pub selinux: SelinuxLabeling,
+    /// Like `cp -u`: files whose destination has the same size and an equal
+    /// or newer mtime are skipped, whatever the conflict resolution. Existing
+    /// directories are then merged rather than overwritten.
+    pub update_only: bool,
+    /// Top-level sources `copy_files` copies at once; 0 and 1 copy them one
+    /// after another.
+    pub max_concurrent: usize,
+    /// Makes `copy_files_report` stop at the first failed source. The other
+    /// copies always stop there.
+    pub stop_on_error: bool,
+    /// Further attempts at a file whose copy failed with one of
+    /// `retriable_errors`, the delay between them doubling each time.
+    pub max_retries: u32,
+    pub retriable_errors: Vec<Errno>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BatchJob {
+    pub sources: Vec<PathBuf>,
+    pub dest_dir: PathBuf,
+    pub label: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct BatchProgress {
+    pub job_index: usize,
+    pub inner: OperationProgress,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConflictInfo {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    pub source_size: u64,
+    pub dest_size: u64,
+}
+
+/// What a copy or move would do, gathered from metadata alone.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DryRunReport {
+    pub would_create: Vec<PathBuf>,
+    pub would_conflict: Vec<ConflictInfo>,
+    /// Bytes that would be written; zero for moves that are plain renames.
+    pub estimated_bytes: u64,
+}
+
+/// A destination that already exists, as found by `LocalFileOps::plan`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedConflict {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    pub source_is_dir: bool,
+    /// Symlinks count as files, even when they point at a directory.
+    pub existing_is_dir: bool,
+}
+
+impl PlannedConflict {
+    /// A directory copied onto an existing directory, which
+    /// `ConflictResolution::Merge` merges entry by entry.
+    pub fn is_merge(&self) -> bool {
+        self.source_is_dir && self.existing_is_dir
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OperationPlan {
+    /// Sorted by destination, so merged directories precede their contents.
+    pub conflicts: Vec<PlannedConflict>,
+}
+
+impl OperationPlan {
+    /// The conflicts inside or outside merged directories, without the
+    /// merges themselves.
+    pub fn collisions(&self) -> impl Iterator<Item = &PlannedConflict> {
+        self.conflicts.iter().filter(|conflict| !conflict.is_merge())
+    }
+}
+
+/// Conflict resolutions keyed by destination path, such as the answers to
+/// an `OperationPlan`. Destinations without an answer use `default`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConflictResolutions {
+    pub default: ConflictResolution,
+    pub per_destination: HashMap<PathBuf, ConflictResolution>,
+}
+
+impl ConflictResolutions {
+    pub fn new(default: ConflictResolution) -> Self {
+        Self {
+            default,
+            per_destination: HashMap::new(),
+        }
+    }
+
+    pub fn with_resolution(mut self, destination: impl Into<PathBuf>, resolution: ConflictResolution) -> Self {
+        self.per_destination.insert(destination.into(), resolution);
+        self
+    }
+
+    pub fn resolve(&self, destination: &Path) -> ConflictResolution {
+        self.per_destination.get(destination).copied().unwrap_or(self.default)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    pub completed: Vec<String>,
+    pub failed: Vec<(String, Error)>,
+}
+
+/// How `mirror_directory` decides that a destination file is already current.
+/// Files of different sizes are always copied.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CompareStrategy {
+    #[default]
+    Size,
+    /// The destination is at least as new as the source.
+    Modified,
+    /// Both files hash the same; reads every file of matching size.
+    Checksum,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MirrorOptions {
+    /// Removes destination entries that have no counterpart in the source.
+    pub delete_extra: bool,
+    pub compare_by: CompareStrategy,
+}
+
+#[derive(Debug, Default)]
+pub struct MirrorReport {
+    pub copied: usize,
+    /// Extra destination entries removed; a directory counts once.
+    pub deleted: usize,
+    pub skipped: usize,
+    pub errors: Vec<(PathBuf, Error)>,
+}
+
+/// Outcome of `copy_files_report` per top-level source.
+#[derive(Debug, Default)]
+pub struct OperationReport {
+    pub succeeded: Vec<PathBuf>,
+    /// Sources left alone because their destination existed and resolved
+    /// to `ConflictResolution::Skip`.
+    pub skipped: Vec<PathBuf>,
+    pub failed: Vec<(PathBuf, Error)>,
+}
+
+pub type OpFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+pub trait FileOps: Send + Sync {
+    fn copy_files(
+        &self,
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+        conflict: ConflictResolution,
+        options: CopyOptions,
+        progress: mpsc::Sender<OperationProgress>,
+        cancel: CancellationToken,
+    ) -> OpFuture<'_, ()>;
+
+    fn copy_files_with_resolutions<'a>(
+        &'a self,
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+        resolutions: &'a ConflictResolutions,
+        options: CopyOptions,
+        progress: mpsc::Sender<OperationProgress>,
+        cancel: CancellationToken,
+    ) -> OpFuture<'a, ()>;
+
+    fn copy_files_report<'a>(
+        &'a self,
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+        resolutions: &'a ConflictResolutions,
+        options: CopyOptions,
+        progress: mpsc::Sender<OperationProgress>,
+        cancel: CancellationToken,
+    ) -> OpFuture<'a, OperationReport>;
+
+    fn batch_copy(
+        &self,
+        jobs: Vec<BatchJob>,
+        conflict: ConflictResolution,
+        options: CopyOptions,
+        progress: mpsc::Sender<BatchProgress>,
+        cancel: CancellationToken,
+    ) -> OpFuture<'_, BatchReport>;
+
+    #[cfg(target_os = "linux")]
+    fn copy_on_write_clone<'a>(&'a self, src: &'a Path, dest: &'a Path) -> OpFuture<'a, ()>;
+
+    fn copy_sparse<'a>(
+        &'a self,
+        src: &'a Path,
+        dest: &'a Path,
+        progress: mpsc::Sender<OperationProgress>,
+        cancel: CancellationToken,
+    ) -> OpFuture<'a, ()>;
+
+    fn move_files(
+        &self,
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+        conflict: ConflictResolution,
+        progress: mpsc::Sender<OperationProgress>,
+        cancel: CancellationToken,
+    ) -> OpFuture<'_, ()>;
+
+    fn move_files_with_resolutions<'a>(
+        &'a self,
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+        resolutions: &'a ConflictResolutions,
+        progress: mpsc::Sender<OperationProgress>,
+        cancel: CancellationToken,
+    ) -> OpFuture<'a, ()>;
+
+    fn delete_files<'a>(
+        &'a self,
+        paths: Vec<PathBuf>,
+        security: Option<&'a Security>,
+        progress: mpsc::Sender<OperationProgress>,
+        cancel: CancellationToken,
+    ) -> OpFuture<'a, ()>;
+
+    fn mirror_directory<'a>(
+        &'a self,
+        source: &'a Path,
+        dest: &'a Path,
+        options: MirrorOptions,
+        progress: mpsc::Sender<OperationProgress>,
+        cancel: CancellationToken,
+    ) -> OpFuture<'a, MirrorReport>;
+
+    fn symlink<'a>(&'a self, target: &'a Path, link: &'a Path, relative: bool) -> OpFuture<'a, ()>;
+
+    fn plan<'a>(&'a self, sources: &'a [PathBuf], dest_dir: &'a Path) -> OpFuture<'a, OperationPlan>;
+
+    fn dry_run_copy<'a>(&'a self, sources: &'a [PathBuf], dest_dir: &'a Path) -> OpFuture<'a, DryRunReport>;
+
+    fn dry_run_move<'a>(&'a self, sources: &'a [PathBuf], dest_dir: &'a Path) -> OpFuture<'a, DryRunReport>;
+
+    fn write_atomic<'a>(&'a self, path: &'a Path, contents: Vec<u8>) -> OpFuture<'a, ()>;
 }
 
-pub struct FileOperations {
+pub struct LocalFileOps {
     max_concurrent: usize,
+    blocking_pool: Option<BlockingPool>,
+    // Errors the next file copy attempts fail with, in order, before touching
+    // the source or destination.
+    #[cfg(test)]
+    io_faults: Arc<Mutex<std::collections::VecDeque<Errno>>>,
+    // Treats every source and destination as on different filesystems.
+    #[cfg(test)]
+    cross_filesystem: bool,
 }
 
-impl FileOperations {
+impl LocalFileOps {
     pub fn new(max_concurrent: usize) -> Self {
-        Self { max_concurrent }
+        Self {
+            max_concurrent,
+            blocking_pool: None,
+            #[cfg(test)]
+            io_faults: Arc::default(),
+            #[cfg(test)]
+            cross_filesystem: false,
+        }
+    }
+
+    /// Runs size calculation on `pool` instead of the caller's runtime.
+    pub fn with_blocking_pool(mut self, pool: BlockingPool) -> Self {
+        self.blocking_pool = Some(pool);
+        self
+    }
+
+    /// Replaces `path` with `data` so readers see either the old or the new
+    /// contents, never a partial write. An existing target keeps its
+    /// permissions. Blocking; meant for small files such as config and
+    /// session state.
+    pub fn atomic_write(path: &Path, data: &[u8]) -> Result<()> {
+        atomic_write_with(path, data, |_| {})
+    }
+
+    /// [`Self::atomic_write`] run on the blocking pool.
+    pub async fn write_atomic(&self, path: &Path, contents: impl Into<Vec<u8>>) -> Result<()> {
+        let path = path.to_path_buf();
+        let contents = contents.into();
+        blocking::spawn_on(self.blocking_pool.as_ref(), move || Self::atomic_write(&path, &contents)).await?
     }
 
     pub async fn copy_files(
@@ -39,61 +360,285 @@ impl FileOperations {
         sources: Vec<PathBuf>,
         dest_dir: PathBuf,
         conflict: ConflictResolution,
+        options: CopyOptions,
+        progress: mpsc::Sender<OperationProgress>,
+        cancel: CancellationToken,
+    ) -> Result<()> {
+        let resolutions = ConflictResolutions::new(conflict);
+        self.copy_files_with_resolutions(sources, dest_dir, &resolutions, options, progress, cancel).await
+    }
+
+    /// Like `copy_files`, with the conflict resolution looked up per
+    /// destination. Entries of directories merged with
+    /// `ConflictResolution::Merge` are looked up one by one as well.
+    pub async fn copy_files_with_resolutions(
+        &self,
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+        resolutions: &ConflictResolutions,
+        options: CopyOptions,
         progress: mpsc::Sender<OperationProgress>,
         cancel: CancellationToken,
     ) -> Result<()> {
-        if !dest_dir.is_dir() {
-            return Err(Error::InvalidPath { path: dest_dir });
+        let targets = copy_targets(&sources, &dest_dir)?;
+        let totals = self.calculate_totals(&sources).await?;
+        if options.max_concurrent > 1 {
+            return self.copy_files_concurrent(targets, totals, resolutions, options, progress, cancel).await;
         }
 
-        let total_bytes = self.calculate_total_size(&sources).await?;
-        let total_files = sources.len();
-        let bytes_copied = Arc::new(AtomicU64::new(0));
-        let files_processed = Arc::new(AtomicU64::new(0));
+        let run = CopyRun {
+            bytes_copied: Arc::new(AtomicU64::new(0)),
+            total_bytes: totals.0,
+            files_processed: Arc::new(AtomicU64::new(0)),
+            total_files: totals.1,
+            options,
+            progress,
+            cancel,
+            in_flight: None,
+        };
 
-        for source in sources {
-            if cancel.is_cancelled() {
+        for (source, dest) in &targets {
+            if run.cancel.is_cancelled() {
                 return Err(Error::Cancelled);
             }
+            self.copy_resolved(source, dest, resolutions, &run).await?;
+        }
 
-            let file_name = source.file_name()
-                .ok_or_else(|| Error::InvalidPath { path: source.clone() })?;
-            let dest = dest_dir.join(file_name);
+        // Updates are sent while a file is written, before it is counted.
+        match targets.last() {
+            Some((source, _)) => run.report(source).await,
+            None => Ok(()),
+        }
+    }
 
-            if dest.exists() {
-                match conflict {
-                    ConflictResolution::Skip => continue,
-                    ConflictResolution::Overwrite => {},
-                    ConflictResolution::Rename => {
-                        let renamed = self.find_unique_name(&dest).await?;
-                        self.copy_file_with_progress(
-                            &source,
-                            &renamed,
-                            &bytes_copied,
-                            total_bytes,
-                            &files_processed,
-                            total_files,
-                            &progress,
-                            &cancel,
-                        ).await?;
-                        continue;
+    /// Like `copy_files_with_resolutions`, but a source that fails is
+    /// recorded and the copy moves on to the next one, unless
+    /// `options.stop_on_error` is set. Sources are copied one after another
+    /// whatever `options.max_concurrent`. Only cancellation is an error.
+    pub async fn copy_files_report(
+        &self,
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+        resolutions: &ConflictResolutions,
+        options: CopyOptions,
+        progress: mpsc::Sender<OperationProgress>,
+        cancel: CancellationToken,
+    ) -> Result<OperationReport> {
+        let targets = copy_targets(&sources, &dest_dir)?;
+        // Taken per source so one that cannot be read fails on its own.
+        let source_totals: Vec<Result<(u64, usize)>> = blocking::spawn_on(self.blocking_pool.as_ref(), move || {
+            sources.iter().map(|source| get_totals_recursive(source)).collect()
+        })
+        .await?;
+        let (total_bytes, total_files) = source_totals
+            .iter()
+            .flatten()
+            .fold((0, 0), |(bytes, files), (b, f)| (bytes + b, files + f));
+
+        let run = CopyRun {
+            bytes_copied: Arc::new(AtomicU64::new(0)),
+            total_bytes,
+            files_processed: Arc::new(AtomicU64::new(0)),
+            total_files,
+            options,
+            progress,
+            cancel,
+            in_flight: None,
+        };
+
+        let mut report = OperationReport::default();
+        for ((source, dest), totals) in targets.into_iter().zip(source_totals) {
+            if run.cancel.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+
+            let (bytes, files) = match totals {
+                Ok(totals) => totals,
+                Err(e) => {
+                    report.failed.push((source, e));
+                    if run.options.stop_on_error {
+                        break;
                     }
+                    continue;
                 }
+            };
+
+            if fs::symlink_metadata(&dest).await.is_ok() && resolutions.resolve(&dest) == ConflictResolution::Skip {
+                self.skip_source(&source, &run).await?;
+                report.skipped.push(source);
+                continue;
             }
 
-            self.copy_file_with_progress(
-                &source,
-                &dest,
-                &bytes_copied,
+            let bytes_before = run.bytes_copied.load(Ordering::Relaxed);
+            let files_before = run.files_processed.load(Ordering::Relaxed);
+            match self.copy_resolved(&source, &dest, resolutions, &run).await {
+                Ok(()) => report.succeeded.push(source),
+                Err(Error::Cancelled) => return Err(Error::Cancelled),
+                Err(e) => {
+                    tracing::warn!("Copying {} failed: {}", source.display(), e);
+                    // What is left of a failed source counts as processed.
+                    run.bytes_copied.store(bytes_before + bytes, Ordering::Relaxed);
+                    run.files_processed.store(files_before + files as u64, Ordering::Relaxed);
+                    run.report(&source).await?;
+                    report.failed.push((source, e));
+                    if run.options.stop_on_error {
+                        break;
+                    }
+                }
+            }
+        }
+
+        if let Some(source) = report.succeeded.last() {
+            run.report(source).await?;
+        }
+        Ok(report)
+    }
+
+    // Every source is copied by its own task, which reports to its own
+    // channel; the progress sent to the caller sums the latest report of
+    // each. On cancellation or the first error the remaining tasks are
+    // aborted and whatever they had started writing is removed.
+    async fn copy_files_concurrent(
+        &self,
+        targets: Vec<(PathBuf, PathBuf)>,
+        (total_bytes, total_files): (u64, usize),
+        resolutions: &ConflictResolutions,
+        options: CopyOptions,
+        progress: mpsc::Sender<OperationProgress>,
+        cancel: CancellationToken,
+    ) -> Result<()> {
+        let (task_progress, mut updates) = mpsc::channel(TASK_PROGRESS_BUFFER * options.max_concurrent);
+        let task = CopyTask {
+            ops: Arc::new(LocalFileOps {
+                max_concurrent: self.max_concurrent,
+                blocking_pool: self.blocking_pool.clone(),
+                #[cfg(test)]
+                io_faults: Arc::clone(&self.io_faults),
+                #[cfg(test)]
+                cross_filesystem: self.cross_filesystem,
+            }),
+            resolutions: Arc::new(resolutions.clone()),
+            permits: Arc::new(Semaphore::new(options.max_concurrent)),
+            in_flight: Arc::new(Mutex::new(HashSet::new())),
+            progress: task_progress,
+            totals: (total_bytes, total_files),
+            options,
+            cancel: cancel.clone(),
+        };
+        let in_flight = Arc::clone(&task.in_flight);
+
+        let mut tasks = JoinSet::new();
+        for (index, (source, dest)) in targets.iter().cloned().enumerate() {
+            tasks.spawn(task.clone().run(index, source, dest));
+        }
+        drop(task);
+
+        // Bytes and files per task, from its latest report.
+        let mut done = vec![(0u64, 0u64); targets.len()];
+        let mut finished = vec![false; targets.len()];
+        let result = loop {
+            let (index, current_file) = tokio::select! {
+                _ = cancel.cancelled() => break Err(Error::Cancelled),
+                Some((index, update)) = updates.recv() => {
+                    // A report can arrive after its task has already finished.
+                    if finished[index] {
+                        continue;
+                    }
+                    done[index] = (update.current_bytes, update.files_processed as u64);
+                    (index, update.current_file)
+                }
+                joined = tasks.join_next() => match joined {
+                    None => break Ok(()),
+                    Some(Ok(Ok((index, bytes, files)))) => {
+                        finished[index] = true;
+                        done[index] = (bytes, files);
+                        (index, targets[index].0.clone())
+                    }
+                    Some(Ok(Err(e))) => break Err(e),
+                    Some(Err(e)) => break Err(e.into()),
+                },
+            };
+
+            let (bytes, files) = done.iter().fold((0, 0), |(bytes, files), (b, f)| (bytes + b, files + f));
+            let update = OperationProgress {
+                current_bytes: bytes,
                 total_bytes,
-                &files_processed,
+                current_file,
+                files_processed: files as usize,
                 total_files,
-                &progress,
-                &cancel,
-            ).await?;
+                up_to_date: false,
+            };
+            let sent = tokio::select! {
+                _ = cancel.cancelled() => break Err(Error::Cancelled),
+                sent = progress.send(update) => sent,
+            };
+            if sent.is_err() {
+                tracing::debug!("Progress receiver closed during copy of {}", targets[index].0.display());
+                break Err(Error::Cancelled);
+            }
+        };
+
+        if result.is_err() {
+            tasks.abort_all();
+            while tasks.join_next().await.is_some() {}
+
+            let partial: Vec<PathBuf> = in_flight.lock().drain().collect();
+            for path in partial {
+                let _ = remove_path(&path).await;
+            }
         }
+        result
+    }
 
-        Ok(())
+    // Jobs run one after another; a failing job is recorded in the report and the
+    // batch moves on, but cancellation stops the whole batch. Every job resolves
+    // conflicts with the same `conflict` policy.
+    pub async fn batch_copy(
+        &self,
+        jobs: Vec<BatchJob>,
+        conflict: ConflictResolution,
+        options: CopyOptions,
+        progress: mpsc::Sender<BatchProgress>,
+        cancel: CancellationToken,
+    ) -> Result<BatchReport> {
+        let mut report = BatchReport::default();
+
+        for (job_index, job) in jobs.into_iter().enumerate() {
+            if cancel.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+
+            let (tx, mut rx) = mpsc::channel(64);
+            let batch_tx = progress.clone();
+            let forwarder = tokio::spawn(async move {
+                while let Some(inner) = rx.recv().await {
+                    if batch_tx.send(BatchProgress { job_index, inner }).await.is_err() {
+                        break;
+                    }
+                }
+            });
+
+            let result = self.copy_files(
+                job.sources,
+                job.dest_dir,
+                conflict,
+                options.clone(),
+                tx,
+                cancel.clone(),
+            ).await;
+            let _ = forwarder.await;
+
+            match result {
+                Ok(()) => report.completed.push(job.label),
+                Err(Error::Cancelled) => return Err(Error::Cancelled),
+                Err(e) => {
+                    tracing::warn!("Batch job {} failed: {}", job.label, e);
+                    report.failed.push((job.label, e));
+                }
+            }
+        }
+
+        Ok(report)
     }
 
     async fn copy_file_with_progress(
@@ -104,59 +649,173 @@ impl FileOperations {
         total_bytes: u64,
         files_processed: &Arc<AtomicU64>,
         total_files: usize,
+        options: &CopyOptions,
         progress: &mpsc::Sender<OperationProgress>,
         cancel: &CancellationToken,
     ) -> Result<()> {
         let metadata = fs::metadata(src).await?;
 
         if metadata.is_dir() {
-            return self.copy_directory(
+            self.copy_directory(
                 src,
                 dest,
                 bytes_copied,
                 total_bytes,
                 files_processed,
                 total_files,
+                options,
                 progress,
                 cancel,
-            ).await;
+            ).await?;
+
+            if options.preserve_xattrs {
+                Self::copy_xattrs(src, dest)?;
+            }
+            apply_selinux_label(src, dest, options.selinux);
+            return Ok(());
         }
 
-        let mut src_file = fs::File::open(src).await?;
-        let mut dest_file = fs::File::create(dest).await?;
-        let mut buffer = vec![0u8; BUFFER_SIZE];
+        let len = metadata.len();
+        let bytes_before = bytes_copied.load(Ordering::Relaxed);
+        retry_transient(options, src, cancel, || async move {
+            // Bytes reported by a failed attempt are reported again by the next.
+            bytes_copied.store(bytes_before, Ordering::Relaxed);
 
-        loop {
-            if cancel.is_cancelled() {
-                let _ = fs::remove_file(dest).await;
-                return Err(Error::Cancelled);
+            #[cfg(test)]
+            if let Some(errno) = self.io_faults.lock().pop_front() {
+                return Err(std::io::Error::from_raw_os_error(errno as i32).into());
             }
 
-            let n = src_file.read(&mut buffer).await?;
-            if n == 0 {
-                break;
+            if self.clone_if_supported(src, dest).await? {
+                let current = bytes_copied.fetch_add(len, Ordering::Relaxed) + len;
+                let processed = files_processed.load(Ordering::Relaxed) as usize;
+
+                progress.send(OperationProgress {
+                    current_bytes: current,
+                    total_bytes,
+                    current_file: src.to_path_buf(),
+                    files_processed: processed,
+                    total_files,
+                    up_to_date: false,
+                }).await.map_err(|_| Error::Cancelled)?;
+            } else if !Self::copy_sparse_tracked(src, dest, bytes_copied, files_processed, (total_bytes, total_files), progress, cancel).await? {
+                let mut src_file = fs::File::open(src).await?;
+                let mut dest_file = fs::File::create(dest).await?;
+                let mut buffer = vec![0u8; BUFFER_SIZE];
+
+                loop {
+                    if cancel.is_cancelled() {
+                        let _ = fs::remove_file(dest).await;
+                        return Err(Error::Cancelled);
+                    }
+
+                    let n = src_file.read(&mut buffer).await?;
+                    if n == 0 {
+                        break;
+                    }
+
+                    dest_file.write_all(&buffer[..n]).await?;
+
+                    let current = bytes_copied.fetch_add(n as u64, Ordering::Relaxed) + n as u64;
+                    let processed = files_processed.load(Ordering::Relaxed) as usize;
+
+                    let sent = progress.send(OperationProgress {
+                        current_bytes: current,
+                        total_bytes,
+                        current_file: src.to_path_buf(),
+                        files_processed: processed,
+                        total_files,
+                        up_to_date: false,
+                    }).await;
+
+                    // A closed progress channel means the caller went away mid-copy.
+                    if sent.is_err() {
+                        let _ = fs::remove_file(dest).await;
+                        return Err(Error::Cancelled);
+                    }
+                }
             }
 
-            dest_file.write_all(&buffer[..n]).await?;
-            
-            let current = bytes_copied.fetch_add(n as u64, Ordering::Relaxed) + n as u64;
-            let processed = files_processed.load(Ordering::Relaxed) as usize;
+            self.preserve_metadata(src, dest, options).await?;
+            if options.preserve_xattrs {
+                Self::copy_xattrs(src, dest)?;
+            }
 
-            progress.send(OperationProgress {
-                current_bytes: current,
-                total_bytes,
-                current_file: src.to_path_buf(),
-                files_processed: processed,
-                total_files,
-            }).await.map_err(|_| Error::Cancelled)?;
-        }
+            Ok(())
+        }).await?;
 
-        self.preserve_metadata(src, dest).await?;
         files_processed.fetch_add(1, Ordering::Relaxed);
 
         Ok(())
     }
 
+    // Sparse copy reporting into the running totals of a multi-file copy.
+    // Returns false, having written nothing, when `src` has no holes.
+    async fn copy_sparse_tracked(
+        src: &Path,
+        dest: &Path,
+        bytes_copied: &Arc<AtomicU64>,
+        files_processed: &Arc<AtomicU64>,
+        (total_bytes, total_files): (u64, usize),
+        progress: &mpsc::Sender<OperationProgress>,
+        cancel: &CancellationToken,
+    ) -> Result<bool> {
+        let task_src = src.to_path_buf();
+        let task_dest = dest.to_path_buf();
+        let task_bytes = Arc::clone(bytes_copied);
+        let task_files = Arc::clone(files_processed);
+        let task_progress = progress.clone();
+        let task_cancel = cancel.clone();
+
+        let copied = tokio::task::spawn_blocking(move || {
+            copy_sparse_blocking(&task_src, &task_dest, &task_cancel, |n| {
+                let current = task_bytes.fetch_add(n, Ordering::Relaxed) + n;
+                task_progress.blocking_send(OperationProgress {
+                    current_bytes: current,
+                    total_bytes,
+                    current_file: task_src.clone(),
+                    files_processed: task_files.load(Ordering::Relaxed) as usize,
+                    total_files,
+                    up_to_date: false,
+                }).map_err(|_| Error::Cancelled)
+            })
+        })
+        .await
+        .map_err(|e| Error::Runtime(context("Sparse copy task failed", e)))?;
+
+        copied.inspect_err(|_| {
+            let _ = std::fs::remove_file(dest);
+        })
+    }
+
+    async fn skip_up_to_date(
+        src: &Path,
+        dest: &Path,
+        bytes_copied: &Arc<AtomicU64>,
+        total_bytes: u64,
+        files_processed: &Arc<AtomicU64>,
+        total_files: usize,
+        progress: &mpsc::Sender<OperationProgress>,
+    ) -> Result<bool> {
+        let Some(len) = up_to_date_len(src, dest).await else {
+            return Ok(false);
+        };
+
+        let current = bytes_copied.fetch_add(len, Ordering::Relaxed) + len;
+        let processed = files_processed.fetch_add(1, Ordering::Relaxed) as usize + 1;
+
+        progress.send(OperationProgress {
+            current_bytes: current,
+            total_bytes,
+            current_file: src.to_path_buf(),
+            files_processed: processed,
+            total_files,
+            up_to_date: true,
+        }).await.map_err(|_| Error::Cancelled)?;
+
+        Ok(true)
+    }
+
     async fn copy_directory(
         &self,
         src: &Path,
@@ -165,6 +824,7 @@ impl FileOperations {
         total_bytes: u64,
         files_processed: &Arc<AtomicU64>,
         total_files: usize,
+        options: &CopyOptions,
         progress: &mpsc::Sender<OperationProgress>,
         cancel: &CancellationToken,
     ) -> Result<()> {
@@ -180,6 +840,18 @@ impl FileOperations {
             let src_path = entry.path();
             let dest_path = dest.join(entry.file_name());
 
+            if options.update_only && Self::skip_up_to_date(
+                &src_path,
+                &dest_path,
+                bytes_copied,
+                total_bytes,
+                files_processed,
+                total_files,
+                progress,
+            ).await? {
+                continue;
+            }
+
             self.copy_file_with_progress(
                 &src_path,
                 &dest_path,
@@ -187,6 +859,7 @@ impl FileOperations {
                 total_bytes,
                 files_processed,
                 total_files,
+                options,
                 progress,
                 cancel,
             ).await?;
@@ -195,47 +868,474 @@ impl FileOperations {
         Ok(())
     }
 
-    pub async fn move_files(
-        &self,
-        sources: Vec<PathBuf>,
-        dest_dir: PathBuf,
-        conflict: ConflictResolution,
-        progress: mpsc::Sender<OperationProgress>,
-        cancel: CancellationToken,
-    ) -> Result<()> {
-        for source in &sources {
-            if cancel.is_cancelled() {
-                return Err(Error::Cancelled);
-            }
+    #[cfg(target_os = "linux")]
+    pub async fn copy_on_write_clone(&self, src: &Path, dest: &Path) -> Result<()> {
+        let task_src = src.to_path_buf();
+        let task_dest = dest.to_path_buf();
+
+        let cloned = tokio::task::spawn_blocking(move || reflink_blocking(&task_src, &task_dest))
+            .await
+            .map_err(|e| Error::Runtime(context("Clone task failed", e)))??;
+
+        if !cloned {
+            tracing::debug!("Reflink unsupported for {}, using buffered copy", src.display());
+            fs::copy(src, dest).await?;
+        }
+
+        self.preserve_metadata(src, dest, &CopyOptions::default()).await
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn clone_if_supported(&self, src: &Path, dest: &Path) -> Result<bool> {
+        let dest_dir = match dest.parent() {
+            Some(parent) => parent,
+            None => return Ok(false),
+        };
+
+        if !is_cow_filesystem(src) || !self.is_same_filesystem(src, dest_dir).await.unwrap_or(false) {
+            return Ok(false);
+        }
+
+        let task_src = src.to_path_buf();
+        let task_dest = dest.to_path_buf();
+        tokio::task::spawn_blocking(move || reflink_blocking(&task_src, &task_dest))
+            .await
+            .map_err(|e| Error::Runtime(context("Clone task failed", e)))?
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    async fn clone_if_supported(&self, _src: &Path, _dest: &Path) -> Result<bool> {
+        Ok(false)
+    }
+
+    #[cfg(target_os = "linux")]
+    pub async fn copy_sparse(
+        &self,
+        src: &Path,
+        dest: &Path,
+        progress: mpsc::Sender<OperationProgress>,
+        cancel: CancellationToken,
+    ) -> Result<()> {
+        let task_src = src.to_path_buf();
+        let task_dest = dest.to_path_buf();
+        let task_progress = progress.clone();
+        let task_cancel = cancel.clone();
+
+        let copied = tokio::task::spawn_blocking(move || {
+            let len = std::fs::metadata(&task_src)?.len();
+            let mut current = 0u64;
+            let copied = copy_sparse_blocking(&task_src, &task_dest, &task_cancel, |n| {
+                current += n;
+                task_progress.blocking_send(OperationProgress {
+                    current_bytes: current,
+                    total_bytes: len,
+                    current_file: task_src.clone(),
+                    files_processed: 0,
+                    total_files: 1,
+                    up_to_date: false,
+                }).map_err(|_| Error::Cancelled)
+            })?;
+
+            if copied {
+                task_progress.blocking_send(OperationProgress {
+                    current_bytes: len,
+                    total_bytes: len,
+                    current_file: task_src.clone(),
+                    files_processed: 1,
+                    total_files: 1,
+                    up_to_date: false,
+                }).map_err(|_| Error::Cancelled)?;
+            }
+            Ok(copied)
+        })
+        .await
+        .map_err(|e| Error::Runtime(context("Sparse copy task failed", e)))?;
+
+        match copied {
+            Ok(true) => self.preserve_metadata(src, dest, &CopyOptions::default()).await,
+            Ok(false) => self.copy_single_file(src, dest, progress, cancel).await,
+            Err(e) => {
+                let _ = fs::remove_file(dest).await;
+                Err(e)
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub async fn copy_sparse(
+        &self,
+        src: &Path,
+        dest: &Path,
+        progress: mpsc::Sender<OperationProgress>,
+        cancel: CancellationToken,
+    ) -> Result<()> {
+        self.copy_single_file(src, dest, progress, cancel).await
+    }
+
+    async fn copy_single_file(
+        &self,
+        src: &Path,
+        dest: &Path,
+        progress: mpsc::Sender<OperationProgress>,
+        cancel: CancellationToken,
+    ) -> Result<()> {
+        let total_bytes = fs::metadata(src).await?.len();
+
+        self.copy_file_with_progress(
+            src,
+            dest,
+            &Arc::new(AtomicU64::new(0)),
+            total_bytes,
+            &Arc::new(AtomicU64::new(0)),
+            1,
+            &CopyOptions::default(),
+            &progress,
+            &cancel,
+        ).await
+    }
+
+    /// Lists the files and directories `copy_files` would create under
+    /// `dest_dir` and the existing files it would collide with. Directories
+    /// are walked, so conflicts inside merged directories are reported too.
+    pub async fn dry_run_copy(&self, sources: &[PathBuf], dest_dir: &Path) -> Result<DryRunReport> {
+        let targets = copy_targets(sources, dest_dir)?;
+
+        blocking::spawn_on(self.blocking_pool.as_ref(), move || {
+            let mut report = DryRunReport::default();
+            for (source, dest) in targets {
+                plan_copy(&source, &dest, &mut report)?;
+            }
+            Ok(report)
+        })
+        .await?
+    }
+
+    /// Finds every destination of copying `sources` into `dest_dir` that
+    /// already exists, without writing anything. Directories that would be
+    /// merged are walked, so collisions inside them are listed too, ready
+    /// to be answered per file alongside `ConflictResolution::Merge`.
+    pub async fn plan(&self, sources: &[PathBuf], dest_dir: &Path) -> Result<OperationPlan> {
+        let targets = copy_targets(sources, dest_dir)?;
+
+        blocking::spawn_on(self.blocking_pool.as_ref(), move || {
+            let mut plan = OperationPlan::default();
+            for (source, dest) in targets {
+                plan_conflicts(&source, &dest, &mut plan)?;
+            }
+            plan.conflicts.sort_by(|a, b| a.destination.cmp(&b.destination));
+            Ok(plan)
+        })
+        .await?
+    }
+
+    // Copies `source` to `dest`, first resolving an existing `dest`.
+    fn copy_resolved<'a>(
+        &'a self,
+        source: &'a Path,
+        dest: &'a Path,
+        resolutions: &'a ConflictResolutions,
+        run: &'a CopyRun,
+    ) -> OpFuture<'a, ()> {
+        Box::pin(async move {
+            // Only paths this copy writes to are tracked, never an existing
+            // destination left alone by Skip or Rename.
+            let copy = |dest: PathBuf| async move {
+                run.track(&dest);
+                self.copy_file_with_progress(
+                    source,
+                    &dest,
+                    &run.bytes_copied,
+                    run.total_bytes,
+                    &run.files_processed,
+                    run.total_files,
+                    &run.options,
+                    &run.progress,
+                    &run.cancel,
+                ).await?;
+                run.untrack(&dest);
+                Ok(())
+            };
+
+            let Ok(existing) = fs::symlink_metadata(dest).await else {
+                return copy(dest.to_path_buf()).await;
+            };
+            let source_is_dir = fs::metadata(source).await?.is_dir();
+            let resolution = resolutions.resolve(dest);
+
+            let merge = matches!(resolution, ConflictResolution::Merge | ConflictResolution::Overwrite);
+            if merge && source_is_dir && existing.is_dir() {
+                let mut read_dir = fs::read_dir(source).await?;
+                while let Some(entry) = read_dir.next_entry().await? {
+                    if run.cancel.is_cancelled() {
+                        return Err(Error::Cancelled);
+                    }
+                    self.copy_resolved(&entry.path(), &dest.join(entry.file_name()), resolutions, run).await?;
+                }
+                return Ok(());
+            }
+
+            if run.options.update_only && Self::skip_up_to_date(
+                source,
+                dest,
+                &run.bytes_copied,
+                run.total_bytes,
+                &run.files_processed,
+                run.total_files,
+                &run.progress,
+            ).await? {
+                return Ok(());
+            }
+
+            match resolution {
+                ConflictResolution::Skip => self.skip_source(source, run).await,
+                ConflictResolution::Overwrite | ConflictResolution::Merge => {
+                    // A file replacing a file is written in place; a file and
+                    // a directory replace one another whole.
+                    if source_is_dir || !existing.is_file() {
+                        remove_path(dest).await?;
+                    }
+                    copy(dest.to_path_buf()).await
+                }
+                ConflictResolution::Rename => copy(self.find_unique_name(dest).await?).await,
+            }
+        })
+    }
+
+    // Counts a skipped source as processed so progress still ends at the
+    // totals, which were taken before any conflict was resolved.
+    async fn skip_source(&self, source: &Path, run: &CopyRun) -> Result<()> {
+        let (bytes, files) = self.calculate_totals(&[source.to_path_buf()]).await?;
+        run.bytes_copied.fetch_add(bytes, Ordering::Relaxed);
+        run.files_processed.fetch_add(files as u64, Ordering::Relaxed);
+        run.report(source).await
+    }
+
+    /// Like `dry_run_copy`, except that sources on the same filesystem as
+    /// `dest_dir` are renamed as a whole and write no data.
+    pub async fn dry_run_move(&self, sources: &[PathBuf], dest_dir: &Path) -> Result<DryRunReport> {
+        let mut renames = Vec::new();
+        let mut copies = Vec::new();
+
+        for (source, dest) in copy_targets(sources, dest_dir)? {
+            if self.is_same_filesystem(&source, dest_dir).await? {
+                renames.push((source, dest));
+            } else {
+                copies.push((source, dest));
+            }
+        }
+
+        blocking::spawn_on(self.blocking_pool.as_ref(), move || {
+            let mut report = DryRunReport::default();
+            for (source, dest) in renames {
+                let source_size = std::fs::metadata(&source)?.len();
+                match std::fs::symlink_metadata(&dest) {
+                    Ok(existing) => report.would_conflict.push(ConflictInfo {
+                        source,
+                        destination: dest,
+                        source_size,
+                        dest_size: existing.len(),
+                    }),
+                    Err(_) => report.would_create.push(dest),
+                }
+            }
+            for (source, dest) in copies {
+                plan_copy(&source, &dest, &mut report)?;
+            }
+            Ok(report)
+        })
+        .await?
+    }
+
+    /// Makes `dest` a copy of the directory `source`: missing or outdated
+    /// files are copied, symlinks recreated and, with `delete_extra`, entries
+    /// only found in `dest` removed. Destination entries of the wrong kind
+    /// are always replaced. Failures on single entries are collected in the
+    /// report; only cancellation aborts the mirror.
+    pub async fn mirror_directory(
+        &self,
+        source: &Path,
+        dest: &Path,
+        options: MirrorOptions,
+        progress: mpsc::Sender<OperationProgress>,
+        cancel: CancellationToken,
+    ) -> Result<MirrorReport> {
+        if !source.is_dir() {
+            return Err(Error::InvalidPath { path: source.to_path_buf() });
+        }
+        fs::create_dir_all(dest).await?;
+
+        let mut walk = MirrorWalk {
+            source: source.to_path_buf(),
+            dest: dest.to_path_buf(),
+            compare_by: options.compare_by,
+            cancel: cancel.clone(),
+            plan: MirrorPlan::default(),
+        };
+        let plan = blocking::spawn_on(self.blocking_pool.as_ref(), move || {
+            walk.dir(Path::new("")).map(|()| walk.plan)
+        })
+        .await??;
+
+        let mut report = MirrorReport {
+            skipped: plan.skipped,
+            errors: plan.errors,
+            ..MirrorReport::default()
+        };
+
+        for path in &plan.replaced {
+            if let Err(e) = remove_path(path).await {
+                report.errors.push((path.clone(), e));
+            }
+        }
+        for relative in &plan.dirs {
+            if let Err(e) = fs::create_dir_all(dest.join(relative)).await {
+                report.errors.push((dest.join(relative), e.into()));
+            }
+        }
+
+        let bytes_copied = Arc::new(AtomicU64::new(0));
+        let files_processed = Arc::new(AtomicU64::new(0));
+        let copy_options = CopyOptions::default();
+
+        for relative in &plan.files {
+            if cancel.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+
+            let src = source.join(relative);
+            let copied = self.copy_file_with_progress(
+                &src,
+                &dest.join(relative),
+                &bytes_copied,
+                plan.bytes,
+                &files_processed,
+                plan.files.len(),
+                &copy_options,
+                &progress,
+                &cancel,
+            ).await;
+
+            match copied {
+                Ok(()) => report.copied += 1,
+                Err(Error::Cancelled) => return Err(Error::Cancelled),
+                Err(e) => report.errors.push((src, e)),
+            }
+        }
+
+        for relative in &plan.links {
+            let src = source.join(relative);
+            let linked = match fs::read_link(&src).await {
+                Ok(target) => fs::symlink(target, dest.join(relative)).await,
+                Err(e) => Err(e),
+            };
+
+            match linked {
+                Ok(()) => report.copied += 1,
+                Err(e) => report.errors.push((src, e.into())),
+            }
+        }
+
+        if options.delete_extra {
+            for path in &plan.extra {
+                if cancel.is_cancelled() {
+                    return Err(Error::Cancelled);
+                }
+
+                match remove_path(path).await {
+                    Ok(()) => report.deleted += 1,
+                    Err(e) => report.errors.push((path.clone(), e)),
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    pub async fn move_files(
+        &self,
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+        conflict: ConflictResolution,
+        progress: mpsc::Sender<OperationProgress>,
+        cancel: CancellationToken,
+    ) -> Result<()> {
+        let resolutions = ConflictResolutions::new(conflict);
+        self.move_files_with_resolutions(sources, dest_dir, &resolutions, progress, cancel).await
+    }
+
+    /// Like `move_files`, with the conflict resolution looked up per
+    /// destination. Skipped sources stay where they are.
+    pub async fn move_files_with_resolutions(
+        &self,
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+        resolutions: &ConflictResolutions,
+        progress: mpsc::Sender<OperationProgress>,
+        cancel: CancellationToken,
+    ) -> Result<()> {
+        for source in &sources {
+            if cancel.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
 
             let file_name = source.file_name()
                 .ok_or_else(|| Error::InvalidPath { path: source.clone() })?;
             let dest = dest_dir.join(file_name);
+            let resolution = resolutions.resolve(&dest);
+            let existing = fs::symlink_metadata(&dest).await.ok();
+
+            if let Some(existing) = &existing {
+                if resolution == ConflictResolution::Skip {
+                    continue;
+                }
+
+                // Directories are merged entry by entry, on one filesystem or
+                // across two, so every entry gets its own resolution.
+                let merge = matches!(resolution, ConflictResolution::Merge | ConflictResolution::Overwrite);
+                if merge && existing.is_dir() && fs::symlink_metadata(source).await?.is_dir() {
+                    let mut entries = Vec::new();
+                    let mut read_dir = fs::read_dir(source).await?;
+                    while let Some(entry) = read_dir.next_entry().await? {
+                        entries.push(entry.path());
+                    }
+                    Box::pin(self.move_files_with_resolutions(
+                        entries,
+                        dest,
+                        resolutions,
+                        progress.clone(),
+                        cancel.clone(),
+                    )).await?;
+                    // Fails, keeping the directory, while skipped
+                    // entries are left in it.
+                    let _ = fs::remove_dir(source).await;
+                    continue;
+                }
+            }
 
             if self.is_same_filesystem(source, &dest_dir).await? {
-                if dest.exists() {
-                    match conflict {
-                        ConflictResolution::Skip => continue,
-                        ConflictResolution::Overwrite => {
-                            fs::remove_file(&dest).await?;
-                        },
-                        ConflictResolution::Rename => {
-                            let renamed = self.find_unique_name(&dest).await?;
-                            fs::rename(source, renamed).await?;
-                            continue;
-                        }
+                if existing.is_some() {
+                    if resolution == ConflictResolution::Rename {
+                        let renamed = self.find_unique_name(&dest).await?;
+                        fs::rename(source, renamed).await?;
+                        continue;
                     }
+                    remove_path(&dest).await?;
                 }
                 fs::rename(source, &dest).await?;
             } else {
-                self.copy_files(
+                self.copy_files_with_resolutions(
                     vec![source.clone()],
                     dest_dir.clone(),
-                    conflict,
+                    resolutions,
+                    CopyOptions {
+                        preserve_xattrs: true,
+                        selinux: SelinuxLabeling::PreserveSource,
+                        ..CopyOptions::default()
+                    },
                     progress.clone(),
                     cancel.clone(),
                 ).await?;
-                fs::remove_file(source).await?;
+                remove_path(source).await?;
             }
         }
 
@@ -245,9 +1345,14 @@ impl FileOperations {
     pub async fn delete_files(
         &self,
         paths: Vec<PathBuf>,
+        security: Option<&Security>,
         progress: mpsc::Sender<OperationProgress>,
         cancel: CancellationToken,
     ) -> Result<()> {
+        if let Some(security) = security {
+            security.authorize_protected(polkit::ACTION_DELETE, &paths).await?;
+        }
+
         let total_files = paths.len();
         let mut files_processed = 0;
 
@@ -256,13 +1361,21 @@ impl FileOperations {
                 return Err(Error::Cancelled);
             }
 
-            let metadata = fs::symlink_metadata(&path).await?;
-            
-            if metadata.is_dir() {
-                fs::remove_dir_all(&path).await?;
-            } else {
-                fs::remove_file(&path).await?;
+            let removed: Result<()> = async {
+                let metadata = fs::symlink_metadata(&path).await?;
+
+                if metadata.is_dir() {
+                    fs::remove_dir_all(&path).await?;
+                } else {
+                    fs::remove_file(&path).await?;
+                }
+                Ok(())
+            }.await;
+
+            if let Some(security) = security {
+                security.record_audit(polkit::ACTION_DELETE, &path, removed.is_ok());
             }
+            removed?;
 
             files_processed += 1;
 
@@ -272,40 +1385,60 @@ impl FileOperations {
                 current_file: path,
                 files_processed,
                 total_files,
+                up_to_date: false,
             }).await.map_err(|_| Error::Cancelled)?;
         }
 
         Ok(())
     }
 
-    async fn calculate_total_size(&self, paths: &[PathBuf]) -> Result<u64> {
-        let mut total = 0u64;
+    // With `relative`, the link stores the path from its own directory to the
+    // target. Directories on both sides are canonicalized, but the target's last
+    // component is kept as given since it may not exist yet.
+    pub async fn symlink(&self, target: &Path, link: &Path, relative: bool) -> Result<()> {
+        let link_parent = fs::canonicalize(parent_or_current(link)).await?;
+        let target_parent = parent_or_current(target);
 
-        for path in paths {
-            total += self.get_size_recursive(path).await?;
+        if let (Some(a), Some(b)) = (device_of(target_parent), device_of(&link_parent)) {
+            if a != b {
+                tracing::warn!(
+                    "Creating symlink {} across filesystems to {}",
+                    link.display(),
+                    target.display()
+                );
+            }
         }
 
-        Ok(total)
-    }
-
-    async fn get_size_recursive(&self, path: &Path) -> Result<u64> {
-        let metadata = fs::metadata(path).await?;
+        let contents = if relative {
+            let name = target.file_name().ok_or_else(|| Error::InvalidPath { path: target.to_path_buf() })?;
+            let resolved = fs::canonicalize(target_parent).await?.join(name);
+            pathdiff::diff_paths(&resolved, &link_parent).unwrap_or(resolved)
+        } else {
+            target.to_path_buf()
+        };
 
-        if metadata.is_file() {
-            return Ok(metadata.len());
+        match fs::symlink(&contents, link).await {
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                Err(Error::AlreadyExists { path: link.to_path_buf() })
+            }
+            result => Ok(result?),
         }
+    }
 
-        let mut total = 0u64;
-        let mut read_dir = fs::read_dir(path).await?;
-
-        while let Some(entry) = read_dir.next_entry().await? {
-            total += self.get_size_recursive(&entry.path()).await?;
-        }
+    // Bytes and number of files below `paths`, directories not counted.
+    async fn calculate_totals(&self, paths: &[PathBuf]) -> Result<(u64, usize)> {
+        let paths = paths.to_vec();
+        let totals = move || {
+            paths.iter().try_fold((0u64, 0usize), |(bytes, files), path| {
+                let (path_bytes, path_files) = get_totals_recursive(path)?;
+                Ok((bytes + path_bytes, files + path_files))
+            })
+        };
 
-        Ok(total)
+        blocking::spawn_on(self.blocking_pool.as_ref(), totals).await?
     }
 
-    async fn preserve_metadata(&self, src: &Path, dest: &Path) -> Result<()> {
+    async fn preserve_metadata(&self, src: &Path, dest: &Path, options: &CopyOptions) -> Result<()> {
         let metadata = fs::metadata(src).await?;
         fs::set_permissions(dest, metadata.permissions()).await?;
 
@@ -316,6 +1449,37 @@ impl FileOperations {
             fs::set_permissions(dest, perms).await?;
         }
 
+        apply_selinux_label(src, dest, options.selinux);
+        Ok(())
+    }
+
+    pub fn copy_xattrs(src: &Path, dest: &Path) -> Result<()> {
+        let names = match xattr::list(src) {
+            Ok(names) => names,
+            Err(e) if e.raw_os_error() == Some(libc::ENOTSUP) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+
+        for name in names {
+            let value = match xattr::get(src, &name) {
+                Ok(Some(value)) => value,
+                Ok(None) => continue,
+                Err(e) if is_skippable_xattr_error(&e) => {
+                    tracing::warn!("Skipping xattr {:?} on {}: {}", name, src.display(), e);
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            match xattr::set(dest, &name, &value) {
+                Ok(()) => {}
+                Err(e) if is_skippable_xattr_error(&e) => {
+                    tracing::warn!("Skipping xattr {:?} on {}: {}", name, dest.display(), e);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
         Ok(())
     }
 
@@ -350,6 +1514,11 @@ impl FileOperations {
     }
 
     async fn is_same_filesystem(&self, path1: &Path, path2: &Path) -> Result<bool> {
+        #[cfg(test)]
+        if self.cross_filesystem {
+            return Ok(false);
+        }
+
         #[cfg(unix)]
         {
             use std::os::unix::fs::MetadataExt;
@@ -365,8 +1534,2112 @@ impl FileOperations {
     }
 }
 
-impl Default for FileOperations {
-    fn default() -> Self {
-        Self::new(4)
+fn copy_targets(sources: &[PathBuf], dest_dir: &Path) -> Result<Vec<(PathBuf, PathBuf)>> {
+    if !dest_dir.is_dir() {
+        return Err(Error::InvalidPath { path: dest_dir.to_path_buf() });
+    }
+
+    sources
+        .iter()
+        .map(|source| {
+            let file_name = source.file_name()
+                .ok_or_else(|| Error::InvalidPath { path: source.clone() })?;
+            Ok((source.clone(), dest_dir.join(file_name)))
+        })
+        .collect()
+}
+
+fn plan_copy(source: &Path, dest: &Path, report: &mut DryRunReport) -> Result<()> {
+    let metadata = std::fs::metadata(source)?;
+    let existing = std::fs::symlink_metadata(dest).ok();
+
+    if metadata.is_dir() {
+        match existing {
+            // Existing directories are merged into.
+            Some(existing) if existing.is_dir() => {}
+            Some(existing) => report.would_conflict.push(ConflictInfo {
+                source: source.to_path_buf(),
+                destination: dest.to_path_buf(),
+                source_size: 0,
+                dest_size: existing.len(),
+            }),
+            None => report.would_create.push(dest.to_path_buf()),
+        }
+
+        for entry in std::fs::read_dir(source)? {
+            let entry = entry?;
+            plan_copy(&entry.path(), &dest.join(entry.file_name()), report)?;
+        }
+        return Ok(());
+    }
+
+    report.estimated_bytes += metadata.len();
+    match existing {
+        Some(existing) => report.would_conflict.push(ConflictInfo {
+            source: source.to_path_buf(),
+            destination: dest.to_path_buf(),
+            source_size: metadata.len(),
+            dest_size: existing.len(),
+        }),
+        None => report.would_create.push(dest.to_path_buf()),
+    }
+    Ok(())
+}
+
+fn plan_conflicts(source: &Path, dest: &Path, plan: &mut OperationPlan) -> Result<()> {
+    let Ok(existing) = std::fs::symlink_metadata(dest) else {
+        return Ok(());
+    };
+    let source_is_dir = std::fs::metadata(source)?.is_dir();
+
+    let conflict = PlannedConflict {
+        source: source.to_path_buf(),
+        destination: dest.to_path_buf(),
+        source_is_dir,
+        existing_is_dir: existing.is_dir(),
+    };
+    let merge = conflict.is_merge();
+    plan.conflicts.push(conflict);
+
+    if merge {
+        for entry in std::fs::read_dir(source)? {
+            let entry = entry?;
+            plan_conflicts(&entry.path(), &dest.join(entry.file_name()), plan)?;
+        }
+    }
+    Ok(())
+}
+
+// Shared state of one copy_files_with_resolutions run.
+struct CopyRun {
+    bytes_copied: Arc<AtomicU64>,
+    total_bytes: u64,
+    files_processed: Arc<AtomicU64>,
+    total_files: usize,
+    options: CopyOptions,
+    progress: mpsc::Sender<OperationProgress>,
+    cancel: CancellationToken,
+    // Set by copy_files_concurrent: the paths being written, removed if the
+    // copy is aborted.
+    in_flight: Option<Arc<Mutex<HashSet<PathBuf>>>>,
+}
+
+impl CopyRun {
+    fn track(&self, path: &Path) {
+        if let Some(in_flight) = &self.in_flight {
+            in_flight.lock().insert(path.to_path_buf());
+        }
+    }
+
+    fn untrack(&self, path: &Path) {
+        if let Some(in_flight) = &self.in_flight {
+            in_flight.lock().remove(path);
+        }
+    }
+
+    async fn report(&self, file: &Path) -> Result<()> {
+        self.progress.send(OperationProgress {
+            current_bytes: self.bytes_copied.load(Ordering::Relaxed),
+            total_bytes: self.total_bytes,
+            current_file: file.to_path_buf(),
+            files_processed: self.files_processed.load(Ordering::Relaxed) as usize,
+            total_files: self.total_files,
+            up_to_date: false,
+        }).await.map_err(|_| Error::Cancelled)
+    }
+}
+
+// One copy of copy_files_concurrent, cloned into each spawned task.
+#[derive(Clone)]
+struct CopyTask {
+    ops: Arc<LocalFileOps>,
+    resolutions: Arc<ConflictResolutions>,
+    permits: Arc<Semaphore>,
+    in_flight: Arc<Mutex<HashSet<PathBuf>>>,
+    progress: mpsc::Sender<(usize, OperationProgress)>,
+    totals: (u64, usize),
+    options: CopyOptions,
+    cancel: CancellationToken,
+}
+
+impl CopyTask {
+    // Returns the task index with the bytes and files it copied.
+    async fn run(self, index: usize, source: PathBuf, dest: PathBuf) -> Result<(usize, u64, u64)> {
+        let _permit = self.permits.acquire().await.map_err(|_| Error::Cancelled)?;
+        if self.cancel.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+
+        let (sender, mut receiver) = mpsc::channel(TASK_PROGRESS_BUFFER);
+        let (total_bytes, total_files) = self.totals;
+        let run = CopyRun {
+            bytes_copied: Arc::new(AtomicU64::new(0)),
+            total_bytes,
+            files_processed: Arc::new(AtomicU64::new(0)),
+            total_files,
+            options: self.options,
+            progress: sender,
+            cancel: self.cancel.clone(),
+            in_flight: Some(Arc::clone(&self.in_flight)),
+        };
+        let bytes_copied = Arc::clone(&run.bytes_copied);
+        let files_processed = Arc::clone(&run.files_processed);
+
+        let copy = async {
+            let copied = self.ops.copy_resolved(&source, &dest, &self.resolutions, &run).await;
+            // Ends the forwarding loop below.
+            drop(run);
+            copied
+        };
+        let forward = async {
+            while let Some(update) = receiver.recv().await {
+                if self.progress.send((index, update)).await.is_err() {
+                    break;
+                }
+            }
+        };
+        let (copied, ()) = tokio::join!(copy, forward);
+        copied?;
+
+        Ok((index, bytes_copied.load(Ordering::Relaxed), files_processed.load(Ordering::Relaxed)))
+    }
+}
+
+// What mirror_directory has to change. `dirs`, `files` and `links` are
+// relative to both roots; `replaced` and `extra` are destination paths.
+#[derive(Debug, Default)]
+struct MirrorPlan {
+    // Destination entries of the wrong kind, removed before anything is written.
+    replaced: Vec<PathBuf>,
+    dirs: Vec<PathBuf>,
+    files: Vec<PathBuf>,
+    links: Vec<PathBuf>,
+    extra: Vec<PathBuf>,
+    bytes: u64,
+    skipped: usize,
+    errors: Vec<(PathBuf, Error)>,
+}
+
+struct MirrorWalk {
+    source: PathBuf,
+    dest: PathBuf,
+    compare_by: CompareStrategy,
+    cancel: CancellationToken,
+    plan: MirrorPlan,
+}
+
+impl MirrorWalk {
+    fn dir(&mut self, relative: &Path) -> Result<()> {
+        if self.cancel.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+
+        let mut names = std::collections::HashSet::new();
+        for entry in std::fs::read_dir(self.source.join(relative))? {
+            let entry = entry?;
+            let name = entry.file_name();
+
+            match self.entry(&relative.join(&name)) {
+                Ok(()) => {}
+                Err(Error::Cancelled) => return Err(Error::Cancelled),
+                Err(e) => self.plan.errors.push((entry.path(), e)),
+            }
+            names.insert(name);
+        }
+
+        let dest_dir = self.dest.join(relative);
+        if std::fs::symlink_metadata(&dest_dir).is_ok_and(|m| m.is_dir()) {
+            for entry in std::fs::read_dir(&dest_dir)? {
+                let entry = entry?;
+                if !names.contains(&entry.file_name()) {
+                    self.plan.extra.push(entry.path());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn entry(&mut self, relative: &Path) -> Result<()> {
+        let src = self.source.join(relative);
+        let dest = self.dest.join(relative);
+        let src_meta = std::fs::symlink_metadata(&src)?;
+        let dest_meta = std::fs::symlink_metadata(&dest).ok();
+
+        if !src_meta.is_dir() && !src_meta.is_file() && !src_meta.is_symlink() {
+            return Err(Error::InvalidOperation(format!("Cannot mirror special file {}", src.display())));
+        }
+
+        if src_meta.is_dir() {
+            if dest_meta.is_some_and(|m| !m.is_dir()) {
+                self.plan.replaced.push(dest);
+            }
+            self.plan.dirs.push(relative.to_path_buf());
+            return self.dir(relative);
+        }
+
+        let current = match &dest_meta {
+            Some(existing) if src_meta.is_symlink() => {
+                existing.is_symlink() && std::fs::read_link(&dest)? == std::fs::read_link(&src)?
+            }
+            Some(existing) if existing.is_file() => self.is_mirrored(&src, &src_meta, &dest, existing)?,
+            _ => false,
+        };
+        if current {
+            self.plan.skipped += 1;
+            return Ok(());
+        }
+
+        let replaces_file = src_meta.is_file() && dest_meta.as_ref().is_some_and(|m| m.is_file());
+        if dest_meta.is_some() && !replaces_file {
+            self.plan.replaced.push(dest);
+        }
+
+        if src_meta.is_symlink() {
+            self.plan.links.push(relative.to_path_buf());
+        } else {
+            self.plan.bytes += src_meta.len();
+            self.plan.files.push(relative.to_path_buf());
+        }
+        Ok(())
+    }
+
+    fn is_mirrored(
+        &self,
+        src: &Path,
+        src_meta: &std::fs::Metadata,
+        dest: &Path,
+        dest_meta: &std::fs::Metadata,
+    ) -> Result<bool> {
+        if !src_meta.is_file() || src_meta.len() != dest_meta.len() {
+            return Ok(false);
+        }
+
+        match self.compare_by {
+            CompareStrategy::Size => Ok(true),
+            CompareStrategy::Modified => Ok(dest_meta.modified()? >= src_meta.modified()?),
+            CompareStrategy::Checksum => {
+                Ok(crate::dedup::hash_file(src, &self.cancel)? == crate::dedup::hash_file(dest, &self.cancel)?)
+            }
+        }
+    }
+}
+
+async fn remove_path(path: &Path) -> Result<()> {
+    if fs::symlink_metadata(path).await?.is_dir() {
+        fs::remove_dir_all(path).await?;
+    } else {
+        fs::remove_file(path).await?;
+    }
+    Ok(())
+}
+
+// Runs `attempt` again while it fails with one of `options.retriable_errors`,
+// up to `options.max_retries` times, waiting twice as long before each retry.
+async fn retry_transient<T, F, Fut>(
+    options: &CopyOptions,
+    path: &Path,
+    cancel: &CancellationToken,
+    mut attempt: F,
+) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut retries = 0;
+    loop {
+        match attempt().await {
+            Err(Error::Io(e)) if retries < options.max_retries && is_retriable(&e, &options.retriable_errors) => {
+                let delay = RETRY_BASE_DELAY.saturating_mul(2u32.saturating_pow(retries)).min(RETRY_MAX_DELAY);
+                retries += 1;
+                tracing::debug!("Retrying {} in {:?} after: {}", path.display(), delay, e);
+
+                tokio::select! {
+                    _ = cancel.cancelled() => return Err(Error::Cancelled),
+                    _ = tokio::time::sleep(delay) => {}
+                }
+            }
+            result => return result,
+        }
+    }
+}
+
+fn is_retriable(error: &std::io::Error, retriable: &[Errno]) -> bool {
+    error.raw_os_error().is_some_and(|code| retriable.contains(&Errno::from_raw(code)))
+}
+
+// Size of `src` when `dest` is a regular file of the same size that is at
+// least as new; `None` whenever it should be copied, including a missing dest.
+async fn up_to_date_len(src: &Path, dest: &Path) -> Option<u64> {
+    let src_meta = fs::metadata(src).await.ok()?;
+    let dest_meta = fs::metadata(dest).await.ok()?;
+
+    if !src_meta.is_file() || !dest_meta.is_file() || src_meta.len() != dest_meta.len() {
+        return None;
+    }
+
+    (dest_meta.modified().ok()? >= src_meta.modified().ok()?).then_some(src_meta.len())
+}
+
+fn get_totals_recursive(path: &Path) -> Result<(u64, usize)> {
+    let metadata = std::fs::metadata(path)?;
+
+    // Like the copy itself, anything but a directory counts as a file.
+    if !metadata.is_dir() {
+        return Ok((metadata.len(), 1));
+    }
+
+    let mut totals = (0u64, 0usize);
+    for entry in std::fs::read_dir(path)? {
+        let (bytes, files) = get_totals_recursive(&entry?.path())?;
+        totals = (totals.0 + bytes, totals.1 + files);
+    }
+
+    Ok(totals)
+}
+
+impl Default for LocalFileOps {
+    fn default() -> Self {
+        Self::new(4)
+    }
+}
+
+impl FileOps for LocalFileOps {
+    fn copy_files(
+        &self,
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+        conflict: ConflictResolution,
+        options: CopyOptions,
+        progress: mpsc::Sender<OperationProgress>,
+        cancel: CancellationToken,
+    ) -> OpFuture<'_, ()> {
+        Box::pin(LocalFileOps::copy_files(self, sources, dest_dir, conflict, options, progress, cancel))
+    }
+
+    fn copy_files_with_resolutions<'a>(
+        &'a self,
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+        resolutions: &'a ConflictResolutions,
+        options: CopyOptions,
+        progress: mpsc::Sender<OperationProgress>,
+        cancel: CancellationToken,
+    ) -> OpFuture<'a, ()> {
+        Box::pin(LocalFileOps::copy_files_with_resolutions(
+            self, sources, dest_dir, resolutions, options, progress, cancel,
+        ))
+    }
+
+    fn copy_files_report<'a>(
+        &'a self,
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+        resolutions: &'a ConflictResolutions,
+        options: CopyOptions,
+        progress: mpsc::Sender<OperationProgress>,
+        cancel: CancellationToken,
+    ) -> OpFuture<'a, OperationReport> {
+        Box::pin(LocalFileOps::copy_files_report(self, sources, dest_dir, resolutions, options, progress, cancel))
+    }
+
+    fn batch_copy(
+        &self,
+        jobs: Vec<BatchJob>,
+        conflict: ConflictResolution,
+        options: CopyOptions,
+        progress: mpsc::Sender<BatchProgress>,
+        cancel: CancellationToken,
+    ) -> OpFuture<'_, BatchReport> {
+        Box::pin(LocalFileOps::batch_copy(self, jobs, conflict, options, progress, cancel))
+    }
+
+    #[cfg(target_os = "linux")]
+    fn copy_on_write_clone<'a>(&'a self, src: &'a Path, dest: &'a Path) -> OpFuture<'a, ()> {
+        Box::pin(LocalFileOps::copy_on_write_clone(self, src, dest))
+    }
+
+    fn copy_sparse<'a>(
+        &'a self,
+        src: &'a Path,
+        dest: &'a Path,
+        progress: mpsc::Sender<OperationProgress>,
+        cancel: CancellationToken,
+    ) -> OpFuture<'a, ()> {
+        Box::pin(LocalFileOps::copy_sparse(self, src, dest, progress, cancel))
+    }
+
+    fn move_files(
+        &self,
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+        conflict: ConflictResolution,
+        progress: mpsc::Sender<OperationProgress>,
+        cancel: CancellationToken,
+    ) -> OpFuture<'_, ()> {
+        Box::pin(LocalFileOps::move_files(self, sources, dest_dir, conflict, progress, cancel))
+    }
+
+    fn move_files_with_resolutions<'a>(
+        &'a self,
+        sources: Vec<PathBuf>,
+        dest_dir: PathBuf,
+        resolutions: &'a ConflictResolutions,
+        progress: mpsc::Sender<OperationProgress>,
+        cancel: CancellationToken,
+    ) -> OpFuture<'a, ()> {
+        Box::pin(LocalFileOps::move_files_with_resolutions(self, sources, dest_dir, resolutions, progress, cancel))
+    }
+
+    fn delete_files<'a>(
+        &'a self,
+        paths: Vec<PathBuf>,
+        security: Option<&'a Security>,
+        progress: mpsc::Sender<OperationProgress>,
+        cancel: CancellationToken,
+    ) -> OpFuture<'a, ()> {
+        Box::pin(LocalFileOps::delete_files(self, paths, security, progress, cancel))
+    }
+
+    fn mirror_directory<'a>(
+        &'a self,
+        source: &'a Path,
+        dest: &'a Path,
+        options: MirrorOptions,
+        progress: mpsc::Sender<OperationProgress>,
+        cancel: CancellationToken,
+    ) -> OpFuture<'a, MirrorReport> {
+        Box::pin(LocalFileOps::mirror_directory(self, source, dest, options, progress, cancel))
+    }
+
+    fn symlink<'a>(&'a self, target: &'a Path, link: &'a Path, relative: bool) -> OpFuture<'a, ()> {
+        Box::pin(LocalFileOps::symlink(self, target, link, relative))
+    }
+
+    fn plan<'a>(&'a self, sources: &'a [PathBuf], dest_dir: &'a Path) -> OpFuture<'a, OperationPlan> {
+        Box::pin(LocalFileOps::plan(self, sources, dest_dir))
+    }
+
+    fn dry_run_copy<'a>(&'a self, sources: &'a [PathBuf], dest_dir: &'a Path) -> OpFuture<'a, DryRunReport> {
+        Box::pin(LocalFileOps::dry_run_copy(self, sources, dest_dir))
+    }
+
+    fn dry_run_move<'a>(&'a self, sources: &'a [PathBuf], dest_dir: &'a Path) -> OpFuture<'a, DryRunReport> {
+        Box::pin(LocalFileOps::dry_run_move(self, sources, dest_dir))
+    }
+
+    fn write_atomic<'a>(&'a self, path: &'a Path, contents: Vec<u8>) -> OpFuture<'a, ()> {
+        Box::pin(LocalFileOps::write_atomic(self, path, contents))
+    }
+}
+
+fn parent_or_current(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+static ATOMIC_WRITE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+fn atomic_write_with(path: &Path, data: &[u8], after_temp_write: impl FnOnce(&Path)) -> Result<()> {
+    use std::io::Write;
+
+    let file_name = path.file_name()
+        .ok_or_else(|| Error::InvalidPath { path: path.to_path_buf() })?;
+    let parent = parent_or_current(path);
+
+    // The temp file lives in the destination directory so the rename never
+    // crosses a filesystem boundary.
+    let temp_path = parent.join(format!(
+        ".{}.{}.{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id(),
+        ATOMIC_WRITE_COUNTER.fetch_add(1, Ordering::Relaxed),
+    ));
+
+    let permissions = std::fs::metadata(path).ok().map(|m| m.permissions());
+    let written = std::fs::File::create(&temp_path).and_then(|mut file| {
+        if let Some(permissions) = permissions {
+            file.set_permissions(permissions)?;
+        }
+        file.write_all(data)?;
+        file.sync_all()
+    });
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(e.into());
+    }
+
+    after_temp_write(&temp_path);
+
+    if let Err(e) = std::fs::rename(&temp_path, path) {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(e.into());
+    }
+
+    #[cfg(unix)]
+    std::fs::File::open(parent)?.sync_all()?;
+
+    Ok(())
+}
+
+fn device_of(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    std::fs::metadata(path).ok().map(|m| m.dev())
+}
+
+// A labeling failure leaves the copied data intact, so it is logged rather than
+// failing the whole operation.
+fn apply_selinux_label(src: &Path, dest: &Path, labeling: SelinuxLabeling) {
+    if labeling == SelinuxLabeling::Ignore || !selinux::is_enabled() {
+        return;
+    }
+
+    let result = match labeling {
+        SelinuxLabeling::PreserveSource => {
+            selinux::get_file_context(src).and_then(|context| selinux::set_file_context(dest, &context))
+        }
+        SelinuxLabeling::PolicyDefault => selinux::restore_context(dest),
+        SelinuxLabeling::Ignore => Ok(()),
+    };
+
+    if let Err(e) = result {
+        tracing::warn!("Failed to label {}: {}", dest.display(), e);
+    }
+}
+
+fn is_skippable_xattr_error(e: &std::io::Error) -> bool {
+    matches!(e.raw_os_error(), Some(libc::EPERM) | Some(libc::EACCES) | Some(libc::ENOTSUP))
+}
+
+#[cfg(target_os = "linux")]
+fn reflink_blocking(src: &Path, dest: &Path) -> Result<bool> {
+    use std::os::unix::io::AsRawFd;
+
+    let src_file = std::fs::File::open(src)?;
+    let dest_file = std::fs::File::create(dest)?;
+
+    let range = libc::file_clone_range {
+        src_fd: src_file.as_raw_fd() as i64,
+        src_offset: 0,
+        src_length: 0,
+        dest_offset: 0,
+    };
+
+    let result = unsafe { libc::ioctl(dest_file.as_raw_fd(), libc::FICLONERANGE, &range) };
+    if result == 0 {
+        return Ok(true);
+    }
+
+    let err = std::io::Error::last_os_error();
+    match err.raw_os_error() {
+        Some(libc::EOPNOTSUPP) | Some(libc::EXDEV) | Some(libc::EINVAL) | Some(libc::ENOTTY) => Ok(false),
+        _ => Err(err.into()),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn is_cow_filesystem(path: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = match std::ffi::CString::new(path.as_os_str().as_bytes()) {
+        Ok(c_path) => c_path,
+        Err(_) => return false,
+    };
+
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return false;
+    }
+
+    let fs_type = stat.f_type as u32;
+    fs_type == libc::BTRFS_SUPER_MAGIC as u32 || fs_type == libc::XFS_SUPER_MAGIC as u32
+}
+
+/// Copies `src` while leaving its holes unallocated in `dest`. `on_progress`
+/// receives the size of each data chunk written and of each hole skipped, so
+/// the reported bytes add up to the file length. Returns false without
+/// touching `dest` when `src` is not sparse or the filesystem cannot say.
+#[cfg(target_os = "linux")]
+fn copy_sparse_blocking(
+    src: &Path,
+    dest: &Path,
+    cancel: &CancellationToken,
+    mut on_progress: impl FnMut(u64) -> Result<()>,
+) -> Result<bool> {
+    use std::os::unix::fs::{FileExt, MetadataExt};
+    use std::os::unix::io::AsRawFd;
+
+    let src_file = std::fs::File::open(src)?;
+    let metadata = src_file.metadata()?;
+    let len = metadata.len();
+    let fd = src_file.as_raw_fd();
+
+    if metadata.blocks() * 512 >= len {
+        return Ok(false);
+    }
+
+    match seek_sparse(fd, 0, libc::SEEK_HOLE) {
+        Ok(first_hole) if first_hole < len => {}
+        _ => return Ok(false),
+    }
+
+    let dest_file = std::fs::File::create(dest)?;
+    dest_file.set_len(len)?;
+
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    let mut offset = 0u64;
+
+    while offset < len {
+        let data_start = match seek_sparse(fd, offset, libc::SEEK_DATA) {
+            Ok(start) => start.min(len),
+            Err(e) if e.raw_os_error() == Some(libc::ENXIO) => len,
+            Err(e) => return Err(e.into()),
+        };
+        if data_start > offset {
+            on_progress(data_start - offset)?;
+        }
+        if data_start >= len {
+            break;
+        }
+        let data_end = seek_sparse(fd, data_start, libc::SEEK_HOLE)?.min(len);
+
+        let mut pos = data_start;
+        while pos < data_end {
+            if cancel.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+
+            let want = ((data_end - pos) as usize).min(BUFFER_SIZE);
+            let n = src_file.read_at(&mut buffer[..want], pos)?;
+            if n == 0 {
+                break;
+            }
+
+            dest_file.write_all_at(&buffer[..n], pos)?;
+            pos += n as u64;
+            on_progress(n as u64)?;
+        }
+
+        offset = data_end;
+    }
+
+    Ok(true)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn copy_sparse_blocking(
+    _src: &Path,
+    _dest: &Path,
+    _cancel: &CancellationToken,
+    _on_progress: impl FnMut(u64) -> Result<()>,
+) -> Result<bool> {
+    Ok(false)
+}
+
+#[cfg(target_os = "linux")]
+fn seek_sparse(fd: std::os::unix::io::RawFd, offset: u64, whence: libc::c_int) -> std::io::Result<u64> {
+    let result = unsafe { libc::lseek(fd, offset as libc::off_t, whence) };
+    if result < 0 {
+        Err(std::io::Error::last_os_error())
+    } else {
+        Ok(result as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::audit::AuditLog;
+    use crate::security::mock_authorizer::{AuthCall, MockAuthorizer};
+    use tempfile::TempDir;
+
+    fn temp_files(dir: &Path) -> Vec<PathBuf> {
+        std::fs::read_dir(dir).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "tmp"))
+            .collect()
+    }
+
+    #[test]
+    fn test_atomic_write() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("config.toml");
+
+        LocalFileOps::atomic_write(&path, b"first").unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"first");
+
+        LocalFileOps::atomic_write(&path, b"second").unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"second");
+        assert!(temp_files(temp_dir.path()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_write_atomic_preserves_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("secrets.toml");
+        std::fs::write(&path, b"token = 1").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
+
+        let ops = LocalFileOps::default();
+        ops.write_atomic(&path, "token = 2").await.unwrap();
+
+        assert_eq!(std::fs::read(&path).unwrap(), b"token = 2");
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+    }
+
+    #[test]
+    fn test_atomic_write_readers_never_see_partial_contents() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("state.json");
+        let old = vec![b'a'; 256 * 1024];
+        let new = vec![b'b'; 512 * 1024];
+        std::fs::write(&path, &old).unwrap();
+
+        let done = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let reader = {
+            let (path, old, new, done) = (path.clone(), old.clone(), new.clone(), Arc::clone(&done));
+            std::thread::spawn(move || {
+                let mut reads = 0;
+                while !done.load(Ordering::Relaxed) || reads == 0 {
+                    let contents = std::fs::read(&path).unwrap();
+                    assert!(contents == old || contents == new, "read {} bytes mid-write", contents.len());
+                    reads += 1;
+                }
+            })
+        };
+
+        for i in 0..50 {
+            LocalFileOps::atomic_write(&path, if i % 2 == 0 { &new } else { &old }).unwrap();
+        }
+        done.store(true, Ordering::Relaxed);
+        reader.join().unwrap();
+    }
+
+    #[test]
+    fn test_atomic_write_crash_before_rename() {
+        let temp_dir = TempDir::new().unwrap();
+        let fresh = temp_dir.path().join("fresh.json");
+        let existing = temp_dir.path().join("existing.json");
+        std::fs::write(&existing, b"old contents").unwrap();
+
+        for path in [&fresh, &existing] {
+            let crashed = std::panic::catch_unwind(|| {
+                atomic_write_with(path, b"new contents", |_| panic!("simulated crash"))
+            });
+            assert!(crashed.is_err());
+        }
+
+        assert!(!fresh.exists());
+        assert_eq!(std::fs::read(&existing).unwrap(), b"old contents");
+
+        // The crash leaves only the fully written temp files behind.
+        let leftovers = temp_files(temp_dir.path());
+        assert_eq!(leftovers.len(), 2);
+        for leftover in leftovers {
+            assert_eq!(std::fs::read(leftover).unwrap(), b"new contents");
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn allocated_bytes(path: &Path) -> u64 {
+        use std::os::unix::fs::MetadataExt;
+        std::fs::metadata(path).unwrap().blocks() * 512
+    }
+
+    // Writes a 4 MiB file with a 2 MiB hole punched in the middle. The temp
+    // filesystem must support hole punching; tmpfs, ext4, xfs and btrfs do.
+    #[cfg(target_os = "linux")]
+    fn write_sparse_file(path: &Path) -> Vec<u8> {
+        use std::os::unix::io::AsRawFd;
+
+        const MIB: usize = 1024 * 1024;
+
+        let data: Vec<u8> = (0..4 * MIB).map(|i| (i % 251) as u8 + 1).collect();
+        std::fs::write(path, &data).unwrap();
+
+        let file = std::fs::OpenOptions::new().write(true).open(path).unwrap();
+        let punched = unsafe {
+            libc::fallocate(
+                file.as_raw_fd(),
+                libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_KEEP_SIZE,
+                MIB as libc::off_t,
+                (2 * MIB) as libc::off_t,
+            )
+        };
+        assert_eq!(punched, 0, "cannot punch holes in {}: {}", path.display(), std::io::Error::last_os_error());
+        drop(file);
+
+        assert!(allocated_bytes(path) < data.len() as u64, "punched file is not sparse");
+        std::fs::read(path).unwrap()
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_copy_sparse_preserves_holes() {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("disk.img");
+        let dest = temp_dir.path().join("disk-copy.img");
+
+        let data = write_sparse_file(&src);
+
+        let (tx, mut rx) = mpsc::channel(1024);
+        let ops = LocalFileOps::default();
+        ops.copy_sparse(&src, &dest, tx, CancellationToken::new()).await.unwrap();
+
+        let mut last = None;
+        while let Ok(p) = rx.try_recv() {
+            last = Some(p);
+        }
+        assert_eq!(last.unwrap().current_bytes, data.len() as u64);
+
+        assert_eq!(std::fs::read(&src).unwrap(), std::fs::read(&dest).unwrap());
+        assert!(allocated_bytes(&dest) < data.len() as u64);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_copy_files_keeps_sparse_files_sparse() {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("disk.img");
+        let dest_dir = temp_dir.path().join("out");
+        std::fs::create_dir(&dest_dir).unwrap();
+
+        let data = write_sparse_file(&src);
+
+        let (tx, mut rx) = mpsc::channel(1024);
+        let ops = LocalFileOps::default();
+        ops.copy_files(
+            vec![src.clone()],
+            dest_dir.clone(),
+            ConflictResolution::Overwrite,
+            CopyOptions::default(),
+            tx,
+            CancellationToken::new(),
+        ).await.unwrap();
+
+        let mut last = None;
+        while let Ok(p) = rx.try_recv() {
+            last = Some(p);
+        }
+        assert_eq!(last.unwrap().current_bytes, data.len() as u64);
+
+        let dest = dest_dir.join("disk.img");
+        assert_eq!(std::fs::read(&dest).unwrap(), data);
+        assert!(allocated_bytes(&dest) < data.len() as u64 / 2 + 512 * 1024);
+    }
+
+    #[tokio::test]
+    async fn test_copy_sparse_dense_fallback() {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("dense.txt");
+        let dest = temp_dir.path().join("dense-copy.txt");
+        std::fs::write(&src, b"no holes here").unwrap();
+
+        let (tx, _rx) = mpsc::channel(1024);
+        let ops = LocalFileOps::default();
+        ops.copy_sparse(&src, &dest, tx, CancellationToken::new()).await.unwrap();
+
+        assert_eq!(std::fs::read(&dest).unwrap(), b"no holes here");
+    }
+
+    #[tokio::test]
+    async fn test_copy_preserves_xattrs() {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("tagged.txt");
+        let dest_dir = temp_dir.path().join("out");
+        std::fs::write(&src, b"with attributes").unwrap();
+        std::fs::create_dir(&dest_dir).unwrap();
+
+        xattr::set(&src, "user.test", b"cheese").expect("temp filesystem must support user xattrs");
+
+        let (tx, _rx) = mpsc::channel(1024);
+        let ops = LocalFileOps::default();
+        ops.copy_files(
+            vec![src.clone()],
+            dest_dir.clone(),
+            ConflictResolution::Overwrite,
+            CopyOptions { preserve_xattrs: true, ..CopyOptions::default() },
+            tx,
+            CancellationToken::new(),
+        ).await.unwrap();
+
+        let value = xattr::get(dest_dir.join("tagged.txt"), "user.test").unwrap();
+        assert_eq!(value.as_deref(), Some(&b"cheese"[..]));
+    }
+
+    #[tokio::test]
+    async fn test_copy_without_xattrs() {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("tagged.txt");
+        let dest_dir = temp_dir.path().join("out");
+        std::fs::write(&src, b"with attributes").unwrap();
+        std::fs::create_dir(&dest_dir).unwrap();
+
+        xattr::set(&src, "user.test", b"cheese").expect("temp filesystem must support user xattrs");
+
+        let (tx, _rx) = mpsc::channel(1024);
+        let ops = LocalFileOps::default();
+        ops.copy_files(
+            vec![src.clone()],
+            dest_dir.clone(),
+            ConflictResolution::Overwrite,
+            CopyOptions::default(),
+            tx,
+            CancellationToken::new(),
+        ).await.unwrap();
+
+        assert!(xattr::get(dest_dir.join("tagged.txt"), "user.test").unwrap().is_none());
+    }
+
+    // Without a working SELinux the labeling options must be a no-op; with it,
+    // the copy must carry the source context.
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_copy_preserves_selinux_context() {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("labeled.txt");
+        let dest_dir = temp_dir.path().join("out");
+        std::fs::write(&src, b"label me").unwrap();
+        std::fs::create_dir(&dest_dir).unwrap();
+
+        for selinux in [SelinuxLabeling::Ignore, SelinuxLabeling::PreserveSource, SelinuxLabeling::PolicyDefault] {
+            let (tx, _rx) = mpsc::channel(1024);
+            LocalFileOps::default().copy_files(
+                vec![src.clone()],
+                dest_dir.clone(),
+                ConflictResolution::Overwrite,
+                CopyOptions { selinux, ..CopyOptions::default() },
+                tx,
+                CancellationToken::new(),
+            ).await.unwrap();
+
+            let dest = dest_dir.join("labeled.txt");
+            assert_eq!(std::fs::read(&dest).unwrap(), b"label me");
+
+            if selinux != SelinuxLabeling::PreserveSource || !selinux::is_enabled() {
+                continue;
+            }
+            if let Ok(source_context) = selinux::get_file_context(&src) {
+                assert_eq!(selinux::get_file_context(&dest).unwrap(), source_context);
+            }
+        }
+    }
+
+    // Whether or not the filesystem can reflink, the clone must hold the
+    // source's bytes.
+    #[cfg(target_os = "linux")]
+    fn assert_clone(dir: &Path) {
+        let src = dir.join("clone-src.bin");
+        let dest = dir.join("clone-dest.bin");
+        std::fs::write(&src, vec![7u8; 256 * 1024]).unwrap();
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(LocalFileOps::default().copy_on_write_clone(&src, &dest)).unwrap();
+        assert_eq!(std::fs::read(&src).unwrap(), std::fs::read(&dest).unwrap());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_copy_on_write_clone_fallback() {
+        let temp_dir = TempDir::new().unwrap();
+        assert_clone(temp_dir.path());
+    }
+
+    // CHEESE_BTRFS_TEST_DIR=/mnt/btrfs/tmp cargo test --lib test_copy_on_write_clone_btrfs -- --ignored
+    #[cfg(target_os = "linux")]
+    #[test]
+    #[ignore = "needs CHEESE_BTRFS_TEST_DIR set to a directory on btrfs"]
+    fn test_copy_on_write_clone_btrfs() {
+        let dir = std::env::var("CHEESE_BTRFS_TEST_DIR").expect("CHEESE_BTRFS_TEST_DIR is not set");
+        let temp_dir = TempDir::new_in(dir).unwrap();
+        assert!(is_cow_filesystem(temp_dir.path()));
+
+        let src = temp_dir.path().join("reflink-src.bin");
+        std::fs::write(&src, vec![7u8; 256 * 1024]).unwrap();
+        assert!(reflink_blocking(&src, &temp_dir.path().join("reflink-dest.bin")).unwrap());
+
+        assert_clone(temp_dir.path());
+    }
+
+    fn batch_job(root: &Path, label: &str, sizes: &[usize]) -> BatchJob {
+        let src_dir = root.join(format!("{}-src", label));
+        let dest_dir = root.join(format!("{}-dest", label));
+        std::fs::create_dir_all(&src_dir).unwrap();
+        std::fs::create_dir_all(&dest_dir).unwrap();
+
+        let sources = sizes
+            .iter()
+            .enumerate()
+            .map(|(i, size)| {
+                let path = src_dir.join(format!("file{}.bin", i));
+                std::fs::write(&path, vec![i as u8; *size]).unwrap();
+                path
+            })
+            .collect();
+
+        BatchJob {
+            sources,
+            dest_dir,
+            label: label.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batch_copy() {
+        let temp_dir = TempDir::new().unwrap();
+        let jobs = vec![
+            batch_job(temp_dir.path(), "small", &[10]),
+            batch_job(temp_dir.path(), "medium", &[4096, 100, 0]),
+            batch_job(temp_dir.path(), "large", &[3 * BUFFER_SIZE + 17, 2 * BUFFER_SIZE]),
+        ];
+        let expected: Vec<(PathBuf, u64)> = jobs
+            .iter()
+            .map(|job| {
+                let total = job.sources.iter().map(|s| std::fs::metadata(s).unwrap().len()).sum();
+                (job.dest_dir.clone(), total)
+            })
+            .collect();
+
+        let (tx, mut rx) = mpsc::channel(1024);
+        let collector = tokio::spawn(async move {
+            let mut events = Vec::new();
+            while let Some(event) = rx.recv().await {
+                events.push(event);
+            }
+            events
+        });
+
+        let report = LocalFileOps::default()
+            .batch_copy(jobs, ConflictResolution::Rename, CopyOptions::default(), tx, CancellationToken::new())
+            .await
+            .unwrap();
+        let events: Vec<BatchProgress> = collector.await.unwrap();
+
+        assert_eq!(report.completed, vec!["small", "medium", "large"]);
+        assert!(report.failed.is_empty());
+
+        assert!(events.windows(2).all(|w| w[0].job_index <= w[1].job_index));
+        for (job_index, (dest_dir, total)) in expected.iter().enumerate() {
+            let last = events.iter().rev().find(|e| e.job_index == job_index).unwrap();
+            assert_eq!(last.inner.current_bytes, *total);
+            assert_eq!(last.inner.total_bytes, *total);
+
+            let copied: u64 = std::fs::read_dir(dest_dir)
+                .unwrap()
+                .map(|e| e.unwrap().metadata().unwrap().len())
+                .sum();
+            assert_eq!(copied, *total);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_transient_errors() {
+        use std::sync::atomic::AtomicU32;
+
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("src.txt");
+        let dest = temp_dir.path().join("dest.txt");
+        std::fs::write(&src, "contents").unwrap();
+        let cancel = CancellationToken::new();
+        let options = CopyOptions { max_retries: 3, retriable_errors: TRANSIENT_ERRORS.to_vec(), ..CopyOptions::default() };
+
+        // Stands in for the IO layer: fails with `errors` in turn, then copies.
+        let flaky = |errors: Vec<Errno>| {
+            let attempts = Arc::new(AtomicU32::new(0));
+            let counter = Arc::clone(&attempts);
+            let (src, dest) = (src.clone(), dest.clone());
+            let attempt = move || {
+                let n = counter.fetch_add(1, Ordering::Relaxed) as usize;
+                let result = match errors.get(n) {
+                    Some(errno) => Err(std::io::Error::from_raw_os_error(*errno as i32).into()),
+                    None => std::fs::copy(&src, &dest).map(|_| ()).map_err(Error::from),
+                };
+                async move { result }
+            };
+            (attempts, attempt)
+        };
+
+        let (attempts, attempt) = flaky(vec![Errno::EAGAIN, Errno::ETIMEDOUT]);
+        retry_transient(&options, &src, &cancel, attempt).await.unwrap();
+        assert_eq!(attempts.load(Ordering::Relaxed), 3);
+        assert_eq!(std::fs::read_to_string(&dest).unwrap(), "contents");
+        std::fs::remove_file(&dest).unwrap();
+
+        for errno in [Errno::ENOSPC, Errno::EACCES] {
+            let (attempts, attempt) = flaky(vec![errno]);
+            let result = retry_transient(&options, &src, &cancel, attempt).await;
+            assert!(matches!(result, Err(Error::Io(e)) if e.raw_os_error() == Some(errno as i32)));
+            assert_eq!(attempts.load(Ordering::Relaxed), 1);
+        }
+
+        let limited = CopyOptions { max_retries: 1, ..options };
+        let (attempts, attempt) = flaky(vec![Errno::EAGAIN, Errno::EAGAIN]);
+        assert!(retry_transient(&limited, &src, &cancel, attempt).await.is_err());
+        assert_eq!(attempts.load(Ordering::Relaxed), 2);
+        assert!(!dest.exists());
+
+        // Retries are off by default.
+        let (attempts, attempt) = flaky(vec![Errno::EAGAIN]);
+        assert!(retry_transient(&CopyOptions::default(), &src, &cancel, attempt).await.is_err());
+        assert_eq!(attempts.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_copy_file_retries_failed_io() {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("src.txt");
+        let dest = temp_dir.path().join("dest.txt");
+        std::fs::write(&src, "contents").unwrap();
+
+        let ops = LocalFileOps::default();
+        ops.io_faults.lock().extend([Errno::EAGAIN, Errno::ETIMEDOUT]);
+        let options = CopyOptions { max_retries: 3, retriable_errors: TRANSIENT_ERRORS.to_vec(), ..CopyOptions::default() };
+        let (bytes_copied, files_processed) = (Arc::new(AtomicU64::new(0)), Arc::new(AtomicU64::new(0)));
+        let (tx, _rx) = mpsc::channel(16);
+
+        let started = tokio::time::Instant::now();
+        ops.copy_file_with_progress(&src, &dest, &bytes_copied, 8, &files_processed, 1, &options, &tx, &CancellationToken::new())
+            .await
+            .unwrap();
+
+        // Two retries, backing off 100ms and then 200ms.
+        assert_eq!(started.elapsed(), RETRY_BASE_DELAY * 3);
+        assert!(ops.io_faults.lock().is_empty());
+        assert_eq!(std::fs::read_to_string(&dest).unwrap(), "contents");
+        assert_eq!(bytes_copied.load(Ordering::Relaxed), 8);
+        assert_eq!(files_processed.load(Ordering::Relaxed), 1);
+
+        std::fs::remove_file(&dest).unwrap();
+        ops.io_faults.lock().extend([Errno::ENOSPC, Errno::EAGAIN]);
+        let result = ops
+            .copy_file_with_progress(&src, &dest, &bytes_copied, 8, &files_processed, 1, &options, &tx, &CancellationToken::new())
+            .await;
+        assert!(matches!(result, Err(Error::Io(e)) if e.raw_os_error() == Some(Errno::ENOSPC as i32)));
+        assert_eq!(ops.io_faults.lock().len(), 1);
+        assert!(!dest.exists());
+    }
+
+    #[tokio::test]
+    async fn test_copy_update_only() {
+        use std::time::{Duration, SystemTime};
+
+        let temp_dir = TempDir::new().unwrap();
+        let src_dir = temp_dir.path().join("src");
+        let dest_dir = temp_dir.path().join("dest");
+        std::fs::create_dir_all(src_dir.join("nested")).unwrap();
+        std::fs::create_dir_all(dest_dir.join("nested")).unwrap();
+
+        let base = SystemTime::now() - Duration::from_secs(3600);
+        let write = |path: PathBuf, contents: &str, mtime: SystemTime| {
+            std::fs::write(&path, contents).unwrap();
+            std::fs::File::options().write(true).open(&path).unwrap().set_modified(mtime).unwrap();
+        };
+
+        // (name, source mtime, destination, copied)
+        let cases = [
+            ("newer.txt", base, Some(("dest", base + Duration::from_secs(60))), false),
+            ("equal.txt", base, Some(("dest", base)), false),
+            ("older.txt", base, Some(("dest", base - Duration::from_secs(60))), true),
+            ("resized.txt", base, Some(("longer dest", base + Duration::from_secs(60))), true),
+            ("missing.txt", base, None, true),
+            ("nested/equal.txt", base, Some(("dest", base)), false),
+        ];
+        for (name, src_mtime, dest, _) in cases {
+            write(src_dir.join(name), "srcs", src_mtime);
+            if let Some((contents, mtime)) = dest {
+                write(dest_dir.join(name), contents, mtime);
+            }
+        }
+
+        let sources = ["newer.txt", "equal.txt", "older.txt", "resized.txt", "missing.txt", "nested"]
+            .iter()
+            .map(|name| src_dir.join(name))
+            .collect();
+        let (tx, mut rx) = mpsc::channel(1024);
+        LocalFileOps::default()
+            .copy_files(
+                sources,
+                dest_dir.clone(),
+                ConflictResolution::Overwrite,
+                CopyOptions { update_only: true, ..CopyOptions::default() },
+                tx,
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+
+        let mut up_to_date = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            if event.up_to_date {
+                up_to_date.push(event.current_file.strip_prefix(&src_dir).unwrap().to_path_buf());
+            }
+        }
+        up_to_date.sort();
+        assert_eq!(
+            up_to_date,
+            vec![PathBuf::from("equal.txt"), PathBuf::from("nested/equal.txt"), PathBuf::from("newer.txt")]
+        );
+
+        for (name, _, _, copied) in cases {
+            let contents = std::fs::read_to_string(dest_dir.join(name)).unwrap();
+            assert_eq!(contents == "srcs", copied, "{}", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_copy_reports_conflicts() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_dir = temp_dir.path().join("src");
+        let dest_dir = temp_dir.path().join("dest");
+        std::fs::create_dir_all(src_dir.join("album")).unwrap();
+        std::fs::create_dir_all(dest_dir.join("album")).unwrap();
+
+        std::fs::write(src_dir.join("notes.txt"), "new notes").unwrap();
+        std::fs::write(src_dir.join("fresh.txt"), "fresh").unwrap();
+        std::fs::write(src_dir.join("album/one.jpg"), "1111").unwrap();
+        std::fs::write(src_dir.join("album/two.jpg"), "22").unwrap();
+        std::fs::write(dest_dir.join("notes.txt"), "old").unwrap();
+        std::fs::write(dest_dir.join("album/one.jpg"), "older image").unwrap();
+
+        let sources: Vec<PathBuf> = ["notes.txt", "fresh.txt", "album"].iter().map(|n| src_dir.join(n)).collect();
+        let ops = LocalFileOps::default();
+        let mut report = ops.dry_run_copy(&sources, &dest_dir).await.unwrap();
+        report.would_create.sort();
+        report.would_conflict.sort_by(|a, b| a.destination.cmp(&b.destination));
+
+        assert_eq!(report.would_create, vec![dest_dir.join("album/two.jpg"), dest_dir.join("fresh.txt")]);
+        assert_eq!(report.would_conflict, vec![
+            ConflictInfo {
+                source: src_dir.join("album/one.jpg"),
+                destination: dest_dir.join("album/one.jpg"),
+                source_size: 4,
+                dest_size: 11,
+            },
+            ConflictInfo {
+                source: src_dir.join("notes.txt"),
+                destination: dest_dir.join("notes.txt"),
+                source_size: 9,
+                dest_size: 3,
+            },
+        ]);
+        assert_eq!(report.estimated_bytes, 9 + 5 + 4 + 2);
+
+        // Nothing was written.
+        assert!(!dest_dir.join("fresh.txt").exists());
+        assert_eq!(std::fs::read_to_string(dest_dir.join("notes.txt")).unwrap(), "old");
+
+        let moved = ops.dry_run_move(&sources, &dest_dir).await.unwrap();
+        assert_eq!(moved.estimated_bytes, 0);
+        assert_eq!(moved.would_create, vec![dest_dir.join("fresh.txt")]);
+        assert_eq!(moved.would_conflict.len(), 2);
+
+        assert!(matches!(
+            ops.dry_run_copy(&sources, &dest_dir.join("missing")).await,
+            Err(Error::InvalidPath { .. })
+        ));
+    }
+
+    fn write_small_files(dir: &Path, count: usize) -> Vec<PathBuf> {
+        std::fs::create_dir_all(dir).unwrap();
+        (0..count)
+            .map(|i| {
+                let path = dir.join(format!("file{}.txt", i));
+                std::fs::write(&path, format!("contents of file {}", i)).unwrap();
+                path
+            })
+            .collect()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_concurrent_copy_copies_every_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut sources = write_small_files(&temp_dir.path().join("src"), 100);
+        std::fs::create_dir_all(temp_dir.path().join("src/nested/deeper")).unwrap();
+        std::fs::write(temp_dir.path().join("src/nested/deeper/inner.txt"), "inner").unwrap();
+        sources.push(temp_dir.path().join("src/nested"));
+        let dest_dir = temp_dir.path().join("dest");
+        std::fs::create_dir_all(&dest_dir).unwrap();
+
+        let (tx, mut rx) = mpsc::channel(16);
+        let updates = tokio::spawn(async move {
+            let mut last = None;
+            while let Some(update) = rx.recv().await {
+                last = Some(update);
+            }
+            last
+        });
+
+        let options = CopyOptions { max_concurrent: 8, ..CopyOptions::default() };
+        LocalFileOps::default()
+            .copy_files(sources, dest_dir.clone(), ConflictResolution::Overwrite, options, tx, CancellationToken::new())
+            .await
+            .unwrap();
+
+        for i in 0..100 {
+            let contents = std::fs::read_to_string(dest_dir.join(format!("file{}.txt", i))).unwrap();
+            assert_eq!(contents, format!("contents of file {}", i));
+        }
+        assert_eq!(std::fs::read_to_string(dest_dir.join("nested/deeper/inner.txt")).unwrap(), "inner");
+
+        let last = updates.await.unwrap().unwrap();
+        assert_eq!(last.current_bytes, last.total_bytes);
+        // Directories themselves are not counted.
+        assert_eq!(last.files_processed, 101);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_concurrent_copy_cancel_removes_partial_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_dir = temp_dir.path().join("src");
+        std::fs::create_dir_all(&src_dir).unwrap();
+        let sources: Vec<PathBuf> = (0..4)
+            .map(|i| {
+                let path = src_dir.join(format!("big{}.bin", i));
+                std::fs::write(&path, vec![i as u8; BUFFER_SIZE * 16]).unwrap();
+                path
+            })
+            .collect();
+        let dest_dir = temp_dir.path().join("dest");
+        std::fs::create_dir_all(&dest_dir).unwrap();
+
+        // Nobody reads progress, so the tasks stall once the channels fill
+        // up, until the copy is cancelled.
+        let (tx, _rx) = mpsc::channel(1);
+        let cancel = CancellationToken::new();
+        let canceller = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            canceller.cancel();
+        });
+
+        let options = CopyOptions { max_concurrent: 2, ..CopyOptions::default() };
+        let result = LocalFileOps::default()
+            .copy_files(sources, dest_dir.clone(), ConflictResolution::Overwrite, options, tx, cancel)
+            .await;
+
+        assert!(matches!(result, Err(Error::Cancelled)));
+        // Copies that finished before the cancellation may stay; nothing
+        // half-written does.
+        for entry in std::fs::read_dir(&dest_dir).unwrap() {
+            let entry = entry.unwrap();
+            assert_eq!(entry.metadata().unwrap().len(), (BUFFER_SIZE * 16) as u64, "{:?}", entry.path());
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_concurrent_copy_cancelled_keeps_renamed_over_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_dir = temp_dir.path().join("src");
+        let dest_dir = temp_dir.path().join("dest");
+        std::fs::create_dir_all(&src_dir).unwrap();
+        std::fs::create_dir_all(&dest_dir).unwrap();
+        let sources: Vec<PathBuf> = (0..2)
+            .map(|i| {
+                let path = src_dir.join(format!("big{}.bin", i));
+                std::fs::write(&path, vec![i as u8; BUFFER_SIZE * 16]).unwrap();
+                std::fs::write(dest_dir.join(format!("big{}.bin", i)), "old").unwrap();
+                path
+            })
+            .collect();
+
+        // As above, the tasks stall on progress until the copy is cancelled.
+        let (tx, _rx) = mpsc::channel(1);
+        let cancel = CancellationToken::new();
+        let canceller = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            canceller.cancel();
+        });
+
+        let options = CopyOptions { max_concurrent: 2, ..CopyOptions::default() };
+        let result = LocalFileOps::default()
+            .copy_files(sources, dest_dir.clone(), ConflictResolution::Rename, options, tx, cancel)
+            .await;
+
+        assert!(matches!(result, Err(Error::Cancelled)));
+        for i in 0..2 {
+            assert_eq!(std::fs::read_to_string(dest_dir.join(format!("big{}.bin", i))).unwrap(), "old");
+            let renamed = dest_dir.join(format!("big{} (1).bin", i));
+            if let Ok(metadata) = std::fs::metadata(&renamed) {
+                assert_eq!(metadata.len(), (BUFFER_SIZE * 16) as u64, "{:?}", renamed);
+            }
+        }
+    }
+
+    // cargo test --lib bench_concurrent_copy -- --ignored --nocapture
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore]
+    async fn bench_concurrent_copy() {
+        // tmpfs keeps the comparison about scheduling rather than the disk.
+        let temp_dir = TempDir::new_in("/dev/shm").unwrap_or_else(|_| TempDir::new().unwrap());
+        let sources = write_small_files(&temp_dir.path().join("src"), 100);
+        let ops = LocalFileOps::default();
+
+        let mut timings = Vec::new();
+        for max_concurrent in [1, 8] {
+            let dest_dir = temp_dir.path().join(format!("dest{}", max_concurrent));
+            std::fs::create_dir_all(&dest_dir).unwrap();
+            let (tx, mut rx) = mpsc::channel(100);
+            tokio::spawn(async move { while rx.recv().await.is_some() {} });
+
+            let start = std::time::Instant::now();
+            let options = CopyOptions { max_concurrent, ..CopyOptions::default() };
+            ops.copy_files(sources.clone(), dest_dir.clone(), ConflictResolution::Overwrite, options, tx, CancellationToken::new())
+                .await
+                .unwrap();
+            timings.push(start.elapsed());
+
+            assert_eq!(std::fs::read_dir(&dest_dir).unwrap().count(), sources.len());
+        }
+
+        println!("{} files: sequential {:?}, concurrent {:?}", sources.len(), timings[0], timings[1]);
+    }
+
+    #[tokio::test]
+    async fn test_mixed_resolutions_for_copy_and_move() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_dir = temp_dir.path().join("src");
+        let dest_dir = temp_dir.path().join("dest");
+        std::fs::create_dir_all(&src_dir).unwrap();
+        std::fs::create_dir_all(&dest_dir).unwrap();
+        for name in ["keep.txt", "replace.txt", "rename.txt", "new.txt"] {
+            std::fs::write(src_dir.join(name), "new").unwrap();
+        }
+        for name in ["keep.txt", "replace.txt", "rename.txt"] {
+            std::fs::write(dest_dir.join(name), "old").unwrap();
+        }
+
+        let resolutions = ConflictResolutions::new(ConflictResolution::Overwrite)
+            .with_resolution(dest_dir.join("keep.txt"), ConflictResolution::Skip)
+            .with_resolution(dest_dir.join("rename.txt"), ConflictResolution::Rename);
+        let sources: Vec<PathBuf> = ["keep.txt", "replace.txt", "rename.txt", "new.txt"]
+            .iter()
+            .map(|n| src_dir.join(n))
+            .collect();
+        let ops = LocalFileOps::default();
+        let read = |path: PathBuf| std::fs::read_to_string(path).unwrap();
+
+        for max_concurrent in [1, 4] {
+            let (tx, mut rx) = mpsc::channel(100);
+            tokio::spawn(async move { while rx.recv().await.is_some() {} });
+            let options = CopyOptions { max_concurrent, ..CopyOptions::default() };
+            ops.copy_files_with_resolutions(sources.clone(), dest_dir.clone(), &resolutions, options, tx, CancellationToken::new())
+                .await
+                .unwrap();
+
+            assert_eq!(read(dest_dir.join("keep.txt")), "old");
+            assert_eq!(read(dest_dir.join("replace.txt")), "new");
+            assert_eq!(read(dest_dir.join("rename.txt")), "old");
+            assert_eq!(read(dest_dir.join("rename (1).txt")), "new");
+            assert_eq!(read(dest_dir.join("new.txt")), "new");
+
+            std::fs::write(dest_dir.join("replace.txt"), "old").unwrap();
+            std::fs::remove_file(dest_dir.join("rename (1).txt")).unwrap();
+            std::fs::remove_file(dest_dir.join("new.txt")).unwrap();
+        }
+
+        let (tx, _rx) = mpsc::channel(100);
+        ops.move_files_with_resolutions(sources, dest_dir.clone(), &resolutions, tx, CancellationToken::new())
+            .await
+            .unwrap();
+
+        assert_eq!(read(dest_dir.join("keep.txt")), "old");
+        assert_eq!(read(src_dir.join("keep.txt")), "new");
+        assert_eq!(read(dest_dir.join("replace.txt")), "new");
+        assert_eq!(read(dest_dir.join("rename (1).txt")), "new");
+        assert_eq!(read(dest_dir.join("new.txt")), "new");
+        assert!(!src_dir.join("replace.txt").exists());
+        assert!(!src_dir.join("rename.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn test_copy_progress_reaches_totals_with_skips() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_dir = temp_dir.path().join("src");
+        std::fs::create_dir_all(src_dir.join("photos/2024")).unwrap();
+        std::fs::write(src_dir.join("new.txt"), "new file").unwrap();
+        std::fs::write(src_dir.join("kept.txt"), "skipped file").unwrap();
+        std::fs::write(src_dir.join("photos/a.jpg"), vec![1u8; 3 * BUFFER_SIZE]).unwrap();
+        std::fs::write(src_dir.join("photos/2024/b.jpg"), vec![2u8; 1000]).unwrap();
+        let sources: Vec<PathBuf> = ["new.txt", "kept.txt", "photos"].iter().map(|n| src_dir.join(n)).collect();
+        let ops = LocalFileOps::default();
+
+        for max_concurrent in [1, 4] {
+            let dest_dir = temp_dir.path().join(format!("dest{}", max_concurrent));
+            std::fs::create_dir_all(dest_dir.join("photos")).unwrap();
+            std::fs::write(dest_dir.join("kept.txt"), "old").unwrap();
+
+            let (tx, mut rx) = mpsc::channel(16);
+            let updates = tokio::spawn(async move {
+                let mut last = None;
+                while let Some(update) = rx.recv().await {
+                    last = Some(update);
+                }
+                last
+            });
+
+            let options = CopyOptions { max_concurrent, ..CopyOptions::default() };
+            ops.copy_files(sources.clone(), dest_dir.clone(), ConflictResolution::Skip, options, tx, CancellationToken::new())
+                .await
+                .unwrap();
+
+            assert_eq!(std::fs::read_to_string(dest_dir.join("new.txt")).unwrap(), "new file");
+            assert_eq!(std::fs::read_to_string(dest_dir.join("kept.txt")).unwrap(), "old");
+            assert!(!dest_dir.join("photos/a.jpg").exists());
+
+            let last = updates.await.unwrap().unwrap();
+            assert_eq!(last.total_bytes, 8 + 12 + 3 * BUFFER_SIZE as u64 + 1000);
+            assert_eq!(last.current_bytes, last.total_bytes, "max_concurrent {}", max_concurrent);
+            assert_eq!(last.total_files, 4);
+            assert_eq!(last.files_processed, last.total_files, "max_concurrent {}", max_concurrent);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_copy_files_report_continues_past_failures() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_dir = temp_dir.path().join("src");
+        std::fs::create_dir_all(src_dir.join("docs")).unwrap();
+        std::fs::write(src_dir.join("first.txt"), "first").unwrap();
+        std::fs::write(src_dir.join("kept.txt"), "new").unwrap();
+        std::fs::write(src_dir.join("docs/readme.md"), "readme").unwrap();
+        // Opening a socket fails for everyone, root included.
+        let _listener = std::os::unix::net::UnixListener::bind(src_dir.join("socket")).unwrap();
+        let sources: Vec<PathBuf> = ["first.txt", "socket", "missing.txt", "kept.txt", "docs"]
+            .iter()
+            .map(|n| src_dir.join(n))
+            .collect();
+        let ops = LocalFileOps::default();
+
+        for stop_on_error in [false, true] {
+            let dest_dir = temp_dir.path().join(format!("dest-{}", stop_on_error));
+            std::fs::create_dir_all(&dest_dir).unwrap();
+            std::fs::write(dest_dir.join("kept.txt"), "old").unwrap();
+            let resolutions = ConflictResolutions::new(ConflictResolution::Overwrite)
+                .with_resolution(dest_dir.join("kept.txt"), ConflictResolution::Skip);
+
+            let (tx, mut rx) = mpsc::channel(16);
+            let updates = tokio::spawn(async move {
+                let mut last = None;
+                while let Some(update) = rx.recv().await {
+                    last = Some(update);
+                }
+                last
+            });
+
+            let options = CopyOptions { stop_on_error, ..CopyOptions::default() };
+            let report = ops
+                .copy_files_report(sources.clone(), dest_dir.clone(), &resolutions, options, tx, CancellationToken::new())
+                .await
+                .unwrap();
+            let last = updates.await.unwrap().unwrap();
+            let failed: Vec<&PathBuf> = report.failed.iter().map(|(path, _)| path).collect();
+
+            assert_eq!(std::fs::read_to_string(dest_dir.join("first.txt")).unwrap(), "first");
+            assert_eq!(std::fs::read_to_string(dest_dir.join("kept.txt")).unwrap(), "old");
+            if stop_on_error {
+                assert_eq!(report.succeeded, vec![src_dir.join("first.txt")]);
+                assert!(report.skipped.is_empty());
+                assert_eq!(failed, vec![&src_dir.join("socket")]);
+                assert!(!dest_dir.join("docs").exists());
+            } else {
+                assert_eq!(report.succeeded, vec![src_dir.join("first.txt"), src_dir.join("docs")]);
+                assert_eq!(report.skipped, vec![src_dir.join("kept.txt")]);
+                assert_eq!(failed, vec![&src_dir.join("socket"), &src_dir.join("missing.txt")]);
+                assert_eq!(std::fs::read_to_string(dest_dir.join("docs/readme.md")).unwrap(), "readme");
+                assert_eq!(last.current_bytes, last.total_bytes);
+                assert_eq!(last.files_processed, last.total_files);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_plan_flags_collisions_in_merged_directories() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_dir = temp_dir.path().join("src");
+        let dest_dir = temp_dir.path().join("dest");
+        std::fs::create_dir_all(src_dir.join("album/2024/raw")).unwrap();
+        std::fs::create_dir_all(src_dir.join("report")).unwrap();
+        std::fs::create_dir_all(dest_dir.join("album/2024")).unwrap();
+        std::fs::create_dir_all(dest_dir.join("notes.txt")).unwrap();
+
+        std::fs::write(src_dir.join("notes.txt"), "notes").unwrap();
+        std::fs::write(src_dir.join("fresh.txt"), "fresh").unwrap();
+        std::fs::write(src_dir.join("album/cover.jpg"), "cover").unwrap();
+        std::fs::write(src_dir.join("album/2024/one.jpg"), "one").unwrap();
+        std::fs::write(src_dir.join("album/2024/raw/one.raw"), "raw").unwrap();
+        std::fs::write(src_dir.join("report/summary.txt"), "summary").unwrap();
+        std::fs::write(dest_dir.join("album/2024/one.jpg"), "old one").unwrap();
+        std::fs::write(dest_dir.join("report"), "a file").unwrap();
+
+        let sources: Vec<PathBuf> = ["notes.txt", "fresh.txt", "album", "report"].iter().map(|n| src_dir.join(n)).collect();
+        let plan = LocalFileOps::default().plan(&sources, &dest_dir).await.unwrap();
+
+        let flagged: Vec<(PathBuf, bool, bool)> = plan.conflicts
+            .iter()
+            .map(|c| (c.destination.strip_prefix(&dest_dir).unwrap().to_path_buf(), c.source_is_dir, c.existing_is_dir))
+            .collect();
+        assert_eq!(flagged, vec![
+            (PathBuf::from("album"), true, true),
+            (PathBuf::from("album/2024"), true, true),
+            (PathBuf::from("album/2024/one.jpg"), false, false),
+            (PathBuf::from("notes.txt"), false, true),
+            (PathBuf::from("report"), true, false),
+        ]);
+
+        let collisions: Vec<&Path> = plan.collisions().map(|c| c.destination.as_path()).collect();
+        assert_eq!(collisions, vec![
+            dest_dir.join("album/2024/one.jpg"),
+            dest_dir.join("notes.txt"),
+            dest_dir.join("report"),
+        ]);
+        assert_eq!(plan.conflicts[2].source, src_dir.join("album/2024/one.jpg"));
+
+        // Nothing was written.
+        assert!(!dest_dir.join("fresh.txt").exists());
+        assert!(!dest_dir.join("album/cover.jpg").exists());
+    }
+
+    #[tokio::test]
+    async fn test_merge_directories() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_dir = temp_dir.path().join("src");
+        let dest_dir = temp_dir.path().join("dest");
+        for (root, files) in [
+            (&src_dir, ["album/a.jpg", "album/b.jpg", "album/2024/c.jpg", "album/2024/d.jpg"]),
+            (&dest_dir, ["album/a.jpg", "album/z.jpg", "album/2024/c.jpg", "album/2024/y.jpg"]),
+        ] {
+            for file in files {
+                let path = root.join(file);
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(&path, if root == &src_dir { "new" } else { "old" }).unwrap();
+            }
+        }
+        let sources = vec![src_dir.join("album")];
+        let ops = LocalFileOps::default();
+        let entries = |pairs: &[(&str, &str)]| -> Vec<(PathBuf, String)> {
+            pairs.iter().map(|(path, contents)| (PathBuf::from(path), contents.to_string())).collect()
+        };
+
+        let resolutions = ConflictResolutions::new(ConflictResolution::Merge)
+            .with_resolution(dest_dir.join("album/2024/c.jpg"), ConflictResolution::Skip);
+        let (tx, mut rx) = mpsc::channel(100);
+        tokio::spawn(async move { while rx.recv().await.is_some() {} });
+        ops.copy_files_with_resolutions(sources.clone(), dest_dir.clone(), &resolutions, CopyOptions::default(), tx, CancellationToken::new())
+            .await
+            .unwrap();
+
+        assert_eq!(tree(&dest_dir), entries(&[
+            ("album", "/"),
+            ("album/2024", "/"),
+            ("album/2024/c.jpg", "old"),
+            ("album/2024/d.jpg", "new"),
+            ("album/2024/y.jpg", "old"),
+            ("album/a.jpg", "new"),
+            ("album/b.jpg", "new"),
+            ("album/z.jpg", "old"),
+        ]));
+
+        // Overwrite merges as well, but replaces every file both have.
+        let (tx, mut rx) = mpsc::channel(100);
+        tokio::spawn(async move { while rx.recv().await.is_some() {} });
+        ops.copy_files(sources.clone(), dest_dir.clone(), ConflictResolution::Overwrite, CopyOptions::default(), tx, CancellationToken::new())
+            .await
+            .unwrap();
+
+        assert_eq!(tree(&dest_dir), entries(&[
+            ("album", "/"),
+            ("album/2024", "/"),
+            ("album/2024/c.jpg", "new"),
+            ("album/2024/d.jpg", "new"),
+            ("album/2024/y.jpg", "old"),
+            ("album/a.jpg", "new"),
+            ("album/b.jpg", "new"),
+            ("album/z.jpg", "old"),
+        ]));
+
+        // Moving merges the same way and removes the emptied source tree.
+        std::fs::write(dest_dir.join("album/z.jpg"), "old").unwrap();
+        std::fs::write(src_dir.join("album/2024/e.jpg"), "new").unwrap();
+        let (tx, _rx) = mpsc::channel(100);
+        ops.move_files(sources, dest_dir.clone(), ConflictResolution::Merge, tx, CancellationToken::new())
+            .await
+            .unwrap();
+
+        assert!(!src_dir.join("album").exists());
+        assert_eq!(tree(&dest_dir), entries(&[
+            ("album", "/"),
+            ("album/2024", "/"),
+            ("album/2024/c.jpg", "new"),
+            ("album/2024/d.jpg", "new"),
+            ("album/2024/e.jpg", "new"),
+            ("album/2024/y.jpg", "old"),
+            ("album/a.jpg", "new"),
+            ("album/b.jpg", "new"),
+            ("album/z.jpg", "old"),
+        ]));
+    }
+
+    #[tokio::test]
+    async fn test_move_merges_directories_across_filesystems() {
+        let entries = |pairs: &[(&str, &str)]| -> Vec<(PathBuf, String)> {
+            pairs.iter().map(|(path, contents)| (PathBuf::from(path), contents.to_string())).collect()
+        };
+
+        for cross_filesystem in [false, true] {
+            let temp_dir = TempDir::new().unwrap();
+            let src_dir = temp_dir.path().join("src");
+            let dest_dir = temp_dir.path().join("dest");
+            for (path, contents) in [
+                ("src/album/a.jpg", "new"),
+                ("src/album/keep.jpg", "new"),
+                ("src/album/sub/b.jpg", "new"),
+                ("src/docs/x.txt", "new"),
+                ("dest/album/keep.jpg", "old"),
+                ("dest/album/z.jpg", "old"),
+            ] {
+                let path = temp_dir.path().join(path);
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(path, contents).unwrap();
+            }
+
+            let ops = LocalFileOps { cross_filesystem, ..LocalFileOps::default() };
+            let resolutions = ConflictResolutions::new(ConflictResolution::Merge)
+                .with_resolution(dest_dir.join("album/keep.jpg"), ConflictResolution::Skip);
+            let (tx, mut rx) = mpsc::channel(100);
+            tokio::spawn(async move { while rx.recv().await.is_some() {} });
+            ops.move_files_with_resolutions(
+                vec![src_dir.join("album"), src_dir.join("docs")],
+                dest_dir.clone(),
+                &resolutions,
+                tx,
+                CancellationToken::new(),
+            ).await.unwrap();
+
+            assert_eq!(tree(&dest_dir), entries(&[
+                ("album", "/"),
+                ("album/a.jpg", "new"),
+                ("album/keep.jpg", "old"),
+                ("album/sub", "/"),
+                ("album/sub/b.jpg", "new"),
+                ("album/z.jpg", "old"),
+                ("docs", "/"),
+                ("docs/x.txt", "new"),
+            ]), "cross_filesystem: {}", cross_filesystem);
+            // Only the skipped file is left behind.
+            assert_eq!(tree(&src_dir), entries(&[
+                ("album", "/"),
+                ("album/keep.jpg", "new"),
+            ]), "cross_filesystem: {}", cross_filesystem);
+        }
+    }
+
+    // Relative path to file contents ("-> target" for symlinks, "/" for
+    // directories) for everything below `root`.
+    fn tree(root: &Path) -> Vec<(PathBuf, String)> {
+        fn walk(root: &Path, dir: &Path, out: &mut Vec<(PathBuf, String)>) {
+            for entry in std::fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                let relative = path.strip_prefix(root).unwrap().to_path_buf();
+                let meta = std::fs::symlink_metadata(&path).unwrap();
+                if meta.is_symlink() {
+                    out.push((relative, format!("-> {}", std::fs::read_link(&path).unwrap().display())));
+                } else if meta.is_dir() {
+                    out.push((relative, "/".to_string()));
+                    walk(root, &path, out);
+                } else {
+                    out.push((relative, std::fs::read_to_string(&path).unwrap()));
+                }
+            }
+        }
+
+        let mut out = Vec::new();
+        walk(root, root, &mut out);
+        out.sort();
+        out
+    }
+
+    async fn mirror(src: &Path, dest: &Path, options: MirrorOptions) -> MirrorReport {
+        let (tx, _rx) = mpsc::channel(1024);
+        LocalFileOps::default()
+            .mirror_directory(src, dest, options, tx, CancellationToken::new())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_mirror_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("src");
+        let dest = temp_dir.path().join("dest");
+        for dir in [src.join("sub"), src.join("swap"), dest.join("sub"), dest.join("extra_dir")] {
+            std::fs::create_dir_all(dir).unwrap();
+        }
+
+        std::fs::write(src.join("same.txt"), "same").unwrap();
+        std::fs::write(src.join("changed.txt"), "new contents").unwrap();
+        std::fs::write(src.join("new.txt"), "new").unwrap();
+        std::fs::write(src.join("sub/inner.txt"), "inner").unwrap();
+        std::fs::write(src.join("swap/file.txt"), "was a file").unwrap();
+        std::os::unix::fs::symlink("same.txt", src.join("link")).unwrap();
+
+        std::fs::write(dest.join("same.txt"), "same").unwrap();
+        std::fs::write(dest.join("changed.txt"), "old").unwrap();
+        std::fs::write(dest.join("swap"), "file in the way").unwrap();
+        std::fs::write(dest.join("extra.txt"), "extra").unwrap();
+        std::fs::write(dest.join("extra_dir/x.txt"), "x").unwrap();
+        std::fs::write(dest.join("sub/stale.txt"), "stale").unwrap();
+
+        let options = MirrorOptions { delete_extra: true, ..MirrorOptions::default() };
+        let report = mirror(&src, &dest, options).await;
+
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert_eq!((report.copied, report.deleted, report.skipped), (5, 3, 1));
+        assert_eq!(tree(&dest), tree(&src));
+
+        let again = mirror(&src, &dest, options).await;
+        assert_eq!((again.copied, again.deleted, again.skipped), (0, 0, 6));
+    }
+
+    #[tokio::test]
+    async fn test_mirror_keeps_extra_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("src");
+        let dest = temp_dir.path().join("dest");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("a.txt"), "a").unwrap();
+
+        std::fs::create_dir_all(&dest).unwrap();
+        std::fs::write(dest.join("keep.txt"), "keep").unwrap();
+
+        let report = mirror(&src, &dest, MirrorOptions::default()).await;
+
+        assert_eq!((report.copied, report.deleted), (1, 0));
+        assert_eq!(std::fs::read_to_string(dest.join("keep.txt")).unwrap(), "keep");
+        assert_eq!(std::fs::read_to_string(dest.join("a.txt")).unwrap(), "a");
+    }
+
+    #[tokio::test]
+    async fn test_mirror_compare_strategies() {
+        use std::time::{Duration, SystemTime};
+
+        let base = SystemTime::now() - Duration::from_secs(3600);
+        // Same size, different contents, destination newer: only a checksum
+        // tells them apart.
+        let cases = [
+            (CompareStrategy::Size, false),
+            (CompareStrategy::Modified, false),
+            (CompareStrategy::Checksum, true),
+        ];
+
+        for (compare_by, copied) in cases {
+            let temp_dir = TempDir::new().unwrap();
+            let src = temp_dir.path().join("src");
+            let dest = temp_dir.path().join("dest");
+            std::fs::create_dir_all(&src).unwrap();
+            std::fs::create_dir_all(&dest).unwrap();
+
+            std::fs::write(src.join("file"), "aaaa").unwrap();
+            std::fs::write(dest.join("file"), "bbbb").unwrap();
+            std::fs::File::options().write(true).open(src.join("file")).unwrap().set_modified(base).unwrap();
+            std::fs::File::options().write(true).open(dest.join("file")).unwrap().set_modified(base + Duration::from_secs(60)).unwrap();
+
+            let report = mirror(&src, &dest, MirrorOptions { compare_by, ..MirrorOptions::default() }).await;
+            assert_eq!(report.copied == 1, copied, "{:?}", compare_by);
+            assert_eq!(std::fs::read_to_string(dest.join("file")).unwrap() == "aaaa", copied, "{:?}", compare_by);
+        }
+
+        // An older destination is copied over when comparing by mtime.
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("src");
+        let dest = temp_dir.path().join("dest");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::create_dir_all(&dest).unwrap();
+        std::fs::write(src.join("file"), "aaaa").unwrap();
+        std::fs::write(dest.join("file"), "bbbb").unwrap();
+        std::fs::File::options().write(true).open(dest.join("file")).unwrap().set_modified(base).unwrap();
+
+        let options = MirrorOptions { compare_by: CompareStrategy::Modified, ..MirrorOptions::default() };
+        assert_eq!(mirror(&src, &dest, options).await.copied, 1);
+    }
+
+    #[tokio::test]
+    async fn test_batch_copy_continues_after_failure() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut broken = batch_job(temp_dir.path(), "broken", &[64]);
+        broken.dest_dir = temp_dir.path().join("missing");
+        let jobs = vec![
+            batch_job(temp_dir.path(), "first", &[128]),
+            broken,
+            batch_job(temp_dir.path(), "last", &[256]),
+        ];
+
+        let (tx, _rx) = mpsc::channel(1024);
+        let report = LocalFileOps::default()
+            .batch_copy(jobs, ConflictResolution::Rename, CopyOptions::default(), tx, CancellationToken::new())
+            .await
+            .unwrap();
+
+        assert_eq!(report.completed, vec!["first", "last"]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, "broken");
+        assert!(matches!(report.failed[0].1, Error::InvalidPath { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_batch_copy_cancelled() {
+        let temp_dir = TempDir::new().unwrap();
+        let jobs = vec![batch_job(temp_dir.path(), "only", &[32])];
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+
+        let (tx, _rx) = mpsc::channel(1024);
+        let result = LocalFileOps::default()
+            .batch_copy(jobs, ConflictResolution::Rename, CopyOptions::default(), tx, cancel)
+            .await;
+
+        assert!(matches!(result, Err(Error::Cancelled)));
+    }
+
+    #[tokio::test]
+    async fn test_batch_copy_honours_conflict_resolution() {
+        let temp_dir = TempDir::new().unwrap();
+        let ops = LocalFileOps::default();
+
+        for (conflict, expected) in [(ConflictResolution::Skip, "old"), (ConflictResolution::Overwrite, "new")] {
+            let job = batch_job(&temp_dir.path().join(format!("{:?}", conflict)), "job", &[3]);
+            let name = job.sources[0].file_name().unwrap().to_owned();
+            std::fs::write(&job.sources[0], "new").unwrap();
+            std::fs::write(job.dest_dir.join(&name), "old").unwrap();
+            let dest_dir = job.dest_dir.clone();
+
+            let (tx, _rx) = mpsc::channel(1024);
+            let report = ops.batch_copy(vec![job], conflict, CopyOptions::default(), tx, CancellationToken::new()).await.unwrap();
+
+            assert_eq!(report.completed, vec!["job"]);
+            assert_eq!(std::fs::read_to_string(dest_dir.join(&name)).unwrap(), expected);
+            assert_eq!(std::fs::read_dir(&dest_dir).unwrap().count(), 1);
+        }
+    }
+
+    fn files(dir: &Path, count: usize) -> Vec<PathBuf> {
+        (0..count)
+            .map(|i| {
+                let path = dir.join(format!("file{}.txt", i));
+                std::fs::write(&path, "x").unwrap();
+                path
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_delete_checks_authorization_once() {
+        let temp_dir = TempDir::new().unwrap();
+        let paths = files(temp_dir.path(), 3);
+        let authorizer = MockAuthorizer::granting();
+        let security = Security::with_authorizer(Box::new(authorizer.clone()))
+            .with_protected_paths(vec![temp_dir.path().to_path_buf()])
+            .with_audit_log(AuditLog::new(temp_dir.path().join("audit.log")));
+        let (tx, _rx) = mpsc::channel(16);
+
+        LocalFileOps::new(1)
+            .delete_files(paths.clone(), Some(&security), tx, CancellationToken::new())
+            .await
+            .unwrap();
+
+        assert!(paths.iter().all(|p| !p.exists()));
+        assert_eq!(authorizer.calls(), vec![AuthCall::Request(polkit::ACTION_DELETE.to_string())]);
+
+        let audited: Vec<(String, PathBuf, bool)> = security.read_audit_log()
+            .unwrap()
+            .into_iter()
+            .map(|event| (event.action, event.path, event.success))
+            .collect();
+        let expected: Vec<(String, PathBuf, bool)> = paths.iter()
+            .map(|path| (polkit::ACTION_DELETE.to_string(), path.clone(), true))
+            .collect();
+        assert_eq!(audited, expected);
+    }
+
+    #[tokio::test]
+    async fn test_delete_refused() {
+        let temp_dir = TempDir::new().unwrap();
+        let paths = files(temp_dir.path(), 2);
+        let ops = LocalFileOps::new(1);
+
+        for authorizer in [MockAuthorizer::denying(), MockAuthorizer::challenging()] {
+            let security = Security::with_authorizer(Box::new(authorizer))
+                .with_protected_paths(vec![temp_dir.path().to_path_buf()]);
+            let (tx, _rx) = mpsc::channel(16);
+
+            let result = ops.delete_files(paths.clone(), Some(&security), tx, CancellationToken::new()).await;
+
+            assert!(matches!(result, Err(Error::PolkitDenied(_))));
+            assert!(paths.iter().all(|p| p.exists()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unprotected_delete_needs_no_authorization() {
+        let temp_dir = TempDir::new().unwrap();
+        let paths = files(temp_dir.path(), 2);
+        let authorizer = MockAuthorizer::denying();
+        let security = Security::with_authorizer(Box::new(authorizer.clone()));
+        let (tx, _rx) = mpsc::channel(16);
+
+        LocalFileOps::new(1)
+            .delete_files(paths.clone(), Some(&security), tx, CancellationToken::new())
+            .await
+            .unwrap();
+
+        assert!(paths.iter().all(|p| !p.exists()));
+        assert!(authorizer.calls().is_empty());
+    }
+
+    async fn link_contents(dir: &Path, target: &str, link: &str, relative: bool) -> PathBuf {
+        let link = dir.join(link);
+        LocalFileOps::new(1).symlink(&dir.join(target), &link, relative).await.unwrap();
+
+        let resolved = std::fs::canonicalize(&link).unwrap();
+        assert_eq!(resolved, std::fs::canonicalize(dir.join(target)).unwrap());
+        std::fs::read_link(&link).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_relative_symlinks() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        for sub in ["sub", "a", "b"] {
+            std::fs::create_dir(dir.join(sub)).unwrap();
+        }
+        for file in ["target.txt", "sub/target.txt", "b/target.txt"] {
+            std::fs::write(dir.join(file), "x").unwrap();
+        }
+
+        assert_eq!(link_contents(dir, "target.txt", "same", true).await, PathBuf::from("target.txt"));
+        assert_eq!(link_contents(dir, "sub/target.txt", "down", true).await, PathBuf::from("sub/target.txt"));
+        assert_eq!(link_contents(dir, "target.txt", "sub/up", true).await, PathBuf::from("../target.txt"));
+        assert_eq!(link_contents(dir, "b/target.txt", "a/across", true).await, PathBuf::from("../b/target.txt"));
+    }
+
+    #[tokio::test]
+    async fn test_absolute_and_dangling_symlinks() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        std::fs::write(dir.join("target.txt"), "x").unwrap();
+        let ops = LocalFileOps::new(1);
+
+        ops.symlink(&dir.join("target.txt"), &dir.join("absolute"), false).await.unwrap();
+        assert_eq!(std::fs::read_link(dir.join("absolute")).unwrap(), dir.join("target.txt"));
+
+        ops.symlink(&dir.join("not-yet.txt"), &dir.join("dangling"), true).await.unwrap();
+        assert_eq!(std::fs::read_link(dir.join("dangling")).unwrap(), PathBuf::from("not-yet.txt"));
+
+        let result = ops.symlink(&dir.join("target.txt"), &dir.join("absolute"), true).await;
+        assert!(matches!(result, Err(Error::AlreadyExists { .. })));
     }
 }
@@ -1,12 +1,23 @@
 use crate::{Error, Result};
-use crate::fs::{DirEntry, validate_path, check_symlink_loop};
+use crate::error::context;
+use crate::cache::DirectoryListingCache;
+use crate::config::{SortBy, SortConfig, SortOrder};
+use crate::fs::{DirEntry, natural_sort, validate_path, check_symlink_loop};
+use std::cmp::Ordering as CmpOrdering;
+use std::collections::BinaryHeap;
 use std::path::{Path, PathBuf};
 use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
-const BATCH_SIZE: usize = 100;
+pub(crate) const BATCH_SIZE: usize = 100;
+const DEFAULT_LARGE_DIR_THRESHOLD: usize = 10000;
+pub const DEFAULT_NANOS_PER_ENTRY: u64 = 500;
+// Fixed cost of opening and reading each directory, on top of the per-entry cost.
+const DIR_READ_OVERHEAD: Duration = Duration::from_micros(20);
+const ESTIMATE_DEPTH: usize = 2;
 
 pub struct ScanResult {
     pub entries: Vec<DirEntry>,
@@ -14,10 +25,16 @@ pub struct ScanResult {
     pub is_complete: bool,
 }
 
+pub type SortKey = Arc<dyn Fn(&DirEntry, &DirEntry) -> CmpOrdering + Send + Sync>;
+
 pub struct Scanner {
     follow_symlinks: bool,
     max_depth: usize,
     show_hidden: bool,
+    large_dir_threshold: usize,
+    nanos_per_entry: u64,
+    sort_key: Option<SortKey>,
+    listing_cache: Option<DirectoryListingCache>,
 }
 
 impl Scanner {
@@ -26,7 +43,82 @@ impl Scanner {
             follow_symlinks,
             max_depth,
             show_hidden,
+            large_dir_threshold: DEFAULT_LARGE_DIR_THRESHOLD,
+            nanos_per_entry: DEFAULT_NANOS_PER_ENTRY,
+            sort_key: None,
+            listing_cache: None,
+        }
+    }
+
+    // A custom key (e.g. from a plugin column) takes precedence over the SortConfig
+    // passed to scan_directory_sorted.
+    pub fn with_sort_key(mut self, key: SortKey) -> Self {
+        self.sort_key = Some(key);
+        self
+    }
+
+    /// Serves `scan_directory` from `cache` when it holds the directory, and
+    /// stores every completed scan in it. Hidden entries are cached too, so
+    /// the listing stays valid when `show_hidden` changes.
+    pub fn with_listing_cache(mut self, cache: DirectoryListingCache) -> Self {
+        self.listing_cache = Some(cache);
+        self
+    }
+
+    pub fn set_large_dir_threshold(&mut self, threshold: usize) {
+        self.large_dir_threshold = threshold.max(1);
+    }
+
+    pub fn set_nanos_per_entry(&mut self, nanos: u64) {
+        self.nanos_per_entry = nanos;
+    }
+
+    /// Rough duration of a recursive scan of `path`, extrapolated from the
+    /// entries in its top two levels. Directories below the large directory
+    /// threshold are considered instant and return `Duration::ZERO`.
+    pub fn estimate_scan_time(&self, path: &Path) -> Result<Duration> {
+        validate_path(path)?;
+
+        let resolved_path = if self.follow_symlinks {
+            check_symlink_loop(path, self.max_depth)?
+        } else {
+            path.to_path_buf()
+        };
+
+        if !resolved_path.is_dir() {
+            return Err(Error::InvalidPath { path: resolved_path });
+        }
+
+        let mut dirs_read = 0;
+        let entries = self.count_entries(&resolved_path, ESTIMATE_DEPTH.min(self.max_depth), &mut dirs_read)?;
+
+        if entries < self.large_dir_threshold {
+            return Ok(Duration::ZERO);
+        }
+
+        let per_entry = Duration::from_nanos(self.nanos_per_entry.saturating_mul(entries as u64));
+        Ok(per_entry + DIR_READ_OVERHEAD * dirs_read)
+    }
+
+    fn count_entries(&self, path: &Path, depth: usize, dirs_read: &mut u32) -> Result<usize> {
+        let mut count = 0;
+        *dirs_read += 1;
+
+        for entry in std::fs::read_dir(path)? {
+            let Ok(entry) = entry else { continue };
+
+            if !self.show_hidden && entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            count += 1;
+
+            if depth > 1 && entry.file_type().is_ok_and(|t| t.is_dir()) {
+                // Unreadable subdirectories are skipped, as they are during the scan.
+                count += self.count_entries(&entry.path(), depth - 1, dirs_read).unwrap_or(0);
+            }
         }
+
+        Ok(count)
     }
 
     pub async fn scan_directory(
@@ -37,6 +129,10 @@ impl Scanner {
     ) -> Result<()> {
         validate_path(&path)?;
 
+        if let Some(listing) = self.listing_cache.as_ref().and_then(|cache| cache.get(&path)) {
+            return self.send_cached(listing, &sender).await;
+        }
+
         let resolved_path = if self.follow_symlinks {
             check_symlink_loop(&path, self.max_depth)?
         } else {
@@ -49,6 +145,7 @@ impl Scanner {
 
         let total_count = Arc::new(AtomicUsize::new(0));
         let mut entries = Vec::with_capacity(BATCH_SIZE);
+        let mut listing = self.listing_cache.as_ref().map(|_| Vec::new());
         
         let mut read_dir = tokio::fs::read_dir(&resolved_path).await?;
 
@@ -59,8 +156,12 @@ impl Scanner {
 
             let entry_path = entry.path();
             
-            match DirEntry::from_path(&entry_path) {
+            match DirEntry::from_path_async(&entry_path).await {
                 Ok(dir_entry) => {
+                    if let Some(listing) = &mut listing {
+                        listing.push(dir_entry.clone());
+                    }
+
                     if !self.show_hidden && dir_entry.is_hidden() {
                         continue;
                     }
@@ -95,9 +196,213 @@ impl Scanner {
             }).await.map_err(|_| Error::Cancelled)?;
         }
 
+        if let (Some(cache), Some(listing)) = (&self.listing_cache, listing) {
+            cache.insert(&path, listing);
+        }
+
+        Ok(())
+    }
+
+    // Replays a cached listing in the batches a scan would have sent.
+    async fn send_cached(&self, listing: Vec<DirEntry>, sender: &mpsc::Sender<ScanResult>) -> Result<()> {
+        let entries: Vec<DirEntry> = listing
+            .into_iter()
+            .filter(|entry| self.show_hidden || !entry.is_hidden())
+            .collect();
+
+        if entries.is_empty() {
+            return sender.send(ScanResult {
+                entries,
+                total_count: 0,
+                is_complete: true,
+            }).await.map_err(|_| Error::Cancelled);
+        }
+
+        let batches = entries.len().div_ceil(BATCH_SIZE);
+        for (i, batch) in entries.chunks(BATCH_SIZE).enumerate() {
+            sender.send(ScanResult {
+                entries: batch.to_vec(),
+                total_count: i * BATCH_SIZE + batch.len(),
+                is_complete: i + 1 == batches,
+            }).await.map_err(|_| Error::Cancelled)?;
+        }
+
         Ok(())
     }
 
+    pub async fn scan_directory_sorted(
+        &self,
+        path: PathBuf,
+        sort: SortConfig,
+        sender: mpsc::Sender<ScanResult>,
+        cancel: CancellationToken,
+    ) -> Result<()> {
+        let entries = self.collect_entries(&path, &cancel).await?;
+        let chunk_size = self.large_dir_threshold;
+        let sort_key = self.sort_key.clone();
+
+        let entries = tokio::task::spawn_blocking(move || {
+            let mut entries = entries;
+            match sort_key {
+                Some(key) => sort_entries_by(&mut entries, &*key, chunk_size),
+                None => sort_entries(&mut entries, &sort, chunk_size),
+            }
+            entries
+        })
+        .await
+        .map_err(|e| Error::Runtime(context("Sort task failed", e)))?;
+
+        if cancel.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+
+        let total_count = entries.len();
+        if total_count == 0 {
+            return sender.send(ScanResult {
+                entries,
+                total_count,
+                is_complete: true,
+            }).await.map_err(|_| Error::Cancelled);
+        }
+
+        let mut sent = 0;
+        let mut remaining = entries.into_iter();
+
+        while sent < total_count {
+            if cancel.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+
+            let batch: Vec<DirEntry> = remaining.by_ref().take(BATCH_SIZE).collect();
+            sent += batch.len();
+
+            sender.send(ScanResult {
+                entries: batch,
+                total_count,
+                is_complete: sent == total_count,
+            }).await.map_err(|_| Error::Cancelled)?;
+        }
+
+        Ok(())
+    }
+
+    /// Streams entries in batches of `batch_size` without waiting for the
+    /// whole directory to be read.
+    ///
+    /// **The output is approximately sorted; only the final batch is fully
+    /// sorted.** Entries are buffered in a heap and the smallest `batch_size`
+    /// are flushed each time it holds twice that many, so an entry read late
+    /// can sort before rows already sent. Callers that need an exact order
+    /// should re-sort once `is_complete` is set, or use
+    /// `scan_directory_sorted`.
+    pub async fn scan_sorted_streaming(
+        &self,
+        path: PathBuf,
+        sort: SortConfig,
+        batch_size: usize,
+        sender: mpsc::Sender<ScanResult>,
+        cancel: CancellationToken,
+    ) -> Result<()> {
+        let resolved_path = self.resolve_dir(&path)?;
+        let batch_size = batch_size.max(1);
+        let compare: SortKey = match &self.sort_key {
+            Some(key) => Arc::clone(key),
+            None => Arc::new(move |a: &DirEntry, b: &DirEntry| compare_entries(a, b, &sort)),
+        };
+
+        let mut heap = BinaryHeap::with_capacity(batch_size * 2);
+        let mut total_count = 0;
+        let mut read_dir = tokio::fs::read_dir(&resolved_path).await?;
+
+        while let Some(entry) = read_dir.next_entry().await? {
+            if cancel.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+
+            let entry_path = entry.path();
+            let dir_entry = match DirEntry::from_path_async(&entry_path).await {
+                Ok(dir_entry) => dir_entry,
+                Err(e) => {
+                    tracing::warn!("Failed to read entry {:?}: {}", entry_path, e);
+                    continue;
+                }
+            };
+            if !self.show_hidden && dir_entry.is_hidden() {
+                continue;
+            }
+
+            heap.push(HeapEntry {
+                entry: dir_entry,
+                seq: total_count,
+                compare: Arc::clone(&compare),
+            });
+            total_count += 1;
+
+            if heap.len() >= batch_size * 2 {
+                let batch = (0..batch_size).filter_map(|_| heap.pop()).map(|e| e.entry).collect();
+                sender.send(ScanResult {
+                    entries: batch,
+                    total_count,
+                    is_complete: false,
+                }).await.map_err(|_| Error::Cancelled)?;
+            }
+        }
+
+        let mut rest = Vec::with_capacity(heap.len());
+        while let Some(e) = heap.pop() {
+            rest.push(e.entry);
+        }
+        sender.send(ScanResult {
+            entries: rest,
+            total_count,
+            is_complete: true,
+        }).await.map_err(|_| Error::Cancelled)
+    }
+
+    fn resolve_dir(&self, path: &Path) -> Result<PathBuf> {
+        validate_path(path)?;
+
+        let resolved_path = if self.follow_symlinks {
+            check_symlink_loop(path, self.max_depth)?
+        } else {
+            path.to_path_buf()
+        };
+
+        if !resolved_path.is_dir() {
+            return Err(Error::InvalidPath { path: resolved_path });
+        }
+        Ok(resolved_path)
+    }
+
+    async fn collect_entries(&self, path: &Path, cancel: &CancellationToken) -> Result<Vec<DirEntry>> {
+        let resolved_path = self.resolve_dir(path)?;
+
+        let mut entries = Vec::new();
+        let mut read_dir = tokio::fs::read_dir(&resolved_path).await?;
+
+        while let Some(entry) = read_dir.next_entry().await? {
+            if cancel.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+
+            let entry_path = entry.path();
+
+            match DirEntry::from_path_async(&entry_path).await {
+                Ok(dir_entry) => {
+                    if !self.show_hidden && dir_entry.is_hidden() {
+                        continue;
+                    }
+                    entries.push(dir_entry);
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to read entry {:?}: {}", entry_path, e);
+                }
+            }
+        }
+
+        Ok(entries)
+    }
+
     pub async fn scan_recursive(
         &self,
         path: PathBuf,
@@ -146,7 +451,7 @@ impl Scanner {
 
                 let entry_path = entry.path();
 
-                match DirEntry::from_path(&entry_path) {
+                match DirEntry::from_path_async(&entry_path).await {
                     Ok(dir_entry) => {
                         if !self.show_hidden && dir_entry.is_hidden() {
                             continue;
@@ -191,8 +496,357 @@ impl Scanner {
     }
 }
 
+// Min-heap entry for scan_sorted_streaming. `seq` keeps equal entries in
+// the order they were read.
+struct HeapEntry {
+    entry: DirEntry,
+    seq: usize,
+    compare: SortKey,
+}
+
+impl Ord for HeapEntry {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        (self.compare)(&self.entry, &other.entry)
+            .then(self.seq.cmp(&other.seq))
+            .reverse()
+    }
+}
+
+impl PartialOrd for HeapEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for HeapEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == CmpOrdering::Equal
+    }
+}
+
+impl Eq for HeapEntry {}
+
 impl Default for Scanner {
     fn default() -> Self {
         Self::new(true, 32, false)
     }
 }
+
+pub fn compare_entries(a: &DirEntry, b: &DirEntry, sort: &SortConfig) -> CmpOrdering {
+    if sort.directories_first && a.is_dir != b.is_dir {
+        return if a.is_dir { CmpOrdering::Less } else { CmpOrdering::Greater };
+    }
+
+    let ordering = match sort.sort_by {
+        SortBy::Name => natural_sort::cmp(&a.name, &b.name),
+        SortBy::Size => a.size.cmp(&b.size),
+        SortBy::Modified => a.modified.cmp(&b.modified),
+        SortBy::Type => a.extension().cmp(&b.extension()),
+    };
+
+    match sort.sort_order {
+        SortOrder::Ascending => ordering,
+        SortOrder::Descending => ordering.reverse(),
+    }
+}
+
+pub fn sort_entries(entries: &mut Vec<DirEntry>, sort: &SortConfig, chunk_size: usize) {
+    sort_entries_by(entries, &|a, b| compare_entries(a, b, sort), chunk_size);
+}
+
+pub fn sort_entries_by(
+    entries: &mut Vec<DirEntry>,
+    compare: &dyn Fn(&DirEntry, &DirEntry) -> CmpOrdering,
+    chunk_size: usize,
+) {
+    if entries.len() <= chunk_size {
+        entries.sort_by(compare);
+        return;
+    }
+
+    let mut runs: Vec<Vec<DirEntry>> = Vec::new();
+    let mut remaining = std::mem::take(entries).into_iter();
+
+    loop {
+        let mut chunk: Vec<DirEntry> = remaining.by_ref().take(chunk_size).collect();
+        if chunk.is_empty() {
+            break;
+        }
+        chunk.sort_by(compare);
+        runs.push(chunk);
+    }
+
+    while runs.len() > 1 {
+        let mut merged = Vec::with_capacity(runs.len().div_ceil(2));
+        let mut pairs = runs.into_iter();
+
+        while let Some(left) = pairs.next() {
+            match pairs.next() {
+                Some(right) => merged.push(merge_sorted(left, right, compare)),
+                None => merged.push(left),
+            }
+        }
+
+        runs = merged;
+    }
+
+    *entries = runs.pop().unwrap_or_default();
+}
+
+fn merge_sorted(
+    left: Vec<DirEntry>,
+    right: Vec<DirEntry>,
+    compare: &dyn Fn(&DirEntry, &DirEntry) -> CmpOrdering,
+) -> Vec<DirEntry> {
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+
+    loop {
+        let take_left = match (left.peek(), right.peek()) {
+            (Some(l), Some(r)) => compare(l, r) != CmpOrdering::Greater,
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => break,
+        };
+
+        let next = if take_left { left.next() } else { right.next() };
+        merged.extend(next);
+    }
+
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::EntryType;
+    use std::time::{Duration, SystemTime};
+    use tempfile::TempDir;
+
+    fn entry(name: &str, size: u64, is_dir: bool) -> DirEntry {
+        DirEntry {
+            name: name.to_string(),
+            path: PathBuf::from("/test").join(name),
+            size,
+            modified: SystemTime::UNIX_EPOCH + Duration::from_secs(size),
+            is_dir,
+            is_symlink: false,
+            entry_type: if is_dir { EntryType::Directory } else { EntryType::File },
+            permissions: 0o644,
+            inode: 0,
+            symlink_target: None,
+            symlink_is_broken: false,
+            target_type: None,
+        }
+    }
+
+    fn names(entries: &[DirEntry]) -> Vec<&str> {
+        entries.iter().map(|e| e.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_sort_is_stable() {
+        let sort = SortConfig {
+            sort_by: SortBy::Size,
+            sort_order: SortOrder::Ascending,
+            directories_first: false,
+        };
+
+        let mut entries = vec![
+            entry("c", 10, false),
+            entry("a", 5, false),
+            entry("b", 10, false),
+            entry("d", 5, false),
+        ];
+        sort_entries(&mut entries, &sort, 100);
+        assert_eq!(names(&entries), vec!["a", "d", "c", "b"]);
+
+        let sort = SortConfig { sort_order: SortOrder::Descending, ..sort };
+        let mut entries = vec![
+            entry("c", 10, false),
+            entry("a", 5, false),
+            entry("b", 10, false),
+            entry("d", 5, false),
+        ];
+        sort_entries(&mut entries, &sort, 100);
+        assert_eq!(names(&entries), vec!["c", "b", "a", "d"]);
+    }
+
+    #[test]
+    fn test_sort_directories_first() {
+        let sort = SortConfig::default();
+        let mut entries = vec![
+            entry("beta.txt", 1, false),
+            entry("Zeta", 1, true),
+            entry("alpha.txt", 1, false),
+            entry("docs", 1, true),
+        ];
+        sort_entries(&mut entries, &sort, 100);
+        assert_eq!(names(&entries), vec!["docs", "Zeta", "alpha.txt", "beta.txt"]);
+    }
+
+    #[test]
+    fn test_chunked_sort_matches_full_sort() {
+        let sort = SortConfig {
+            sort_by: SortBy::Size,
+            sort_order: SortOrder::Ascending,
+            directories_first: true,
+        };
+
+        let original: Vec<DirEntry> = (0..257)
+            .map(|i| entry(&format!("file{}", i), (i * 7919 % 13) as u64, i % 5 == 0))
+            .collect();
+
+        let mut full = original.clone();
+        sort_entries(&mut full, &sort, original.len());
+
+        let mut chunked = original;
+        sort_entries(&mut chunked, &sort, 16);
+
+        assert_eq!(names(&full), names(&chunked));
+    }
+
+    #[test]
+    fn test_estimate_scan_time() {
+        let temp_dir = TempDir::new().unwrap();
+        let nested = temp_dir.path().join("nested");
+        std::fs::create_dir(&nested).unwrap();
+        for i in 0..1000 {
+            let dir = if i % 2 == 0 { temp_dir.path() } else { nested.as_path() };
+            std::fs::write(dir.join(format!("file{}.txt", i)), "").unwrap();
+        }
+
+        let mut scanner = Scanner::default();
+        assert_eq!(scanner.estimate_scan_time(temp_dir.path()).unwrap(), Duration::ZERO);
+
+        scanner.set_large_dir_threshold(1000);
+        let estimate = scanner.estimate_scan_time(temp_dir.path()).unwrap();
+        assert!(estimate > Duration::ZERO);
+        assert_eq!(
+            estimate,
+            Duration::from_nanos(DEFAULT_NANOS_PER_ENTRY * 1001) + DIR_READ_OVERHEAD * 2
+        );
+
+        assert!(scanner.estimate_scan_time(&temp_dir.path().join("file0.txt")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_scan_directory_sorted() {
+        let temp_dir = TempDir::new().unwrap();
+        for name in ["b.txt", "a.txt", "c.txt"] {
+            std::fs::write(temp_dir.path().join(name), name).unwrap();
+        }
+        std::fs::create_dir(temp_dir.path().join("z-dir")).unwrap();
+
+        let (tx, mut rx) = mpsc::channel(16);
+        let scanner = Scanner::default();
+        scanner.scan_directory_sorted(
+            temp_dir.path().to_path_buf(),
+            SortConfig::default(),
+            tx,
+            CancellationToken::new(),
+        ).await.unwrap();
+
+        let result = rx.recv().await.unwrap();
+        assert!(result.is_complete);
+        assert_eq!(result.total_count, 4);
+        assert_eq!(names(&result.entries), vec!["z-dir", "a.txt", "b.txt", "c.txt"]);
+    }
+
+    #[tokio::test]
+    async fn test_scan_sorted_streaming_first_batch_latency() {
+        let temp_dir = TempDir::new().unwrap();
+        for i in 0..10_000 {
+            std::fs::write(temp_dir.path().join(format!("file{}.txt", i)), b"").unwrap();
+        }
+
+        // A single-slot channel keeps the scanner from running ahead, so the
+        // first batch must be sent while most of the directory is unread.
+        let (tx, mut rx) = mpsc::channel(1);
+        let path = temp_dir.path().to_path_buf();
+        let scan = tokio::spawn(async move {
+            Scanner::default()
+                .scan_sorted_streaming(path, SortConfig::default(), 100, tx, CancellationToken::new())
+                .await
+        });
+
+        let first = rx.recv().await.unwrap();
+        assert!(!first.is_complete);
+        assert_eq!(first.entries.len(), 100);
+        assert_eq!(first.total_count, 200);
+        assert!(!scan.is_finished());
+
+        let by_name = |a: &DirEntry, b: &DirEntry| natural_sort::cmp(&a.name, &b.name);
+        let mut batches = vec![first];
+        while let Some(result) = rx.recv().await {
+            batches.push(result);
+        }
+        scan.await.unwrap().unwrap();
+
+        let last = batches.last().unwrap();
+        assert!(last.is_complete);
+        assert_eq!(last.total_count, 10_000);
+        for batch in &batches {
+            assert!(batch.entries.windows(2).all(|w| by_name(&w[0], &w[1]) != CmpOrdering::Greater));
+        }
+
+        let mut seen: Vec<String> = batches.into_iter().flat_map(|b| b.entries).map(|e| e.name).collect();
+        seen.sort();
+        seen.dedup();
+        assert_eq!(seen.len(), 10_000);
+    }
+
+    #[tokio::test]
+    async fn test_scan_sorted_streaming_small_dir_is_exact() {
+        let temp_dir = TempDir::new().unwrap();
+        for name in ["b.txt", "a.txt", "c.txt"] {
+            std::fs::write(temp_dir.path().join(name), name).unwrap();
+        }
+        std::fs::create_dir(temp_dir.path().join("z-dir")).unwrap();
+
+        let (tx, mut rx) = mpsc::channel(16);
+        Scanner::default().scan_sorted_streaming(
+            temp_dir.path().to_path_buf(),
+            SortConfig::default(),
+            10,
+            tx,
+            CancellationToken::new(),
+        ).await.unwrap();
+
+        let result = rx.recv().await.unwrap();
+        assert!(result.is_complete);
+        assert_eq!(names(&result.entries), vec!["z-dir", "a.txt", "b.txt", "c.txt"]);
+        assert!(rx.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_scan_directory_with_sort_key() {
+        let temp_dir = TempDir::new().unwrap();
+        for name in ["medium.txt", "a.rs", "very-long-name.txt", "bb.md"] {
+            std::fs::write(temp_dir.path().join(name), name).unwrap();
+        }
+        std::fs::create_dir(temp_dir.path().join("directory")).unwrap();
+
+        let by_length: SortKey = Arc::new(|a: &DirEntry, b: &DirEntry| {
+            a.name.len().cmp(&b.name.len()).then_with(|| a.name.cmp(&b.name))
+        });
+        let mut scanner = Scanner::default().with_sort_key(by_length);
+        scanner.set_large_dir_threshold(2);
+
+        let (tx, mut rx) = mpsc::channel(16);
+        scanner.scan_directory_sorted(
+            temp_dir.path().to_path_buf(),
+            SortConfig::default(),
+            tx,
+            CancellationToken::new(),
+        ).await.unwrap();
+
+        let result = rx.recv().await.unwrap();
+        assert_eq!(
+            names(&result.entries),
+            vec!["a.rs", "bb.md", "directory", "medium.txt", "very-long-name.txt"]
+        );
+        assert!(result.entries.windows(2).all(|w| w[0].name.len() <= w[1].name.len()));
+    }
+}
@@ -0,0 +1,193 @@
+use crate::{Error, Result};
+use crate::fs::validate_path;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tokio_util::sync::CancellationToken;
+
+#[derive(Debug, Clone)]
+pub struct UsageNode {
+    pub path: PathBuf,
+    pub is_dir: bool,
+    pub size: u64,
+    pub disk_size: u64,
+    pub total_size: u64,
+    pub total_disk_size: u64,
+    pub children: Vec<UsageNode>,
+}
+
+impl UsageNode {
+    pub fn largest(&self, n: usize) -> Vec<&UsageNode> {
+        let mut children: Vec<&UsageNode> = self.children.iter().collect();
+        children.sort_by(|a, b| b.total_size.cmp(&a.total_size).then_with(|| a.path.cmp(&b.path)));
+        children.truncate(n);
+        children
+    }
+
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.path.display().to_string())
+    }
+}
+
+pub async fn disk_usage(root: &Path, cancel: CancellationToken) -> Result<UsageNode> {
+    validate_path(root)?;
+
+    let root = root.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let mut seen = HashSet::new();
+        walk(&root, &mut seen, &cancel)
+    })
+    .await
+    .map_err(|e| Error::InvalidOperation(format!("Disk usage scan failed: {}", e)))?
+}
+
+fn walk(path: &Path, seen: &mut HashSet<(u64, u64)>, cancel: &CancellationToken) -> Result<UsageNode> {
+    if cancel.is_cancelled() {
+        return Err(Error::Cancelled);
+    }
+
+    let metadata = std::fs::symlink_metadata(path)?;
+    let (size, disk_size) = if is_counted(&metadata, seen) {
+        (metadata.len(), disk_blocks(&metadata))
+    } else {
+        (0, 0)
+    };
+
+    let mut node = UsageNode {
+        path: path.to_path_buf(),
+        is_dir: metadata.is_dir(),
+        size,
+        disk_size,
+        total_size: size,
+        total_disk_size: disk_size,
+        children: Vec::new(),
+    };
+
+    if !node.is_dir {
+        return Ok(node);
+    }
+
+    let read_dir = match std::fs::read_dir(path) {
+        Ok(read_dir) => read_dir,
+        Err(e) => {
+            tracing::warn!("Failed to read directory {}: {}", path.display(), e);
+            return Ok(node);
+        }
+    };
+
+    for entry in read_dir {
+        let entry_path = match entry {
+            Ok(entry) => entry.path(),
+            Err(e) => {
+                tracing::warn!("Failed to read entry in {}: {}", path.display(), e);
+                continue;
+            }
+        };
+
+        match walk(&entry_path, seen, cancel) {
+            Ok(child) => {
+                node.total_size += child.total_size;
+                node.total_disk_size += child.total_disk_size;
+                node.children.push(child);
+            }
+            Err(Error::Cancelled) => return Err(Error::Cancelled),
+            Err(e) => tracing::warn!("Failed to measure {}: {}", entry_path.display(), e),
+        }
+    }
+
+    Ok(node)
+}
+
+// Hard-linked files are only counted the first time they are seen.
+#[cfg(unix)]
+fn is_counted(metadata: &std::fs::Metadata, seen: &mut HashSet<(u64, u64)>) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    if metadata.is_dir() || metadata.nlink() <= 1 {
+        return true;
+    }
+    seen.insert((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn is_counted(_metadata: &std::fs::Metadata, _seen: &mut HashSet<(u64, u64)>) -> bool {
+    true
+}
+
+#[cfg(unix)]
+fn disk_blocks(metadata: &std::fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.blocks() * 512
+}
+
+#[cfg(not(unix))]
+fn disk_blocks(metadata: &std::fs::Metadata) -> u64 {
+    metadata.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn dir_size(path: &Path) -> u64 {
+        std::fs::symlink_metadata(path).unwrap().len()
+    }
+
+    #[tokio::test]
+    async fn test_disk_usage_tree() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        std::fs::create_dir_all(root.join("big/deeper")).unwrap();
+        std::fs::create_dir(root.join("small")).unwrap();
+
+        std::fs::write(root.join("top.bin"), vec![0u8; 100]).unwrap();
+        std::fs::write(root.join("big/a.bin"), vec![1u8; 4000]).unwrap();
+        std::fs::write(root.join("big/deeper/b.bin"), vec![2u8; 6000]).unwrap();
+        std::fs::write(root.join("small/c.bin"), vec![3u8; 10]).unwrap();
+
+        let tree = disk_usage(root, CancellationToken::new()).await.unwrap();
+
+        let big_dirs = dir_size(&root.join("big")) + dir_size(&root.join("big/deeper"));
+        let all_dirs = big_dirs + dir_size(root) + dir_size(&root.join("small"));
+        assert_eq!(tree.total_size, 100 + 4000 + 6000 + 10 + all_dirs);
+        assert_eq!(tree.children.len(), 3);
+
+        let largest = tree.largest(2);
+        assert_eq!(largest.len(), 2);
+        assert_eq!(largest[0].name(), "big");
+        assert_eq!(largest[0].total_size, 10000 + big_dirs);
+        assert_eq!(largest[0].largest(1)[0].name(), "deeper");
+
+        let top = tree.children.iter().find(|c| c.name() == "top.bin").unwrap();
+        assert!(!top.is_dir);
+        assert_eq!(top.size, 100);
+        assert_eq!(top.total_size, 100);
+    }
+
+    #[tokio::test]
+    async fn test_disk_usage_sparse_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = std::fs::File::create(temp_dir.path().join("sparse")).unwrap();
+        file.set_len(64 * 1024 * 1024).unwrap();
+
+        let tree = disk_usage(temp_dir.path(), CancellationToken::new()).await.unwrap();
+        let sparse = &tree.children[0];
+
+        assert_eq!(sparse.size, 64 * 1024 * 1024);
+        assert!(sparse.disk_size < sparse.size);
+    }
+
+    #[tokio::test]
+    async fn test_disk_usage_hard_links_counted_once() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("a"), vec![0u8; 500]).unwrap();
+        std::fs::hard_link(temp_dir.path().join("a"), temp_dir.path().join("b")).unwrap();
+
+        let tree = disk_usage(temp_dir.path(), CancellationToken::new()).await.unwrap();
+
+        assert_eq!(tree.total_size, 500 + dir_size(temp_dir.path()));
+    }
+}
@@ -1,13 +1,19 @@
 use crate::{Error, Result};
+use crate::error::context;
+use crate::fs::metadata::ExtendedMetadata;
 use std::path::{Path, PathBuf};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 use notify::{Event, EventKind, RecursiveMode, Watcher as NotifyWatcher};
 use parking_lot::Mutex;
 use std::sync::Arc;
+use std::sync::mpsc::{self as std_mpsc, RecvTimeoutError};
 
 const DEBOUNCE_DURATION: Duration = Duration::from_millis(50);
+// Used for a batch sender when `with_batch_delivery` was not called.
+const DEFAULT_MAX_BATCH: usize = 256;
+const DEFAULT_BATCH_DELAY: Duration = Duration::from_millis(100);
 
 #[derive(Debug, Clone)]
 pub enum WatchEvent {
@@ -15,12 +21,89 @@ pub enum WatchEvent {
     Modified(PathBuf),
     Deleted(PathBuf),
     Renamed { from: PathBuf, to: PathBuf },
+    AttributesChanged(PathBuf),
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct WatchFilter {
+    pub patterns: Vec<glob::Pattern>,
+}
+
+impl WatchFilter {
+    pub fn new(patterns: &[&str]) -> Result<Self> {
+        let mut filter = Self::default();
+        for pattern in patterns {
+            filter.add_pattern(pattern)?;
+        }
+        Ok(filter)
+    }
+
+    pub fn add_pattern(&mut self, pattern: &str) -> Result<()> {
+        let compiled = glob::Pattern::new(pattern)
+            .map_err(|e| Error::Watcher(context(format!("Invalid filter pattern '{}'", pattern), e)))?;
+        if !self.patterns.contains(&compiled) {
+            self.patterns.push(compiled);
+        }
+        Ok(())
+    }
+
+    pub fn remove_pattern(&mut self, pattern: &str) {
+        self.patterns.retain(|p| p.as_str() != pattern);
+    }
+
+    // Patterns are tried against the full path and the bare file name, so both
+    // `*.o` and `**/.git/objects/**` work as expected.
+    pub fn matches(&self, path: &Path) -> bool {
+        let file_name = path.file_name().map(Path::new);
+        self.patterns.iter().any(|pattern| {
+            pattern.matches_path(path) || file_name.is_some_and(|name| pattern.matches_path(name))
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+}
+
+/// Where `Watcher::start` delivers events: one message per event, or
+/// batches gathered as configured by `Watcher::with_batch_delivery`.
+pub enum WatchSender {
+    Events(mpsc::UnboundedSender<WatchEvent>),
+    Batches(mpsc::Sender<Vec<WatchEvent>>),
+}
+
+impl From<mpsc::UnboundedSender<WatchEvent>> for WatchSender {
+    fn from(sender: mpsc::UnboundedSender<WatchEvent>) -> Self {
+        WatchSender::Events(sender)
+    }
+}
+
+impl From<mpsc::Sender<Vec<WatchEvent>>> for WatchSender {
+    fn from(sender: mpsc::Sender<Vec<WatchEvent>>) -> Self {
+        WatchSender::Batches(sender)
+    }
+}
+
+enum BatchMessage {
+    Event(WatchEvent),
+    Flush,
+}
+
+type PathSet = Arc<Mutex<HashSet<PathBuf>>>;
+
 pub struct Watcher {
     inner: Arc<Mutex<Option<notify::RecommendedWatcher>>>,
     watched_paths: Arc<Mutex<HashMap<PathBuf, Instant>>>,
+    // Directories passed to `watch`, and files passed to `watch_file` whose
+    // parent is watched on their behalf.
+    watched_dirs: PathSet,
+    watched_files: PathSet,
+    filter: Arc<Mutex<WatchFilter>>,
+    attribute_snapshots: Arc<Mutex<HashMap<PathBuf, ExtendedMetadata>>>,
     debounce_duration: Duration,
+    batching: (usize, Duration),
+    // Feeds the batching thread while a batch sender is started.
+    batch_input: Mutex<Option<std_mpsc::Sender<BatchMessage>>>,
 }
 
 impl Watcher {
@@ -28,19 +111,72 @@ impl Watcher {
         Self {
             inner: Arc::new(Mutex::new(None)),
             watched_paths: Arc::new(Mutex::new(HashMap::new())),
+            watched_dirs: Arc::new(Mutex::new(HashSet::new())),
+            watched_files: Arc::new(Mutex::new(HashSet::new())),
+            filter: Arc::new(Mutex::new(WatchFilter::default())),
+            attribute_snapshots: Arc::new(Mutex::new(HashMap::new())),
             debounce_duration,
+            batching: (DEFAULT_MAX_BATCH, DEFAULT_BATCH_DELAY),
+            batch_input: Mutex::new(None),
         }
     }
 
-    pub fn start(&self, sender: mpsc::UnboundedSender<WatchEvent>) -> Result<()> {
+    /// With a `WatchSender::Batches` sender, events are sent once `max_batch`
+    /// of them are pending or `max_delay` after the first of them, whichever
+    /// comes first. Has no effect on a `WatchSender::Events` sender.
+    pub fn with_batch_delivery(mut self, max_batch: usize, max_delay: Duration) -> Self {
+        self.batching = (max_batch.max(1), max_delay);
+        self
+    }
+
+    pub fn with_filter(self, filter: WatchFilter) -> Self {
+        *self.filter.lock() = filter;
+        self
+    }
+
+    pub fn add_filter_pattern(&self, pattern: &str) -> Result<()> {
+        self.filter.lock().add_pattern(pattern)
+    }
+
+    pub fn remove_filter_pattern(&self, pattern: &str) {
+        self.filter.lock().remove_pattern(pattern);
+    }
+
+    pub fn start(&self, sender: impl Into<WatchSender>) -> Result<()> {
+        let deliver: Box<dyn Fn(WatchEvent) + Send> = match sender.into() {
+            WatchSender::Events(sender) => Box::new(move |event| {
+                let _ = sender.send(event);
+            }),
+            WatchSender::Batches(sender) => {
+                let (input, messages) = std_mpsc::channel();
+                let (max_batch, max_delay) = self.batching;
+                std::thread::Builder::new()
+                    .name("cheese-watch-batch".to_string())
+                    .spawn(move || deliver_batches(messages, sender, max_batch, max_delay))?;
+
+                *self.batch_input.lock() = Some(input.clone());
+                Box::new(move |event| {
+                    let _ = input.send(BatchMessage::Event(event));
+                })
+            }
+        };
+
         let watched_paths = Arc::clone(&self.watched_paths);
+        let watched_dirs = Arc::clone(&self.watched_dirs);
+        let watched_files = Arc::clone(&self.watched_files);
+        let filter = Arc::clone(&self.filter);
+        let attribute_snapshots = Arc::clone(&self.attribute_snapshots);
         let debounce_duration = self.debounce_duration;
 
         let watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
             match res {
                 Ok(event) => {
-                    if let Some(watch_event) = Self::convert_event(event, &watched_paths, debounce_duration) {
-                        let _ = sender.send(watch_event);
+                    let scope = (&watched_dirs, &watched_files);
+                    if let Some(watch_event) = Self::convert_event(event, &watched_paths, scope, &filter, debounce_duration) {
+                        if Self::is_noop_attribute_change(&watch_event, &attribute_snapshots) {
+                            return;
+                        }
+                        deliver(watch_event);
                     }
                 }
                 Err(e) => {
@@ -59,9 +195,10 @@ impl Watcher {
         if let Some(w) = watcher.as_mut() {
             w.watch(path, RecursiveMode::NonRecursive)?;
             self.watched_paths.lock().insert(path.to_path_buf(), Instant::now());
+            self.watched_dirs.lock().insert(path.to_path_buf());
             Ok(())
         } else {
-            Err(Error::Watcher("Watcher not started".to_string()))
+            Err(Error::Watcher("Watcher not started".into()))
         }
     }
 
@@ -69,22 +206,103 @@ impl Watcher {
         let mut watcher = self.inner.lock();
         
         if let Some(w) = watcher.as_mut() {
-            w.unwatch(path)?;
+            self.watched_dirs.lock().remove(path);
+            // Watched files in `path` still need the directory watch.
+            if !self.has_watched_file_in(path) {
+                w.unwatch(path)?;
+            }
+            self.watched_paths.lock().remove(path);
+            self.attribute_snapshots.lock().retain(|p, _| p.parent() != Some(path));
+            Ok(())
+        } else {
+            Err(Error::Watcher("Watcher not started".into()))
+        }
+    }
+
+    /// Watches a single file through its parent directory. Events for its
+    /// siblings are dropped unless the directory is also watched with `watch`.
+    pub fn watch_file(&self, path: &Path) -> Result<()> {
+        let parent = path.parent().ok_or_else(|| Error::InvalidPath { path: path.to_path_buf() })?;
+        let mut watcher = self.inner.lock();
+
+        if let Some(w) = watcher.as_mut() {
+            w.watch(parent, RecursiveMode::NonRecursive)?;
+            self.watched_files.lock().insert(path.to_path_buf());
+            Ok(())
+        } else {
+            Err(Error::Watcher("Watcher not started".into()))
+        }
+    }
+
+    pub fn unwatch_file(&self, path: &Path) -> Result<()> {
+        let parent = path.parent().ok_or_else(|| Error::InvalidPath { path: path.to_path_buf() })?;
+        let mut watcher = self.inner.lock();
+
+        if let Some(w) = watcher.as_mut() {
+            if !self.watched_files.lock().remove(path) {
+                return Err(Error::Watcher(format!("Not watching {}", path.display()).into()));
+            }
+            if !self.has_watched_file_in(parent) && !self.watched_dirs.lock().contains(parent) {
+                w.unwatch(parent)?;
+            }
             self.watched_paths.lock().remove(path);
+            self.attribute_snapshots.lock().remove(path);
             Ok(())
         } else {
-            Err(Error::Watcher("Watcher not started".to_string()))
+            Err(Error::Watcher("Watcher not started".into()))
+        }
+    }
+
+    fn has_watched_file_in(&self, dir: &Path) -> bool {
+        self.watched_files.lock().iter().any(|file| file.parent() == Some(dir))
+    }
+
+    /// Sends the pending batch right away. Does nothing unless started with
+    /// a `WatchSender::Batches` sender.
+    pub fn flush(&self) {
+        if let Some(input) = self.batch_input.lock().as_ref() {
+            let _ = input.send(BatchMessage::Flush);
         }
     }
 
     pub fn stop(&self) {
+        // Dropping both inputs ends the batching thread after a last batch.
         *self.inner.lock() = None;
+        *self.batch_input.lock() = None;
         self.watched_paths.lock().clear();
+        self.watched_dirs.lock().clear();
+        self.watched_files.lock().clear();
+        self.attribute_snapshots.lock().clear();
+    }
+
+    // Editors and sync tools often rewrite attributes without changing them;
+    // compare against the last snapshot so those events are not forwarded.
+    fn is_noop_attribute_change(
+        event: &WatchEvent,
+        snapshots: &Arc<Mutex<HashMap<PathBuf, ExtendedMetadata>>>,
+    ) -> bool {
+        match event {
+            WatchEvent::AttributesChanged(path) => {
+                let Ok(current) = ExtendedMetadata::from_path(path) else {
+                    snapshots.lock().remove(path);
+                    return false;
+                };
+                let previous = snapshots.lock().insert(path.clone(), current.clone());
+                previous.is_some_and(|previous| previous.diff(&current).is_empty())
+            }
+            WatchEvent::Deleted(path) | WatchEvent::Renamed { from: path, .. } => {
+                snapshots.lock().remove(path);
+                false
+            }
+            _ => false,
+        }
     }
 
     fn convert_event(
         event: Event,
         watched_paths: &Arc<Mutex<HashMap<PathBuf, Instant>>>,
+        (watched_dirs, watched_files): (&PathSet, &PathSet),
+        filter: &Arc<Mutex<WatchFilter>>,
         debounce_duration: Duration,
     ) -> Option<WatchEvent> {
         let now = Instant::now();
@@ -94,6 +312,17 @@ impl Watcher {
             return None;
         }
 
+        if !Self::in_scope(&paths, &watched_dirs.lock(), &watched_files.lock()) {
+            return None;
+        }
+
+        {
+            let filter = filter.lock();
+            if !filter.is_empty() && paths.iter().all(|p| filter.matches(p)) {
+                return None;
+            }
+        }
+
         let path = &paths[0];
         
         {
@@ -108,6 +337,10 @@ impl Watcher {
 
         match event.kind {
             EventKind::Create(_) => Some(WatchEvent::Created(path.clone())),
+
+            EventKind::Modify(notify::event::ModifyKind::Metadata(_)) => {
+                Some(WatchEvent::AttributesChanged(path.clone()))
+            }
             
             EventKind::Modify(_) => Some(WatchEvent::Modified(path.clone())),
             
@@ -136,8 +369,22 @@ impl Watcher {
         }
     }
 
+    // A path in a directory that is only watched for some of its files is
+    // in scope when it is one of those files. Renames count when either side is.
+    fn in_scope(paths: &[PathBuf], watched_dirs: &HashSet<PathBuf>, watched_files: &HashSet<PathBuf>) -> bool {
+        if watched_files.is_empty() {
+            return true;
+        }
+
+        paths.iter().any(|path| {
+            watched_files.contains(path) || path.parent().is_none_or(|parent| {
+                watched_dirs.contains(parent) || !watched_files.iter().any(|file| file.parent() == Some(parent))
+            })
+        })
+    }
+
     pub fn is_watching(&self, path: &Path) -> bool {
-        self.watched_paths.lock().contains_key(path)
+        self.watched_paths.lock().contains_key(path) || self.watched_files.lock().contains(path)
     }
 
     pub fn watched_count(&self) -> usize {
@@ -145,6 +392,47 @@ impl Watcher {
     }
 }
 
+// Runs on its own thread until every input is dropped or the receiver of
+// `output` is gone.
+fn deliver_batches(
+    input: std_mpsc::Receiver<BatchMessage>,
+    output: mpsc::Sender<Vec<WatchEvent>>,
+    max_batch: usize,
+    max_delay: Duration,
+) {
+    let mut batch = Vec::new();
+    let mut deadline: Option<Instant> = None;
+
+    loop {
+        let message = match deadline {
+            Some(deadline) => input.recv_timeout(deadline.saturating_duration_since(Instant::now())),
+            None => input.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+
+        let send = match message {
+            Ok(BatchMessage::Event(event)) => {
+                deadline.get_or_insert_with(|| Instant::now() + max_delay);
+                batch.push(event);
+                batch.len() >= max_batch
+            }
+            Ok(BatchMessage::Flush) | Err(RecvTimeoutError::Timeout) => true,
+            Err(RecvTimeoutError::Disconnected) => {
+                if !batch.is_empty() {
+                    let _ = output.blocking_send(batch);
+                }
+                return;
+            }
+        };
+
+        if send && !batch.is_empty() {
+            deadline = None;
+            if output.blocking_send(std::mem::take(&mut batch)).is_err() {
+                return;
+            }
+        }
+    }
+}
+
 impl Default for Watcher {
     fn default() -> Self {
         Self::new(DEBOUNCE_DURATION)
@@ -208,4 +496,229 @@ mod tests {
             }
         }
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_batch_delivery() {
+        let temp_dir = TempDir::new().unwrap();
+        let (tx, mut rx) = mpsc::channel(16);
+
+        let watcher = Watcher::default().with_batch_delivery(50, Duration::from_secs(5));
+        watcher.start(tx).unwrap();
+        watcher.watch(temp_dir.path()).unwrap();
+
+        let files: Vec<PathBuf> = (0..50).map(|i| temp_dir.path().join(format!("file{}.txt", i))).collect();
+        for file in &files {
+            fs::write(file, "x").unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        watcher.flush();
+
+        let mut batches = Vec::new();
+        let mut created = HashSet::new();
+        while created.len() < files.len() {
+            let batch = tokio::time::timeout(Duration::from_secs(2), rx.recv()).await.unwrap().unwrap();
+            for event in &batch {
+                if let WatchEvent::Created(path) = event {
+                    created.insert(path.clone());
+                }
+            }
+            batches.push(batch);
+        }
+
+        assert!(batches.len() <= 3, "{} batches", batches.len());
+        assert!(batches.iter().all(|batch| !batch.is_empty() && batch.len() <= 50));
+        assert_eq!(created, files.into_iter().collect());
+    }
+
+    #[test]
+    fn test_deliver_batches_by_size_and_delay() {
+        let (input, messages) = std_mpsc::channel();
+        let (tx, mut rx) = mpsc::channel(16);
+        let worker = std::thread::spawn(move || deliver_batches(messages, tx, 20, Duration::from_millis(50)));
+        let event = |i: usize| BatchMessage::Event(WatchEvent::Created(PathBuf::from(format!("/dir/{}", i))));
+
+        for i in 0..45 {
+            input.send(event(i)).unwrap();
+        }
+        assert_eq!(rx.blocking_recv().unwrap().len(), 20);
+        assert_eq!(rx.blocking_recv().unwrap().len(), 20);
+        // The last five go out once the delay has passed.
+        assert_eq!(rx.blocking_recv().unwrap().len(), 5);
+
+        input.send(event(45)).unwrap();
+        input.send(BatchMessage::Flush).unwrap();
+        match rx.blocking_recv().unwrap().as_slice() {
+            [WatchEvent::Created(path)] => assert_eq!(path, Path::new("/dir/45")),
+            other => panic!("Unexpected batch: {:?}", other),
+        }
+
+        input.send(event(46)).unwrap();
+        drop(input);
+        assert_eq!(rx.blocking_recv().unwrap().len(), 1);
+        worker.join().unwrap();
+        assert!(rx.blocking_recv().is_none());
+    }
+
+    fn create_event(path: &Path) -> Event {
+        Event::new(EventKind::Create(notify::event::CreateKind::File)).add_path(path.to_path_buf())
+    }
+
+    #[test]
+    fn test_watch_filter_matches() {
+        let mut filter = WatchFilter::new(&["*.o", "**/.git/objects/**"]).unwrap();
+
+        assert!(filter.matches(Path::new("/src/build/main.o")));
+        assert!(filter.matches(Path::new("/repo/.git/objects/ab/cdef")));
+        assert!(!filter.matches(Path::new("/src/main.c")));
+        assert!(!filter.matches(Path::new("/repo/.git/HEAD")));
+
+        filter.remove_pattern("*.o");
+        assert!(!filter.matches(Path::new("/src/build/main.o")));
+
+        assert!(matches!(WatchFilter::new(&["[unclosed"]), Err(Error::Watcher(_))));
+    }
+
+    #[test]
+    fn test_convert_event_discards_filtered_paths() {
+        let watched_paths = Arc::new(Mutex::new(HashMap::new()));
+        let watched_dirs = Arc::new(Mutex::new(HashSet::new()));
+        let watched_files = Arc::new(Mutex::new(HashSet::new()));
+        let filter = Arc::new(Mutex::new(WatchFilter::new(&["*.o"]).unwrap()));
+
+        let object = Path::new("/src/main.o");
+        let source = Path::new("/src/main.c");
+        let scope = (&watched_dirs, &watched_files);
+
+        assert!(Watcher::convert_event(create_event(object), &watched_paths, scope, &filter, DEBOUNCE_DURATION).is_none());
+        assert!(!watched_paths.lock().contains_key(object));
+
+        match Watcher::convert_event(create_event(source), &watched_paths, scope, &filter, DEBOUNCE_DURATION) {
+            Some(WatchEvent::Created(path)) => assert_eq!(path, source),
+            other => panic!("Expected Created event, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_noop_attribute_change_skipped() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("file.txt");
+        fs::write(&file, "data").unwrap();
+
+        let snapshots = Arc::new(Mutex::new(HashMap::new()));
+        let event = WatchEvent::AttributesChanged(file.clone());
+
+        assert!(!Watcher::is_noop_attribute_change(&event, &snapshots));
+        assert!(Watcher::is_noop_attribute_change(&event, &snapshots));
+
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o600)).unwrap();
+        assert!(!Watcher::is_noop_attribute_change(&event, &snapshots));
+        assert!(Watcher::is_noop_attribute_change(&event, &snapshots));
+
+        assert!(!Watcher::is_noop_attribute_change(&WatchEvent::Deleted(file.clone()), &snapshots));
+        assert!(snapshots.lock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_watch_filter_suppresses_object_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        let watcher = Watcher::default().with_filter(WatchFilter::new(&["*.o"]).unwrap());
+        watcher.start(tx).unwrap();
+        watcher.watch(temp_dir.path()).unwrap();
+
+        for i in 0..5 {
+            fs::write(temp_dir.path().join(format!("unit{}.o", i)), "obj").unwrap();
+        }
+        let source = temp_dir.path().join("main.c");
+        fs::write(&source, "int main;").unwrap();
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let mut seen = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            match event {
+                WatchEvent::Created(path)
+                | WatchEvent::Modified(path)
+                | WatchEvent::Deleted(path)
+                | WatchEvent::AttributesChanged(path) => seen.push(path),
+                WatchEvent::Renamed { from, to } => seen.extend([from, to]),
+            }
+        }
+
+        assert!(seen.iter().all(|p| p.extension().is_none_or(|ext| ext != "o")));
+        assert!(seen.contains(&source));
+
+        watcher.remove_filter_pattern("*.o");
+        let object = temp_dir.path().join("late.o");
+        fs::write(&object, "obj").unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let mut late = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            if let WatchEvent::Created(path) | WatchEvent::Modified(path) = event {
+                late.push(path);
+            }
+        }
+        assert!(late.contains(&object));
+    }
+
+    #[test]
+    fn test_in_scope_for_watched_files() {
+        let config = PathBuf::from("/etc/cheese/cheese.toml");
+        let sibling = PathBuf::from("/etc/cheese/other.toml");
+        let elsewhere = PathBuf::from("/home/user/notes.txt");
+        let files = HashSet::from([config.clone()]);
+        let mut dirs = HashSet::new();
+
+        assert!(Watcher::in_scope(std::slice::from_ref(&config), &dirs, &files));
+        assert!(!Watcher::in_scope(std::slice::from_ref(&sibling), &dirs, &files));
+        assert!(Watcher::in_scope(std::slice::from_ref(&elsewhere), &dirs, &files));
+        // Atomic saves rename a temporary file over the watched one.
+        assert!(Watcher::in_scope(&[sibling.clone(), config.clone()], &dirs, &files));
+
+        dirs.insert(PathBuf::from("/etc/cheese"));
+        assert!(Watcher::in_scope(&[sibling], &dirs, &files));
+    }
+
+    #[tokio::test]
+    async fn test_watch_file_ignores_siblings() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = temp_dir.path().join("cheese.toml");
+        let sibling = temp_dir.path().join("other.txt");
+        fs::write(&config, "a = 1").unwrap();
+        fs::write(&sibling, "x").unwrap();
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let watcher = Watcher::new(Duration::ZERO);
+        watcher.start(tx).unwrap();
+        watcher.watch_file(&config).unwrap();
+        assert!(watcher.is_watching(&config));
+
+        fs::write(&sibling, "y").unwrap();
+        fs::write(&config, "a = 2").unwrap();
+        fs::write(temp_dir.path().join("new.txt"), "new").unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let mut seen = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            match event {
+                WatchEvent::Created(path)
+                | WatchEvent::Modified(path)
+                | WatchEvent::Deleted(path)
+                | WatchEvent::AttributesChanged(path) => seen.push(path),
+                WatchEvent::Renamed { from, to } => seen.extend([from, to]),
+            }
+        }
+        assert!(!seen.is_empty());
+        assert!(seen.iter().all(|path| *path == config), "{:?}", seen);
+
+        watcher.unwatch_file(&config).unwrap();
+        assert!(!watcher.is_watching(&config));
+        fs::write(&config, "a = 3").unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(rx.try_recv().is_err());
+    }
 }
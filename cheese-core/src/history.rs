@@ -0,0 +1,167 @@
+use crate::{Error, Result};
+use crate::error::context;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use xdg::BaseDirectories;
+
+const HISTORY_FILE: &str = "history.toml";
+const DEFAULT_CAPACITY: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub path: PathBuf,
+    pub last_used: SystemTime,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct HistoryFile {
+    #[serde(default)]
+    dirs: Vec<HistoryEntry>,
+    #[serde(default)]
+    files: Vec<HistoryEntry>,
+}
+
+pub struct History {
+    dirs: Vec<HistoryEntry>,
+    files: Vec<HistoryEntry>,
+    capacity: usize,
+    file_path: PathBuf,
+}
+
+impl History {
+    pub fn load() -> Result<Self> {
+        let xdg_dirs = BaseDirectories::with_prefix("cheese")
+            .map_err(|e| Error::Config(context("Failed to get XDG directories", e)))?;
+
+        Self::load_from(xdg_dirs.get_data_home().join(HISTORY_FILE))
+    }
+
+    pub fn load_from(file_path: PathBuf) -> Result<Self> {
+        let file = if file_path.exists() {
+            let contents = std::fs::read_to_string(&file_path)?;
+            toml::from_str(&contents)?
+        } else {
+            HistoryFile::default()
+        };
+
+        Ok(Self {
+            dirs: file.dirs,
+            files: file.files,
+            capacity: DEFAULT_CAPACITY,
+            file_path,
+        })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.file_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let file = HistoryFile {
+            dirs: self.dirs.clone(),
+            files: self.files.clone(),
+        };
+        let toml_str = toml::to_string_pretty(&file)
+            .map_err(|e| Error::Config(context("Failed to serialize history", e)))?;
+
+        std::fs::write(&self.file_path, toml_str)?;
+        Ok(())
+    }
+
+    pub fn record_dir(&mut self, path: &Path) -> Result<()> {
+        record(&mut self.dirs, path, self.capacity);
+        self.save()
+    }
+
+    pub fn record_file(&mut self, path: &Path) -> Result<()> {
+        record(&mut self.files, path, self.capacity);
+        self.save()
+    }
+
+    pub fn recent_dirs(&self, limit: usize) -> Vec<HistoryEntry> {
+        self.dirs.iter().take(limit).cloned().collect()
+    }
+
+    pub fn recent_files(&self, limit: usize) -> Vec<HistoryEntry> {
+        self.files.iter().take(limit).cloned().collect()
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.dirs.truncate(capacity);
+        self.files.truncate(capacity);
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn clear(&mut self) -> Result<()> {
+        self.dirs.clear();
+        self.files.clear();
+        self.save()
+    }
+}
+
+fn record(entries: &mut Vec<HistoryEntry>, path: &Path, capacity: usize) {
+    entries.retain(|e| e.path != path);
+    entries.insert(0, HistoryEntry {
+        path: path.to_path_buf(),
+        last_used: SystemTime::now(),
+    });
+    entries.truncate(capacity);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_history_dedup() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut history = History::load_from(temp_dir.path().join(HISTORY_FILE)).unwrap();
+
+        history.record_dir(Path::new("/a")).unwrap();
+        history.record_dir(Path::new("/b")).unwrap();
+        history.record_dir(Path::new("/a")).unwrap();
+
+        let recent: Vec<_> = history.recent_dirs(10).into_iter().map(|e| e.path).collect();
+        assert_eq!(recent, vec![PathBuf::from("/a"), PathBuf::from("/b")]);
+        assert!(history.recent_files(10).is_empty());
+    }
+
+    #[test]
+    fn test_history_capping() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut history = History::load_from(temp_dir.path().join(HISTORY_FILE)).unwrap();
+        history.set_capacity(3);
+
+        for i in 0..5 {
+            history.record_file(&PathBuf::from(format!("/file{}", i))).unwrap();
+        }
+
+        let recent: Vec<_> = history.recent_files(10).into_iter().map(|e| e.path).collect();
+        assert_eq!(recent, vec![
+            PathBuf::from("/file4"),
+            PathBuf::from("/file3"),
+            PathBuf::from("/file2"),
+        ]);
+        assert_eq!(history.recent_files(1).len(), 1);
+    }
+
+    #[test]
+    fn test_history_persistence() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join(HISTORY_FILE);
+
+        let mut history = History::load_from(file_path.clone()).unwrap();
+        history.record_dir(Path::new("/tmp")).unwrap();
+        history.record_file(Path::new("/tmp/notes.txt")).unwrap();
+
+        let reloaded = History::load_from(file_path).unwrap();
+        assert_eq!(reloaded.recent_dirs(10), history.recent_dirs(10));
+        assert_eq!(reloaded.recent_files(10), history.recent_files(10));
+    }
+}
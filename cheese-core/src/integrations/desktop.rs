@@ -0,0 +1,506 @@
+use super::{resolve_program, spawn_detached, terminal_command};
+use crate::config::Config;
+use crate::fs::detect_mime;
+use crate::{Error, Result};
+use crate::error::context;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use xdg::BaseDirectories;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DesktopApp {
+    pub id: String,
+    pub name: String,
+    pub exec: String,
+    pub icon: Option<String>,
+    pub mime_types: Vec<String>,
+    pub terminal: bool,
+    pub path: PathBuf,
+}
+
+impl DesktopApp {
+    pub fn parse(id: &str, path: &Path, contents: &str) -> Option<Self> {
+        let entry = parse_group(contents, "Desktop Entry");
+
+        if entry.get("Type").map(String::as_str) != Some("Application")
+            || entry.get("Hidden").map(String::as_str) == Some("true")
+        {
+            return None;
+        }
+
+        Some(Self {
+            id: id.to_string(),
+            name: entry.get("Name")?.clone(),
+            exec: entry.get("Exec")?.clone(),
+            icon: entry.get("Icon").cloned(),
+            mime_types: split_list(entry.get("MimeType").map(String::as_str).unwrap_or("")),
+            terminal: entry.get("Terminal").map(String::as_str) == Some("true"),
+            path: path.to_path_buf(),
+        })
+    }
+
+    // Expands the Exec line's field codes for the given files. Codes that take a
+    // single file only use the first one; deprecated codes are dropped.
+    pub fn command_line(&self, files: &[PathBuf]) -> Vec<String> {
+        let mut args = Vec::new();
+
+        for word in split_exec(&self.exec) {
+            match word.as_str() {
+                "%f" => args.extend(files.first().map(|f| f.display().to_string())),
+                "%F" => args.extend(files.iter().map(|f| f.display().to_string())),
+                "%u" => args.extend(files.first().map(|f| file_uri(f))),
+                "%U" => args.extend(files.iter().map(|f| file_uri(f))),
+                "%i" => {
+                    if let Some(icon) = &self.icon {
+                        args.push("--icon".to_string());
+                        args.push(icon.clone());
+                    }
+                }
+                _ => {
+                    let expanded = expand_inline_codes(&word, self);
+                    if !expanded.is_empty() || !word.starts_with('%') {
+                        args.push(expanded);
+                    }
+                }
+            }
+        }
+
+        args
+    }
+
+    pub fn handles(&self, mime: &str) -> bool {
+        self.mime_types.iter().any(|m| m == mime)
+    }
+}
+
+pub struct DesktopDatabase {
+    config_dirs: Vec<PathBuf>,
+    data_dirs: Vec<PathBuf>,
+}
+
+impl DesktopDatabase {
+    pub fn from_env() -> Result<Self> {
+        let xdg_dirs = BaseDirectories::new()
+            .map_err(|e| Error::Config(context("Failed to get XDG directories", e)))?;
+
+        let mut config_dirs = vec![xdg_dirs.get_config_home()];
+        config_dirs.extend(xdg_dirs.get_config_dirs());
+
+        let mut data_dirs = vec![xdg_dirs.get_data_home()];
+        data_dirs.extend(xdg_dirs.get_data_dirs());
+
+        Ok(Self::with_dirs(config_dirs, data_dirs))
+    }
+
+    pub fn with_dirs(config_dirs: Vec<PathBuf>, data_dirs: Vec<PathBuf>) -> Self {
+        Self { config_dirs, data_dirs }
+    }
+
+    // Earlier data directories take precedence when two provide the same desktop id.
+    pub fn applications(&self) -> Vec<DesktopApp> {
+        let mut apps: Vec<DesktopApp> = Vec::new();
+
+        for dir in &self.data_dirs {
+            let app_dir = dir.join("applications");
+            let mut found = Vec::new();
+            collect_desktop_files(&app_dir, &app_dir, &mut found);
+            found.sort();
+
+            for (id, path) in found {
+                if apps.iter().any(|a| a.id == id) {
+                    continue;
+                }
+                match std::fs::read_to_string(&path) {
+                    Ok(contents) => apps.extend(DesktopApp::parse(&id, &path, &contents)),
+                    Err(e) => tracing::warn!("Failed to read {}: {}", path.display(), e),
+                }
+            }
+        }
+
+        apps
+    }
+
+    pub fn applications_for(&self, mime: &str) -> Vec<DesktopApp> {
+        let apps = self.applications();
+        let associations = self.associations(mime);
+        let find = |id: &String| apps.iter().find(|a| &a.id == id);
+
+        let mut result: Vec<DesktopApp> = Vec::new();
+        let mut push = |app: &DesktopApp| {
+            if !associations.removed.contains(&app.id) && !result.iter().any(|a| a.id == app.id) {
+                result.push(app.clone());
+            }
+        };
+
+        associations.defaults.iter().filter_map(find).for_each(&mut push);
+        associations.added.iter().filter_map(find).for_each(&mut push);
+        apps.iter().filter(|a| a.handles(mime)).for_each(&mut push);
+
+        result
+    }
+
+    pub fn default_application(&self, mime: &str) -> Option<DesktopApp> {
+        self.applications_for(mime).into_iter().next()
+    }
+
+    fn mimeapps_lists(&self) -> Vec<PathBuf> {
+        let mut lists: Vec<PathBuf> = self.config_dirs.iter().map(|d| d.join("mimeapps.list")).collect();
+        for dir in &self.data_dirs {
+            lists.push(dir.join("applications/mimeapps.list"));
+            lists.push(dir.join("applications/defaults.list"));
+        }
+        lists
+    }
+
+    fn associations(&self, mime: &str) -> Associations {
+        let mut associations = Associations::default();
+
+        for list in self.mimeapps_lists() {
+            let Ok(contents) = std::fs::read_to_string(&list) else {
+                continue;
+            };
+
+            let lookup = |group: &str| {
+                parse_group(&contents, group)
+                    .get(mime)
+                    .map(|v| split_list(v))
+                    .unwrap_or_default()
+            };
+
+            associations.defaults.extend(lookup("Default Applications"));
+            associations.added.extend(lookup("Added Associations"));
+            associations.removed.extend(lookup("Removed Associations"));
+        }
+
+        associations
+    }
+}
+
+#[derive(Default)]
+struct Associations {
+    defaults: Vec<String>,
+    added: Vec<String>,
+    removed: Vec<String>,
+}
+
+pub fn applications_for(mime: &str) -> Vec<DesktopApp> {
+    match DesktopDatabase::from_env() {
+        Ok(database) => database.applications_for(mime),
+        Err(e) => {
+            tracing::warn!("Failed to read desktop entries: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+pub fn open_with_default(path: &Path) -> Result<()> {
+    let mime = detect_mime(path);
+    let app = DesktopDatabase::from_env()?
+        .default_application(&mime)
+        .ok_or_else(|| Error::InvalidOperation(format!("No application registered for {}", mime)))?;
+
+    launch(&app, &[path.to_path_buf()])
+}
+
+pub fn launch(app: &DesktopApp, files: &[PathBuf]) -> Result<()> {
+    let args = app.command_line(files);
+    let program = args
+        .first()
+        .ok_or_else(|| Error::InvalidOperation(format!("{} has an empty Exec line", app.id)))?;
+    let program = resolve_program(program)?;
+    let cwd = files
+        .first()
+        .and_then(|f| f.parent())
+        .filter(|p| p.is_dir())
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("/"));
+
+    let command = if app.terminal {
+        let terminal = Config::load()?.integrations.terminal;
+        let mut exec: Vec<OsString> = vec![program.into_os_string()];
+        exec.extend(args[1..].iter().map(OsString::from));
+        terminal_command(&terminal, &cwd, &exec)?
+    } else {
+        let mut command = Command::new(program);
+        command.args(&args[1..]).current_dir(&cwd);
+        command
+    };
+
+    spawn_detached(command)
+}
+
+fn collect_desktop_files(root: &Path, dir: &Path, found: &mut Vec<(String, PathBuf)>) {
+    let Ok(read_dir) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in read_dir.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_desktop_files(root, &path, found);
+        } else if path.extension().and_then(|e| e.to_str()) == Some("desktop") {
+            // Desktop ids use '-' in place of the directory separator.
+            if let Ok(relative) = path.strip_prefix(root) {
+                let id = relative.to_string_lossy().replace('/', "-");
+                found.push((id, path));
+            }
+        }
+    }
+}
+
+fn parse_group(contents: &str, group: &str) -> HashMap<String, String> {
+    let mut values = HashMap::new();
+    let mut in_group = false;
+
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            in_group = name == group;
+            continue;
+        }
+
+        if !in_group {
+            continue;
+        }
+
+        // Localized keys such as Name[de] are skipped, so the untranslated value wins.
+        if let Some((key, value)) = line.split_once('=') {
+            let key = key.trim();
+            if !key.contains('[') {
+                values.entry(key.to_string()).or_insert_with(|| unescape_value(value.trim()));
+            }
+        }
+    }
+
+    values
+}
+
+fn unescape_value(value: &str) -> String {
+    let mut output = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            output.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => output.push(' '),
+            Some('n') => output.push('\n'),
+            Some('t') => output.push('\t'),
+            Some('r') => output.push('\r'),
+            Some('\\') => output.push('\\'),
+            Some(other) => {
+                output.push('\\');
+                output.push(other);
+            }
+            None => output.push('\\'),
+        }
+    }
+
+    output
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(';')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+// Splits an Exec value into arguments following the desktop entry quoting rules:
+// double quotes group words and a backslash escapes the next character inside them.
+fn split_exec(exec: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut quoted = false;
+    let mut chars = exec.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_word = true;
+            }
+            '\\' if quoted => current.extend(chars.next()),
+            c if c.is_whitespace() && !quoted => {
+                if in_word {
+                    words.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            c => {
+                current.push(c);
+                in_word = true;
+            }
+        }
+    }
+
+    if in_word {
+        words.push(current);
+    }
+
+    words
+}
+
+fn expand_inline_codes(word: &str, app: &DesktopApp) -> String {
+    let mut output = String::with_capacity(word.len());
+    let mut chars = word.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            output.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('%') => output.push('%'),
+            Some('c') => output.push_str(&app.name),
+            Some('k') => output.push_str(&app.path.to_string_lossy()),
+            Some(_) | None => {}
+        }
+    }
+
+    output
+}
+
+fn file_uri(path: &Path) -> String {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().map(|d| d.join(path)).unwrap_or_else(|_| path.to_path_buf())
+    };
+
+    let mut uri = String::from("file://");
+    for byte in absolute.to_string_lossy().bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const IMAGE_VIEWER: &str = r#"# comment
+[Desktop Entry]
+Type=Application
+Name=Image Viewer
+Name[de]=Bildbetrachter
+Exec=viewer --title "%c \"quoted\"" --cfg=%k %U
+Icon=viewer
+MimeType=image/png;image/jpeg;
+Terminal=false
+
+[Desktop Action new-window]
+Name=New Window
+Exec=viewer --new-window
+"#;
+
+    fn app(exec: &str) -> DesktopApp {
+        DesktopApp {
+            id: "test.desktop".to_string(),
+            name: "Test".to_string(),
+            exec: exec.to_string(),
+            icon: Some("test-icon".to_string()),
+            mime_types: Vec::new(),
+            terminal: false,
+            path: PathBuf::from("/usr/share/applications/test.desktop"),
+        }
+    }
+
+    fn write_entry(dir: &Path, id: &str, name: &str, mime: &str) {
+        let contents = format!(
+            "[Desktop Entry]\nType=Application\nName={}\nExec={} %f\nMimeType={};\n",
+            name, name, mime
+        );
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(dir.join(id), contents).unwrap();
+    }
+
+    #[test]
+    fn test_parse_desktop_entry() {
+        let path = Path::new("/usr/share/applications/viewer.desktop");
+        let app = DesktopApp::parse("viewer.desktop", path, IMAGE_VIEWER).unwrap();
+
+        assert_eq!(app.name, "Image Viewer");
+        assert_eq!(app.exec, r#"viewer --title "%c \"quoted\"" --cfg=%k %U"#);
+        assert_eq!(app.icon.as_deref(), Some("viewer"));
+        assert_eq!(app.mime_types, vec!["image/png", "image/jpeg"]);
+        assert!(!app.terminal);
+        assert!(app.handles("image/jpeg"));
+
+        let files = vec![PathBuf::from("/pics/a b.png"), PathBuf::from("/pics/c.png")];
+        assert_eq!(
+            app.command_line(&files),
+            vec![
+                "viewer",
+                "--title",
+                "Image Viewer \"quoted\"",
+                "--cfg=/usr/share/applications/viewer.desktop",
+                "file:///pics/a%20b.png",
+                "file:///pics/c.png",
+            ]
+        );
+
+        assert!(DesktopApp::parse("x.desktop", path, "[Desktop Entry]\nType=Link\nName=x\nExec=x\n").is_none());
+        assert!(DesktopApp::parse("x.desktop", path, "[Desktop Entry]\nType=Application\nName=x\nExec=x\nHidden=true\n").is_none());
+    }
+
+    #[test]
+    fn test_field_codes() {
+        let files = vec![PathBuf::from("/tmp/one.txt"), PathBuf::from("/tmp/two.txt")];
+
+        assert_eq!(app("edit %f").command_line(&files), vec!["edit", "/tmp/one.txt"]);
+        assert_eq!(app("edit %F").command_line(&files), vec!["edit", "/tmp/one.txt", "/tmp/two.txt"]);
+        assert_eq!(app("edit %u").command_line(&files), vec!["edit", "file:///tmp/one.txt"]);
+        assert_eq!(app("edit %f").command_line(&[]), vec!["edit"]);
+        assert_eq!(app("edit %i %d %m 100%%").command_line(&[]), vec!["edit", "--icon", "test-icon", "100%"]);
+        assert_eq!(app(r"sh -c 'echo\s$1'").command_line(&[]), vec!["sh", "-c", r"'echo\s$1'"]);
+    }
+
+    #[test]
+    fn test_mimeapps_precedence() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_home = temp_dir.path().join("config");
+        let data_home = temp_dir.path().join("data-home");
+        let data_system = temp_dir.path().join("data-system");
+
+        write_entry(&data_system.join("applications"), "alpha.desktop", "alpha", "text/plain");
+        write_entry(&data_system.join("applications"), "beta.desktop", "beta", "text/plain");
+        write_entry(&data_system.join("applications/kde"), "gamma.desktop", "gamma", "text/plain");
+        write_entry(&data_system.join("applications"), "removed.desktop", "removed", "text/plain");
+        write_entry(&data_home.join("applications"), "beta.desktop", "beta-local", "text/plain");
+
+        std::fs::create_dir_all(&config_home).unwrap();
+        std::fs::write(
+            config_home.join("mimeapps.list"),
+            "[Default Applications]\ntext/plain=missing.desktop;kde-gamma.desktop;\n\
+             [Added Associations]\ntext/plain=beta.desktop;\n\
+             [Removed Associations]\ntext/plain=removed.desktop;\n",
+        )
+        .unwrap();
+
+        let database = DesktopDatabase::with_dirs(vec![config_home], vec![data_home, data_system]);
+        let apps = database.applications_for("text/plain");
+        let ids: Vec<&str> = apps.iter().map(|a| a.id.as_str()).collect();
+
+        assert_eq!(ids, vec!["kde-gamma.desktop", "beta.desktop", "alpha.desktop"]);
+        assert_eq!(apps[1].name, "beta-local");
+        assert_eq!(database.default_application("text/plain").unwrap().id, "kde-gamma.desktop");
+        assert!(database.default_application("image/png").is_none());
+    }
+}
@@ -0,0 +1,409 @@
+pub mod desktop;
+
+pub use desktop::{applications_for, open_with_default, DesktopApp};
+
+use crate::config::IntegrationsConfig;
+use crate::{Error, Result};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+// Editors that need a terminal to run in rather than opening their own window.
+const TERMINAL_EDITORS: &[&str] = &["vi", "vim", "nvim", "nano", "micro", "hx", "helix", "kak", "ne", "joe", "mg"];
+
+pub fn open_terminal(config: &IntegrationsConfig, cwd: &Path) -> Result<()> {
+    let cwd = working_directory(cwd)?;
+    spawn_detached(terminal_command(&config.terminal, &cwd, &[])?)
+}
+
+pub fn open_editor(config: &IntegrationsConfig, path: &Path) -> Result<()> {
+    let words = split_words(&expand_env(&config.editor));
+    let program = words
+        .first()
+        .ok_or_else(|| Error::InvalidOperation("No editor configured".to_string()))?;
+    let cwd = working_directory(path)?;
+
+    if TERMINAL_EDITORS.contains(&program_name(program).as_str()) {
+        let mut exec: Vec<OsString> = vec![resolve_program(program)?.into_os_string()];
+        exec.extend(words[1..].iter().map(OsString::from));
+        exec.push(path.as_os_str().to_owned());
+        return spawn_detached(terminal_command(&config.terminal, &cwd, &exec)?);
+    }
+
+    let mut command = program_command(&config.editor, &[path.as_os_str().to_owned()])?;
+    command.current_dir(&cwd);
+    spawn_detached(command)
+}
+
+pub fn open_archive_manager(config: &IntegrationsConfig, path: &Path) -> Result<()> {
+    let mut command = program_command(&config.archive_manager, &[path.as_os_str().to_owned()])?;
+    command.current_dir(working_directory(path)?);
+    spawn_detached(command)
+}
+
+fn working_directory(path: &Path) -> Result<PathBuf> {
+    if path.is_dir() {
+        return Ok(path.to_path_buf());
+    }
+
+    match path.parent() {
+        Some(parent) if parent.is_dir() => Ok(parent.to_path_buf()),
+        _ => Err(Error::NotFound { path: path.to_path_buf() }),
+    }
+}
+
+fn program_command(configured: &str, args: &[OsString]) -> Result<Command> {
+    let words = split_words(&expand_env(configured));
+    let program = words
+        .first()
+        .ok_or_else(|| Error::InvalidOperation(format!("No program configured in {:?}", configured)))?;
+
+    let mut command = Command::new(resolve_program(program)?);
+    command.args(&words[1..]).args(args);
+    Ok(command)
+}
+
+// Terminals disagree on how to set the working directory and how to pass a
+// command to run, so the flags are picked by executable name. Unknown terminals
+// rely on the inherited working directory and the xterm-style `-e`.
+fn terminal_command(configured: &str, cwd: &Path, exec: &[OsString]) -> Result<Command> {
+    let mut command = program_command(configured, &[])?;
+    let name = program_name(command.get_program());
+    command.current_dir(cwd);
+
+    let joined = |flag: &str| {
+        let mut arg = OsString::from(flag);
+        arg.push(cwd.as_os_str());
+        arg
+    };
+
+    match name.as_str() {
+        "gnome-terminal" | "kgx" | "ptyxis" => {
+            command.arg(joined("--working-directory="));
+            if !exec.is_empty() {
+                command.arg("--").args(exec);
+            }
+        }
+        "xfce4-terminal" | "terminator" | "tilix" => {
+            command.arg(joined("--working-directory="));
+            if !exec.is_empty() {
+                command.arg("-x").args(exec);
+            }
+        }
+        "foot" => {
+            command.arg(joined("--working-directory="));
+            command.args(exec);
+        }
+        "kitty" => {
+            command.arg("--directory").arg(cwd);
+            command.args(exec);
+        }
+        "alacritty" => {
+            command.arg("--working-directory").arg(cwd);
+            if !exec.is_empty() {
+                command.arg("-e").args(exec);
+            }
+        }
+        "konsole" => {
+            command.arg("--workdir").arg(cwd);
+            if !exec.is_empty() {
+                command.arg("-e").args(exec);
+            }
+        }
+        "wezterm" => {
+            command.arg("start").arg("--cwd").arg(cwd);
+            if !exec.is_empty() {
+                command.arg("--").args(exec);
+            }
+        }
+        _ => {
+            if !exec.is_empty() {
+                command.arg("-e").args(exec);
+            }
+        }
+    }
+
+    Ok(command)
+}
+
+// The child gets its own process group and is reaped from a background thread,
+// so it neither receives Cheese's terminal signals nor lingers as a zombie.
+fn spawn_detached(mut command: Command) -> Result<()> {
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+
+    let mut child = command.spawn()?;
+    std::thread::spawn(move || {
+        let _ = child.wait();
+    });
+
+    Ok(())
+}
+
+fn resolve_program(program: &str) -> Result<PathBuf> {
+    let not_found = || Error::InvalidOperation(format!("{} not found on PATH", program));
+
+    if program.contains('/') {
+        let path = PathBuf::from(program);
+        return if is_executable(&path) { Ok(path) } else { Err(not_found()) };
+    }
+
+    let search_path = std::env::var_os("PATH").ok_or_else(not_found)?;
+    std::env::split_paths(&search_path)
+        .map(|dir| dir.join(program))
+        .find(|candidate| is_executable(candidate))
+        .ok_or_else(not_found)
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path)
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+fn program_name(program: impl AsRef<std::ffi::OsStr>) -> String {
+    Path::new(program.as_ref())
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn expand_env(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            output.push(c);
+            continue;
+        }
+
+        let braced = chars.next_if_eq(&'{').is_some();
+        let mut name = String::new();
+        while let Some(&c) = chars.peek() {
+            if braced {
+                chars.next();
+                if c == '}' {
+                    break;
+                }
+                name.push(c);
+            } else if c.is_ascii_alphanumeric() || c == '_' {
+                name.push(c);
+                chars.next();
+            } else {
+                break;
+            }
+        }
+
+        if name.is_empty() && !braced {
+            output.push('$');
+        } else {
+            output.push_str(&std::env::var(&name).unwrap_or_default());
+        }
+    }
+
+    output
+}
+
+fn split_words(input: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut quote = None;
+
+    for c in input.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => current.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(expand_home(std::mem::take(&mut current)));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_word = true;
+            }
+        }
+    }
+
+    if in_word {
+        words.push(expand_home(current));
+    }
+
+    words
+}
+
+fn expand_home(word: String) -> String {
+    match (word.strip_prefix("~/"), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => format!("{}/{}", home.trim_end_matches('/'), rest),
+        _ => word,
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::{Duration, Instant};
+    use tempfile::TempDir;
+
+    fn stub(dir: &Path, name: &str, script: &str) -> String {
+        let path = dir.join(name);
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path.display().to_string()
+    }
+
+    fn config(terminal: &str, editor: &str) -> IntegrationsConfig {
+        IntegrationsConfig {
+            terminal: terminal.to_string(),
+            editor: editor.to_string(),
+            archive_manager: "xarchiver".to_string(),
+        }
+    }
+
+    fn args(command: &Command) -> Vec<String> {
+        command.get_args().map(|a| a.to_string_lossy().into_owned()).collect()
+    }
+
+    fn wait_for(path: &Path) -> String {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            if let Ok(contents) = std::fs::read_to_string(path) {
+                if contents.ends_with("done\n") {
+                    return contents;
+                }
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        panic!("stub never wrote {}", path.display());
+    }
+
+    #[test]
+    fn test_expand_env_and_split() {
+        std::env::set_var("CHEESE_TEST_EDITOR", "code --wait");
+
+        assert_eq!(expand_env("$CHEESE_TEST_EDITOR -n"), "code --wait -n");
+        assert_eq!(expand_env("${CHEESE_TEST_EDITOR}x"), "code --waitx");
+        assert_eq!(expand_env("cost $5 and $"), "cost  and $");
+        assert_eq!(expand_env("$CHEESE_TEST_UNSET_VARIABLE"), "");
+
+        assert_eq!(
+            split_words(r#"kitty --title "My Shell" -o 'font_size=12'"#),
+            vec!["kitty", "--title", "My Shell", "-o", "font_size=12"]
+        );
+        assert_eq!(split_words("  "), Vec::<String>::new());
+        assert_eq!(split_words(r#"a "" b"#), vec!["a", "", "b"]);
+    }
+
+    #[test]
+    fn test_terminal_flag_quirks() {
+        let temp_dir = TempDir::new().unwrap();
+        let cwd = temp_dir.path().join("work dir");
+        std::fs::create_dir(&cwd).unwrap();
+        let exec = vec![OsString::from("htop")];
+        let cwd_str = cwd.display().to_string();
+
+        let gnome = stub(temp_dir.path(), "gnome-terminal", "");
+        let command = terminal_command(&gnome, &cwd, &exec).unwrap();
+        assert_eq!(args(&command), vec![format!("--working-directory={}", cwd_str), "--".into(), "htop".into()]);
+
+        let kitty = stub(temp_dir.path(), "kitty", "");
+        let command = terminal_command(&format!("{} --single-instance", kitty), &cwd, &exec).unwrap();
+        assert_eq!(args(&command), vec!["--single-instance", "--directory", &cwd_str, "htop"]);
+
+        let konsole = stub(temp_dir.path(), "konsole", "");
+        let command = terminal_command(&konsole, &cwd, &[]).unwrap();
+        assert_eq!(args(&command), vec!["--workdir", &cwd_str]);
+
+        let xterm = stub(temp_dir.path(), "xterm", "");
+        let command = terminal_command(&xterm, &cwd, &exec).unwrap();
+        assert_eq!(args(&command), vec!["-e", "htop"]);
+        assert_eq!(command.get_current_dir(), Some(cwd.as_path()));
+    }
+
+    #[test]
+    fn test_missing_program() {
+        let temp_dir = TempDir::new().unwrap();
+        let missing = config("cheese-no-such-terminal", "");
+
+        assert!(matches!(
+            open_terminal(&missing, temp_dir.path()),
+            Err(Error::InvalidOperation(_))
+        ));
+        assert!(matches!(
+            open_editor(&missing, temp_dir.path()),
+            Err(Error::InvalidOperation(_))
+        ));
+
+        let not_executable = temp_dir.path().join("plain");
+        std::fs::write(&not_executable, "").unwrap();
+        assert!(matches!(
+            resolve_program(&not_executable.display().to_string()),
+            Err(Error::InvalidOperation(_))
+        ));
+    }
+
+    #[test]
+    fn test_open_terminal_spawns_in_cwd() {
+        let temp_dir = TempDir::new().unwrap();
+        let out = temp_dir.path().join("out.txt");
+        let terminal = stub(
+            temp_dir.path(),
+            "xterm",
+            &format!("{{ pwd; echo \"$@\"; echo done; }} > '{}'", out.display()),
+        );
+        let file = temp_dir.path().join("notes.txt");
+        std::fs::write(&file, "").unwrap();
+
+        open_terminal(&config(&terminal, ""), &file).unwrap();
+
+        let contents = wait_for(&out);
+        let mut lines = contents.lines();
+        assert_eq!(lines.next(), Some(temp_dir.path().canonicalize().unwrap().to_str().unwrap()));
+        assert_eq!(lines.next(), Some(""));
+    }
+
+    #[test]
+    fn test_terminal_editor_runs_inside_terminal() {
+        let temp_dir = TempDir::new().unwrap();
+        let out = temp_dir.path().join("out.txt");
+        let terminal = stub(
+            temp_dir.path(),
+            "alacritty",
+            &format!("{{ echo \"$@\"; echo done; }} > '{}'", out.display()),
+        );
+        let vim = stub(temp_dir.path(), "vim", "");
+        let file = temp_dir.path().join("notes.txt");
+        std::fs::write(&file, "").unwrap();
+
+        open_editor(&config(&terminal, &format!("{} -p", vim)), &file).unwrap();
+
+        let contents = wait_for(&out);
+        assert_eq!(
+            contents.lines().next().unwrap(),
+            format!("--working-directory {} -e {} -p {}", temp_dir.path().display(), vim, file.display())
+        );
+    }
+}
@@ -1,4 +1,5 @@
 pub mod error;
+pub mod blocking;
 pub mod fs;
 pub mod cache;
 pub mod security;
@@ -6,41 +7,191 @@ pub mod plugins;
 pub mod config;
 pub mod trash;
 pub mod mounts;
+pub mod bookmarks;
+pub mod history;
+pub mod archive;
+pub mod preview;
+pub mod dedup;
+pub mod network;
+pub mod location;
+pub mod integrations;
+pub mod search;
 
 pub use error::{Error, Result};
+use error::context;
 
+use fs::operation_manager::OperationManager;
+use fs::ops::LocalFileOps;
+use fs::scanner::{ScanResult, Scanner};
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 use parking_lot::RwLock;
 use tokio::runtime::Runtime;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+const SCAN_BUFFER: usize = 16;
+// Upper bound on the runtime teardown when the core is dropped.
+const RUNTIME_SHUTDOWN_GRACE: Duration = Duration::from_secs(1);
 
 pub struct CheeseCore {
-    runtime: Arc<Runtime>,
+    runtime: Option<Runtime>,
     config: Arc<RwLock<config::Config>>,
+    plugins: Arc<plugins::PluginManager>,
+    operations: Arc<OperationManager>,
+    blocking_pool: blocking::BlockingPool,
+    cache_manager: cache::CacheManager,
+    listing_cache: cache::DirectoryListingCache,
 }
 
 impl CheeseCore {
     pub fn new() -> Result<Self> {
+        Self::with_config(config::Config::load()?)
+    }
+
+    pub fn with_config(config: config::Config) -> Result<Self> {
         let runtime = tokio::runtime::Builder::new_multi_thread()
-            .worker_threads(4)
+            .worker_threads(config.performance.runtime_worker_threads())
             .thread_name("cheese-worker")
             .enable_all()
             .build()?;
 
-        let config = config::Config::load()?;
+        let xdg_dirs = xdg::BaseDirectories::with_prefix("cheese")
+            .map_err(|e| Error::Config(context("Failed to get XDG directories", e)))?;
+        let plugins = plugins::PluginManager::new(xdg_dirs.get_data_home().join("plugins"))?;
+        plugins.set_settings(config.plugins.settings.clone());
+        plugins.set_runtime(runtime.handle().clone());
+        plugins.set_max_download_size(config.plugins.max_download_mb.saturating_mul(1024 * 1024));
+
+        let blocking_pool = blocking::BlockingPool::new(config.performance.blocking_threads)?;
+        let file_ops = Arc::new(
+            LocalFileOps::new(config.performance.max_concurrent_ops).with_blocking_pool(blocking_pool.clone()),
+        );
+        let mut operations = OperationManager::new(file_ops, runtime.handle().clone());
+        match security::Security::from_config(&config.security) {
+            Ok(security) => operations = operations.with_security(Arc::new(security)),
+            Err(e) => tracing::warn!("Protected paths will not be enforced: {}", e),
+        }
+
+        let cache_manager = build_cache_manager(&config.performance, cache::thumbnail::ThumbnailCache::default_dir())?;
+        let listing_cache = cache::DirectoryListingCache::new(config.performance.listing_cache_dirs);
 
         Ok(Self {
-            runtime: Arc::new(runtime),
+            runtime: Some(runtime),
             config: Arc::new(RwLock::new(config)),
+            plugins: Arc::new(plugins),
+            operations: Arc::new(operations),
+            blocking_pool,
+            cache_manager,
+            listing_cache,
         })
     }
 
     pub fn runtime(&self) -> &Runtime {
-        &self.runtime
+        self.runtime.as_ref().expect("runtime is only taken on drop")
     }
 
     pub fn config(&self) -> Arc<RwLock<config::Config>> {
         Arc::clone(&self.config)
     }
+
+    pub fn plugins(&self) -> Arc<plugins::PluginManager> {
+        Arc::clone(&self.plugins)
+    }
+
+    pub fn operations(&self) -> Arc<OperationManager> {
+        Arc::clone(&self.operations)
+    }
+
+    /// Pool for synchronous filesystem work; see `BlockingPool` for what
+    /// belongs there rather than on `runtime()`.
+    pub fn blocking_pool(&self) -> blocking::BlockingPool {
+        self.blocking_pool.clone()
+    }
+
+    /// Metadata and thumbnail caches, sized from `PerformanceConfig`.
+    pub fn cache_manager(&self) -> &cache::CacheManager {
+        &self.cache_manager
+    }
+
+    /// Listings of recently opened directories, sized from
+    /// `PerformanceConfig::listing_cache_dirs`. Feed it watch events so a
+    /// changed directory is scanned again.
+    pub fn listing_cache(&self) -> &cache::DirectoryListingCache {
+        &self.listing_cache
+    }
+
+    pub fn scanner(&self) -> Scanner {
+        let config = self.config.read();
+        let mut scanner = Scanner::new(config.navigation.follow_symlinks, config.navigation.max_depth, config.ui.show_hidden)
+            .with_listing_cache(self.listing_cache.clone());
+        scanner.set_large_dir_threshold(config.performance.large_dir_threshold);
+        scanner.set_nanos_per_entry(config.performance.scan_nanos_per_entry);
+        scanner
+    }
+
+    /// Formats a size in the configured `UiConfig::byte_format`.
+    pub fn format_size(&self, bytes: u64) -> String {
+        fs::metadata::format_bytes_with(bytes, self.config.read().ui.byte_format)
+    }
+
+    /// Starts scanning `path` on the core runtime. The receiver closes when the
+    /// scan finishes, fails or is cancelled through the returned token.
+    pub fn open_directory(&self, path: PathBuf) -> (mpsc::Receiver<ScanResult>, CancellationToken) {
+        let (tx, rx) = mpsc::channel(SCAN_BUFFER);
+        let cancel = CancellationToken::new();
+        let scanner = self.scanner();
+        let scan_cancel = cancel.clone();
+
+        self.runtime().spawn(async move {
+            if let Err(e) = scanner.scan_directory(path.clone(), tx, scan_cancel).await {
+                tracing::warn!("Failed to scan {}: {}", path.display(), e);
+            }
+        });
+
+        (rx, cancel)
+    }
+
+    /// Cancels tracked operations and waits up to `timeout` for them to clean
+    /// up their partial files, then shuts plugins down. Must not be called from
+    /// within the core runtime.
+    pub fn shutdown(&self, timeout: Duration) -> Result<()> {
+        self.operations.cancel_all();
+
+        self.runtime().block_on(async {
+            let drained = tokio::time::timeout(timeout, self.operations.wait_idle()).await;
+
+            if let Err(e) = self.plugins.shutdown_all().await {
+                tracing::warn!("Plugin shutdown failed: {}", e);
+            }
+
+            drained.map_err(|_| Error::Timeout(format!(
+                "{} operations still running after {:?}",
+                self.operations.list_active().len(),
+                timeout,
+            )))
+        })
+    }
+}
+
+// Thumbnails live in `thumbnail_dir`. If it cannot be used, for example
+// because the XDG cache directory is read-only, a private directory under the
+// system temp dir stands in so the core still starts.
+fn build_cache_manager(performance: &config::PerformanceConfig, thumbnail_dir: Result<PathBuf>) -> Result<cache::CacheManager> {
+    let budget = performance.cache_size_mb + performance.thumbnail_cache_mb;
+    let cache_manager = match thumbnail_dir.and_then(|dir| cache::CacheManager::with_thumbnail_dir(dir, budget)) {
+        Ok(cache_manager) => cache_manager,
+        Err(e) => {
+            let fallback = std::env::temp_dir().join(format!("cheese-thumbnails-{}", nix::unistd::Uid::effective()));
+            tracing::warn!("Thumbnail cache unavailable ({}), using {}", e, fallback.display());
+            cache::CacheManager::with_thumbnail_dir(fallback, budget)?
+        }
+    }
+    .with_max_concurrent_thumbnails(performance.max_concurrent_ops);
+
+    cache_manager.reconfigure(performance.cache_size_mb, performance.thumbnail_cache_mb)?;
+    Ok(cache_manager)
 }
 
 impl Default for CheeseCore {
@@ -48,3 +199,177 @@ impl Default for CheeseCore {
         Self::new().expect("Failed to initialize CheeseCore")
     }
 }
+
+impl Drop for CheeseCore {
+    fn drop(&mut self) {
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_timeout(RUNTIME_SHUTDOWN_GRACE);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn drain(core: &CheeseCore, rx: &mut mpsc::Receiver<ScanResult>) -> Vec<String> {
+        let mut names = Vec::new();
+        while let Some(result) = core.runtime().block_on(rx.recv()) {
+            names.extend(result.entries.into_iter().map(|entry| entry.name));
+        }
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_open_directory_uses_config() {
+        let temp_dir = TempDir::new().unwrap();
+        for name in ["b.txt", "a.txt", ".hidden"] {
+            std::fs::write(temp_dir.path().join(name), name).unwrap();
+        }
+        std::fs::create_dir(temp_dir.path().join("sub")).unwrap();
+
+        let core = CheeseCore::with_config(config::Config::default()).unwrap();
+        let (mut rx, _cancel) = core.open_directory(temp_dir.path().to_path_buf());
+        assert_eq!(drain(&core, &mut rx), vec!["a.txt", "b.txt", "sub"]);
+
+        core.config().write().ui.show_hidden = true;
+        let (mut rx, _cancel) = core.open_directory(temp_dir.path().to_path_buf());
+        assert_eq!(drain(&core, &mut rx), vec![".hidden", "a.txt", "b.txt", "sub"]);
+    }
+
+    #[test]
+    fn test_open_directory_uses_listing_cache() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("a.txt"), b"").unwrap();
+
+        let mut config = config::Config::default();
+        config.performance.listing_cache_dirs = 1;
+        let core = CheeseCore::with_config(config).unwrap();
+        let (mut rx, _cancel) = core.open_directory(temp_dir.path().to_path_buf());
+        assert_eq!(drain(&core, &mut rx), vec!["a.txt"]);
+        assert_eq!(core.listing_cache().len(), 1);
+
+        // Served from the cache until a watch event invalidates the directory.
+        let created = temp_dir.path().join("b.txt");
+        std::fs::write(&created, b"").unwrap();
+        let (mut rx, _cancel) = core.open_directory(temp_dir.path().to_path_buf());
+        assert_eq!(drain(&core, &mut rx), vec!["a.txt"]);
+
+        core.listing_cache().invalidate_on_event(&fs::watcher::WatchEvent::Created(created));
+        let (mut rx, _cancel) = core.open_directory(temp_dir.path().to_path_buf());
+        assert_eq!(drain(&core, &mut rx), vec!["a.txt", "b.txt"]);
+
+        // Capacity comes from the config.
+        let other = TempDir::new().unwrap();
+        let (mut rx, _cancel) = core.open_directory(other.path().to_path_buf());
+        drain(&core, &mut rx);
+        assert_eq!(core.listing_cache().len(), 1);
+        assert!(core.listing_cache().get(temp_dir.path()).is_none());
+    }
+
+    #[test]
+    fn test_format_size_follows_config() {
+        let core = CheeseCore::with_config(config::Config::default()).unwrap();
+        assert_eq!(core.format_size(2048), "2.00 KiB");
+
+        core.config().write().ui.byte_format = fs::metadata::ByteFormat::Si;
+        assert_eq!(core.format_size(2048), "2.05 KB");
+    }
+
+    #[test]
+    fn test_shutdown_cancels_copy_and_removes_partial_file() {
+        use fs::operation_manager::{OperationRequest, OperationStatus};
+        use fs::ops::{ConflictResolution, ConflictResolutions, CopyOptions};
+
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("large.bin");
+        std::fs::write(&source, vec![7u8; 32 * 1024 * 1024]).unwrap();
+        let dest_dir = temp_dir.path().join("dest");
+        std::fs::create_dir(&dest_dir).unwrap();
+
+        let core = CheeseCore::with_config(config::Config::default()).unwrap();
+        let operations = core.operations();
+        let id = operations.submit(OperationRequest::Copy {
+            sources: vec![source],
+            dest_dir: dest_dir.clone(),
+            resolutions: ConflictResolutions::new(ConflictResolution::Overwrite),
+            options: CopyOptions::default(),
+        });
+        // Paused, the copy stalls on its progress channel after the first chunk.
+        operations.pause(id).unwrap();
+
+        let partial = dest_dir.join("large.bin");
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while !partial.exists() {
+            assert!(std::time::Instant::now() < deadline, "copy never started");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        core.shutdown(Duration::from_secs(5)).unwrap();
+
+        assert_eq!(operations.info(id).unwrap().status, OperationStatus::Cancelled);
+        assert!(!partial.exists());
+        assert!(operations.list_active().is_empty());
+    }
+
+    #[test]
+    fn test_worker_threads_from_config() {
+        let mut config = config::Config::default();
+        config.performance.worker_threads = Some(3);
+        let core = CheeseCore::with_config(config).unwrap();
+        assert_eq!(core.runtime().metrics().num_workers(), 3);
+
+        let mut config = config::Config::default();
+        config.performance.worker_threads = None;
+        let expected = config.performance.runtime_worker_threads();
+        let core = CheeseCore::with_config(config).unwrap();
+        assert_eq!(core.runtime().metrics().num_workers(), expected);
+    }
+
+    #[test]
+    fn test_cache_manager_from_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut performance = config::Config::default().performance;
+        performance.cache_size_mb = 40;
+        performance.thumbnail_cache_mb = 80;
+        performance.max_concurrent_ops = 7;
+
+        let cache_manager = build_cache_manager(&performance, Ok(temp_dir.path().join("thumbnails"))).unwrap();
+
+        assert_eq!(cache_manager.total_budget_mb(), 120);
+        assert_eq!(cache_manager.allocation(), (40, 80));
+        assert_eq!(cache_manager.thumbnails().max_concurrent(), 7);
+        assert!(temp_dir.path().join("thumbnails").is_dir());
+    }
+
+    #[test]
+    fn test_open_directory_cancel_and_missing_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let core = CheeseCore::with_config(config::Config::default()).unwrap();
+
+        let (mut rx, _cancel) = core.open_directory(temp_dir.path().join("missing"));
+        assert!(drain(&core, &mut rx).is_empty());
+
+        let batch = fs::scanner::BATCH_SIZE;
+        for i in 0..4 * SCAN_BUFFER * batch {
+            std::fs::write(temp_dir.path().join(format!("file{}.txt", i)), b"").unwrap();
+        }
+
+        // The channel holds SCAN_BUFFER batches, so the scan is blocked on
+        // sending, well short of the end, when it gets cancelled.
+        let (mut rx, cancel) = core.open_directory(temp_dir.path().to_path_buf());
+        let first = core.runtime().block_on(rx.recv()).unwrap();
+        assert!(!first.is_complete);
+        cancel.cancel();
+
+        let mut received = first.entries.len();
+        while let Some(result) = core.runtime().block_on(rx.recv()) {
+            assert!(!result.is_complete);
+            received += result.entries.len();
+        }
+        assert!(received <= (SCAN_BUFFER + 2) * batch, "{} entries sent after cancelling", received);
+        assert!(core.listing_cache().is_empty());
+    }
+}
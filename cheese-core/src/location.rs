@@ -0,0 +1,34 @@
+use crate::network::smb::SmbPath;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Location {
+    Local(PathBuf),
+    Smb(SmbPath),
+}
+
+impl Location {
+    pub fn parse(s: &str) -> Self {
+        match SmbPath::parse(s) {
+            Some(smb) => Self::Smb(smb),
+            None => Self::Local(PathBuf::from(s)),
+        }
+    }
+
+    pub fn display_name(&self) -> String {
+        match self {
+            Self::Local(path) => path.display().to_string(),
+            Self::Smb(smb) => smb.to_uri(),
+        }
+    }
+
+    pub fn is_remote(&self) -> bool {
+        !matches!(self, Self::Local(_))
+    }
+}
+
+impl From<PathBuf> for Location {
+    fn from(path: PathBuf) -> Self {
+        Self::Local(path)
+    }
+}
@@ -1,5 +1,8 @@
 use crate::{Error, Result};
-use zbus::{Connection, proxy};
+use crate::error::context;
+use crate::network::smb::{SmbBrowser, SmbCredentials, ShareInfo};
+use secrecy::SecretString;
+use zbus::{CacheProperties, Connection, proxy};
 use std::path::PathBuf;
 use std::collections::HashMap;
 
@@ -26,6 +29,9 @@ trait UDisks2Filesystem {
     
     async fn unmount(&self, options: HashMap<String, zbus::zvariant::Value<'_>>)
         -> zbus::Result<()>;
+
+    #[zbus(property)]
+    async fn mount_points(&self) -> zbus::Result<Vec<Vec<u8>>>;
 }
 
 #[proxy(
@@ -38,46 +44,172 @@ trait UDisks2Block {
     
     #[zbus(property)]
     async fn id_label(&self) -> zbus::Result<String>;
+
+    #[zbus(property, name = "IdUUID")]
+    fn id_uuid(&self) -> zbus::Result<String>;
     
     #[zbus(property)]
     async fn id_type(&self) -> zbus::Result<String>;
     
     #[zbus(property)]
     async fn size(&self) -> zbus::Result<u64>;
+
+    async fn format(&self, fs_type: &str, options: HashMap<String, zbus::zvariant::Value<'_>>)
+        -> zbus::Result<()>;
 }
 
+// Filesystems UDisks2 can create, with the longest label each accepts in bytes.
+const FORMAT_TYPES: &[(&str, usize)] = &[
+    ("vfat", 11),
+    ("exfat", 15),
+    ("ntfs", 32),
+    ("ext2", 16),
+    ("ext3", 16),
+    ("ext4", 16),
+    ("xfs", 12),
+    ("btrfs", 255),
+    ("f2fs", 512),
+    ("udf", 126),
+];
+
+const PROC_MOUNTS: &str = "/proc/mounts";
+const FSTAB: &str = "/etc/fstab";
+
 pub struct MountManager {
     connection: Connection,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NetworkProtocol {
+    Nfs,
+    Smb,
+    Ftp,
+    Sftp,
+}
+
+impl NetworkProtocol {
+    fn filesystem_types(&self) -> &'static [&'static str] {
+        match self {
+            Self::Nfs => &["nfs", "nfs4"],
+            Self::Smb => &["cifs", "smb3", "smbfs"],
+            Self::Ftp => &["fuse.curlftpfs", "curlftpfs"],
+            Self::Sftp => &["fuse.sshfs", "sshfs"],
+        }
+    }
+
+    // Splits the device column into host and remote path: `host:/export` for
+    // NFS and sshfs, `//host/share` for SMB and `ftp://host/dir` for curlftpfs.
+    fn parse_device(&self, device: &str) -> Option<(String, String)> {
+        let (host, path) = match self {
+            Self::Nfs | Self::Sftp => {
+                let (host, path) = match device.strip_prefix('[') {
+                    Some(bracketed) => bracketed.split_once("]:")?,
+                    None => device.split_once(':')?,
+                };
+                let host = host.rsplit('@').next().unwrap_or(host);
+                (host, path.to_string())
+            }
+            Self::Smb => {
+                let rest = device.strip_prefix("//")?;
+                let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+                (host, format!("/{}", path))
+            }
+            Self::Ftp => {
+                let rest = &device[device.find("ftp://")? + "ftp://".len()..];
+                let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+                let host = host.rsplit('@').next().unwrap_or(host);
+                (host, format!("/{}", path))
+            }
+        };
+
+        if host.is_empty() {
+            return None;
+        }
+        let path = if path.is_empty() { "/".to_string() } else { path };
+        Some((host.to_string(), path))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkShare {
+    pub host: String,
+    pub path: String,
+    pub protocol: NetworkProtocol,
+    pub is_mounted: bool,
+    /// Where the share is mounted, or would be mounted for an fstab entry.
+    pub local_mount: Option<PathBuf>,
+}
+
 #[derive(Debug, Clone)]
 pub struct MountPoint {
     pub device: String,
     pub mount_path: PathBuf,
     pub label: String,
     pub filesystem_type: String,
+    /// Filesystem UUID, which unlike `device` stays the same across reboots
+    /// and plug order.
+    pub uuid: Option<String>,
     pub size: u64,
     pub is_mounted: bool,
 }
 
+// The ways a block device can be named when mounting it.
+#[derive(Debug, Clone, Copy)]
+enum DeviceSelector<'a> {
+    Device(&'a str),
+    Uuid(&'a str),
+    Label(&'a str),
+}
+
+impl DeviceSelector<'_> {
+    fn matches(&self, ids: &BlockIds) -> bool {
+        match self {
+            Self::Device(device) => ids.device == *device,
+            // UUIDs are hex, written in either case depending on the tool.
+            Self::Uuid(uuid) => !uuid.is_empty() && ids.uuid.eq_ignore_ascii_case(uuid),
+            Self::Label(label) => !label.is_empty() && ids.label == *label,
+        }
+    }
+
+    // The fstab spelling, for errors.
+    fn describe(&self) -> String {
+        match self {
+            Self::Device(device) => device.to_string(),
+            Self::Uuid(uuid) => format!("UUID={}", uuid),
+            Self::Label(label) => format!("LABEL={}", label),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct BlockIds {
+    device: String,
+    uuid: String,
+    label: String,
+}
+
 impl MountManager {
     pub async fn new() -> Result<Self> {
         let connection = Connection::system()
             .await
-            .map_err(|e| Error::DBus(format!("Failed to connect to system bus: {}", e)))?;
+            .map_err(|e| Error::DBus(context("Failed to connect to system bus", e)))?;
 
         Ok(Self { connection })
     }
 
+    pub fn with_connection(connection: Connection) -> Self {
+        Self { connection }
+    }
+
     pub async fn list_devices(&self) -> Result<Vec<MountPoint>> {
         let manager = UDisks2ManagerProxy::new(&self.connection)
             .await
-            .map_err(|e| Error::DBus(format!("Failed to create manager proxy: {}", e)))?;
+            .map_err(|e| Error::DBus(context("Failed to create manager proxy", e)))?;
 
         let options = HashMap::new();
         let block_devices = manager.get_block_devices(options)
             .await
-            .map_err(|e| Error::DBus(format!("Failed to get block devices: {}", e)))?;
+            .map_err(|e| Error::DBus(context("Failed to get block devices", e)))?;
 
         let mut devices = Vec::new();
 
@@ -98,13 +230,13 @@ impl MountManager {
     async fn get_device_info(&self, path: &zbus::zvariant::OwnedObjectPath) -> Result<Option<MountPoint>> {
         let block_proxy = UDisks2BlockProxy::builder(&self.connection)
             .path(path.as_ref())
-            .map_err(|e| Error::DBus(format!("Invalid path: {}", e)))?
+            .map_err(|e| Error::DBus(context("Invalid path", e)))?
             .build()
             .await
-            .map_err(|e| Error::DBus(format!("Failed to create block proxy: {}", e)))?;
+            .map_err(|e| Error::DBus(context("Failed to create block proxy", e)))?;
 
         let device_bytes = block_proxy.device().await
-            .map_err(|e| Error::DBus(format!("Failed to get device: {}", e)))?;
+            .map_err(|e| Error::DBus(context("Failed to get device", e)))?;
         let device = String::from_utf8_lossy(&device_bytes)
             .trim_end_matches('\0')
             .to_string();
@@ -114,6 +246,7 @@ impl MountManager {
         }
 
         let label = block_proxy.id_label().await.unwrap_or_default();
+        let uuid = block_proxy.id_uuid().await.unwrap_or_default();
         let fs_type = block_proxy.id_type().await.unwrap_or_default();
         let size = block_proxy.size().await.unwrap_or(0);
 
@@ -133,25 +266,41 @@ impl MountManager {
                 label
             },
             filesystem_type: fs_type,
+            uuid: (!uuid.is_empty()).then_some(uuid),
             size,
             is_mounted,
         }))
     }
 
     pub async fn mount(&self, device: &str) -> Result<PathBuf> {
-        let device_path = self.find_device_path(device).await?;
+        self.mount_selected(DeviceSelector::Device(device)).await
+    }
+
+    /// Mounts the filesystem with UUID `uuid`, wherever its device node is.
+    pub async fn mount_by_uuid(&self, uuid: &str) -> Result<PathBuf> {
+        self.mount_selected(DeviceSelector::Uuid(uuid)).await
+    }
+
+    /// Mounts the filesystem labelled `label`. Fails when several devices
+    /// carry the label.
+    pub async fn mount_by_label(&self, label: &str) -> Result<PathBuf> {
+        self.mount_selected(DeviceSelector::Label(label)).await
+    }
+
+    async fn mount_selected(&self, selector: DeviceSelector<'_>) -> Result<PathBuf> {
+        let device_path = self.find_block(selector).await?;
 
         let fs_proxy = UDisks2FilesystemProxy::builder(&self.connection)
             .path(device_path.as_ref())
-            .map_err(|e| Error::MountError(format!("Invalid path: {}", e)))?
+            .map_err(|e| Error::MountError(context("Invalid path", e)))?
             .build()
             .await
-            .map_err(|e| Error::MountError(format!("Failed to create filesystem proxy: {}", e)))?;
+            .map_err(|e| Error::MountError(context("Failed to create filesystem proxy", e)))?;
 
         let options = HashMap::new();
         let mount_path = fs_proxy.mount(options)
             .await
-            .map_err(|e| Error::MountError(format!("Mount failed: {}", e)))?;
+            .map_err(|e| Error::MountError(context("Mount failed", e)))?;
 
         Ok(PathBuf::from(mount_path))
     }
@@ -161,54 +310,188 @@ impl MountManager {
 
         let fs_proxy = UDisks2FilesystemProxy::builder(&self.connection)
             .path(device_path.as_ref())
-            .map_err(|e| Error::MountError(format!("Invalid path: {}", e)))?
+            .map_err(|e| Error::MountError(context("Invalid path", e)))?
             .build()
             .await
-            .map_err(|e| Error::MountError(format!("Failed to create filesystem proxy: {}", e)))?;
+            .map_err(|e| Error::MountError(context("Failed to create filesystem proxy", e)))?;
 
         let options = HashMap::new();
         fs_proxy.unmount(options)
             .await
-            .map_err(|e| Error::MountError(format!("Unmount failed: {}", e)))?;
+            .map_err(|e| Error::MountError(context("Unmount failed", e)))?;
 
         Ok(())
     }
 
+    // UDisks2 runs Format synchronously unless `no-block` is set, so the call
+    // returns once the new filesystem has been written.
+    pub async fn format_device(&self, device: &str, fs_type: &str, label: &str, dry_run: bool) -> Result<()> {
+        validate_format(fs_type, label)?;
+        let device_path = self.find_device_path(device).await?;
+
+        if dry_run {
+            return Ok(());
+        }
+
+        // MountPoints is read again after unmounting, so it must not come from
+        // the proxy's property cache.
+        let fs_proxy = UDisks2FilesystemProxy::builder(&self.connection)
+            .path(device_path.as_ref())
+            .map_err(|e| Error::MountError(context("Invalid path", e)))?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await
+            .map_err(|e| Error::MountError(context("Failed to create filesystem proxy", e)))?;
+
+        // Devices without a filesystem have no Filesystem interface and cannot be mounted.
+        let mounted = !fs_proxy.mount_points().await.unwrap_or_default().is_empty();
+        if mounted {
+            fs_proxy.unmount(HashMap::new())
+                .await
+                .map_err(|e| Error::MountError(context(format!("formatting while mounted: {}", device), e)))?;
+
+            let mount_points = fs_proxy.mount_points().await.unwrap_or_default();
+            if let Some(point) = mount_points.first() {
+                return Err(Error::MountError(format!(
+                    "formatting while mounted: {} is still mounted at {}",
+                    device,
+                    String::from_utf8_lossy(point).trim_end_matches('\0')
+                ).into()));
+            }
+        }
+
+        let block_proxy = UDisks2BlockProxy::builder(&self.connection)
+            .path(device_path.as_ref())
+            .map_err(|e| Error::MountError(context("Invalid path", e)))?
+            .build()
+            .await
+            .map_err(|e| Error::MountError(context("Failed to create block proxy", e)))?;
+
+        let mut options = HashMap::new();
+        options.insert("label".to_string(), zbus::zvariant::Value::from(label));
+        options.insert("update-partition-type".to_string(), zbus::zvariant::Value::from(true));
+
+        block_proxy.format(fs_type, options)
+            .await
+            .map_err(|e| Error::MountError(context("Format failed", e)))?;
+
+        tracing::info!("Formatted {} as {}", device, fs_type);
+        Ok(())
+    }
+
+    /// Mounted and fstab-configured shares for `protocol`. SMB shares mounted
+    /// through GVfs are included when a session bus is available.
+    pub async fn network_shares(&self, protocol: NetworkProtocol) -> Result<Vec<NetworkShare>> {
+        let mounts = tokio::fs::read_to_string(PROC_MOUNTS)
+            .await
+            .map_err(|e| Error::MountError(context(format!("Failed to read {}", PROC_MOUNTS), e)))?;
+        let fstab = tokio::fs::read_to_string(FSTAB).await.unwrap_or_default();
+
+        let mut shares = network_shares_from(&mounts, &fstab, protocol);
+
+        if protocol == NetworkProtocol::Smb {
+            match gvfs_smb_shares().await {
+                Ok(gvfs) => {
+                    for share in gvfs {
+                        if !shares.iter().any(|s| s.host == share.host && s.path == share.path) {
+                            shares.push(share);
+                        }
+                    }
+                }
+                Err(e) => tracing::debug!("GVfs SMB mounts unavailable: {}", e),
+            }
+        }
+
+        Ok(shares)
+    }
+
+    /// Mounts `share` and returns its local path. SMB goes through GVfs, with
+    /// `username`, `password` and `domain` taken from `options`; other
+    /// protocols need an fstab entry the user may mount, and `options` are
+    /// passed to `mount -o`.
+    pub async fn mount_network(&self, share: &NetworkShare, options: HashMap<String, String>) -> Result<PathBuf> {
+        if share.is_mounted {
+            if let Some(local_mount) = &share.local_mount {
+                return Ok(local_mount.clone());
+            }
+        }
+
+        if share.protocol == NetworkProtocol::Smb {
+            return mount_smb(share, &options).await;
+        }
+
+        let local_mount = share.local_mount.as_ref().ok_or_else(|| Error::MountError(format!(
+            "{}:{} has no {} entry to mount from",
+            share.host, share.path, FSTAB
+        ).into()))?;
+
+        let mut command = tokio::process::Command::new("mount");
+        let mut options: Vec<String> = options
+            .into_iter()
+            .map(|(key, value)| if value.is_empty() { key } else { format!("{}={}", key, value) })
+            .collect();
+        if !options.is_empty() {
+            options.sort();
+            command.arg("-o").arg(options.join(","));
+        }
+
+        let output = command
+            .arg(local_mount)
+            .output()
+            .await
+            .map_err(|e| Error::MountError(context("Failed to run mount", e)))?;
+
+        if !output.status.success() {
+            return Err(Error::MountError(format!(
+                "Mounting {} failed: {}",
+                local_mount.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ).into()));
+        }
+
+        Ok(local_mount.clone())
+    }
+
     async fn find_device_path(&self, device: &str) -> Result<zbus::zvariant::OwnedObjectPath> {
+        self.find_block(DeviceSelector::Device(device)).await
+    }
+
+    async fn find_block(&self, selector: DeviceSelector<'_>) -> Result<zbus::zvariant::OwnedObjectPath> {
         let manager = UDisks2ManagerProxy::new(&self.connection)
             .await
-            .map_err(|e| Error::DBus(format!("Failed to create manager proxy: {}", e)))?;
+            .map_err(|e| Error::DBus(context("Failed to create manager proxy", e)))?;
 
         let options = HashMap::new();
         let block_devices = manager.get_block_devices(options)
             .await
-            .map_err(|e| Error::DBus(format!("Failed to get block devices: {}", e)))?;
+            .map_err(|e| Error::DBus(context("Failed to get block devices", e)))?;
 
+        let mut devices = Vec::new();
         for path in block_devices {
             let block_proxy = UDisks2BlockProxy::builder(&self.connection)
                 .path(path.as_ref())
-                .map_err(|e| Error::DBus(format!("Invalid path: {}", e)))?
+                .map_err(|e| Error::DBus(context("Invalid path", e)))?
                 .build()
                 .await
-                .map_err(|e| Error::DBus(format!("Failed to create block proxy: {}", e)))?;
-
-            if let Ok(device_bytes) = block_proxy.device().await {
-                let dev = String::from_utf8_lossy(&device_bytes)
-                    .trim_end_matches('\0')
-                    .to_string();
-                
-                if dev == device {
-                    return Ok(path);
-                }
-            }
+                .map_err(|e| Error::DBus(context("Failed to create block proxy", e)))?;
+
+            let Ok(device_bytes) = block_proxy.device().await else {
+                continue;
+            };
+            let ids = BlockIds {
+                device: String::from_utf8_lossy(&device_bytes).trim_end_matches('\0').to_string(),
+                uuid: block_proxy.id_uuid().await.unwrap_or_default(),
+                label: block_proxy.id_label().await.unwrap_or_default(),
+            };
+            devices.push((path, ids));
         }
 
-        Err(Error::NotFound { path: PathBuf::from(device) })
+        select_device(devices, selector)
     }
 
     fn get_mount_path(&self, device: &str) -> Result<PathBuf> {
         let mounts = std::fs::read_to_string("/proc/mounts")
-            .map_err(|e| Error::MountError(format!("Failed to read /proc/mounts: {}", e)))?;
+            .map_err(|e| Error::MountError(context("Failed to read /proc/mounts", e)))?;
 
         for line in mounts.lines() {
             let parts: Vec<&str> = line.split_whitespace().collect();
@@ -222,3 +505,437 @@ impl MountManager {
         ))
     }
 }
+
+// The one device `selector` names. Labels need not be unique, so a label
+// carried by several devices is refused rather than guessed at.
+fn select_device<T>(devices: Vec<(T, BlockIds)>, selector: DeviceSelector<'_>) -> Result<T> {
+    let mut matching = devices.into_iter().filter(|(_, ids)| selector.matches(ids));
+
+    match (matching.next(), matching.next()) {
+        (Some((device, _)), None) => Ok(device),
+        (None, _) => Err(Error::NotFound { path: PathBuf::from(selector.describe()) }),
+        (Some((_, first)), Some((_, second))) => Err(Error::MountError(format!(
+            "{} matches both {} and {}",
+            selector.describe(), first.device, second.device
+        ).into())),
+    }
+}
+
+async fn gvfs_smb_shares() -> Result<Vec<NetworkShare>> {
+    let browser = SmbBrowser::new().await?;
+    Ok(browser
+        .mounted_shares()
+        .await?
+        .into_iter()
+        .map(|(share, mount_path)| NetworkShare {
+            host: share.host,
+            path: format!("/{}", share.share),
+            protocol: NetworkProtocol::Smb,
+            is_mounted: true,
+            local_mount: Some(mount_path),
+        })
+        .collect())
+}
+
+async fn mount_smb(share: &NetworkShare, options: &HashMap<String, String>) -> Result<PathBuf> {
+    let share_name = share.path.trim_start_matches('/').split('/').next().unwrap_or_default();
+    if share_name.is_empty() {
+        return Err(Error::MountError(format!("No share name in //{}{}", share.host, share.path).into()));
+    }
+
+    let credentials = options.get("username").map(|username| SmbCredentials {
+        username: username.clone(),
+        password: SecretString::new(options.get("password").cloned().unwrap_or_default()),
+        domain: options.get("domain").cloned(),
+    });
+
+    let info = ShareInfo {
+        host: share.host.clone(),
+        share: share_name.to_string(),
+        comment: String::new(),
+        is_printer: false,
+    };
+
+    let browser = SmbBrowser::new().await?;
+    Ok(browser.mount_share(&info, credentials).await?.mount_path)
+}
+
+// `mounts` and `fstab` share a layout: device, mount point, type, options.
+// Live mounts win over fstab entries for the same share.
+fn network_shares_from(mounts: &str, fstab: &str, protocol: NetworkProtocol) -> Vec<NetworkShare> {
+    let mut shares: Vec<NetworkShare> = Vec::new();
+
+    for (table, is_mounted) in [(mounts, true), (fstab, false)] {
+        for (host, path, mount_point) in parse_mount_table(table, protocol) {
+            if shares.iter().any(|s| s.host == host && s.path == path) {
+                continue;
+            }
+            shares.push(NetworkShare {
+                host,
+                path,
+                protocol,
+                is_mounted,
+                local_mount: Some(mount_point),
+            });
+        }
+    }
+
+    shares
+}
+
+fn parse_mount_table(table: &str, protocol: NetworkProtocol) -> Vec<(String, String, PathBuf)> {
+    table
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let device = unescape_mount_field(fields.next()?);
+            let mount_point = unescape_mount_field(fields.next()?);
+            let fs_type = fields.next()?;
+
+            if !protocol.filesystem_types().contains(&fs_type) {
+                return None;
+            }
+            let (host, path) = protocol.parse_device(&device)?;
+            Some((host, path, PathBuf::from(mount_point)))
+        })
+        .collect()
+}
+
+// The kernel and fstab escape whitespace and backslashes as octal, e.g. `\040`.
+fn unescape_mount_field(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 4 <= bytes.len() && bytes[i + 1..i + 4].iter().all(|b| (b'0'..=b'7').contains(b)) {
+            let octal = std::str::from_utf8(&bytes[i + 1..i + 4]).unwrap_or("0");
+            if let Ok(byte) = u8::from_str_radix(octal, 8) {
+                out.push(byte);
+                i += 4;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn validate_format(fs_type: &str, label: &str) -> Result<()> {
+    let max_label = FORMAT_TYPES
+        .iter()
+        .find(|(name, _)| *name == fs_type)
+        .map(|(_, max)| *max)
+        .ok_or_else(|| Error::InvalidOperation(format!("Unsupported filesystem type: {}", fs_type)))?;
+
+    if label.len() > max_label {
+        return Err(Error::InvalidOperation(format!(
+            "Label {:?} is too long for {} (maximum {} bytes)",
+            label, fs_type, max_label
+        )));
+    }
+
+    if label.contains('\0') {
+        return Err(Error::InvalidOperation("Label must not contain NUL characters".to_string()));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+    use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+
+    const SDB1: &str = "/org/freedesktop/UDisks2/block_devices/sdb1";
+
+    #[derive(Default)]
+    struct DiskState {
+        mounted: bool,
+        unmount_fails: bool,
+        formatted: Option<(String, String)>,
+    }
+
+    struct MockManager;
+
+    #[zbus::interface(name = "org.freedesktop.UDisks2.Manager")]
+    impl MockManager {
+        async fn get_block_devices(&self, _options: HashMap<String, OwnedValue>) -> Vec<OwnedObjectPath> {
+            vec![OwnedObjectPath::try_from(SDB1).unwrap()]
+        }
+    }
+
+    struct MockBlock(Arc<Mutex<DiskState>>);
+
+    #[zbus::interface(name = "org.freedesktop.UDisks2.Block")]
+    impl MockBlock {
+        #[zbus(property)]
+        async fn device(&self) -> Vec<u8> {
+            b"/dev/sdb1\0".to_vec()
+        }
+
+        #[zbus(property)]
+        async fn id_label(&self) -> String {
+            "USB".to_string()
+        }
+
+        #[zbus(property, name = "IdUUID")]
+        async fn id_uuid(&self) -> String {
+            "2f4a-91c0".to_string()
+        }
+
+        async fn format(&self, fs_type: String, options: HashMap<String, OwnedValue>) -> zbus::fdo::Result<()> {
+            let label = options
+                .get("label")
+                .and_then(|v| v.downcast_ref::<&str>().ok())
+                .unwrap_or_default()
+                .to_string();
+            self.0.lock().formatted = Some((fs_type, label));
+            Ok(())
+        }
+    }
+
+    struct MockFilesystem(Arc<Mutex<DiskState>>);
+
+    #[zbus::interface(name = "org.freedesktop.UDisks2.Filesystem")]
+    impl MockFilesystem {
+        async fn unmount(&self, _options: HashMap<String, OwnedValue>) -> zbus::fdo::Result<()> {
+            let mut state = self.0.lock();
+            if state.unmount_fails {
+                return Err(zbus::fdo::Error::Failed("target is busy".to_string()));
+            }
+            state.mounted = false;
+            Ok(())
+        }
+
+        #[zbus(property)]
+        async fn mount_points(&self) -> Vec<Vec<u8>> {
+            if self.0.lock().mounted {
+                vec![b"/run/media/user/USB\0".to_vec()]
+            } else {
+                vec![]
+            }
+        }
+    }
+
+    // Serves the mock UDisks2 objects over a peer-to-peer connection so no
+    // system bus is needed.
+    async fn mock_manager(state: Arc<Mutex<DiskState>>) -> (MountManager, Connection) {
+        let (client, server) = std::os::unix::net::UnixStream::pair().unwrap();
+        let guid = zbus::Guid::generate();
+
+        let server = zbus::connection::Builder::unix_stream(server)
+            .server(guid)
+            .unwrap()
+            .p2p()
+            .serve_at("/org/freedesktop/UDisks2/Manager", MockManager)
+            .unwrap()
+            .serve_at(SDB1, MockBlock(state.clone()))
+            .unwrap()
+            .serve_at(SDB1, MockFilesystem(state))
+            .unwrap()
+            .build();
+        let client = zbus::connection::Builder::unix_stream(client).p2p().build();
+
+        let (server, client) = tokio::try_join!(server, client).unwrap();
+        (MountManager::with_connection(client), server)
+    }
+
+    const MOCK_PROC_MOUNTS: &str = "\
+/dev/sda2 / ext4 rw,relatime 0 0
+proc /proc proc rw,nosuid,nodev,noexec,relatime 0 0
+nas.local:/export/media /mnt/media nfs4 rw,relatime,vers=4.2 0 0
+192.168.1.10:/srv/backup /mnt/My\\040Backups nfs rw,vers=3 0 0
+[fd00::2]:/export/home /mnt/home6 nfs4 rw 0 0
+//fileserver/projects /mnt/projects cifs rw,vers=3.1.1 0 0
+alice@shell.example.com:/home/alice /home/alice/remote fuse.sshfs rw,user_id=1000 0 0
+";
+
+    const MOCK_FSTAB: &str = "\
+# <file system> <mount point> <type> <options> <dump> <pass>
+UUID=1234 / ext4 defaults 0 1
+nas.local:/export/media /mnt/media nfs4 defaults,user 0 0
+nas.local:/export/photos /mnt/photos nfs defaults,user,noauto 0 0
+";
+
+    #[test]
+    fn test_parse_nfs_mounts() {
+        let shares = network_shares_from(MOCK_PROC_MOUNTS, "", NetworkProtocol::Nfs);
+
+        let summary: Vec<_> = shares
+            .iter()
+            .map(|s| (s.host.as_str(), s.path.as_str(), s.local_mount.clone().unwrap()))
+            .collect();
+        assert_eq!(summary, vec![
+            ("nas.local", "/export/media", PathBuf::from("/mnt/media")),
+            ("192.168.1.10", "/srv/backup", PathBuf::from("/mnt/My Backups")),
+            ("fd00::2", "/export/home", PathBuf::from("/mnt/home6")),
+        ]);
+        assert!(shares.iter().all(|s| s.is_mounted && s.protocol == NetworkProtocol::Nfs));
+    }
+
+    #[test]
+    fn test_unescape_mount_field() {
+        assert_eq!(unescape_mount_field("/mnt/trailing\\040"), "/mnt/trailing ");
+        assert_eq!(unescape_mount_field("\\134srv\\011share"), "\\srv\tshare");
+        assert_eq!(unescape_mount_field("/mnt/short\\04"), "/mnt/short\\04");
+        assert_eq!(unescape_mount_field("/mnt/odd\\089"), "/mnt/odd\\089");
+    }
+
+    #[test]
+    fn test_fstab_entries_merge_with_mounts() {
+        let shares = network_shares_from(MOCK_PROC_MOUNTS, MOCK_FSTAB, NetworkProtocol::Nfs);
+        assert_eq!(shares.len(), 4);
+
+        let media = shares.iter().find(|s| s.path == "/export/media").unwrap();
+        assert!(media.is_mounted);
+
+        let photos = shares.iter().find(|s| s.path == "/export/photos").unwrap();
+        assert_eq!(photos, &NetworkShare {
+            host: "nas.local".to_string(),
+            path: "/export/photos".to_string(),
+            protocol: NetworkProtocol::Nfs,
+            is_mounted: false,
+            local_mount: Some(PathBuf::from("/mnt/photos")),
+        });
+    }
+
+    #[test]
+    fn test_parse_other_protocols() {
+        let smb = network_shares_from(MOCK_PROC_MOUNTS, "", NetworkProtocol::Smb);
+        assert_eq!(smb.len(), 1);
+        assert_eq!((smb[0].host.as_str(), smb[0].path.as_str()), ("fileserver", "/projects"));
+
+        let sftp = network_shares_from(MOCK_PROC_MOUNTS, "", NetworkProtocol::Sftp);
+        assert_eq!(sftp.len(), 1);
+        assert_eq!((sftp[0].host.as_str(), sftp[0].path.as_str()), ("shell.example.com", "/home/alice"));
+
+        assert!(network_shares_from(MOCK_PROC_MOUNTS, "", NetworkProtocol::Ftp).is_empty());
+        assert_eq!(
+            NetworkProtocol::Ftp.parse_device("curlftpfs#ftp://user@ftp.example.com/pub"),
+            Some(("ftp.example.com".to_string(), "/pub".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mount_network_requires_fstab_entry() {
+        let (manager, _server) = mock_manager(Arc::new(Mutex::new(DiskState::default()))).await;
+
+        let mounted = NetworkShare {
+            host: "nas.local".to_string(),
+            path: "/export/media".to_string(),
+            protocol: NetworkProtocol::Nfs,
+            is_mounted: true,
+            local_mount: Some(PathBuf::from("/mnt/media")),
+        };
+        assert_eq!(manager.mount_network(&mounted, HashMap::new()).await.unwrap(), PathBuf::from("/mnt/media"));
+
+        let unknown = NetworkShare { is_mounted: false, local_mount: None, ..mounted };
+        assert!(matches!(manager.mount_network(&unknown, HashMap::new()).await, Err(Error::MountError(_))));
+    }
+
+    #[test]
+    fn test_validate_format() {
+        assert!(validate_format("ext4", "backup").is_ok());
+        assert!(validate_format("vfat", "").is_ok());
+        assert!(matches!(validate_format("zfs", "pool"), Err(Error::InvalidOperation(_))));
+        assert!(matches!(validate_format("vfat", "TWELVE_CHARS"), Err(Error::InvalidOperation(_))));
+        assert!(validate_format("ntfs", "TWELVE_CHARS").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_format_unmounts_first() {
+        let state = Arc::new(Mutex::new(DiskState { mounted: true, ..DiskState::default() }));
+        let (manager, _server) = mock_manager(state.clone()).await;
+
+        manager.format_device("/dev/sdb1", "exfat", "USB", false).await.unwrap();
+
+        let state = state.lock();
+        assert!(!state.mounted);
+        assert_eq!(state.formatted, Some(("exfat".to_string(), "USB".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_format_refuses_busy_mount() {
+        let state = Arc::new(Mutex::new(DiskState { mounted: true, unmount_fails: true, ..DiskState::default() }));
+        let (manager, _server) = mock_manager(state.clone()).await;
+
+        let result = manager.format_device("/dev/sdb1", "ext4", "data", false).await;
+
+        assert!(matches!(result, Err(Error::MountError(ref msg)) if msg.to_string().starts_with("formatting while mounted")));
+        assert!(state.lock().formatted.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_format_dry_run() {
+        let state = Arc::new(Mutex::new(DiskState { mounted: true, ..DiskState::default() }));
+        let (manager, _server) = mock_manager(state.clone()).await;
+
+        manager.format_device("/dev/sdb1", "ext4", "data", true).await.unwrap();
+        assert!(matches!(
+            manager.format_device("/dev/sdc", "ext4", "data", true).await,
+            Err(Error::NotFound { .. })
+        ));
+        assert!(matches!(
+            manager.format_device("/dev/sdb1", "vfat", "much too long", true).await,
+            Err(Error::InvalidOperation(_))
+        ));
+
+        let state = state.lock();
+        assert!(state.mounted);
+        assert!(state.formatted.is_none());
+    }
+
+    fn block(device: &str, uuid: &str, label: &str) -> BlockIds {
+        BlockIds {
+            device: device.to_string(),
+            uuid: uuid.to_string(),
+            label: label.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_select_device() {
+        let devices = || vec![
+            ("sda1", block("/dev/sda1", "8c2e1f7a-5d3b-4e2a-9f61-0b7d3c4e5a21", "")),
+            ("sdb1", block("/dev/sdb1", "2F4A-91C0", "USB")),
+            ("sdc1", block("/dev/sdc1", "", "USB")),
+            ("sdd1", block("/dev/sdd1", "", "Photos")),
+        ];
+
+        assert_eq!(select_device(devices(), DeviceSelector::Uuid("2f4a-91c0")).unwrap(), "sdb1");
+        assert_eq!(select_device(devices(), DeviceSelector::Label("Photos")).unwrap(), "sdd1");
+        assert_eq!(select_device(devices(), DeviceSelector::Device("/dev/sda1")).unwrap(), "sda1");
+
+        assert!(matches!(
+            select_device(devices(), DeviceSelector::Label("USB")),
+            Err(Error::MountError(_))
+        ));
+        // Devices without a UUID must not match an empty one.
+        assert!(matches!(
+            select_device(devices(), DeviceSelector::Uuid("")),
+            Err(Error::NotFound { path }) if path == std::path::Path::new("UUID=")
+        ));
+        assert!(matches!(
+            select_device(devices(), DeviceSelector::Label("Backup")),
+            Err(Error::NotFound { path }) if path == std::path::Path::new("LABEL=Backup")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_find_block_by_uuid_and_label() {
+        let (manager, _server) = mock_manager(Arc::default()).await;
+
+        let by_uuid = manager.find_block(DeviceSelector::Uuid("2F4A-91C0")).await.unwrap();
+        let by_label = manager.find_block(DeviceSelector::Label("USB")).await.unwrap();
+        assert_eq!(by_uuid.as_str(), SDB1);
+        assert_eq!(by_label.as_str(), SDB1);
+        assert!(manager.find_block(DeviceSelector::Uuid("0000-0000")).await.is_err());
+    }
+}
@@ -0,0 +1,537 @@
+use crate::mounts::MountPoint;
+use crate::{Error, Result};
+use crate::error::context;
+use secrecy::{ExposeSecret, SecretString};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value};
+use zbus::{proxy, Connection};
+use zeroize::Zeroize;
+
+const SMB_SCHEME: &str = "smb://";
+const MOUNT_OPERATION_PATH: &str = "/org/cheese/MountOperation";
+const MAX_PASSWORD_ATTEMPTS: u32 = 1;
+
+static NEXT_MOUNT_OPERATION: AtomicU64 = AtomicU64::new(0);
+
+type MountSpec<'a> = (Vec<u8>, HashMap<&'a str, Value<'a>>);
+
+type GvfsMount = (
+    String,
+    OwnedObjectPath,
+    String,
+    String,
+    String,
+    String,
+    String,
+    bool,
+    Vec<u8>,
+    (Vec<u8>, HashMap<String, OwnedValue>),
+    Vec<u8>,
+);
+
+#[proxy(
+    interface = "org.gtk.vfs.MountTracker",
+    default_service = "org.gtk.vfs.Daemon",
+    default_path = "/org/gtk/vfs/mounttracker"
+)]
+trait MountTracker {
+    async fn lookup_mount(&self, mount_spec: &MountSpec<'_>) -> zbus::Result<GvfsMount>;
+
+    async fn list_mounts(&self) -> zbus::Result<Vec<GvfsMount>>;
+
+    async fn mount_location(
+        &self,
+        mount_spec: &MountSpec<'_>,
+        mount_source: &(&str, ObjectPath<'_>),
+    ) -> zbus::Result<()>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmbPath {
+    pub host: String,
+    pub share: Option<String>,
+    pub path: PathBuf,
+}
+
+impl SmbPath {
+    pub fn parse(uri: &str) -> Option<Self> {
+        let rest = uri.strip_prefix(SMB_SCHEME)?;
+        let mut parts = rest.splitn(3, '/');
+
+        let host = parts.next().filter(|h| !h.is_empty())?.to_string();
+        let share = parts.next().filter(|s| !s.is_empty()).map(String::from);
+        let path = PathBuf::from(format!("/{}", parts.next().unwrap_or("")));
+
+        Some(Self { host, share, path })
+    }
+
+    pub fn to_uri(&self) -> String {
+        match &self.share {
+            Some(share) => {
+                let path = self.path.to_string_lossy();
+                format!("{}{}/{}{}", SMB_SCHEME, self.host, share, path.trim_end_matches('/'))
+            }
+            None => format!("{}{}", SMB_SCHEME, self.host),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShareInfo {
+    pub host: String,
+    pub share: String,
+    pub comment: String,
+    pub is_printer: bool,
+}
+
+impl ShareInfo {
+    pub fn path(&self) -> SmbPath {
+        SmbPath {
+            host: self.host.clone(),
+            share: Some(self.share.clone()),
+            path: PathBuf::from("/"),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SmbCredentials {
+    pub username: String,
+    pub password: SecretString,
+    pub domain: Option<String>,
+}
+
+impl Zeroize for SmbCredentials {
+    fn zeroize(&mut self) {
+        self.username.zeroize();
+        self.password = SecretString::new(String::new());
+        if let Some(domain) = self.domain.as_mut() {
+            domain.zeroize();
+        }
+        self.domain = None;
+    }
+}
+
+impl Drop for SmbCredentials {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl std::fmt::Debug for SmbCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SmbCredentials")
+            .field("username", &self.username)
+            .field("password", &"[redacted]")
+            .field("domain", &self.domain)
+            .finish()
+    }
+}
+
+struct MountOperation {
+    credentials: Option<SmbCredentials>,
+    attempts: u32,
+}
+
+#[zbus::interface(name = "org.gtk.vfs.MountOperation")]
+impl MountOperation {
+    async fn ask_password(
+        &mut self,
+        _message: String,
+        default_user: String,
+        default_domain: String,
+        _flags: u32,
+    ) -> (bool, bool, String, String, String, bool, u32) {
+        self.attempts += 1;
+
+        match &self.credentials {
+            Some(credentials) if self.attempts <= MAX_PASSWORD_ATTEMPTS => (
+                true,
+                false,
+                credentials.password.expose_secret().clone(),
+                credentials.username.clone(),
+                credentials.domain.clone().unwrap_or(default_domain),
+                false,
+                0,
+            ),
+            Some(_) => (true, true, String::new(), String::new(), String::new(), false, 0),
+            None => (true, false, String::new(), default_user, default_domain, true, 0),
+        }
+    }
+
+    async fn ask_question(&self, _message: String, _choices: Vec<String>) -> (bool, bool, u32) {
+        (true, true, 0)
+    }
+
+    async fn aborted(&self) {}
+}
+
+pub struct SmbBrowser {
+    connection: Connection,
+}
+
+impl SmbBrowser {
+    pub async fn new() -> Result<Self> {
+        let connection = Connection::session()
+            .await
+            .map_err(|e| Error::DBus(context("Failed to connect to session bus", e)))?;
+
+        Ok(Self { connection })
+    }
+
+    pub async fn list_workgroups(&self) -> Result<Vec<String>> {
+        let spec = mount_spec(&[("type", "smb-network")]);
+        let root = self.mount_and_lookup(&spec, None).await?;
+
+        let entries = read_fuse_dir(&root).await?;
+        Ok(entries.into_iter().map(|(name, _)| name).collect())
+    }
+
+    /// Lists the file and printer shares of `host` with their comments, as
+    /// reported by `smbclient`. GVfs reports neither share types nor
+    /// comments, so without `smbclient` only the file shares it mounts are
+    /// listed, with empty comments.
+    pub async fn list_shares(&self, host: &str) -> Result<Vec<ShareInfo>> {
+        let host = host.to_lowercase();
+
+        let output = tokio::process::Command::new("smbclient")
+            .args(["--grepable", "--no-pass", "--list"])
+            .arg(format!("//{}", host))
+            .output()
+            .await;
+
+        let output = match output {
+            Ok(output) => output,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return self.list_gvfs_shares(&host).await,
+            Err(e) => return Err(Error::MountError(context("Failed to run smbclient", e))),
+        };
+
+        if !output.status.success() {
+            return Err(Error::MountError(format!(
+                "Listing shares on {} failed: {}",
+                host,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ).into()));
+        }
+
+        Ok(parse_share_list(&host, &String::from_utf8_lossy(&output.stdout)))
+    }
+
+    async fn list_gvfs_shares(&self, host: &str) -> Result<Vec<ShareInfo>> {
+        let spec = mount_spec(&[("type", "smb-server"), ("server", host)]);
+        let root = self.mount_and_lookup(&spec, None).await?;
+
+        let entries = read_fuse_dir(&root).await?;
+        Ok(entries
+            .into_iter()
+            .filter(|(_, is_dir)| *is_dir)
+            .map(|(share, _)| ShareInfo {
+                host: host.to_string(),
+                share,
+                comment: String::new(),
+                is_printer: false,
+            })
+            .collect())
+    }
+
+    pub async fn mount_share(
+        &self,
+        share: &ShareInfo,
+        credentials: Option<SmbCredentials>,
+    ) -> Result<MountPoint> {
+        if share.is_printer {
+            return Err(Error::MountError(format!(
+                "{} is a printer share",
+                share.path().to_uri()
+            ).into()));
+        }
+
+        let host = share.host.to_lowercase();
+        let share_name = share.share.to_lowercase();
+        let mut fields = vec![
+            ("type", "smb-share"),
+            ("server", host.as_str()),
+            ("share", share_name.as_str()),
+        ];
+        if let Some(credentials) = &credentials {
+            fields.push(("user", credentials.username.as_str()));
+            if let Some(domain) = &credentials.domain {
+                fields.push(("domain", domain.as_str()));
+            }
+        }
+
+        let spec = mount_spec(&fields);
+        let mount_path = self.mount_and_lookup(&spec, credentials.clone()).await?;
+
+        Ok(MountPoint {
+            device: share.path().to_uri(),
+            mount_path,
+            label: format!("{} on {}", share.share, share.host),
+            filesystem_type: "smb".to_string(),
+            uuid: None,
+            size: 0,
+            is_mounted: true,
+        })
+    }
+
+    /// Shares currently mounted through GVfs, with their FUSE paths.
+    pub async fn mounted_shares(&self) -> Result<Vec<(ShareInfo, PathBuf)>> {
+        let tracker = MountTrackerProxy::new(&self.connection)
+            .await
+            .map_err(|e| Error::DBus(context("Failed to create GVfs mount tracker proxy", e)))?;
+
+        let mounts = tracker.list_mounts()
+            .await
+            .map_err(|e| Error::DBus(context("Failed to list GVfs mounts", e)))?;
+
+        Ok(mounts
+            .iter()
+            .filter_map(|mount| {
+                let spec = &mount.9.1;
+                if spec_field(spec, "type").as_deref() != Some("smb-share") {
+                    return None;
+                }
+
+                let share = ShareInfo {
+                    host: spec_field(spec, "server")?,
+                    share: spec_field(spec, "share")?,
+                    comment: String::new(),
+                    is_printer: false,
+                };
+                Some((share, fuse_path(mount).ok()?))
+            })
+            .collect())
+    }
+
+    async fn mount_and_lookup(
+        &self,
+        spec: &MountSpec<'_>,
+        credentials: Option<SmbCredentials>,
+    ) -> Result<PathBuf> {
+        let tracker = MountTrackerProxy::new(&self.connection)
+            .await
+            .map_err(|e| Error::DBus(context("Failed to create GVfs mount tracker proxy", e)))?;
+
+        if let Ok(mount) = tracker.lookup_mount(spec).await {
+            return fuse_path(&mount);
+        }
+
+        let op_path = format!(
+            "{}/{}",
+            MOUNT_OPERATION_PATH,
+            NEXT_MOUNT_OPERATION.fetch_add(1, Ordering::Relaxed)
+        );
+        let object_path = ObjectPath::try_from(op_path.as_str())
+            .map_err(|e| Error::DBus(context("Invalid object path", e)))?;
+
+        self.connection
+            .object_server()
+            .at(object_path.clone(), MountOperation { credentials, attempts: 0 })
+            .await
+            .map_err(|e| Error::DBus(context("Failed to export mount operation", e)))?;
+
+        let dbus_id = self.connection.unique_name().map(|n| n.to_string()).unwrap_or_default();
+        let mounted = tracker.mount_location(spec, &(dbus_id.as_str(), object_path.clone())).await;
+
+        let _ = self.connection
+            .object_server()
+            .remove::<MountOperation, _>(object_path)
+            .await;
+
+        mounted.map_err(|e| Error::MountError(context("GVfs mount failed", e)))?;
+
+        let mount = tracker.lookup_mount(spec)
+            .await
+            .map_err(|e| Error::MountError(context("Mounted location not found", e)))?;
+
+        fuse_path(&mount)
+    }
+}
+
+fn mount_spec<'a>(fields: &[(&'a str, &str)]) -> MountSpec<'a> {
+    let spec = fields
+        .iter()
+        .map(|(key, value)| (*key, Value::from(bytestring(value))))
+        .collect();
+
+    (bytestring("/"), spec)
+}
+
+fn bytestring(s: &str) -> Vec<u8> {
+    let mut bytes = s.as_bytes().to_vec();
+    bytes.push(0);
+    bytes
+}
+
+fn spec_field(spec: &HashMap<String, OwnedValue>, key: &str) -> Option<String> {
+    let bytes = Vec::<u8>::try_from(spec.get(key)?.try_clone().ok()?).ok()?;
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    Some(String::from_utf8_lossy(&bytes[..end]).into_owned())
+}
+
+fn fuse_path(mount: &GvfsMount) -> Result<PathBuf> {
+    let raw = &mount.8;
+    let end = raw.iter().position(|&b| b == 0).unwrap_or(raw.len());
+
+    if end == 0 {
+        return Err(Error::MountError("GVfs FUSE daemon is not running".into()));
+    }
+
+    use std::os::unix::ffi::OsStrExt;
+    Ok(PathBuf::from(std::ffi::OsStr::from_bytes(&raw[..end])))
+}
+
+// Parses `smbclient --grepable --list` output, `type|name|comment` per line.
+// IPC shares and the server and workgroup lines are skipped.
+fn parse_share_list(host: &str, output: &str) -> Vec<ShareInfo> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '|');
+            let is_printer = match fields.next()? {
+                "Disk" => false,
+                "Printer" => true,
+                _ => return None,
+            };
+
+            Some(ShareInfo {
+                host: host.to_string(),
+                share: fields.next().filter(|name| !name.is_empty())?.to_string(),
+                comment: fields.next().unwrap_or("").to_string(),
+                is_printer,
+            })
+        })
+        .collect()
+}
+
+async fn read_fuse_dir(path: &Path) -> Result<Vec<(String, bool)>> {
+    let mut entries = Vec::new();
+    let mut read_dir = tokio::fs::read_dir(path).await?;
+
+    while let Some(entry) = read_dir.next_entry().await? {
+        let is_dir = entry.file_type().await.map(|t| t.is_dir()).unwrap_or(false);
+        entries.push((entry.file_name().to_string_lossy().into_owned(), is_dir));
+    }
+
+    entries.sort();
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_smb_path_parse() {
+        let path = SmbPath::parse("smb://fileserver/projects/docs/report.odt").unwrap();
+        assert_eq!(path.host, "fileserver");
+        assert_eq!(path.share.as_deref(), Some("projects"));
+        assert_eq!(path.path, PathBuf::from("/docs/report.odt"));
+        assert_eq!(path.to_uri(), "smb://fileserver/projects/docs/report.odt");
+
+        let host_only = SmbPath::parse("smb://fileserver").unwrap();
+        assert_eq!(host_only.share, None);
+        assert_eq!(host_only.to_uri(), "smb://fileserver");
+
+        assert!(SmbPath::parse("smb://").is_none());
+        assert!(SmbPath::parse("/home/user").is_none());
+    }
+
+    #[test]
+    fn test_mount_spec_bytestrings() {
+        let (prefix, fields) = mount_spec(&[("type", "smb-share"), ("server", "host")]);
+        assert_eq!(prefix, b"/\0");
+        assert_eq!(fields.get("server"), Some(&Value::from(b"host\0".to_vec())));
+    }
+
+    #[test]
+    fn test_spec_field_strips_nul() {
+        let mut spec = HashMap::new();
+        spec.insert("server".to_string(), OwnedValue::try_from(Value::from(bytestring("fileserver"))).unwrap());
+
+        assert_eq!(spec_field(&spec, "server").as_deref(), Some("fileserver"));
+        assert_eq!(spec_field(&spec, "share"), None);
+    }
+
+    #[test]
+    fn test_parse_share_list() {
+        let output = "Disk|projects|Team projects\n\
+                      Disk|homes|\n\
+                      Printer|laser|Second floor | colour\n\
+                      IPC|IPC$|IPC Service (Samba)\n\
+                      Server|FILESERVER|Samba\n\
+                      Workgroup|WORKGROUP|FILESERVER\n";
+
+        assert_eq!(parse_share_list("fileserver", output), vec![
+            ShareInfo {
+                host: "fileserver".to_string(),
+                share: "projects".to_string(),
+                comment: "Team projects".to_string(),
+                is_printer: false,
+            },
+            ShareInfo {
+                host: "fileserver".to_string(),
+                share: "homes".to_string(),
+                comment: String::new(),
+                is_printer: false,
+            },
+            ShareInfo {
+                host: "fileserver".to_string(),
+                share: "laser".to_string(),
+                comment: "Second floor | colour".to_string(),
+                is_printer: true,
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_credentials_zeroize() {
+        let mut credentials = SmbCredentials {
+            username: "alice".to_string(),
+            password: SecretString::new("hunter2".to_string()),
+            domain: Some("WORKGROUP".to_string()),
+        };
+
+        assert!(!format!("{:?}", credentials).contains("hunter2"));
+
+        credentials.zeroize();
+        assert!(credentials.username.is_empty());
+        assert!(credentials.password.expose_secret().is_empty());
+        assert!(credentials.domain.is_none());
+    }
+
+    #[cfg(feature = "smb-tests")]
+    mod integration {
+        use super::*;
+
+        fn test_host() -> String {
+            std::env::var("CHEESE_SMB_TEST_HOST").unwrap_or_else(|_| "localhost".to_string())
+        }
+
+        #[tokio::test]
+        async fn test_list_workgroups() {
+            let browser = SmbBrowser::new().await.unwrap();
+            let workgroups = browser.list_workgroups().await.unwrap();
+            assert!(!workgroups.is_empty());
+        }
+
+        #[tokio::test]
+        async fn test_list_and_mount_share() {
+            let browser = SmbBrowser::new().await.unwrap();
+            let shares = browser.list_shares(&test_host()).await.unwrap();
+            let share = shares.iter().find(|s| !s.is_printer).expect("No file shares on test host");
+
+            let credentials = std::env::var("CHEESE_SMB_TEST_USER").ok().map(|username| SmbCredentials {
+                username,
+                password: SecretString::new(std::env::var("CHEESE_SMB_TEST_PASSWORD").unwrap_or_default()),
+                domain: std::env::var("CHEESE_SMB_TEST_DOMAIN").ok(),
+            });
+
+            let mount = browser.mount_share(share, credentials).await.unwrap();
+            assert!(mount.is_mounted);
+            assert!(mount.mount_path.is_dir());
+        }
+    }
+}
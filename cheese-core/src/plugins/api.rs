@@ -1,7 +1,11 @@
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
 use serde::{Deserialize, Serialize};
 
-pub const API_VERSION: u32 = 1;
+// Kept in step with `PLUGIN_API_VERSION`.
+pub const API_VERSION: u32 = 2;
 
 #[repr(C)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,6 +26,7 @@ pub enum Capability {
     FileOverlay,
     CustomColumn,
     SearchProvider,
+    StatusBar,
 }
 
 #[repr(C)]
@@ -68,6 +73,16 @@ pub struct MenuItem {
     pub separator_after: bool,
 }
 
+/// A shortcut a plugin contributes; `action` is passed back to the plugin
+/// like a menu item action.
+#[repr(C)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyBinding {
+    pub label: String,
+    pub accelerator: String,
+    pub action: String,
+}
+
 #[repr(C)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContextMenuRequest {
@@ -139,6 +154,52 @@ pub struct SearchResponse {
     pub results: Vec<SearchResult>,
 }
 
+#[repr(C)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsSchema {
+    pub fields: Vec<SchemaField>,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaField {
+    pub id: String,
+    pub label: String,
+    pub kind: FieldKind,
+    pub default: FieldValue,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FieldKind {
+    Bool,
+    Int { min: i64, max: i64 },
+    String,
+    Choice(Vec<String>),
+}
+
+impl FieldKind {
+    pub fn accepts(&self, value: &FieldValue) -> bool {
+        match (self, value) {
+            (FieldKind::Bool, FieldValue::Bool(_)) => true,
+            (FieldKind::Int { min, max }, FieldValue::Int(v)) => v >= min && v <= max,
+            (FieldKind::String, FieldValue::String(_)) => true,
+            (FieldKind::Choice(choices), FieldValue::String(v)) => choices.contains(v),
+            _ => false,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FieldValue {
+    Bool(bool),
+    Int(i64),
+    String(String),
+}
+
+pub type PreviewFuture = Pin<Box<dyn Future<Output = Result<PreviewResponse, String>> + Send>>;
+
 pub trait PluginInterface: Send + Sync {
     fn info(&self) -> PluginInfo;
     
@@ -146,10 +207,23 @@ pub trait PluginInterface: Send + Sync {
     
     fn shutdown(&mut self) -> Result<(), String>;
     
+    /// Whether previews of files of type `mime` should be routed here.
+    fn can_handle_mime(&self, mime: &str) -> bool {
+        let _ = mime;
+        false
+    }
+
     fn preview(&self, request: PreviewRequest) -> Result<PreviewResponse, String> {
         let _ = request;
         Err("Not implemented".to_string())
     }
+
+    // PluginManager calls this from `spawn_blocking`, so the default can run
+    // the blocking `preview` directly. Async plugins override this instead.
+    fn preview_async(&self, request: PreviewRequest) -> PreviewFuture {
+        let result = self.preview(request);
+        Box::pin(async move { result })
+    }
     
     fn context_menu(&self, request: ContextMenuRequest) -> Result<ContextMenuResponse, String> {
         let _ = request;
@@ -174,6 +248,25 @@ pub trait PluginInterface: Send + Sync {
         let _ = request;
         Err("Not implemented".to_string())
     }
+
+    /// Short text shown in the status bar while `dir` is open.
+    fn status_bar_text(&self, dir: &Path) -> Result<String, String> {
+        let _ = dir;
+        Err("Not implemented".to_string())
+    }
+
+    fn settings_schema(&self) -> Option<SettingsSchema> {
+        None
+    }
+
+    fn key_bindings(&self) -> Vec<KeyBinding> {
+        Vec::new()
+    }
+
+    fn apply_settings(&mut self, settings: HashMap<String, FieldValue>) -> Result<(), String> {
+        let _ = settings;
+        Err("Not implemented".to_string())
+    }
 }
 
 #[macro_export]
@@ -229,5 +322,20 @@ mod tests {
         let info = plugin.info();
         assert_eq!(info.name, "Test Plugin");
         assert_eq!(info.api_version, API_VERSION);
+        assert!(plugin.settings_schema().is_none());
+    }
+
+    #[test]
+    fn test_field_kind_accepts() {
+        assert!(FieldKind::Bool.accepts(&FieldValue::Bool(true)));
+        assert!(!FieldKind::Bool.accepts(&FieldValue::Int(1)));
+
+        let range = FieldKind::Int { min: 1, max: 10 };
+        assert!(range.accepts(&FieldValue::Int(5)));
+        assert!(!range.accepts(&FieldValue::Int(11)));
+
+        let choice = FieldKind::Choice(vec!["a".to_string(), "b".to_string()]);
+        assert!(choice.accepts(&FieldValue::String("b".to_string())));
+        assert!(!choice.accepts(&FieldValue::String("c".to_string())));
     }
 }
@@ -0,0 +1,398 @@
+use super::api::{
+    ColumnDefinition, ColumnValueRequest, ColumnValueResponse, ContextMenuRequest, ContextMenuResponse, FieldValue,
+    OverlayRequest, OverlayResponse, PluginInfo, PluginInterface, PreviewFuture, PreviewRequest, PreviewResponse,
+    SearchRequest, SearchResponse, SettingsSchema,
+};
+use super::{check_api_version, Plugin};
+use crate::{Error, Result};
+use crate::error::context;
+use libloading::{Library, Symbol};
+use std::collections::HashMap;
+use std::path::Path;
+use std::ptr::NonNull;
+
+// Matches the symbols emitted by `export_plugin!`; both sides are Rust, so the
+// trait object pointer layout agrees even though C has no equivalent.
+#[allow(improper_ctypes_definitions)]
+type CreateFn = unsafe extern "C" fn() -> *mut dyn PluginInterface;
+#[allow(improper_ctypes_definitions)]
+type DestroyFn = unsafe extern "C" fn(*mut dyn PluginInterface);
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PluginLoader;
+
+// A plugin created by a shared library, kept together with that library. The
+// plugin is handed back to `_plugin_destroy` before the library is unloaded, so
+// its code stays mapped for as long as the plugin can be called.
+pub struct LoadedPlugin {
+    plugin: NonNull<dyn PluginInterface>,
+    destroy: DestroyFn,
+    _library: Library,
+}
+
+// PluginInterface requires Send + Sync, and the pointer is uniquely owned.
+unsafe impl Send for LoadedPlugin {}
+unsafe impl Sync for LoadedPlugin {}
+
+impl PluginLoader {
+    // Creates the plugin and checks its API version without initializing it,
+    // for callers such as PluginManager that drive the lifecycle themselves.
+    pub fn open(&self, path: &Path) -> Result<LoadedPlugin> {
+        let library = unsafe { Library::new(path) }
+            .map_err(|e| Error::Plugin(context(format!("Failed to load {}", path.display()), e)))?;
+
+        let (create, destroy) = unsafe {
+            let create: Symbol<CreateFn> = library.get(b"_plugin_create\0").map_err(|e| missing_symbol(path, e))?;
+            let destroy: Symbol<DestroyFn> = library.get(b"_plugin_destroy\0").map_err(|e| missing_symbol(path, e))?;
+            (*create, *destroy)
+        };
+
+        let plugin = NonNull::new(unsafe { create() })
+            .ok_or_else(|| Error::Plugin(format!("{} did not create a plugin", path.display()).into()))?;
+        let loaded = LoadedPlugin { plugin, destroy, _library: library };
+
+        check_api_version(&loaded.metadata())?;
+        Ok(loaded)
+    }
+
+    pub fn load(&self, path: &Path) -> Result<LoadedPlugin> {
+        let mut loaded = self.open(path)?;
+        Plugin::initialize(&mut loaded)?;
+        Ok(loaded)
+    }
+}
+
+impl Drop for LoadedPlugin {
+    fn drop(&mut self) {
+        unsafe { (self.destroy)(self.plugin.as_ptr()) };
+    }
+}
+
+impl LoadedPlugin {
+    fn inner(&self) -> &dyn PluginInterface {
+        unsafe { self.plugin.as_ref() }
+    }
+
+    fn inner_mut(&mut self) -> &mut dyn PluginInterface {
+        unsafe { self.plugin.as_mut() }
+    }
+}
+
+impl PluginInterface for LoadedPlugin {
+    fn info(&self) -> PluginInfo {
+        self.inner().info()
+    }
+
+    fn initialize(&mut self) -> std::result::Result<(), String> {
+        self.inner_mut().initialize()
+    }
+
+    fn shutdown(&mut self) -> std::result::Result<(), String> {
+        self.inner_mut().shutdown()
+    }
+
+    fn can_handle_mime(&self, mime: &str) -> bool {
+        self.inner().can_handle_mime(mime)
+    }
+
+    fn preview(&self, request: PreviewRequest) -> std::result::Result<PreviewResponse, String> {
+        self.inner().preview(request)
+    }
+
+    fn preview_async(&self, request: PreviewRequest) -> PreviewFuture {
+        self.inner().preview_async(request)
+    }
+
+    fn context_menu(&self, request: ContextMenuRequest) -> std::result::Result<ContextMenuResponse, String> {
+        self.inner().context_menu(request)
+    }
+
+    fn overlay(&self, request: OverlayRequest) -> std::result::Result<OverlayResponse, String> {
+        self.inner().overlay(request)
+    }
+
+    fn custom_columns(&self) -> std::result::Result<Vec<ColumnDefinition>, String> {
+        self.inner().custom_columns()
+    }
+
+    fn column_value(&self, request: ColumnValueRequest) -> std::result::Result<ColumnValueResponse, String> {
+        self.inner().column_value(request)
+    }
+
+    fn search(&self, request: SearchRequest) -> std::result::Result<SearchResponse, String> {
+        self.inner().search(request)
+    }
+
+    fn settings_schema(&self) -> Option<SettingsSchema> {
+        self.inner().settings_schema()
+    }
+
+    fn apply_settings(&mut self, settings: HashMap<String, FieldValue>) -> std::result::Result<(), String> {
+        self.inner_mut().apply_settings(settings)
+    }
+}
+
+fn missing_symbol(path: &Path, e: libloading::Error) -> Error {
+    Error::Plugin(context(format!("{} is not a Cheese plugin", path.display()), e))
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::plugins::{PluginManager, PLUGIN_API_VERSION};
+    use std::ffi::{c_char, CStr};
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    // Hands out whatever plugin the test registers through `stub_set_plugin`.
+    // A `*mut dyn` travels as a (data, vtable) pair under the C ABI.
+    const STUB_SOURCE: &str = r#"
+        typedef struct { void *data; const void *vtable; } plugin_ptr;
+
+        static plugin_ptr plugin;
+        static int destroyed;
+
+        void stub_set_plugin(void *data, const void *vtable) {
+            plugin.data = data;
+            plugin.vtable = vtable;
+        }
+
+        int stub_destroyed(void) { return destroyed; }
+
+        plugin_ptr _plugin_create(void) { return plugin; }
+
+        void _plugin_destroy(void *data, const void *vtable) {
+            (void)data;
+            (void)vtable;
+            destroyed++;
+        }
+    "#;
+
+    // Lets every copy of the versioned stub hand out plugins from the test,
+    // since each dlopen of a copy gets its own statics.
+    const REGISTRY_SOURCE: &str = r#"
+        typedef struct { void *data; const void *vtable; } plugin_ptr;
+        typedef plugin_ptr (*create_fn)(const char *version);
+        typedef void (*destroy_fn)(void *data, const void *vtable);
+
+        static create_fn create;
+        static destroy_fn destroy;
+
+        void registry_set(create_fn c, destroy_fn d) {
+            create = c;
+            destroy = d;
+        }
+
+        plugin_ptr registry_create(const char *version) { return create(version); }
+
+        void registry_destroy(void *data, const void *vtable) { destroy(data, vtable); }
+    "#;
+
+    // Prefixed with a `VERSION` definition before compiling.
+    const VERSIONED_STUB_SOURCE: &str = r#"
+        typedef struct { void *data; const void *vtable; } plugin_ptr;
+
+        plugin_ptr registry_create(const char *version);
+        void registry_destroy(void *data, const void *vtable);
+
+        plugin_ptr _plugin_create(void) { return registry_create(VERSION); }
+
+        void _plugin_destroy(void *data, const void *vtable) { registry_destroy(data, vtable); }
+    "#;
+
+    struct StubPlugin {
+        api_version: u32,
+        version: String,
+        initialized: Arc<AtomicBool>,
+    }
+
+    impl PluginInterface for StubPlugin {
+        fn info(&self) -> PluginInfo {
+            PluginInfo {
+                api_version: self.api_version,
+                name: "stub".to_string(),
+                version: self.version.clone(),
+                description: String::new(),
+                author: String::new(),
+                capabilities: vec![],
+            }
+        }
+
+        fn initialize(&mut self) -> std::result::Result<(), String> {
+            self.initialized.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn shutdown(&mut self) -> std::result::Result<(), String> {
+            Ok(())
+        }
+    }
+
+    fn compile(dir: &Path, source: &str) -> PathBuf {
+        build(dir, "stub", source, &[])
+    }
+
+    fn build(dir: &Path, name: &str, source: &str, link_args: &[String]) -> PathBuf {
+        let c_file = dir.join(format!("{}.c", name));
+        let library = dir.join(format!("lib{}.so", name));
+        std::fs::write(&c_file, source).unwrap();
+
+        let target = format!("{}-unknown-linux-gnu", std::env::consts::ARCH);
+        let status = cc::Build::new()
+            .target(&target)
+            .host(&target)
+            .opt_level(0)
+            .cargo_metadata(false)
+            .get_compiler()
+            .to_command()
+            .args(["-shared", "-fPIC", "-o"])
+            .arg(&library)
+            .arg(&c_file)
+            .args(link_args)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        library
+    }
+
+    // The returned raw pointer must be reclaimed by the test once the library
+    // has given it back.
+    fn register(library: &Path, plugin: StubPlugin) -> (Library, *mut dyn PluginInterface) {
+        let raw: *mut dyn PluginInterface = Box::into_raw(Box::new(plugin));
+        let (data, vtable): (*mut (), *const ()) = unsafe { std::mem::transmute(raw) };
+
+        let handle = unsafe { Library::new(library) }.unwrap();
+        unsafe {
+            let set: Symbol<unsafe extern "C" fn(*mut (), *const ())> = handle.get(b"stub_set_plugin\0").unwrap();
+            set(data, vtable);
+        }
+        (handle, raw)
+    }
+
+    fn destroyed(handle: &Library) -> i32 {
+        unsafe {
+            let destroyed: Symbol<unsafe extern "C" fn() -> i32> = handle.get(b"stub_destroyed\0").unwrap();
+            destroyed()
+        }
+    }
+
+    #[test]
+    fn test_load_initializes_and_destroys() {
+        let temp_dir = TempDir::new().unwrap();
+        let library = compile(temp_dir.path(), STUB_SOURCE);
+        let initialized = Arc::new(AtomicBool::new(false));
+        let (handle, raw) = register(
+            &library,
+            StubPlugin { api_version: PLUGIN_API_VERSION, version: "0.1.0".to_string(), initialized: initialized.clone() },
+        );
+
+        let loaded = PluginLoader.load(&library).unwrap();
+        assert!(initialized.load(Ordering::SeqCst));
+        assert_eq!(loaded.metadata().name, "stub");
+        assert_eq!(destroyed(&handle), 0);
+
+        drop(loaded);
+        assert_eq!(destroyed(&handle), 1);
+        drop(unsafe { Box::from_raw(raw) });
+    }
+
+    #[test]
+    fn test_api_version_mismatch() {
+        let temp_dir = TempDir::new().unwrap();
+        let library = compile(temp_dir.path(), STUB_SOURCE);
+        let initialized = Arc::new(AtomicBool::new(false));
+        let (handle, raw) = register(
+            &library,
+            StubPlugin { api_version: PLUGIN_API_VERSION + 1, version: "0.1.0".to_string(), initialized: initialized.clone() },
+        );
+
+        let result = PluginLoader.load(&library);
+
+        assert!(matches!(result, Err(Error::Plugin(ref msg)) if msg.to_string().contains("API version mismatch")));
+        assert!(!initialized.load(Ordering::SeqCst));
+        assert_eq!(destroyed(&handle), 1);
+        drop(unsafe { Box::from_raw(raw) });
+    }
+
+    #[test]
+    fn test_invalid_libraries() {
+        let temp_dir = TempDir::new().unwrap();
+
+        // Nothing registered, so `_plugin_create` returns null.
+        let empty = compile(temp_dir.path(), STUB_SOURCE);
+        assert!(matches!(PluginLoader.open(&empty), Err(Error::Plugin(_))));
+
+        let other_dir = TempDir::new().unwrap();
+        let unrelated = compile(other_dir.path(), "int answer(void) { return 42; }");
+        assert!(matches!(
+            PluginLoader.open(&unrelated),
+            Err(Error::Plugin(ref msg)) if msg.to_string().contains("not a Cheese plugin")
+        ));
+
+        let garbage = temp_dir.path().join("garbage.so");
+        std::fs::write(&garbage, b"not an elf").unwrap();
+        assert!(matches!(PluginLoader.open(&garbage), Err(Error::Plugin(_))));
+    }
+
+    #[allow(improper_ctypes_definitions)]
+    extern "C" fn create_versioned(version: *const c_char) -> *mut dyn PluginInterface {
+        let version = unsafe { CStr::from_ptr(version) }.to_string_lossy().into_owned();
+        let initialized = Arc::new(AtomicBool::new(false));
+        Box::into_raw(Box::new(StubPlugin { api_version: PLUGIN_API_VERSION, version, initialized }))
+    }
+
+    #[allow(improper_ctypes_definitions)]
+    extern "C" fn destroy_versioned(plugin: *mut dyn PluginInterface) {
+        drop(unsafe { Box::from_raw(plugin) });
+    }
+
+    #[allow(improper_ctypes_definitions)]
+    type RegistrySetFn = unsafe extern "C" fn(
+        extern "C" fn(*const c_char) -> *mut dyn PluginInterface,
+        extern "C" fn(*mut dyn PluginInterface),
+    );
+
+    #[tokio::test]
+    async fn test_update_plugin_runs_new_library() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = build(temp_dir.path(), "registry", REGISTRY_SOURCE, &[]);
+        let registry = unsafe { Library::new(registry) }.unwrap();
+        unsafe {
+            let set: Symbol<RegistrySetFn> = registry.get(b"registry_set\0").unwrap();
+            set(create_versioned, destroy_versioned);
+        }
+
+        let link_args = [
+            format!("-L{}", temp_dir.path().display()),
+            "-lregistry".to_string(),
+            format!("-Wl,-rpath,{}", temp_dir.path().display()),
+        ];
+        let versioned = |version: &str| {
+            let dir = temp_dir.path().join(version);
+            std::fs::create_dir(&dir).unwrap();
+            let source = format!("#define VERSION \"{}\"\n{}", version, VERSIONED_STUB_SOURCE);
+            build(&dir, "stub", &source, &link_args)
+        };
+        let (old, new) = (versioned("1.0.0"), versioned("1.1.0"));
+
+        let plugin_dir = temp_dir.path().join("plugins");
+        let manager = PluginManager::new(plugin_dir.clone()).unwrap();
+        let installed = plugin_dir.join("stub.so");
+        std::fs::copy(&old, &installed).unwrap();
+        manager.load_plugin(&installed).await.unwrap();
+        assert_eq!(manager.get_plugin("stub").unwrap().version, "1.0.0");
+
+        let change = manager.update_plugin("stub", &new).await.unwrap();
+        assert_eq!(change.new_version, "1.1.0");
+        assert_eq!(manager.get_plugin("stub").unwrap().version, "1.1.0");
+
+        // A second update loads yet another copy rather than the first one.
+        manager.update_plugin("stub", &old).await.unwrap();
+        assert_eq!(manager.get_plugin("stub").unwrap().version, "1.0.0");
+
+        manager.shutdown_all().await.unwrap();
+    }
+}
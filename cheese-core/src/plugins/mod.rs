@@ -1,13 +1,30 @@
 pub mod loader;
 pub mod api;
+#[cfg(feature = "remote-plugins")]
+pub mod repository;
 
 use crate::{Error, Result};
+use crate::error::context;
+use loader::PluginLoader;
+use api::{
+    Capability, ContextMenuRequest, ContextMenuResponse, FieldValue, KeyBinding, PluginInterface, PreviewFuture,
+    PreviewRequest, PreviewResponse, SettingsSchema,
+};
 use std::path::{Path, PathBuf};
 use std::collections::HashMap;
+use std::future::Future;
 use parking_lot::RwLock;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::runtime::Handle;
 
-pub const PLUGIN_API_VERSION: u32 = 1;
+// 2: `can_handle_mime` added to the plugin vtable.
+pub const PLUGIN_API_VERSION: u32 = 2;
+pub const DEFAULT_MAX_DOWNLOAD_MB: u64 = 64;
+
+// Numbers the staged copies made by `update_plugin`.
+static UPDATE_SEQUENCE: AtomicU64 = AtomicU64::new(0);
 
 #[derive(Debug, Clone)]
 pub struct PluginMetadata {
@@ -23,15 +40,139 @@ pub trait Plugin: Send + Sync {
     fn metadata(&self) -> PluginMetadata;
     fn initialize(&mut self) -> Result<()>;
     fn shutdown(&mut self) -> Result<()>;
+
+    fn can_handle_mime(&self, mime: &str) -> bool {
+        let _ = mime;
+        false
+    }
+
+    fn preview_async(&self, request: PreviewRequest) -> PreviewFuture {
+        let _ = request;
+        Box::pin(async { Err("Not implemented".to_string()) })
+    }
+
+    fn context_menu(&self, request: ContextMenuRequest) -> Result<ContextMenuResponse> {
+        let _ = request;
+        Err(Error::Plugin("Not implemented".into()))
+    }
+
+    fn key_bindings(&self) -> Vec<KeyBinding> {
+        Vec::new()
+    }
+
+    fn status_bar_text(&self, dir: &Path) -> Result<String> {
+        let _ = dir;
+        Err(Error::Plugin("Not implemented".into()))
+    }
+
+    fn settings_schema(&self) -> Option<SettingsSchema> {
+        None
+    }
+
+    fn apply_settings(&mut self, settings: HashMap<String, FieldValue>) -> Result<()> {
+        let _ = settings;
+        Err(Error::Plugin("Not implemented".into()))
+    }
+}
+
+impl<T: PluginInterface> Plugin for T {
+    fn metadata(&self) -> PluginMetadata {
+        let info = self.info();
+        PluginMetadata {
+            name: info.name,
+            version: info.version,
+            description: info.description,
+            author: info.author,
+            api_version: info.api_version,
+            capabilities: info.capabilities
+                .into_iter()
+                .map(|c| PluginCapability::from_api(c).as_str().to_string())
+                .collect(),
+        }
+    }
+
+    fn initialize(&mut self) -> Result<()> {
+        PluginInterface::initialize(self).map_err(|e| Error::Plugin(e.into()))
+    }
+
+    fn shutdown(&mut self) -> Result<()> {
+        PluginInterface::shutdown(self).map_err(|e| Error::Plugin(e.into()))
+    }
+
+    fn can_handle_mime(&self, mime: &str) -> bool {
+        PluginInterface::can_handle_mime(self, mime)
+    }
+
+    fn preview_async(&self, request: PreviewRequest) -> PreviewFuture {
+        PluginInterface::preview_async(self, request)
+    }
+
+    fn context_menu(&self, request: ContextMenuRequest) -> Result<ContextMenuResponse> {
+        PluginInterface::context_menu(self, request).map_err(|e| Error::Plugin(e.into()))
+    }
+
+    fn key_bindings(&self) -> Vec<KeyBinding> {
+        PluginInterface::key_bindings(self)
+    }
+
+    fn status_bar_text(&self, dir: &Path) -> Result<String> {
+        PluginInterface::status_bar_text(self, dir).map_err(|e| Error::Plugin(e.into()))
+    }
+
+    fn settings_schema(&self) -> Option<SettingsSchema> {
+        PluginInterface::settings_schema(self)
+    }
+
+    fn apply_settings(&mut self, settings: HashMap<String, FieldValue>) -> Result<()> {
+        PluginInterface::apply_settings(self, settings).map_err(|e| Error::Plugin(e.into()))
+    }
+}
+
+pub type PluginSettings = HashMap<String, HashMap<String, toml::Value>>;
+
+pub type PluginFactory = Arc<dyn Fn(&Path) -> Result<Box<dyn Plugin>> + Send + Sync>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionChange {
+    pub old_version: String,
+    pub new_version: String,
+}
+
+type SharedPlugin = Arc<RwLock<Box<dyn Plugin>>>;
+type MetadataCheck<'a> = &'a (dyn Fn(&PluginMetadata) -> Result<()> + Send + Sync);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PluginState {
+    Active,
+    Degraded(String),
+}
+
+// Metadata is captured at load time so listing plugins never waits on a
+// plugin whose lock is still held by a hung call.
+struct PluginEntry {
+    plugin: SharedPlugin,
+    metadata: PluginMetadata,
+    key_bindings: Vec<KeyBinding>,
+    path: PathBuf,
+    state: PluginState,
 }
 
 pub struct PluginManager {
-    plugins: Arc<RwLock<HashMap<String, Box<dyn Plugin>>>>,
+    plugins: Arc<RwLock<HashMap<String, PluginEntry>>>,
     plugin_dir: PathBuf,
+    settings: Arc<RwLock<PluginSettings>>,
+    factory: PluginFactory,
+    runtime: RwLock<Option<Handle>>,
+    timeout: RwLock<Option<Duration>>,
+    max_download_size: RwLock<u64>,
 }
 
 impl PluginManager {
     pub fn new(plugin_dir: PathBuf) -> Result<Self> {
+        Self::with_factory(plugin_dir, Arc::new(load_shared_library))
+    }
+
+    pub fn with_factory(plugin_dir: PathBuf, factory: PluginFactory) -> Result<Self> {
         if !plugin_dir.exists() {
             std::fs::create_dir_all(&plugin_dir)?;
         }
@@ -39,10 +180,147 @@ impl PluginManager {
         Ok(Self {
             plugins: Arc::new(RwLock::new(HashMap::new())),
             plugin_dir,
+            settings: Arc::new(RwLock::new(HashMap::new())),
+            factory,
+            runtime: RwLock::new(None),
+            timeout: RwLock::new(None),
+            max_download_size: RwLock::new(DEFAULT_MAX_DOWNLOAD_MB * 1024 * 1024),
+        })
+    }
+
+    pub fn set_runtime(&self, runtime: Handle) {
+        *self.runtime.write() = Some(runtime);
+    }
+
+    /// Caps how long `initialize`, `shutdown`, `preview`, `context_menu` and
+    /// `status_bar_text` may run. A call that overruns keeps its blocking thread but no longer
+    /// holds up the caller.
+    pub fn set_timeout(&self, duration: Duration) {
+        *self.timeout.write() = Some(duration);
+    }
+
+    /// Bytes `install` downloads before giving up on a plugin, normally from
+    /// `PluginsConfig::max_download_mb`.
+    pub fn set_max_download_size(&self, bytes: u64) {
+        *self.max_download_size.write() = bytes;
+    }
+
+    pub fn plugin_state(&self, name: &str) -> Option<PluginState> {
+        self.plugins.read().get(name).map(|e| e.state.clone())
+    }
+
+    pub async fn preview(&self, name: &str, request: PreviewRequest) -> Result<PreviewResponse> {
+        let plugin = self.active_plugin(name)?;
+        let runtime = self.runtime();
+
+        self.with_timeout(name, "preview", async {
+            let future = runtime.spawn_blocking(move || plugin.read().preview_async(request))
+                .await
+                .map_err(|e| Error::Plugin(context(format!("Preview task for {} failed", name), e)))?;
+
+            runtime.spawn(future)
+                .await
+                .map_err(|e| Error::Plugin(context(format!("Preview task for {} failed", name), e)))?
+                .map_err(|e| Error::Plugin(context(format!("Preview failed in {}", name), e)))
+        }).await
+    }
+
+    /// Active plugins that accept previews of `mime`, ordered by name.
+    /// Plugins busy initializing or shutting down are left out.
+    pub fn plugins_for_mime(&self, mime: &str) -> Vec<String> {
+        let plugins = self.plugins.read();
+        let mut names: Vec<String> = plugins
+            .iter()
+            .filter(|(_, entry)| entry.state == PluginState::Active)
+            .filter(|(_, entry)| entry.plugin.try_read().is_some_and(|plugin| plugin.can_handle_mime(mime)))
+            .map(|(name, _)| name.clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Preview from the first plugin in `plugins_for_mime` order that
+    /// succeeds for the request's file, as `(plugin name, response)`. `None`
+    /// when no plugin handles the type or all of them fail.
+    pub async fn preview_file(&self, request: PreviewRequest) -> Option<(String, PreviewResponse)> {
+        for name in self.plugins_for_mime(&request.file.mime_type) {
+            match self.preview(&name, request.clone()).await {
+                Ok(response) => return Some((name, response)),
+                Err(e) => tracing::warn!("Preview of {} by {} failed: {}", request.file.path.display(), name, e),
+            }
+        }
+        None
+    }
+
+    pub async fn context_menu(&self, name: &str, request: ContextMenuRequest) -> Result<ContextMenuResponse> {
+        let plugin = self.active_plugin(name)?;
+        let task = self.runtime().spawn_blocking(move || plugin.read().context_menu(request));
+
+        self.with_timeout(name, "context_menu", async {
+            task.await
+                .map_err(|e| Error::Plugin(context(format!("Context menu task for {} failed", name), e)))?
+        }).await
+    }
+
+    fn active_plugin(&self, name: &str) -> Result<SharedPlugin> {
+        let plugins = self.plugins.read();
+        let entry = plugins.get(name)
+            .ok_or_else(|| Error::Plugin(format!("Plugin not found: {}", name).into()))?;
+
+        match &entry.state {
+            PluginState::Active => Ok(Arc::clone(&entry.plugin)),
+            PluginState::Degraded(reason) => Err(Error::Plugin(format!("Plugin {} is degraded: {}", name, reason).into())),
+        }
+    }
+
+    fn runtime(&self) -> Handle {
+        self.runtime.read().clone().unwrap_or_else(Handle::current)
+    }
+
+    async fn with_timeout<T>(&self, name: &str, operation: &str, future: impl Future<Output = Result<T>>) -> Result<T> {
+        let Some(limit) = *self.timeout.read() else {
+            return future.await;
+        };
+
+        tokio::time::timeout(limit, future).await.unwrap_or_else(|_| {
+            tracing::error!("Plugin {} timed out in {} after {:?}", name, operation, limit);
+            Err(Error::Timeout(format!("{} in plugin {} after {:?}", operation, name, limit)))
         })
     }
 
-    pub fn load_plugin(&self, path: &Path) -> Result<()> {
+    async fn run_lifecycle(
+        &self,
+        name: &str,
+        operation: &str,
+        plugin: &SharedPlugin,
+        call: impl FnOnce(&mut dyn Plugin) -> Result<()> + Send + 'static,
+    ) -> Result<()> {
+        let plugin = Arc::clone(plugin);
+        let task = self.runtime().spawn_blocking(move || call(plugin.write().as_mut()));
+
+        self.with_timeout(name, operation, async {
+            task.await
+                .map_err(|e| Error::Plugin(context(format!("{} task for {} failed", operation, name), e)))?
+        }).await
+    }
+
+    pub fn set_settings(&self, settings: PluginSettings) {
+        *self.settings.write() = settings;
+    }
+
+    /// Passes the stored settings of `plugin`, or the schema defaults, to
+    /// `apply_settings`. Loading and updating do this before `initialize`.
+    pub fn configure_plugin(&self, plugin: &mut dyn Plugin) -> Result<()> {
+        configure_plugin(&self.settings, plugin)
+    }
+
+    pub async fn load_plugin(&self, path: &Path) -> Result<()> {
+        self.load_plugin_checked(path, &|_| Ok(())).await
+    }
+
+    // Loads like `load_plugin`, refusing the library if `check` rejects its
+    // metadata before it is initialized.
+    pub(super) async fn load_plugin_checked(&self, path: &Path, check: MetadataCheck<'_>) -> Result<()> {
         if !path.exists() {
             return Err(Error::NotFound { path: path.to_path_buf() });
         }
@@ -51,34 +329,239 @@ impl PluginManager {
             return Err(Error::Plugin(format!(
                 "Invalid plugin file: {}",
                 path.display()
-            )));
+            ).into()));
         }
 
         tracing::info!("Loading plugin from: {}", path.display());
-        
+
+        let plugin = (self.factory)(path)?;
+        let metadata = plugin.metadata();
+        check_api_version(&metadata)?;
+        check(&metadata)?;
+        let key_bindings = plugin.key_bindings();
+
+        if self.is_loaded(&metadata.name) {
+            return Err(Error::Plugin(format!("Plugin already loaded: {}", metadata.name).into()));
+        }
+
+        let plugin: SharedPlugin = Arc::new(RwLock::new(plugin));
+        let settings = Arc::clone(&self.settings);
+        let initialize = move |p: &mut dyn Plugin| {
+            configure_plugin(&settings, p)?;
+            p.initialize()
+        };
+        let state = match self.run_lifecycle(&metadata.name, "initialize", &plugin, initialize).await {
+            Ok(()) => PluginState::Active,
+            Err(e @ Error::Timeout(_)) => PluginState::Degraded(e.to_string()),
+            Err(e) => return Err(e),
+        };
+
+        self.plugins.write().insert(metadata.name.clone(), PluginEntry {
+            plugin,
+            metadata,
+            key_bindings,
+            path: path.to_path_buf(),
+            state,
+        });
+
         Ok(())
     }
 
-    pub fn unload_plugin(&self, name: &str) -> Result<()> {
-        let mut plugins = self.plugins.write();
-        
-        if let Some(mut plugin) = plugins.remove(name) {
-            plugin.shutdown()?;
+    /// Swaps the installed library of `name` for `new_path` and reloads it,
+    /// putting the old library back if the new one fails to initialize. The
+    /// plugin reports as degraded while the swap is in progress.
+    pub async fn update_plugin(&self, name: &str, new_path: &Path) -> Result<VersionChange> {
+        self.update_plugin_checked(name, new_path, &|_| Ok(())).await
+    }
+
+    // Updates like `update_plugin`, keeping the running version if `check`
+    // rejects the new library's metadata.
+    pub(super) async fn update_plugin_checked(
+        &self,
+        name: &str,
+        new_path: &Path,
+        check: MetadataCheck<'_>,
+    ) -> Result<VersionChange> {
+        if !self.is_valid_plugin(new_path)? {
+            return Err(Error::Plugin(format!(
+                "Invalid plugin file: {}",
+                new_path.display()
+            ).into()));
+        }
+
+        let (plugin, installed, old_version) = {
+            let plugins = self.plugins.read();
+            let entry = plugins.get(name)
+                .ok_or_else(|| Error::Plugin(format!("Plugin not found: {}", name).into()))?;
+            (Arc::clone(&entry.plugin), entry.path.clone(), entry.metadata.version.clone())
+        };
+
+        // dlopen hands back an already loaded library when the path matches,
+        // so the candidate is loaded from a name this process has not opened.
+        let sequence = UPDATE_SEQUENCE.fetch_add(1, Ordering::Relaxed);
+        let staged = installed.with_extension(format!("so.new.{}", sequence));
+        let backup = installed.with_extension("so.bak");
+
+        tokio::fs::copy(new_path, &staged).await?;
+        let candidate = match self.load_candidate(name, &staged).and_then(|c| check(&c.metadata()).map(|_| c)) {
+            Ok(candidate) => candidate,
+            Err(e) => {
+                let _ = tokio::fs::remove_file(&staged).await;
+                return Err(e);
+            }
+        };
+
+        if let Err(e) = tokio::fs::copy(&installed, &backup).await {
+            let _ = tokio::fs::remove_file(&staged).await;
+            return Err(e.into());
+        }
+        if let Err(e) = tokio::fs::rename(&staged, &installed).await {
+            let _ = tokio::fs::remove_file(&staged).await;
+            let _ = tokio::fs::remove_file(&backup).await;
+            return Err(e.into());
+        }
+
+        let new_metadata = candidate.metadata();
+        let new_key_bindings = candidate.key_bindings();
+        let new_version = new_metadata.version.clone();
+        let candidate: SharedPlugin = Arc::new(RwLock::new(candidate));
+
+        self.set_state(name, PluginState::Degraded(format!("Updating to {}", new_version)));
+
+        if let Err(e) = self.run_lifecycle(name, "shutdown", &plugin, |p| p.shutdown()).await {
+            restore_backup(&backup, &installed);
+            self.set_state(name, PluginState::Active);
+            return Err(e);
+        }
+
+        let settings = Arc::clone(&self.settings);
+        let initialize = move |p: &mut dyn Plugin| {
+            configure_plugin(&settings, p)?;
+            p.initialize()
+        };
+        if let Err(e) = self.run_lifecycle(name, "initialize", &candidate, initialize).await {
+            tracing::warn!("Plugin {} {} failed to initialize, rolling back: {}", name, new_version, e);
+            restore_backup(&backup, &installed);
+
+            let restored = self.run_lifecycle(name, "initialize", &plugin, |p| p.initialize()).await;
+            self.set_state(name, match &restored {
+                Ok(()) => PluginState::Active,
+                Err(restore_error) => PluginState::Degraded(restore_error.to_string()),
+            });
+            restored?;
+
+            return Err(Error::Plugin(context(
+                format!("Failed to initialize {} {}, rolled back to {}", name, new_version, old_version),
+                e,
+            )));
+        }
+
+        if let Some(entry) = self.plugins.write().get_mut(name) {
+            entry.plugin = candidate;
+            entry.metadata = new_metadata;
+            entry.key_bindings = new_key_bindings;
+            entry.state = PluginState::Active;
+        }
+        let _ = tokio::fs::remove_file(&backup).await;
+        tracing::info!("Updated plugin {} from {} to {}", name, old_version, new_version);
+
+        Ok(VersionChange { old_version, new_version })
+    }
+
+    fn set_state(&self, name: &str, state: PluginState) {
+        if let Some(entry) = self.plugins.write().get_mut(name) {
+            entry.state = state;
+        }
+    }
+
+    fn load_candidate(&self, name: &str, path: &Path) -> Result<Box<dyn Plugin>> {
+        let candidate = (self.factory)(path)?;
+        let metadata = candidate.metadata();
+        check_api_version(&metadata)?;
+
+        if metadata.name != name {
+            return Err(Error::Plugin(format!(
+                "Plugin name mismatch: expected {}, found {}",
+                name, metadata.name
+            ).into()));
+        }
+
+        Ok(candidate)
+    }
+
+    pub async fn unload_plugin(&self, name: &str) -> Result<()> {
+        let entry = self.plugins.write().remove(name);
+
+        if let Some(entry) = entry {
+            self.run_lifecycle(name, "shutdown", &entry.plugin, |p| p.shutdown()).await?;
             tracing::info!("Unloaded plugin: {}", name);
             Ok(())
         } else {
-            Err(Error::Plugin(format!("Plugin not found: {}", name)))
+            Err(Error::Plugin(format!("Plugin not found: {}", name).into()))
         }
     }
 
     pub fn get_plugin(&self, name: &str) -> Option<PluginMetadata> {
         let plugins = self.plugins.read();
-        plugins.get(name).map(|p| p.metadata())
+        plugins.get(name).map(|e| e.metadata.clone())
     }
 
     pub fn list_plugins(&self) -> Vec<PluginMetadata> {
         let plugins = self.plugins.read();
-        plugins.values().map(|p| p.metadata()).collect()
+        plugins.values().map(|e| e.metadata.clone()).collect()
+    }
+
+    /// Shortcuts contributed by loaded plugins as `(plugin name, binding)`,
+    /// ordered by plugin name.
+    pub fn registered_key_bindings(&self) -> Vec<(String, KeyBinding)> {
+        let plugins = self.plugins.read();
+        let mut bindings: Vec<(String, KeyBinding)> = plugins
+            .iter()
+            .flat_map(|(name, entry)| entry.key_bindings.iter().map(move |b| (name.clone(), b.clone())))
+            .collect();
+        bindings.sort_by(|a, b| a.0.cmp(&b.0));
+        bindings
+    }
+
+    /// Status bar text for `dir` from every active plugin with the
+    /// `status_bar` capability, as `(plugin name, text)` ordered by name.
+    /// Plugins that fail, time out or return nothing are left out.
+    pub async fn collect_status_bar_text(&self, dir: &Path) -> Vec<(String, String)> {
+        let contributors: Vec<(String, SharedPlugin)> = {
+            let status_bar = PluginCapability::StatusBar.as_str();
+            self.plugins.read()
+                .iter()
+                .filter(|(_, entry)| entry.state == PluginState::Active)
+                .filter(|(_, entry)| entry.metadata.capabilities.iter().any(|c| c == status_bar))
+                .map(|(name, entry)| (name.clone(), Arc::clone(&entry.plugin)))
+                .collect()
+        };
+
+        let runtime = self.runtime();
+        let tasks: Vec<_> = contributors
+            .into_iter()
+            .map(|(name, plugin)| {
+                let dir = dir.to_path_buf();
+                (name, runtime.spawn_blocking(move || plugin.read().status_bar_text(&dir)))
+            })
+            .collect();
+
+        let mut texts = Vec::new();
+        for (name, task) in tasks {
+            let result = self.with_timeout(&name, "status_bar_text", async {
+                task.await
+                    .map_err(|e| Error::Plugin(context(format!("Status bar task for {} failed", name), e)))?
+            }).await;
+
+            match result {
+                Ok(text) if !text.trim().is_empty() => texts.push((name, text)),
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Status bar text from {} failed: {}", name, e),
+            }
+        }
+
+        texts.sort_by(|a, b| a.0.cmp(&b.0));
+        texts
     }
 
     pub fn discover_plugins(&self) -> Result<Vec<PathBuf>> {
@@ -100,11 +583,11 @@ impl PluginManager {
         Ok(plugin_paths)
     }
 
-    pub fn load_all_plugins(&self) -> Result<()> {
+    pub async fn load_all_plugins(&self) -> Result<()> {
         let plugin_paths = self.discover_plugins()?;
 
         for path in plugin_paths {
-            match self.load_plugin(&path) {
+            match self.load_plugin(&path).await {
                 Ok(_) => tracing::info!("Successfully loaded: {}", path.display()),
                 Err(e) => tracing::warn!("Failed to load {}: {}", path.display(), e),
             }
@@ -113,11 +596,11 @@ impl PluginManager {
         Ok(())
     }
 
-    pub fn shutdown_all(&self) -> Result<()> {
-        let mut plugins = self.plugins.write();
-        
-        for (name, mut plugin) in plugins.drain() {
-            if let Err(e) = plugin.shutdown() {
+    pub async fn shutdown_all(&self) -> Result<()> {
+        let entries: Vec<_> = self.plugins.write().drain().collect();
+
+        for (name, entry) in entries {
+            if let Err(e) = self.run_lifecycle(&name, "shutdown", &entry.plugin, |p| p.shutdown()).await {
                 tracing::error!("Failed to shutdown plugin {}: {}", name, e);
             }
         }
@@ -162,9 +645,89 @@ impl Default for PluginManager {
     }
 }
 
+// Best effort for plugins left loaded without awaiting `shutdown_all`. A plugin
+// whose lock is still held (e.g. a timed-out initialize) is skipped.
 impl Drop for PluginManager {
     fn drop(&mut self) {
-        let _ = self.shutdown_all();
+        for (name, entry) in self.plugins.write().drain() {
+            match entry.plugin.try_write() {
+                Some(mut plugin) => {
+                    if let Err(e) = plugin.shutdown() {
+                        tracing::error!("Failed to shutdown plugin {}: {}", name, e);
+                    }
+                }
+                None => tracing::warn!("Skipping shutdown of busy plugin {}", name),
+            }
+        }
+    }
+}
+
+fn load_shared_library(path: &Path) -> Result<Box<dyn Plugin>> {
+    Ok(Box::new(PluginLoader.open(path)?))
+}
+
+fn check_api_version(metadata: &PluginMetadata) -> Result<()> {
+    if metadata.api_version != PLUGIN_API_VERSION {
+        return Err(Error::Plugin(format!(
+            "API version mismatch: {} requires {}, expected {}",
+            metadata.name, metadata.api_version, PLUGIN_API_VERSION
+        ).into()));
+    }
+
+    Ok(())
+}
+
+fn restore_backup(backup: &Path, installed: &Path) {
+    if let Err(e) = std::fs::rename(backup, installed) {
+        tracing::error!("Failed to restore {}: {}", installed.display(), e);
+    }
+}
+
+fn configure_plugin(settings: &RwLock<PluginSettings>, plugin: &mut dyn Plugin) -> Result<()> {
+    let Some(schema) = plugin.settings_schema() else {
+        return Ok(());
+    };
+
+    let name = plugin.metadata().name;
+    let stored = settings.read().get(&name).cloned().unwrap_or_default();
+    let resolved = resolve_settings(&name, &schema, &stored);
+
+    plugin.apply_settings(resolved)
+        .map_err(|e| Error::Plugin(context(format!("Failed to apply settings for {}", name), e)))
+}
+
+fn resolve_settings(
+    plugin_name: &str,
+    schema: &SettingsSchema,
+    stored: &HashMap<String, toml::Value>,
+) -> HashMap<String, FieldValue> {
+    let mut resolved = HashMap::with_capacity(schema.fields.len());
+
+    for field in &schema.fields {
+        let value = match stored.get(&field.id).and_then(field_value_from_toml) {
+            Some(value) if field.kind.accepts(&value) => value,
+            Some(_) => {
+                tracing::warn!(
+                    "Invalid value for setting {}.{}, using default",
+                    plugin_name, field.id
+                );
+                field.default.clone()
+            }
+            None => field.default.clone(),
+        };
+
+        resolved.insert(field.id.clone(), value);
+    }
+
+    resolved
+}
+
+fn field_value_from_toml(value: &toml::Value) -> Option<FieldValue> {
+    match value {
+        toml::Value::Boolean(b) => Some(FieldValue::Bool(*b)),
+        toml::Value::Integer(i) => Some(FieldValue::Int(*i)),
+        toml::Value::String(s) => Some(FieldValue::String(s.clone())),
+        _ => None,
     }
 }
 
@@ -175,9 +738,21 @@ pub enum PluginCapability {
     FileOverlay,
     CustomColumn,
     SearchProvider,
+    StatusBar,
 }
 
 impl PluginCapability {
+    pub fn from_api(capability: Capability) -> Self {
+        match capability {
+            Capability::FilePreview => Self::FilePreview,
+            Capability::ContextMenu => Self::ContextMenu,
+            Capability::FileOverlay => Self::FileOverlay,
+            Capability::CustomColumn => Self::CustomColumn,
+            Capability::SearchProvider => Self::SearchProvider,
+            Capability::StatusBar => Self::StatusBar,
+        }
+    }
+
     pub fn as_str(&self) -> &str {
         match self {
             Self::FilePreview => "file_preview",
@@ -185,6 +760,7 @@ impl PluginCapability {
             Self::FileOverlay => "file_overlay",
             Self::CustomColumn => "custom_column",
             Self::SearchProvider => "search_provider",
+            Self::StatusBar => "status_bar",
         }
     }
 
@@ -195,6 +771,7 @@ impl PluginCapability {
             "file_overlay" => Some(Self::FileOverlay),
             "custom_column" => Some(Self::CustomColumn),
             "search_provider" => Some(Self::SearchProvider),
+            "status_bar" => Some(Self::StatusBar),
             _ => None,
         }
     }
@@ -203,8 +780,481 @@ impl PluginCapability {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use api::{FieldKind, FileContext, PluginInfo, PreviewContent, SchemaField, API_VERSION};
+    use std::time::Duration;
     use tempfile::TempDir;
 
+    // `applied` is shared so tests can see it once the manager owns the plugin.
+    #[derive(Default)]
+    struct SettingsPlugin {
+        applied: Arc<parking_lot::Mutex<HashMap<String, FieldValue>>>,
+    }
+
+    impl PluginInterface for SettingsPlugin {
+        fn info(&self) -> PluginInfo {
+            PluginInfo {
+                api_version: API_VERSION,
+                name: "settings-plugin".to_string(),
+                version: "1.0.0".to_string(),
+                description: "Plugin with settings".to_string(),
+                author: "Test Author".to_string(),
+                capabilities: vec![],
+            }
+        }
+
+        fn initialize(&mut self) -> std::result::Result<(), String> {
+            Ok(())
+        }
+
+        fn shutdown(&mut self) -> std::result::Result<(), String> {
+            Ok(())
+        }
+
+        fn settings_schema(&self) -> Option<SettingsSchema> {
+            Some(SettingsSchema {
+                fields: vec![
+                    SchemaField {
+                        id: "enabled".to_string(),
+                        label: "Enabled".to_string(),
+                        kind: FieldKind::Bool,
+                        default: FieldValue::Bool(true),
+                    },
+                    SchemaField {
+                        id: "depth".to_string(),
+                        label: "Depth".to_string(),
+                        kind: FieldKind::Int { min: 1, max: 8 },
+                        default: FieldValue::Int(2),
+                    },
+                    SchemaField {
+                        id: "mode".to_string(),
+                        label: "Mode".to_string(),
+                        kind: FieldKind::Choice(vec!["fast".to_string(), "full".to_string()]),
+                        default: FieldValue::String("fast".to_string()),
+                    },
+                ],
+            })
+        }
+
+        fn apply_settings(&mut self, settings: HashMap<String, FieldValue>) -> std::result::Result<(), String> {
+            *self.applied.lock() = settings;
+            Ok(())
+        }
+    }
+
+    struct StubPlugin {
+        metadata: PluginMetadata,
+        fail_init: bool,
+        init_delay: Duration,
+        key_bindings: Vec<KeyBinding>,
+        status_text: Option<String>,
+    }
+
+    impl Plugin for StubPlugin {
+        fn metadata(&self) -> PluginMetadata {
+            self.metadata.clone()
+        }
+
+        fn initialize(&mut self) -> Result<()> {
+            std::thread::sleep(self.init_delay);
+            if self.fail_init {
+                return Err(Error::Plugin("initialize failed".into()));
+            }
+            Ok(())
+        }
+
+        fn shutdown(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn key_bindings(&self) -> Vec<KeyBinding> {
+            self.key_bindings.clone()
+        }
+
+        fn status_bar_text(&self, dir: &Path) -> Result<String> {
+            self.status_text.as_ref()
+                .map(|text| text.replace("{dir}", &dir.display().to_string()))
+                .ok_or_else(|| Error::Plugin("status bar failed".into()))
+        }
+    }
+
+    fn stub_binding(version: &str) -> KeyBinding {
+        KeyBinding {
+            label: format!("Stub action {}", version),
+            accelerator: "Ctrl+Alt+S".to_string(),
+            action: "stub.action".to_string(),
+        }
+    }
+
+    // Stub plugin files contain
+    // "name:version:api_version[:fail|:slow|:keys|:status|:status-empty|:status-fail]".
+    // Only the `status*` flags declare the status bar capability.
+    fn stub_factory(path: &Path) -> Result<Box<dyn Plugin>> {
+        let contents = std::fs::read_to_string(path)?;
+        let fields: Vec<&str> = contents.trim().split(':').collect();
+        let flag = fields.get(3).copied().unwrap_or_default();
+
+        Ok(Box::new(StubPlugin {
+            metadata: PluginMetadata {
+                name: fields[0].to_string(),
+                version: fields[1].to_string(),
+                description: String::new(),
+                author: String::new(),
+                api_version: fields[2].parse().unwrap(),
+                capabilities: if flag.starts_with("status") { vec!["status_bar".to_string()] } else { vec![] },
+            },
+            fail_init: flag == "fail",
+            init_delay: if flag == "slow" { Duration::from_secs(1) } else { Duration::ZERO },
+            key_bindings: if flag == "keys" { vec![stub_binding(fields[1])] } else { vec![] },
+            status_text: match flag {
+                "status-empty" => Some(String::new()),
+                "status-fail" => None,
+                _ => Some(format!("{} in {{dir}}", fields[0])),
+            },
+        }))
+    }
+
+    async fn stub_manager(temp_dir: &TempDir) -> PluginManager {
+        let plugin_dir = temp_dir.path().join("plugins");
+        let manager = PluginManager::with_factory(plugin_dir.clone(), Arc::new(stub_factory)).unwrap();
+
+        let installed = plugin_dir.join("stub.so");
+        std::fs::write(&installed, format!("stub:1.0.0:{}", PLUGIN_API_VERSION)).unwrap();
+        manager.load_plugin(&installed).await.unwrap();
+
+        manager
+    }
+
+    fn write_update(temp_dir: &TempDir, contents: &str) -> PathBuf {
+        let path = temp_dir.path().join("update.so");
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn test_update_plugin() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = stub_manager(&temp_dir).await;
+        let update = write_update(&temp_dir, &format!("stub:1.1.0:{}", PLUGIN_API_VERSION));
+
+        let change = manager.update_plugin("stub", &update).await.unwrap();
+
+        assert_eq!(change, VersionChange {
+            old_version: "1.0.0".to_string(),
+            new_version: "1.1.0".to_string(),
+        });
+        assert_eq!(manager.get_plugin("stub").unwrap().version, "1.1.0");
+
+        let plugin_dir = temp_dir.path().join("plugins");
+        assert!(std::fs::read_to_string(plugin_dir.join("stub.so")).unwrap().starts_with("stub:1.1.0"));
+        assert_eq!(plugin_files(&plugin_dir), vec!["stub.so"]);
+    }
+
+    fn plugin_files(plugin_dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(plugin_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[tokio::test]
+    async fn test_registered_key_bindings() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = stub_manager(&temp_dir).await;
+        assert!(manager.registered_key_bindings().is_empty());
+
+        let update = write_update(&temp_dir, &format!("stub:1.1.0:{}:keys", PLUGIN_API_VERSION));
+        manager.update_plugin("stub", &update).await.unwrap();
+
+        assert_eq!(manager.registered_key_bindings(), vec![("stub".to_string(), stub_binding("1.1.0"))]);
+
+        manager.unload_plugin("stub").await.unwrap();
+        assert!(manager.registered_key_bindings().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_collect_status_bar_text() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = PluginManager::with_factory(temp_dir.path().to_path_buf(), Arc::new(stub_factory)).unwrap();
+
+        for (name, flag) in [("zeta", ":status"), ("alpha", ":status"), ("plain", ""), ("empty", ":status-empty"), ("broken", ":status-fail")] {
+            let path = temp_dir.path().join(format!("{}.so", name));
+            std::fs::write(&path, format!("{}:1.0.0:{}{}", name, PLUGIN_API_VERSION, flag)).unwrap();
+            manager.load_plugin(&path).await.unwrap();
+        }
+
+        let texts = manager.collect_status_bar_text(Path::new("/tmp/project")).await;
+        assert_eq!(texts, vec![
+            ("alpha".to_string(), "alpha in /tmp/project".to_string()),
+            ("zeta".to_string(), "zeta in /tmp/project".to_string()),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_update_plugin_api_mismatch() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = stub_manager(&temp_dir).await;
+        let update = write_update(&temp_dir, &format!("stub:2.0.0:{}", PLUGIN_API_VERSION + 1));
+
+        match manager.update_plugin("stub", &update).await {
+            Err(Error::Plugin(msg)) => assert!(msg.to_string().starts_with("API version mismatch")),
+            other => panic!("Unexpected result: {:?}", other.map(|_| ())),
+        }
+
+        assert_eq!(manager.get_plugin("stub").unwrap().version, "1.0.0");
+        let plugin_dir = temp_dir.path().join("plugins");
+        assert!(std::fs::read_to_string(plugin_dir.join("stub.so")).unwrap().starts_with("stub:1.0.0"));
+        assert_eq!(plugin_files(&plugin_dir), vec!["stub.so"]);
+    }
+
+    #[tokio::test]
+    async fn test_update_plugin_initialize_rollback() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = stub_manager(&temp_dir).await;
+        let update = write_update(&temp_dir, &format!("stub:1.1.0:{}:fail", PLUGIN_API_VERSION));
+
+        assert!(manager.update_plugin("stub", &update).await.is_err());
+
+        assert!(manager.is_loaded("stub"));
+        assert_eq!(manager.get_plugin("stub").unwrap().version, "1.0.0");
+
+        let plugin_dir = temp_dir.path().join("plugins");
+        assert!(std::fs::read_to_string(plugin_dir.join("stub.so")).unwrap().starts_with("stub:1.0.0"));
+        assert_eq!(plugin_files(&plugin_dir), vec!["stub.so"]);
+    }
+
+    #[tokio::test]
+    async fn test_update_plugin_initialize_timeout_rolls_back() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = stub_manager(&temp_dir).await;
+        manager.set_timeout(Duration::from_millis(100));
+        let update = write_update(&temp_dir, &format!("stub:1.1.0:{}:slow", PLUGIN_API_VERSION));
+
+        let started = std::time::Instant::now();
+        assert!(manager.update_plugin("stub", &update).await.is_err());
+        assert!(started.elapsed() < Duration::from_millis(900));
+
+        assert_eq!(manager.get_plugin("stub").unwrap().version, "1.0.0");
+        assert_eq!(manager.plugin_state("stub"), Some(PluginState::Active));
+    }
+
+    #[tokio::test]
+    async fn test_initialize_timeout_marks_degraded() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = PluginManager::with_factory(temp_dir.path().to_path_buf(), Arc::new(stub_factory)).unwrap();
+        manager.set_timeout(Duration::from_millis(100));
+
+        let path = temp_dir.path().join("slow.so");
+        std::fs::write(&path, format!("slow:1.0.0:{}:slow", PLUGIN_API_VERSION)).unwrap();
+
+        let started = std::time::Instant::now();
+        manager.load_plugin(&path).await.unwrap();
+        assert!(started.elapsed() < Duration::from_millis(900));
+
+        assert!(manager.is_loaded("slow"));
+        assert!(matches!(manager.plugin_state("slow"), Some(PluginState::Degraded(_))));
+        assert_eq!(manager.list_plugins()[0].name, "slow");
+        assert!(manager.preview("slow", preview_request()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_initialize_within_timeout_is_active() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = PluginManager::with_factory(temp_dir.path().to_path_buf(), Arc::new(stub_factory)).unwrap();
+        manager.set_timeout(Duration::from_secs(5));
+
+        let path = temp_dir.path().join("quick.so");
+        std::fs::write(&path, format!("quick:1.0.0:{}", PLUGIN_API_VERSION)).unwrap();
+        manager.load_plugin(&path).await.unwrap();
+
+        assert_eq!(manager.plugin_state("quick"), Some(PluginState::Active));
+        manager.unload_plugin("quick").await.unwrap();
+        assert!(!manager.is_loaded("quick"));
+    }
+
+    fn preview_info(name: &str) -> PluginInfo {
+        PluginInfo {
+            api_version: API_VERSION,
+            name: name.to_string(),
+            version: "1.0.0".to_string(),
+            description: String::new(),
+            author: String::new(),
+            capabilities: vec![api::Capability::FilePreview],
+        }
+    }
+
+    fn text_preview(request: &PreviewRequest) -> PreviewResponse {
+        PreviewResponse {
+            content: PreviewContent::Text(request.file.path.display().to_string()),
+            cacheable: true,
+        }
+    }
+
+    struct AsyncPreviewPlugin;
+
+    impl PluginInterface for AsyncPreviewPlugin {
+        fn info(&self) -> PluginInfo {
+            preview_info("async-preview")
+        }
+
+        fn initialize(&mut self) -> std::result::Result<(), String> {
+            Ok(())
+        }
+
+        fn shutdown(&mut self) -> std::result::Result<(), String> {
+            Ok(())
+        }
+
+        fn preview_async(&self, request: PreviewRequest) -> PreviewFuture {
+            Box::pin(async move {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                Ok(text_preview(&request))
+            })
+        }
+    }
+
+    struct BlockingPreviewPlugin;
+
+    impl PluginInterface for BlockingPreviewPlugin {
+        fn info(&self) -> PluginInfo {
+            preview_info("blocking-preview")
+        }
+
+        fn initialize(&mut self) -> std::result::Result<(), String> {
+            Ok(())
+        }
+
+        fn shutdown(&mut self) -> std::result::Result<(), String> {
+            Ok(())
+        }
+
+        fn preview(&self, request: PreviewRequest) -> std::result::Result<PreviewResponse, String> {
+            std::thread::sleep(Duration::from_millis(10));
+            Ok(text_preview(&request))
+        }
+    }
+
+    struct SvgPreviewPlugin;
+
+    impl PluginInterface for SvgPreviewPlugin {
+        fn info(&self) -> PluginInfo {
+            preview_info("svg-preview")
+        }
+
+        fn initialize(&mut self) -> std::result::Result<(), String> {
+            Ok(())
+        }
+
+        fn shutdown(&mut self) -> std::result::Result<(), String> {
+            Ok(())
+        }
+
+        fn can_handle_mime(&self, mime: &str) -> bool {
+            mime == "image/svg+xml"
+        }
+
+        fn preview(&self, request: PreviewRequest) -> std::result::Result<PreviewResponse, String> {
+            Ok(text_preview(&request))
+        }
+    }
+
+    async fn preview_manager(temp_dir: &TempDir) -> PluginManager {
+        let factory: PluginFactory = Arc::new(|path: &Path| -> Result<Box<dyn Plugin>> {
+            match path.file_stem().and_then(|s| s.to_str()) {
+                Some("async") => Ok(Box::new(AsyncPreviewPlugin)),
+                Some("svg") => Ok(Box::new(SvgPreviewPlugin)),
+                _ => Ok(Box::new(BlockingPreviewPlugin)),
+            }
+        });
+        let manager = PluginManager::with_factory(temp_dir.path().to_path_buf(), factory).unwrap();
+
+        for name in ["async.so", "blocking.so", "svg.so"] {
+            let path = temp_dir.path().join(name);
+            std::fs::write(&path, b"stub").unwrap();
+            manager.load_plugin(&path).await.unwrap();
+        }
+
+        manager
+    }
+
+    fn preview_request() -> PreviewRequest {
+        PreviewRequest {
+            file: FileContext {
+                path: PathBuf::from("/tmp/image.png"),
+                is_directory: false,
+                size: 0,
+                mime_type: "image/png".to_string(),
+                permissions: 0o644,
+            },
+            max_width: 256,
+            max_height: 256,
+        }
+    }
+
+    async fn assert_preview_does_not_block(manager: &PluginManager, name: &str) {
+        let order = parking_lot::Mutex::new(Vec::new());
+
+        let preview = async {
+            let response = manager.preview(name, preview_request()).await;
+            order.lock().push("preview");
+            response
+        };
+        let ticker = async {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            order.lock().push("ticker");
+        };
+
+        let (response, _) = tokio::join!(preview, ticker);
+
+        match response.unwrap().content {
+            PreviewContent::Text(text) => assert_eq!(text, "/tmp/image.png"),
+            other => panic!("Unexpected preview: {:?}", other),
+        }
+        assert_eq!(*order.lock(), vec!["ticker", "preview"]);
+    }
+
+    #[tokio::test]
+    async fn test_preview_async_plugin() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = preview_manager(&temp_dir).await;
+
+        assert_preview_does_not_block(&manager, "async-preview").await;
+    }
+
+    #[tokio::test]
+    async fn test_preview_blocking_plugin_default() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = preview_manager(&temp_dir).await;
+
+        assert_preview_does_not_block(&manager, "blocking-preview").await;
+        assert!(manager.preview("missing", preview_request()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_preview_routed_by_mime() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = preview_manager(&temp_dir).await;
+
+        assert_eq!(manager.plugins_for_mime("image/svg+xml"), vec!["svg-preview".to_string()]);
+        assert!(manager.plugins_for_mime("image/png").is_empty());
+
+        assert!(manager.preview_file(preview_request()).await.is_none());
+
+        let mut request = preview_request();
+        request.file.path = PathBuf::from("/tmp/logo.svg");
+        request.file.mime_type = "image/svg+xml".to_string();
+        let (name, response) = manager.preview_file(request).await.unwrap();
+        assert_eq!(name, "svg-preview");
+        match response.content {
+            PreviewContent::Text(text) => assert_eq!(text, "/tmp/logo.svg"),
+            other => panic!("Unexpected preview: {:?}", other),
+        }
+
+        manager.unload_plugin("svg-preview").await.unwrap();
+        assert!(manager.plugins_for_mime("image/svg+xml").is_empty());
+    }
+
     #[test]
     fn test_plugin_manager_creation() {
         let temp_dir = TempDir::new().unwrap();
@@ -222,4 +1272,70 @@ mod tests {
         let plugins = manager.discover_plugins().unwrap();
         assert_eq!(plugins.len(), 1);
     }
+
+    #[test]
+    fn test_configure_plugin_defaults() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = PluginManager::new(temp_dir.path().to_path_buf()).unwrap();
+        let mut plugin = SettingsPlugin::default();
+
+        manager.configure_plugin(&mut plugin).unwrap();
+
+        let applied = plugin.applied.lock();
+        assert_eq!(applied.get("enabled"), Some(&FieldValue::Bool(true)));
+        assert_eq!(applied.get("depth"), Some(&FieldValue::Int(2)));
+        assert_eq!(applied.get("mode"), Some(&FieldValue::String("fast".to_string())));
+    }
+
+    #[test]
+    fn test_configure_plugin_stored_settings() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = PluginManager::new(temp_dir.path().to_path_buf()).unwrap();
+
+        let mut stored = HashMap::new();
+        stored.insert("enabled".to_string(), toml::Value::Boolean(false));
+        stored.insert("depth".to_string(), toml::Value::Integer(42));
+        stored.insert("mode".to_string(), toml::Value::String("full".to_string()));
+
+        let mut settings = HashMap::new();
+        settings.insert("settings-plugin".to_string(), stored);
+        manager.set_settings(settings);
+
+        let mut plugin = SettingsPlugin::default();
+        manager.configure_plugin(&mut plugin).unwrap();
+
+        let applied = plugin.applied.lock();
+        assert_eq!(applied.get("enabled"), Some(&FieldValue::Bool(false)));
+        assert_eq!(applied.get("depth"), Some(&FieldValue::Int(2)));
+        assert_eq!(applied.get("mode"), Some(&FieldValue::String("full".to_string())));
+    }
+
+    fn stored_depth(depth: i64) -> PluginSettings {
+        let stored = HashMap::from([("depth".to_string(), toml::Value::Integer(depth))]);
+        HashMap::from([("settings-plugin".to_string(), stored)])
+    }
+
+    #[tokio::test]
+    async fn test_load_and_update_apply_stored_settings() {
+        let temp_dir = TempDir::new().unwrap();
+        let applied = Arc::new(parking_lot::Mutex::new(HashMap::new()));
+        let shared = Arc::clone(&applied);
+        let factory: PluginFactory = Arc::new(move |_: &Path| {
+            Ok(Box::new(SettingsPlugin { applied: Arc::clone(&shared) }) as Box<dyn Plugin>)
+        });
+        let manager = PluginManager::with_factory(temp_dir.path().to_path_buf(), factory).unwrap();
+        manager.set_settings(stored_depth(4));
+
+        let path = temp_dir.path().join("settings.so");
+        std::fs::write(&path, "settings").unwrap();
+        manager.load_plugin(&path).await.unwrap();
+
+        assert_eq!(applied.lock().get("depth"), Some(&FieldValue::Int(4)));
+        assert_eq!(applied.lock().get("enabled"), Some(&FieldValue::Bool(true)));
+
+        manager.set_settings(stored_depth(6));
+        manager.update_plugin("settings-plugin", &write_update(&temp_dir, "settings")).await.unwrap();
+
+        assert_eq!(applied.lock().get("depth"), Some(&FieldValue::Int(6)));
+    }
 }
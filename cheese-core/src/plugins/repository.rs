@@ -0,0 +1,381 @@
+use super::{restore_backup, MetadataCheck, PluginManager, PluginMetadata};
+use crate::{Error, Result};
+use crate::error::context;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use tokio_util::sync::CancellationToken;
+
+// Below the plugin directory, where discover_plugins does not look, so an
+// interrupted download is never loaded.
+const STAGING_DIR: &str = ".downloads";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PluginManifest {
+    pub name: String,
+    pub version: String,
+    pub description: String,
+    pub download_url: String,
+    pub checksum_sha256: String,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+}
+
+impl PluginManager {
+    pub async fn list_available(&self, repo_url: &str) -> Result<Vec<PluginManifest>> {
+        let response = reqwest::get(repo_url)
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| Error::Plugin(context(format!("Failed to fetch plugin index from {}", repo_url), e)))?;
+
+        response
+            .json::<Vec<PluginManifest>>()
+            .await
+            .map_err(|e| Error::Plugin(context(format!("Invalid plugin index from {}", repo_url), e)))
+    }
+
+    pub async fn install(&self, manifest: &PluginManifest, cancel: CancellationToken) -> Result<()> {
+        let dest = self.install_path(manifest)?;
+        let max_size = *self.max_download_size.read();
+        let bytes = download(&manifest.download_url, max_size, &cancel).await?;
+
+        let checksum = format!("{:x}", Sha256::digest(&bytes));
+        if !checksum.eq_ignore_ascii_case(manifest.checksum_sha256.trim()) {
+            return Err(Error::Plugin(format!(
+                "Checksum mismatch for {}: expected {}, got {}",
+                manifest.name, manifest.checksum_sha256, checksum
+            ).into()));
+        }
+
+        let staging = self.plugin_dir.join(STAGING_DIR);
+        tokio::fs::create_dir_all(&staging).await?;
+        let staged = staging.join(format!("{}.so", manifest.name));
+        tokio::fs::write(&staged, &bytes).await?;
+
+        // A loaded plugin is upgraded in place, keeping the running version
+        // if the new one fails.
+        let check = |metadata: &PluginMetadata| check_manifest(manifest, metadata);
+        let result = if self.is_loaded(&manifest.name) {
+            self.update_plugin_checked(&manifest.name, &staged, &check).await.map(|_| ())
+        } else {
+            self.install_staged(&staged, &dest, &check).await
+        };
+        let _ = tokio::fs::remove_file(&staged).await;
+        result?;
+
+        tracing::info!("Installed plugin {} {}", manifest.name, manifest.version);
+        Ok(())
+    }
+
+    // Moves `staged` to `dest` and loads it. If loading fails, whatever file
+    // was at `dest` before is put back.
+    async fn install_staged(&self, staged: &Path, dest: &Path, check: MetadataCheck<'_>) -> Result<()> {
+        let backup = dest.with_extension("so.bak");
+        let replaced = match tokio::fs::rename(dest, &backup).await {
+            Ok(()) => true,
+            Err(e) if e.kind() == ErrorKind::NotFound => false,
+            Err(e) => return Err(e.into()),
+        };
+
+        let installed = match tokio::fs::rename(staged, dest).await {
+            Ok(()) => self.load_plugin_checked(dest, check).await,
+            Err(e) => Err(e.into()),
+        };
+
+        match (installed, replaced) {
+            (Ok(()), true) => {
+                let _ = tokio::fs::remove_file(&backup).await;
+                Ok(())
+            }
+            (Ok(()), false) => Ok(()),
+            (Err(e), true) => {
+                restore_backup(&backup, dest);
+                Err(e)
+            }
+            (Err(e), false) => {
+                let _ = tokio::fs::remove_file(dest).await;
+                Err(e)
+            }
+        }
+    }
+
+    fn install_path(&self, manifest: &PluginManifest) -> Result<PathBuf> {
+        let valid = !manifest.name.is_empty()
+            && manifest.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(Error::Plugin(format!("Invalid plugin name in manifest: {:?}", manifest.name).into()));
+        }
+
+        Ok(self.plugin_dir.join(format!("{}.so", manifest.name)))
+    }
+}
+
+// The library must be the plugin and release the manifest describes, so a
+// repository cannot ship something else under a known name.
+fn check_manifest(manifest: &PluginManifest, metadata: &PluginMetadata) -> Result<()> {
+    if metadata.name != manifest.name || metadata.version != manifest.version {
+        return Err(Error::Plugin(format!(
+            "Downloaded plugin is {} {}, but the manifest lists {} {}",
+            metadata.name, metadata.version, manifest.name, manifest.version
+        ).into()));
+    }
+
+    Ok(())
+}
+
+async fn download(url: &str, max_size: u64, cancel: &CancellationToken) -> Result<Vec<u8>> {
+    let fetch_error = |e: reqwest::Error| Error::Plugin(context(format!("Failed to download {}", url), e));
+    let too_large = || Error::Plugin(format!("Download of {} exceeds the {} byte limit", url, max_size).into());
+
+    let mut response = tokio::select! {
+        _ = cancel.cancelled() => return Err(Error::Cancelled),
+        response = reqwest::get(url) => response.and_then(|r| r.error_for_status()).map_err(fetch_error)?,
+    };
+
+    if response.content_length().is_some_and(|len| len > max_size) {
+        return Err(too_large());
+    }
+
+    let mut bytes = Vec::new();
+    loop {
+        let chunk = tokio::select! {
+            _ = cancel.cancelled() => return Err(Error::Cancelled),
+            chunk = response.chunk() => chunk.map_err(fetch_error)?,
+        };
+
+        match chunk {
+            Some(chunk) if (bytes.len() + chunk.len()) as u64 > max_size => return Err(too_large()),
+            Some(chunk) => bytes.extend_from_slice(&chunk),
+            None => return Ok(bytes),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::{Plugin, PluginMetadata, PLUGIN_API_VERSION};
+    use std::path::Path;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    struct DownloadedPlugin {
+        name: String,
+        api_version: u32,
+    }
+
+    impl Plugin for DownloadedPlugin {
+        fn metadata(&self) -> PluginMetadata {
+            PluginMetadata {
+                name: self.name.clone(),
+                version: "1.0.0".to_string(),
+                description: String::new(),
+                author: String::new(),
+                api_version: self.api_version,
+                capabilities: vec![],
+            }
+        }
+
+        fn initialize(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn shutdown(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    // Plugin files contain the plugin name, followed by ":old-api" for a
+    // plugin built against another API version.
+    fn manager(dir: &Path) -> PluginManager {
+        PluginManager::with_factory(
+            dir.to_path_buf(),
+            Arc::new(|path: &Path| {
+                let contents = std::fs::read_to_string(path)?;
+                let (name, old_api) = match contents.trim().split_once(':') {
+                    Some((name, _)) => (name.to_string(), true),
+                    None => (contents.trim().to_string(), false),
+                };
+                let api_version = if old_api { PLUGIN_API_VERSION - 1 } else { PLUGIN_API_VERSION };
+                Ok(Box::new(DownloadedPlugin { name, api_version }) as Box<dyn Plugin>)
+            }),
+        )
+        .unwrap()
+    }
+
+    // Serves each request path from `routes` as a 200 response, anything else as a 404.
+    async fn serve(routes: Vec<(&'static str, Vec<u8>)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = vec![0u8; 4096];
+                let n = socket.read(&mut request).await.unwrap();
+                let request = String::from_utf8_lossy(&request[..n]);
+                let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+
+                let (status, body) = match routes.iter().find(|(p, _)| *p == path) {
+                    Some((_, body)) => ("200 OK", body.clone()),
+                    None => ("404 Not Found", Vec::new()),
+                };
+                let header = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                );
+                socket.write_all(header.as_bytes()).await.unwrap();
+                socket.write_all(&body).await.unwrap();
+            }
+        });
+
+        format!("http://{}", addr)
+    }
+
+    fn manifest(name: &str, url: &str, payload: &[u8]) -> PluginManifest {
+        PluginManifest {
+            name: name.to_string(),
+            version: "1.0.0".to_string(),
+            description: "Shows git status".to_string(),
+            download_url: url.to_string(),
+            checksum_sha256: format!("{:x}", Sha256::digest(payload)),
+            capabilities: vec!["overlay".to_string()],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_and_install() {
+        let temp_dir = TempDir::new().unwrap();
+        let payload = b"git-status".to_vec();
+        let placeholder = manifest("git-status", "", &payload);
+        let index = serde_json::to_vec(&vec![placeholder]).unwrap();
+        let base = serve(vec![("/index.json", index), ("/git-status.so", payload)]).await;
+
+        let manager = manager(temp_dir.path());
+        let mut available = manager.list_available(&format!("{}/index.json", base)).await.unwrap();
+        assert_eq!(available.len(), 1);
+        assert_eq!(available[0].capabilities, vec!["overlay"]);
+
+        available[0].download_url = format!("{}/git-status.so", base);
+        manager.install(&available[0], CancellationToken::new()).await.unwrap();
+
+        assert!(manager.is_loaded("git-status"));
+        assert!(temp_dir.path().join("git-status.so").exists());
+        assert!(!temp_dir.path().join(STAGING_DIR).join("git-status.so").exists());
+        assert_eq!(manager.discover_plugins().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_failed_install_keeps_previous_plugin() {
+        let temp_dir = TempDir::new().unwrap();
+        let broken = b"git-status:old-api".to_vec();
+        let base = serve(vec![("/good.so", b"git-status".to_vec()), ("/broken.so", broken.clone())]).await;
+        let manager = manager(temp_dir.path());
+        let installed = temp_dir.path().join("git-status.so");
+
+        // Installed but not loaded: loading the download fails.
+        std::fs::write(&installed, "git-status").unwrap();
+        let upgrade = manifest("git-status", &format!("{}/broken.so", base), &broken);
+        assert!(manager.install(&upgrade, CancellationToken::new()).await.is_err());
+        assert!(!manager.is_loaded("git-status"));
+        assert_eq!(std::fs::read_to_string(&installed).unwrap(), "git-status");
+
+        // Loaded: the upgrade goes through update_plugin and is rolled back.
+        let good = manifest("git-status", &format!("{}/good.so", base), b"git-status");
+        manager.install(&good, CancellationToken::new()).await.unwrap();
+        assert!(manager.is_loaded("git-status"));
+        assert!(manager.install(&upgrade, CancellationToken::new()).await.is_err());
+        assert!(manager.is_loaded("git-status"));
+        assert_eq!(std::fs::read_to_string(&installed).unwrap(), "git-status");
+
+        assert!(!temp_dir.path().join("git-status.so.bak").exists());
+        assert!(!temp_dir.path().join(STAGING_DIR).join("git-status.so").exists());
+    }
+
+    #[tokio::test]
+    async fn test_install_rejects_bad_checksum() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = serve(vec![("/tampered.so", b"tampered".to_vec())]).await;
+
+        let manager = manager(temp_dir.path());
+        let manifest = manifest("tampered", &format!("{}/tampered.so", base), b"original");
+        let result = manager.install(&manifest, CancellationToken::new()).await;
+
+        assert!(matches!(result, Err(Error::Plugin(ref msg)) if msg.to_string().contains("Checksum mismatch")));
+        assert!(!manager.is_loaded("tampered"));
+        assert!(!temp_dir.path().join("tampered.so").exists());
+    }
+
+    #[tokio::test]
+    async fn test_install_enforces_download_limit() {
+        let temp_dir = TempDir::new().unwrap();
+        let payload = b"git-status".to_vec();
+        let base = serve(vec![("/git-status.so", payload.clone())]).await;
+        let manager = manager(temp_dir.path());
+        let manifest = manifest("git-status", &format!("{}/git-status.so", base), &payload);
+
+        manager.set_max_download_size(payload.len() as u64 - 1);
+        let result = manager.install(&manifest, CancellationToken::new()).await;
+        assert!(matches!(result, Err(Error::Plugin(ref msg)) if msg.to_string().contains("byte limit")));
+        assert!(!temp_dir.path().join("git-status.so").exists());
+
+        manager.set_max_download_size(payload.len() as u64);
+        manager.install(&manifest, CancellationToken::new()).await.unwrap();
+        assert!(manager.is_loaded("git-status"));
+    }
+
+    #[tokio::test]
+    async fn test_install_checks_manifest_identity() {
+        let temp_dir = TempDir::new().unwrap();
+        let payload = b"git-status".to_vec();
+        let url = format!("{}/git-status.so", serve(vec![("/git-status.so", payload.clone())]).await);
+        let manager = manager(temp_dir.path());
+
+        let renamed = manifest("other", &url, &payload);
+        assert!(manager.install(&renamed, CancellationToken::new()).await.is_err());
+        assert!(!manager.is_loaded("git-status"));
+        assert!(!temp_dir.path().join("other.so").exists());
+
+        let mut newer = manifest("git-status", &url, &payload);
+        newer.version = "2.0.0".to_string();
+        let result = manager.install(&newer, CancellationToken::new()).await;
+        assert!(matches!(result, Err(Error::Plugin(ref msg)) if msg.to_string().contains("manifest lists git-status 2.0.0")));
+        assert!(!manager.is_loaded("git-status"));
+        assert!(!temp_dir.path().join("git-status.so").exists());
+
+        // An upgrade that does not match keeps the running plugin.
+        manager.install(&manifest("git-status", &url, &payload), CancellationToken::new()).await.unwrap();
+        assert!(manager.install(&newer, CancellationToken::new()).await.is_err());
+        assert_eq!(manager.get_plugin("git-status").unwrap().version, "1.0.0");
+        assert!(temp_dir.path().join("git-status.so").exists());
+    }
+
+    #[tokio::test]
+    async fn test_install_errors() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = serve(vec![]).await;
+        let manager = manager(temp_dir.path());
+
+        let escaping = manifest("../escape", &format!("{}/escape.so", base), b"");
+        assert!(matches!(
+            manager.install(&escaping, CancellationToken::new()).await,
+            Err(Error::Plugin(_))
+        ));
+
+        let missing = manifest("missing", &format!("{}/missing.so", base), b"");
+        assert!(matches!(
+            manager.install(&missing, CancellationToken::new()).await,
+            Err(Error::Plugin(_))
+        ));
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        assert!(matches!(manager.install(&missing, cancel).await, Err(Error::Cancelled)));
+
+        assert!(manager.list_available(&format!("{}/index.json", base)).await.is_err());
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{Error, Result};
+use crate::plugins::api::PreviewContent;
+use std::fmt::Write as _;
+use std::path::Path;
+use tokio::io::AsyncReadExt;
+
+const HEX_BYTES_PER_LINE: usize = 16;
+
+pub async fn preview_text(path: &Path, max_bytes: usize) -> Result<PreviewContent> {
+    let metadata = tokio::fs::metadata(path).await?;
+    if metadata.is_dir() {
+        return Err(Error::InvalidOperation("Cannot preview a directory".to_string()));
+    }
+
+    let file = tokio::fs::File::open(path).await?;
+    let mut data = Vec::with_capacity(max_bytes.min(metadata.len() as usize));
+    file.take(max_bytes as u64).read_to_end(&mut data).await?;
+
+    let truncated = (data.len() as u64) < metadata.len();
+
+    match decode_text(&data, truncated) {
+        Some(text) => Ok(PreviewContent::Text(text)),
+        None => Ok(PreviewContent::Text(hex_dump(&data))),
+    }
+}
+
+fn decode_text(data: &[u8], truncated: bool) -> Option<String> {
+    if data.contains(&0) {
+        return None;
+    }
+
+    match std::str::from_utf8(data) {
+        Ok(text) => Some(text.to_string()),
+        Err(e) if truncated && e.error_len().is_none() => {
+            Some(String::from_utf8_lossy(&data[..e.valid_up_to()]).into_owned())
+        }
+        Err(_) => None,
+    }
+}
+
+pub fn hex_dump(data: &[u8]) -> String {
+    let mut output = String::with_capacity(data.len() * 4 + data.len() / HEX_BYTES_PER_LINE * 12);
+
+    for (line, chunk) in data.chunks(HEX_BYTES_PER_LINE).enumerate() {
+        let _ = write!(output, "{:08x} ", line * HEX_BYTES_PER_LINE);
+
+        for i in 0..HEX_BYTES_PER_LINE {
+            if i % 8 == 0 {
+                output.push(' ');
+            }
+            match chunk.get(i) {
+                Some(byte) => {
+                    let _ = write!(output, "{:02x} ", byte);
+                }
+                None => output.push_str("   "),
+            }
+        }
+
+        output.push_str(" |");
+        for &byte in chunk {
+            output.push(if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' });
+        }
+        output.push_str("|\n");
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_preview_text_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("notes.txt");
+        std::fs::write(&path, "héllo wörld\nsecond line\n").unwrap();
+
+        match preview_text(&path, 1024).await.unwrap() {
+            PreviewContent::Text(text) => assert_eq!(text, "héllo wörld\nsecond line\n"),
+            other => panic!("Unexpected preview: {:?}", other),
+        }
+
+        match preview_text(&path, 2).await.unwrap() {
+            PreviewContent::Text(text) => assert_eq!(text, "h"),
+            other => panic!("Unexpected preview: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_preview_binary_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("data.bin");
+        std::fs::write(&path, [0x7f, b'E', b'L', b'F', 0x00, 0x01, 0xff]).unwrap();
+
+        match preview_text(&path, 1024).await.unwrap() {
+            PreviewContent::Text(text) => {
+                assert_eq!(
+                    text,
+                    "00000000  7f 45 4c 46 00 01 ff                              |.ELF...|\n"
+                );
+            }
+            other => panic!("Unexpected preview: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_preview_empty_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("empty");
+        std::fs::write(&path, b"").unwrap();
+
+        match preview_text(&path, 1024).await.unwrap() {
+            PreviewContent::Text(text) => assert!(text.is_empty()),
+            other => panic!("Unexpected preview: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use regex::Regex;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+const MATCH_BUFFER: usize = 256;
+const READ_BUFFER: usize = 64 * 1024;
+// Files with a NUL byte in their first block are treated as binary.
+const BINARY_SNIFF_LEN: usize = 8 * 1024;
+// Lines are matched on at most this many bytes so one huge line cannot
+// exhaust memory; the rest of such a line is skipped.
+const MAX_LINE_LEN: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrepMatch {
+    pub path: PathBuf,
+    /// 1-based.
+    pub line_number: u64,
+    /// The line without its line ending.
+    pub line: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GrepOptions {
+    /// Skips paths excluded by `.gitignore`, `.ignore` and git excludes.
+    pub respect_gitignore: bool,
+    pub include_hidden: bool,
+    pub follow_symlinks: bool,
+    pub max_depth: Option<usize>,
+}
+
+impl Default for GrepOptions {
+    fn default() -> Self {
+        Self {
+            respect_gitignore: true,
+            include_hidden: false,
+            follow_symlinks: false,
+            max_depth: None,
+        }
+    }
+}
+
+/// Searches the text files below `root` for `pattern`, streaming matches in
+/// walk order. Files are read line by line, so memory stays bounded however
+/// large they are. The search stops between files once `cancel` fires or the
+/// receiver is dropped.
+pub fn grep(root: PathBuf, pattern: Regex, opts: GrepOptions, cancel: CancellationToken) -> mpsc::Receiver<GrepMatch> {
+    let (sender, receiver) = mpsc::channel(MATCH_BUFFER);
+
+    tokio::task::spawn_blocking(move || {
+        let walker = ignore::WalkBuilder::new(&root)
+            .standard_filters(false)
+            .hidden(!opts.include_hidden)
+            .git_ignore(opts.respect_gitignore)
+            .git_exclude(opts.respect_gitignore)
+            .ignore(opts.respect_gitignore)
+            .require_git(false)
+            .follow_links(opts.follow_symlinks)
+            .max_depth(opts.max_depth)
+            .build();
+
+        for entry in walker {
+            if cancel.is_cancelled() || sender.is_closed() {
+                return;
+            }
+
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    tracing::debug!("Skipping unreadable entry during grep: {}", e);
+                    continue;
+                }
+            };
+            if !entry.file_type().is_some_and(|t| t.is_file()) {
+                continue;
+            }
+
+            match grep_file(entry.path(), &pattern, &sender) {
+                Ok(true) => {}
+                Ok(false) => return,
+                Err(e) => tracing::debug!("Skipping {} during grep: {}", entry.path().display(), e),
+            }
+        }
+    });
+
+    receiver
+}
+
+// Returns false once the receiver is gone.
+fn grep_file(path: &Path, pattern: &Regex, sender: &mpsc::Sender<GrepMatch>) -> io::Result<bool> {
+    let mut reader = BufReader::with_capacity(READ_BUFFER, File::open(path)?);
+
+    let head = reader.fill_buf()?;
+    if head[..head.len().min(BINARY_SNIFF_LEN)].contains(&0) {
+        return Ok(true);
+    }
+
+    let mut line = Vec::new();
+    let mut line_number = 0;
+    while read_line(&mut reader, &mut line)? {
+        line_number += 1;
+
+        let text = String::from_utf8_lossy(&line);
+        let text = text.trim_end_matches(['\n', '\r']);
+        if pattern.is_match(text) {
+            let found = GrepMatch {
+                path: path.to_path_buf(),
+                line_number,
+                line: text.to_string(),
+            };
+            if sender.blocking_send(found).is_err() {
+                return Ok(false);
+            }
+        }
+    }
+
+    Ok(true)
+}
+
+fn read_line(reader: &mut impl BufRead, line: &mut Vec<u8>) -> io::Result<bool> {
+    line.clear();
+    let read = reader.by_ref().take(MAX_LINE_LEN).read_until(b'\n', line)?;
+    if read == 0 {
+        return Ok(false);
+    }
+
+    if read as u64 == MAX_LINE_LEN && line.last() != Some(&b'\n') {
+        skip_line(reader)?;
+    }
+    Ok(true)
+}
+
+fn skip_line(reader: &mut impl BufRead) -> io::Result<()> {
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            return Ok(());
+        }
+        match buf.iter().position(|&b| b == b'\n') {
+            Some(end) => {
+                reader.consume(end + 1);
+                return Ok(());
+            }
+            None => {
+                let len = buf.len();
+                reader.consume(len);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn fixture_tree() -> TempDir {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join("target")).unwrap();
+        std::fs::write(root.join("src/main.rs"), "fn main() {\n    todo!();\n}\r\n// TODO: docs\n").unwrap();
+        std::fs::write(root.join("src/lib.rs"), "pub mod todo_list;\n").unwrap();
+        std::fs::write(root.join("target/out.rs"), "todo!()\n").unwrap();
+        std::fs::write(root.join("image.bin"), b"todo\0\x01\x02").unwrap();
+        std::fs::write(root.join(".hidden"), "todo\n").unwrap();
+        std::fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        temp_dir
+    }
+
+    async fn collect(root: &Path, pattern: &str, opts: GrepOptions) -> Vec<GrepMatch> {
+        let mut receiver = grep(root.to_path_buf(), Regex::new(pattern).unwrap(), opts, CancellationToken::new());
+        let mut matches = Vec::new();
+        while let Some(found) = receiver.recv().await {
+            matches.push(found);
+        }
+        matches.sort_by(|a, b| (&a.path, a.line_number).cmp(&(&b.path, b.line_number)));
+        matches
+    }
+
+    #[tokio::test]
+    async fn test_grep_streams_matches() {
+        let temp_dir = fixture_tree();
+        let root = temp_dir.path();
+
+        let matches = collect(root, "(?i)todo", GrepOptions::default()).await;
+
+        assert_eq!(matches, vec![
+            GrepMatch { path: root.join("src/lib.rs"), line_number: 1, line: "pub mod todo_list;".to_string() },
+            GrepMatch { path: root.join("src/main.rs"), line_number: 2, line: "    todo!();".to_string() },
+            GrepMatch { path: root.join("src/main.rs"), line_number: 4, line: "// TODO: docs".to_string() },
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_grep_without_gitignore_or_hidden_filters() {
+        let temp_dir = fixture_tree();
+        let root = temp_dir.path();
+        let opts = GrepOptions {
+            respect_gitignore: false,
+            include_hidden: true,
+            ..GrepOptions::default()
+        };
+
+        let paths: Vec<PathBuf> = collect(root, "^todo", opts).await.into_iter().map(|m| m.path).collect();
+
+        assert_eq!(paths, vec![root.join(".hidden"), root.join("target/out.rs")]);
+    }
+
+    #[tokio::test]
+    async fn test_grep_cancelled() {
+        let temp_dir = fixture_tree();
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+
+        let mut receiver = grep(temp_dir.path().to_path_buf(), Regex::new("todo").unwrap(), GrepOptions::default(), cancel);
+        assert!(receiver.recv().await.is_none());
+    }
+
+    #[test]
+    fn test_read_line_truncates_long_lines() {
+        let long = "x".repeat(MAX_LINE_LEN as usize + 10);
+        let data = format!("{}\nshort\n", long);
+        let mut reader = BufReader::new(data.as_bytes());
+        let mut line = Vec::new();
+
+        assert!(read_line(&mut reader, &mut line).unwrap());
+        assert_eq!(line.len() as u64, MAX_LINE_LEN);
+        assert!(read_line(&mut reader, &mut line).unwrap());
+        assert_eq!(line, b"short\n");
+        assert!(!read_line(&mut reader, &mut line).unwrap());
+    }
+}
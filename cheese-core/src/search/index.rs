@@ -0,0 +1,366 @@
+use crate::{Error, Result};
+use crate::error::context;
+use crate::fs::ops::LocalFileOps;
+use crate::fs::scanner::Scanner;
+use crate::fs::watcher::{WatchEvent, Watcher};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use xdg::BaseDirectories;
+
+const INDEX_FILE: &str = "search_index.json";
+const SCAN_BUFFER: usize = 16;
+
+/// Every path below a set of roots, for search as you type. The initial
+/// build scans each root once; after that the index follows `WatchEvent`s
+/// and can be saved and loaded to resume without rescanning.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Index {
+    roots: Vec<PathBuf>,
+    // Path to is_dir. Ordering by component keeps a directory's descendants
+    // right after it, so subtrees are a contiguous range.
+    entries: BTreeMap<PathBuf, bool>,
+}
+
+impl Index {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn build(scanner: &Scanner, roots: &[PathBuf], cancel: CancellationToken) -> Result<Self> {
+        let mut index = Self::new();
+        for root in roots {
+            index.add_root(scanner, root, cancel.clone()).await?;
+        }
+        Ok(index)
+    }
+
+    pub fn index_path() -> Result<PathBuf> {
+        let xdg_dirs = BaseDirectories::with_prefix("cheese")
+            .map_err(|e| Error::Config(context("Failed to get XDG directories", e)))?;
+        Ok(xdg_dirs.get_data_home().join(INDEX_FILE))
+    }
+
+    /// Returns an empty index when nothing has been saved yet.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        serde_json::from_str(&contents)
+            .map_err(|e| Error::Cache(context("Failed to parse search index", e)))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let json = serde_json::to_string(self)
+            .map_err(|e| Error::Cache(context("Failed to serialize search index", e)))?;
+
+        LocalFileOps::atomic_write(path, json.as_bytes())
+    }
+
+    /// Scans `root` and adds everything below it. Scanning a root again
+    /// refreshes it after changes the index did not see.
+    pub async fn add_root(&mut self, scanner: &Scanner, root: &Path, cancel: CancellationToken) -> Result<()> {
+        let (sender, mut results) = mpsc::channel(SCAN_BUFFER);
+        let mut scanned = BTreeMap::new();
+
+        let scan = scanner.scan_recursive(root.to_path_buf(), sender, cancel);
+        let collect = async {
+            while let Some(result) = results.recv().await {
+                scanned.extend(result.entries.into_iter().map(|entry| (entry.path, entry.is_dir)));
+            }
+        };
+        let (scan_result, ()) = tokio::join!(scan, collect);
+        scan_result?;
+
+        self.remove_subtree(root);
+        self.entries.extend(scanned);
+        if !self.roots.iter().any(|r| r == root) {
+            self.roots.push(root.to_path_buf());
+        }
+
+        Ok(())
+    }
+
+    pub fn roots(&self) -> &[PathBuf] {
+        &self.roots
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.entries.contains_key(path)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Roots and indexed directories; the watcher is not recursive, so each
+    /// of them has to be watched on its own.
+    pub fn directories(&self) -> impl Iterator<Item = &Path> {
+        self.roots.iter()
+            .map(PathBuf::as_path)
+            .chain(self.entries.iter().filter(|(_, is_dir)| **is_dir).map(|(path, _)| path.as_path()))
+    }
+
+    pub fn apply(&mut self, event: &WatchEvent) {
+        match event {
+            WatchEvent::Created(path) => self.insert(path),
+            WatchEvent::Deleted(path) => self.remove_subtree(path),
+            WatchEvent::Renamed { from, to } => self.rename(from, to),
+            WatchEvent::Modified(path) | WatchEvent::AttributesChanged(path) => {
+                if !self.contains(path) {
+                    self.insert(path);
+                }
+            }
+        }
+    }
+
+    /// Paths matching `query`, best first: file names containing it, then
+    /// paths below a root containing it, then file names containing its
+    /// characters in order. Case is ignored.
+    pub fn query(&self, query: &str) -> Vec<PathBuf> {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return Vec::new();
+        }
+
+        let mut matches: Vec<(MatchKind, usize, &PathBuf)> = self.entries
+            .keys()
+            .filter_map(|path| {
+                let relative = self.relative_to_root(path).to_string_lossy().to_lowercase();
+                let name = path.file_name()?.to_string_lossy().to_lowercase();
+                let kind = match_kind(&query, &name, &relative)?;
+                Some((kind, relative.len(), path))
+            })
+            .collect();
+
+        matches.sort();
+        matches.into_iter().map(|(_, _, path)| path.clone()).collect()
+    }
+
+    fn insert(&mut self, path: &Path) {
+        if self.roots.iter().any(|root| path.starts_with(root) && path != root) {
+            self.entries.insert(path.to_path_buf(), path.is_dir());
+        }
+    }
+
+    fn subtree(&self, path: &Path) -> Vec<(PathBuf, bool)> {
+        self.entries
+            .range(path.to_path_buf()..)
+            .take_while(|(entry, _)| entry.starts_with(path))
+            .map(|(entry, is_dir)| (entry.clone(), *is_dir))
+            .collect()
+    }
+
+    fn remove_subtree(&mut self, path: &Path) {
+        for (entry, _) in self.subtree(path) {
+            self.entries.remove(&entry);
+        }
+    }
+
+    fn rename(&mut self, from: &Path, to: &Path) {
+        let moved = self.subtree(from);
+        if moved.is_empty() {
+            self.insert(to);
+            return;
+        }
+
+        self.remove_subtree(from);
+        if !self.roots.iter().any(|root| to.starts_with(root) && to != root) {
+            return;
+        }
+        for (entry, is_dir) in moved {
+            if let Ok(rest) = entry.strip_prefix(from) {
+                self.entries.insert(to.join(rest), is_dir);
+            }
+        }
+    }
+
+    fn relative_to_root<'a>(&self, path: &'a Path) -> &'a Path {
+        self.roots.iter()
+            .find_map(|root| path.strip_prefix(root).ok())
+            .unwrap_or(path)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum MatchKind {
+    Name,
+    Path,
+    Fuzzy,
+}
+
+fn match_kind(query: &str, name: &str, relative: &str) -> Option<MatchKind> {
+    if name.contains(query) {
+        Some(MatchKind::Name)
+    } else if relative.contains(query) {
+        Some(MatchKind::Path)
+    } else if is_subsequence(query, name) {
+        Some(MatchKind::Fuzzy)
+    } else {
+        None
+    }
+}
+
+fn is_subsequence(needle: &str, haystack: &str) -> bool {
+    let mut haystack = haystack.chars();
+    needle.chars().all(|c| haystack.any(|h| h == c))
+}
+
+/// Applies watcher events to `index` until the channel closes or `cancel`
+/// fires. Directories created meanwhile are added to `watcher` so their
+/// contents are followed too.
+pub async fn keep_fresh(
+    index: Arc<RwLock<Index>>,
+    watcher: &Watcher,
+    mut events: mpsc::UnboundedReceiver<WatchEvent>,
+    cancel: CancellationToken,
+) {
+    loop {
+        let event = tokio::select! {
+            biased;
+            _ = cancel.cancelled() => break,
+            event = events.recv() => match event {
+                Some(event) => event,
+                None => break,
+            },
+        };
+
+        index.write().apply(&event);
+
+        let created_dir = match &event {
+            WatchEvent::Created(path) | WatchEvent::Renamed { to: path, .. } if path.is_dir() => Some(path),
+            _ => None,
+        };
+        if let Some(dir) = created_dir {
+            if let Err(e) = watcher.watch(dir) {
+                tracing::debug!("Not watching new directory {}: {}", dir.display(), e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    fn sample_tree() -> TempDir {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        std::fs::create_dir_all(root.join("src/ui")).unwrap();
+        std::fs::create_dir_all(root.join("docs")).unwrap();
+        std::fs::write(root.join("src/main.rs"), "").unwrap();
+        std::fs::write(root.join("src/ui/window.rs"), "").unwrap();
+        std::fs::write(root.join("docs/manual.md"), "").unwrap();
+        temp_dir
+    }
+
+    async fn build_index(root: &Path) -> Index {
+        let scanner = Scanner::new(false, 16, false);
+        Index::build(&scanner, &[root.to_path_buf()], CancellationToken::new()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_build_and_query() {
+        let temp_dir = sample_tree();
+        let root = temp_dir.path();
+        let index = build_index(root).await;
+
+        assert_eq!(index.len(), 6);
+        assert_eq!(index.query("window"), vec![root.join("src/ui/window.rs")]);
+        assert_eq!(index.query("MAIN"), vec![root.join("src/main.rs")]);
+        assert!(index.query("nothing-like-this").is_empty());
+        assert!(index.query("  ").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_query_ranks_name_before_path_before_fuzzy() {
+        let temp_dir = sample_tree();
+        let root = temp_dir.path();
+        let index = build_index(root).await;
+
+        assert_eq!(index.query("ui"), vec![root.join("src/ui"), root.join("src/ui/window.rs")]);
+        assert_eq!(index.query("mnl"), vec![root.join("docs/manual.md")]);
+        assert_eq!(index.query("man"), vec![root.join("docs/manual.md"), root.join("src/main.rs")]);
+    }
+
+    #[tokio::test]
+    async fn test_apply_events() {
+        let temp_dir = sample_tree();
+        let root = temp_dir.path();
+        let mut index = build_index(root).await;
+
+        std::fs::write(root.join("src/lib.rs"), "").unwrap();
+        index.apply(&WatchEvent::Created(root.join("src/lib.rs")));
+        assert_eq!(index.query("lib"), vec![root.join("src/lib.rs")]);
+
+        index.apply(&WatchEvent::Deleted(root.join("docs")));
+        assert!(index.query("manual").is_empty());
+        assert!(!index.contains(&root.join("docs")));
+
+        index.apply(&WatchEvent::Renamed {
+            from: root.join("src/ui"),
+            to: root.join("src/view"),
+        });
+        assert_eq!(index.query("window"), vec![root.join("src/view/window.rs")]);
+        assert!(index.contains(&root.join("src/view")));
+        assert!(!index.contains(&root.join("src/ui")));
+
+        index.apply(&WatchEvent::Created(PathBuf::from("/elsewhere/file.txt")));
+        assert!(!index.contains(Path::new("/elsewhere/file.txt")));
+        assert_eq!(index.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_save_and_load() {
+        let temp_dir = sample_tree();
+        let root = temp_dir.path().join("src");
+        let index = build_index(&root).await;
+
+        let path = temp_dir.path().join("state").join(INDEX_FILE);
+        index.save(&path).unwrap();
+
+        let loaded = Index::load(&path).unwrap();
+        assert_eq!(loaded.roots(), index.roots());
+        assert_eq!(loaded.query("window"), vec![root.join("ui/window.rs")]);
+        assert!(Index::load(&temp_dir.path().join("missing.json")).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_keep_fresh() {
+        let temp_dir = sample_tree();
+        let root = temp_dir.path();
+        let index = Arc::new(RwLock::new(build_index(root).await));
+        let watcher = Watcher::new(Duration::from_millis(10));
+        let (sender, events) = mpsc::unbounded_channel();
+
+        sender.send(WatchEvent::Deleted(root.join("src/main.rs"))).unwrap();
+        sender.send(WatchEvent::Renamed {
+            from: root.join("docs/manual.md"),
+            to: root.join("docs/guide.md"),
+        }).unwrap();
+        drop(sender);
+
+        keep_fresh(Arc::clone(&index), &watcher, events, CancellationToken::new()).await;
+
+        let index = index.read();
+        assert!(index.query("main").is_empty());
+        assert_eq!(index.query("guide"), vec![root.join("docs/guide.md")]);
+    }
+}
@@ -0,0 +1,7 @@
+pub mod grep;
+pub mod index;
+pub mod recent_queries;
+
+pub use grep::{grep, GrepMatch, GrepOptions};
+pub use index::Index;
+pub use recent_queries::RecentQueries;
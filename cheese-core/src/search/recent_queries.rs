@@ -0,0 +1,177 @@
+use crate::{Error, Result};
+use crate::error::context;
+use crate::fs::ops::LocalFileOps;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use xdg::BaseDirectories;
+
+const HISTORY_FILE: &str = "search_history.json";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct HistoryFile {
+    #[serde(default)]
+    queries: VecDeque<String>,
+}
+
+/// Search bar history, most recent query first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecentQueries {
+    queries: VecDeque<String>,
+    max_size: usize,
+}
+
+impl RecentQueries {
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            queries: VecDeque::with_capacity(max_size),
+            max_size,
+        }
+    }
+
+    pub fn history_path() -> Result<PathBuf> {
+        let xdg_dirs = BaseDirectories::with_prefix("cheese")
+            .map_err(|e| Error::Config(context("Failed to get XDG directories", e)))?;
+        Ok(xdg_dirs.get_data_home().join(HISTORY_FILE))
+    }
+
+    pub fn load(path: &Path, max_size: usize) -> Result<Self> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::new(max_size)),
+            Err(e) => return Err(e.into()),
+        };
+
+        let file: HistoryFile = serde_json::from_str(&contents)
+            .map_err(|e| Error::Config(context("Failed to parse search history", e)))?;
+
+        // Replay oldest first so duplicates and the size limit are applied the
+        // same way as for live queries.
+        let mut recent = Self::new(max_size);
+        for query in file.queries.iter().rev() {
+            recent.record(query);
+        }
+        Ok(recent)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let file = HistoryFile {
+            queries: self.queries.clone(),
+        };
+        let json = serde_json::to_string_pretty(&file)
+            .map_err(|e| Error::Config(context("Failed to serialize search history", e)))?;
+
+        LocalFileOps::atomic_write(path, json.as_bytes())
+    }
+
+    /// Moves `query` to the front, replacing any earlier entry that differs
+    /// only in case.
+    pub fn record(&mut self, query: &str) {
+        let query = query.trim();
+        if query.is_empty() || self.max_size == 0 {
+            return;
+        }
+
+        let lowered = query.to_lowercase();
+        self.queries.retain(|existing| existing.to_lowercase() != lowered);
+        self.queries.push_front(query.to_string());
+        self.queries.truncate(self.max_size);
+    }
+
+    pub fn suggest(&self, prefix: &str) -> Vec<String> {
+        let prefix = prefix.trim().to_lowercase();
+        self.queries
+            .iter()
+            .filter(|query| query.to_lowercase().starts_with(&prefix))
+            .cloned()
+            .collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.queries.clear();
+    }
+
+    pub fn set_max_size(&mut self, max_size: usize) {
+        self.max_size = max_size;
+        self.queries.truncate(max_size);
+    }
+
+    pub fn len(&self) -> usize {
+        self.queries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn recent(queries: &[&str]) -> RecentQueries {
+        let mut recent = RecentQueries::new(20);
+        for query in queries {
+            recent.record(query);
+        }
+        recent
+    }
+
+    #[test]
+    fn test_record_most_recent_first() {
+        let mut recent = RecentQueries::new(3);
+        for query in ["alpha", "beta", "  ", "gamma", "delta"] {
+            recent.record(query);
+        }
+
+        assert_eq!(recent.suggest(""), vec!["delta", "gamma", "beta"]);
+    }
+
+    #[test]
+    fn test_record_deduplicates() {
+        let recent = recent(&["report", "invoice", "Report "]);
+
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent.suggest(""), vec!["Report", "invoice"]);
+    }
+
+    #[test]
+    fn test_suggest_prefix_case_insensitive() {
+        let recent = recent(&["Photos 2023", "podcast", "music", "PHOTOS raw"]);
+
+        assert_eq!(recent.suggest("pho"), vec!["PHOTOS raw", "Photos 2023"]);
+        assert_eq!(recent.suggest("P"), vec!["PHOTOS raw", "podcast", "Photos 2023"]);
+        assert!(recent.suggest("video").is_empty());
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut recent = recent(&["one", "two"]);
+        recent.clear();
+        assert!(recent.is_empty());
+        assert!(recent.suggest("").is_empty());
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("nested").join(HISTORY_FILE);
+
+        assert!(RecentQueries::load(&path, 20).unwrap().is_empty());
+
+        let recent = recent(&["first", "second", "third"]);
+        recent.save(&path).unwrap();
+        assert_eq!(RecentQueries::load(&path, 20).unwrap(), recent);
+
+        let truncated = RecentQueries::load(&path, 2).unwrap();
+        assert_eq!(truncated.suggest(""), vec!["third", "second"]);
+
+        std::fs::write(&path, "not json").unwrap();
+        assert!(matches!(RecentQueries::load(&path, 20), Err(Error::Config(_))));
+    }
+}
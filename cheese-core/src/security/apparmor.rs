@@ -0,0 +1,170 @@
+use crate::{Error, Result};
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfinementStatus {
+    Unconfined,
+    Enforcing(String),
+    Complaining(String),
+}
+
+// Like libapparmor: the module must be enabled in the kernel and its
+// securityfs interface mounted.
+pub fn is_enabled() -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        let module_enabled = std::fs::read_to_string("/sys/module/apparmor/parameters/enabled")
+            .is_ok_and(|enabled| enabled.trim() == "Y");
+
+        module_enabled && unsafe {
+            let result = libc::access(
+                c"/sys/kernel/security/apparmor/profiles".as_ptr(),
+                libc::F_OK,
+            );
+            result == 0
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        false
+    }
+}
+
+// AppArmor has no per-file labels; access to a path is governed by the
+// profile confining the current process.
+#[cfg(target_os = "linux")]
+pub fn get_profile(path: &Path) -> Result<String> {
+    if !is_enabled() {
+        return Err(Error::AppArmor("AppArmor not enabled".to_string()));
+    }
+
+    if !path.exists() {
+        return Err(Error::NotFound { path: path.to_path_buf() });
+    }
+
+    let (label, _) = current_confinement()?;
+    Ok(label)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn get_profile(_path: &Path) -> Result<String> {
+    Err(Error::AppArmor("AppArmor not available".to_string()))
+}
+
+pub fn check_confinement() -> ConfinementStatus {
+    if !is_enabled() {
+        return ConfinementStatus::Unconfined;
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        match current_confinement() {
+            Ok((label, mode)) => match mode.as_deref() {
+                Some("enforce") => ConfinementStatus::Enforcing(label),
+                Some("complain") => ConfinementStatus::Complaining(label),
+                _ => ConfinementStatus::Unconfined,
+            },
+            Err(e) => {
+                tracing::warn!("Failed to query AppArmor confinement: {}", e);
+                ConfinementStatus::Unconfined
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        ConfinementStatus::Unconfined
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn current_confinement() -> Result<(String, Option<String>)> {
+    use std::ffi::CStr;
+    use std::ptr;
+
+    unsafe {
+        let mut label: *mut libc::c_char = ptr::null_mut();
+        let mut mode: *mut libc::c_char = ptr::null_mut();
+        let result = aa_getcon(
+            &mut label as *mut *mut libc::c_char,
+            &mut mode as *mut *mut libc::c_char,
+        );
+
+        if result < 0 {
+            return Err(Error::AppArmor("Failed to get current confinement".to_string()));
+        }
+
+        if label.is_null() {
+            return Err(Error::AppArmor("Null label returned".to_string()));
+        }
+
+        let label_str = CStr::from_ptr(label).to_string_lossy().into_owned();
+        let mode_str = if mode.is_null() {
+            None
+        } else {
+            Some(CStr::from_ptr(mode).to_string_lossy().into_owned())
+        };
+
+        libc::free(label as *mut libc::c_void);
+
+        Ok((label_str, mode_str))
+    }
+}
+
+#[cfg(target_os = "linux")]
+extern "C" {
+    fn aa_getcon(label: *mut *mut libc::c_char, mode: *mut *mut libc::c_char) -> libc::c_int;
+}
+
+pub fn validate_operation(path: &Path) -> Result<()> {
+    if !is_enabled() {
+        return Ok(());
+    }
+
+    match check_confinement() {
+        ConfinementStatus::Enforcing(profile) => {
+            tracing::debug!("AppArmor profile {} enforcing for {}", profile, path.display());
+        }
+        ConfinementStatus::Complaining(profile) => {
+            tracing::warn!(
+                "AppArmor profile {} in complain mode for {}",
+                profile,
+                path.display()
+            );
+        }
+        ConfinementStatus::Unconfined => {}
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apparmor_enabled() {
+        let kernel_enabled = std::fs::read_to_string("/sys/module/apparmor/parameters/enabled")
+            .is_ok_and(|enabled| enabled.trim() == "Y");
+        let securityfs = Path::new("/sys/kernel/security/apparmor/profiles").exists();
+
+        assert_eq!(is_enabled(), kernel_enabled && securityfs);
+    }
+
+    #[test]
+    fn test_check_confinement() {
+        let status = check_confinement();
+        if !is_enabled() {
+            assert_eq!(status, ConfinementStatus::Unconfined);
+        }
+    }
+
+    #[test]
+    fn test_get_profile() {
+        let result = get_profile(Path::new("/tmp"));
+        if !is_enabled() {
+            assert!(result.is_err());
+        }
+    }
+}
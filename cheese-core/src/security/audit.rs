@@ -0,0 +1,213 @@
+use crate::{Error, Result};
+use crate::error::context;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use xdg::BaseDirectories;
+
+const AUDIT_FILE: &str = "audit.log";
+const DEFAULT_MAX_BYTES: u64 = 10 * 1024 * 1024;
+const KEPT_ROTATIONS: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEvent {
+    pub timestamp: SystemTime,
+    pub uid: u32,
+    pub action: String,
+    /// Empty for actions that are not about a single path.
+    pub path: PathBuf,
+    pub success: bool,
+}
+
+impl AuditEvent {
+    /// An event happening now, on behalf of the current user.
+    pub fn new(action: impl Into<String>, path: impl Into<PathBuf>, success: bool) -> Self {
+        Self {
+            timestamp: SystemTime::now(),
+            uid: nix::unistd::getuid().as_raw(),
+            action: action.into(),
+            path: path.into(),
+            success,
+        }
+    }
+}
+
+/// JSON lines file of `AuditEvent`s. Before an append would take it past
+/// `max_bytes` it is renamed to `audit.log.1`, shifting older rotations up
+/// to `audit.log.3` and dropping the one after.
+pub struct AuditLog {
+    path: PathBuf,
+    max_bytes: u64,
+    // Keeps appends and rotations from interleaving.
+    lock: Mutex<()>,
+}
+
+impl AuditLog {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            max_bytes: DEFAULT_MAX_BYTES,
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn default_path() -> Result<PathBuf> {
+        let xdg_dirs = BaseDirectories::with_prefix("cheese")
+            .map_err(|e| Error::Config(context("Failed to get XDG directories", e)))?;
+        Ok(xdg_dirs.get_data_home().join(AUDIT_FILE))
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&self, event: &AuditEvent) -> Result<()> {
+        let mut line = serde_json::to_string(event).map_err(std::io::Error::from)?;
+        line.push('\n');
+
+        let _guard = self.lock.lock();
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let size = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata.len(),
+            Err(e) if e.kind() == ErrorKind::NotFound => 0,
+            Err(e) => return Err(e.into()),
+        };
+        if size > 0 && size + line.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .mode(0o600)
+            .open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    /// Events of the rotated files and the current one, oldest first. Lines
+    /// that do not parse are skipped.
+    pub fn read(&self) -> Result<Vec<AuditEvent>> {
+        let _guard = self.lock.lock();
+        let files = (1..=KEPT_ROTATIONS)
+            .rev()
+            .map(|index| self.rotated_path(index))
+            .chain([self.path.clone()]);
+
+        let mut events = Vec::new();
+        for file in files {
+            let contents = match fs::read_to_string(&file) {
+                Ok(contents) => contents,
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+
+            for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+                match serde_json::from_str(line) {
+                    Ok(event) => events.push(event),
+                    Err(e) => tracing::warn!("Skipping malformed line in {}: {}", file.display(), e),
+                }
+            }
+        }
+
+        Ok(events)
+    }
+
+    fn rotate(&self) -> Result<()> {
+        for index in (1..KEPT_ROTATIONS).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                fs::rename(&from, self.rotated_path(index + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated_path(1))?;
+        Ok(())
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn event(n: usize) -> AuditEvent {
+        AuditEvent::new("delete", format!("/home/user/file{}", n), true)
+    }
+
+    fn line_len() -> u64 {
+        serde_json::to_string(&event(0)).unwrap().len() as u64 + 1
+    }
+
+    #[test]
+    fn test_append_and_read() {
+        let temp_dir = TempDir::new().unwrap();
+        let log = AuditLog::new(temp_dir.path().join("cheese/audit.log"));
+        assert!(log.read().unwrap().is_empty());
+
+        let first = event(0);
+        let second = AuditEvent::new("org.cheese.delete", "", false);
+        log.append(&first).unwrap();
+        log.append(&second).unwrap();
+        std::fs::OpenOptions::new().append(true).open(log.path()).unwrap().write_all(b"not json\n").unwrap();
+
+        assert_eq!(log.read().unwrap(), vec![first, second]);
+        let mode = std::os::unix::fs::PermissionsExt::mode(&std::fs::metadata(log.path()).unwrap().permissions());
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn test_rotation_keeps_last_three() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("audit.log");
+        // Room for two events per file.
+        let log = AuditLog::new(path.clone()).with_max_bytes(line_len() * 2);
+
+        let events: Vec<AuditEvent> = (0..10).map(event).collect();
+        for (n, event) in events.iter().enumerate() {
+            log.append(event).unwrap();
+            if n == 2 {
+                assert_eq!(std::fs::read_to_string(log.rotated_path(1)).unwrap().lines().count(), 2);
+                assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 1);
+            }
+        }
+
+        // The first two events went with the fourth rotation.
+        assert!(!log.rotated_path(4).exists());
+        for index in 1..=3 {
+            assert!(std::fs::metadata(log.rotated_path(index)).unwrap().len() <= line_len() * 2);
+        }
+        assert_eq!(log.read().unwrap(), events[2..].to_vec());
+    }
+
+    #[test]
+    fn test_oversized_event_still_written() {
+        let temp_dir = TempDir::new().unwrap();
+        let log = AuditLog::new(temp_dir.path().join("audit.log")).with_max_bytes(1);
+
+        let events = vec![event(0), event(1)];
+        for event in &events {
+            log.append(event).unwrap();
+        }
+
+        assert_eq!(std::fs::read_to_string(log.path()).unwrap().lines().count(), 1);
+        assert_eq!(log.read().unwrap(), events);
+    }
+}
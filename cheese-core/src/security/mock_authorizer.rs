@@ -0,0 +1,61 @@
+use super::polkit::AuthorizationResult;
+use super::{AuthFuture, Authorizer};
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthCall {
+    Check(String),
+    Request(String),
+}
+
+// Answers every check with the same polkit result and records the calls.
+// Clones share the call log, so a test can keep one after boxing the other.
+#[derive(Clone)]
+pub struct MockAuthorizer {
+    is_authorized: bool,
+    is_challenge: bool,
+    calls: Arc<Mutex<Vec<AuthCall>>>,
+}
+
+impl MockAuthorizer {
+    pub fn granting() -> Self {
+        Self::new(true, false)
+    }
+
+    pub fn denying() -> Self {
+        Self::new(false, false)
+    }
+
+    pub fn challenging() -> Self {
+        Self::new(false, true)
+    }
+
+    fn new(is_authorized: bool, is_challenge: bool) -> Self {
+        Self {
+            is_authorized,
+            is_challenge,
+            calls: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn calls(&self) -> Vec<AuthCall> {
+        self.calls.lock().clone()
+    }
+
+    fn answer(&self, call: AuthCall) -> AuthFuture<'_> {
+        self.calls.lock().push(call);
+        let result = AuthorizationResult::new(self.is_authorized, self.is_challenge);
+        Box::pin(async move { Ok(result) })
+    }
+}
+
+impl Authorizer for MockAuthorizer {
+    fn check_permission<'a>(&'a self, action: &'a str) -> AuthFuture<'a> {
+        self.answer(AuthCall::Check(action.to_string()))
+    }
+
+    fn request_authorization<'a>(&'a self, action: &'a str) -> AuthFuture<'a> {
+        self.answer(AuthCall::Request(action.to_string()))
+    }
+}
@@ -1,31 +1,233 @@
+pub mod apparmor;
+pub mod audit;
+#[cfg(test)]
+pub mod mock_authorizer;
 pub mod polkit;
 pub mod selinux;
 
+use crate::config::SecurityConfig;
 use crate::{Error, Result};
-use std::path::Path;
+use audit::{AuditEvent, AuditLog};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+
+const DEFAULT_AUTH_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+const DEFAULT_PROTECTED_PATHS: &[&str] = &[
+    "/bin",
+    "/boot",
+    "/dev",
+    "/etc",
+    "/lib",
+    "/lib64",
+    "/proc",
+    "/root",
+    "/sbin",
+    "/sys",
+    "/usr/bin",
+    "/usr/sbin",
+    "/usr/lib",
+    "/usr/lib64",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthorizationDecision {
+    Authorized,
+    Denied,
+    Dismissed,
+}
+
+impl AuthorizationDecision {
+    pub fn require(self) -> Result<()> {
+        match self {
+            AuthorizationDecision::Authorized => Ok(()),
+            AuthorizationDecision::Denied => Err(Error::PolkitDenied("Authorization denied".to_string())),
+            AuthorizationDecision::Dismissed => {
+                Err(Error::PolkitDenied("User cancelled authentication".to_string()))
+            }
+        }
+    }
+}
+
+pub type AuthFuture<'a> = Pin<Box<dyn Future<Output = Result<polkit::AuthorizationResult>> + Send + 'a>>;
+
+// The backend Security asks for authorization. Polkit in practice; tests plug in
+// their own so nothing needs a live system bus.
+pub trait Authorizer: Send + Sync {
+    fn check_permission<'a>(&'a self, action: &'a str) -> AuthFuture<'a>;
+    fn request_authorization<'a>(&'a self, action: &'a str) -> AuthFuture<'a>;
+}
 
 pub struct Security {
-    polkit: polkit::PolkitClient,
+    authorizer: Box<dyn Authorizer>,
+    auth_cache: Mutex<AuthCache>,
+    protected_paths: Vec<PathBuf>,
     selinux_enabled: bool,
+    apparmor_enabled: bool,
+    audit_log: Option<AuditLog>,
+}
+
+// Remembers recent grants per action id. Challenges are never stored, since
+// they only mean the user could be authorized after authenticating.
+struct AuthCache {
+    ttl: Duration,
+    granted: HashMap<String, Instant>,
+}
+
+impl AuthCache {
+    fn new(ttl: Duration) -> Self {
+        Self { ttl, granted: HashMap::new() }
+    }
+
+    fn is_granted(&mut self, action: &str, now: Instant) -> bool {
+        match self.granted.get(action) {
+            Some(granted_at) if now.duration_since(*granted_at) < self.ttl => true,
+            Some(_) => {
+                self.granted.remove(action);
+                false
+            }
+            None => false,
+        }
+    }
+
+    fn record(&mut self, action: &str, result: &polkit::AuthorizationResult, now: Instant) {
+        if result.is_authorized() && !result.is_challenge() {
+            self.granted.insert(action.to_string(), now);
+        } else {
+            self.granted.remove(action);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.granted.clear();
+    }
 }
 
 impl Security {
     pub fn new() -> Result<Self> {
-        let polkit = polkit::PolkitClient::new()?;
+        let security = Self::with_authorizer(Box::new(polkit::PolkitClient::new()?));
+        Ok(match AuditLog::default_path() {
+            Ok(path) => security.with_audit_log(AuditLog::new(path)),
+            Err(e) => {
+                tracing::warn!("Security-sensitive operations will not be audited: {}", e);
+                security
+            }
+        })
+    }
+
+    /// Polkit-backed security that protects the paths listed in the
+    /// `[security]` section of the configuration.
+    pub fn from_config(config: &SecurityConfig) -> Result<Self> {
+        Ok(Self::new()?.with_protected_paths(config.protected_paths.clone()))
+    }
+
+    pub fn with_authorizer(authorizer: Box<dyn Authorizer>) -> Self {
         let selinux_enabled = selinux::is_enabled();
+        let apparmor_enabled = !selinux_enabled && apparmor::is_enabled();
 
-        Ok(Self {
-            polkit,
+        Self {
+            authorizer,
+            auth_cache: Mutex::new(AuthCache::new(DEFAULT_AUTH_CACHE_TTL)),
+            protected_paths: default_protected_paths(),
             selinux_enabled,
-        })
+            apparmor_enabled,
+            audit_log: None,
+        }
+    }
+
+    /// `Security::new` logs to `AuditLog::default_path()`; without a log,
+    /// events are dropped.
+    pub fn with_audit_log(mut self, audit_log: AuditLog) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
+    pub fn with_auth_cache_ttl(self, ttl: Duration) -> Self {
+        *self.auth_cache.lock() = AuthCache::new(ttl);
+        self
+    }
+
+    pub fn with_protected_paths(mut self, paths: Vec<PathBuf>) -> Self {
+        self.protected_paths = paths;
+        self
+    }
+
+    pub fn is_protected_path(&self, path: &Path) -> bool {
+        is_protected(path, &self.protected_paths)
+    }
+
+    pub fn validate_symlink_target(&self, link: &Path, target: &Path) -> Result<()> {
+        if target.is_absolute() && self.is_protected_path(target) {
+            tracing::warn!("Symlink points to protected path: {} -> {}",
+                link.display(), target.display());
+        }
+
+        Ok(())
     }
 
     pub async fn check_permission(&self, action: &str) -> Result<bool> {
-        self.polkit.check_authorization(action).await
+        if self.auth_cache.lock().is_granted(action, Instant::now()) {
+            return Ok(true);
+        }
+
+        let result = self.authorizer.check_permission(action).await?;
+        self.auth_cache.lock().record(action, &result, Instant::now());
+        Ok(result.is_authorized())
     }
 
     pub async fn request_authorization(&self, action: &str) -> Result<bool> {
-        self.polkit.request_authorization(action).await
+        let granted = self.request_authorization_cached(action).await;
+        self.record_audit(action, Path::new(""), matches!(granted, Ok(true)));
+        granted
+    }
+
+    async fn request_authorization_cached(&self, action: &str) -> Result<bool> {
+        if self.auth_cache.lock().is_granted(action, Instant::now()) {
+            return Ok(true);
+        }
+
+        let result = self.authorizer.request_authorization(action).await?;
+        self.auth_cache.lock().record(action, &result, Instant::now());
+        result.granted()
+    }
+
+    // One interactive check covers the whole batch, so the user is prompted at
+    // most once however many paths are involved.
+    pub async fn authorize_operation(&self, action: &str, paths: &[PathBuf]) -> Result<AuthorizationDecision> {
+        if paths.is_empty() || self.auth_cache.lock().is_granted(action, Instant::now()) {
+            return Ok(AuthorizationDecision::Authorized);
+        }
+
+        tracing::debug!("Requesting {} for {} paths", action, paths.len());
+        let result = self.authorizer.request_authorization(action).await?;
+        self.auth_cache.lock().record(action, &result, Instant::now());
+
+        Ok(if result.is_authorized() {
+            AuthorizationDecision::Authorized
+        } else if result.is_challenge() {
+            AuthorizationDecision::Dismissed
+        } else {
+            AuthorizationDecision::Denied
+        })
+    }
+
+    /// Requests `action` once if any of `paths` is protected. Operations that
+    /// only touch unprotected paths go ahead without asking polkit.
+    pub async fn authorize_protected(&self, action: &str, paths: &[PathBuf]) -> Result<()> {
+        let protected: Vec<PathBuf> = paths.iter()
+            .filter(|path| self.is_protected_path(path))
+            .cloned()
+            .collect();
+
+        self.authorize_operation(action, &protected).await?.require()
+    }
+
+    pub fn invalidate_auth_cache(&self) {
+        self.auth_cache.lock().clear();
     }
 
     pub fn check_selinux_context(&self, path: &Path) -> Result<()> {
@@ -40,19 +242,52 @@ impl Security {
         self.selinux_enabled
     }
 
+    pub fn is_apparmor_enabled(&self) -> bool {
+        self.apparmor_enabled
+    }
+
+    pub fn audit_log(&self, event: AuditEvent) -> Result<()> {
+        match &self.audit_log {
+            Some(audit_log) => audit_log.append(&event),
+            None => Ok(()),
+        }
+    }
+
+    pub fn read_audit_log(&self) -> Result<Vec<AuditEvent>> {
+        match &self.audit_log {
+            Some(audit_log) => audit_log.read(),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    // An audit failure must not change the outcome of what was audited.
+    pub(crate) fn record_audit(&self, action: &str, path: &Path, success: bool) {
+        if let Err(e) = self.audit_log(AuditEvent::new(action, path, success)) {
+            tracing::warn!("Failed to write audit log for {} on {}: {}", action, path.display(), e);
+        }
+    }
+
     pub fn validate_safe_operation(&self, path: &Path) -> Result<()> {
+        let validated = self.check_safe_operation(path);
+        self.record_audit("validate_safe_operation", path, validated.is_ok());
+        validated
+    }
+
+    fn check_safe_operation(&self, path: &Path) -> Result<()> {
         if is_running_as_root() {
             return Err(Error::InvalidOperation(
                 "Cheese must not be run as root".to_string()
             ));
         }
 
-        if is_system_path(path) {
+        if self.is_protected_path(path) {
             return Err(Error::PermissionDenied { path: path.to_path_buf() });
         }
 
         if self.selinux_enabled {
             self.check_selinux_context(path)?;
+        } else if self.apparmor_enabled {
+            apparmor::validate_operation(path)?;
         }
 
         Ok(())
@@ -78,53 +313,198 @@ pub fn is_running_as_root() -> bool {
     }
 }
 
-pub fn is_system_path(path: &Path) -> bool {
-    let system_paths = [
-        "/bin",
-        "/boot",
-        "/dev",
-        "/etc",
-        "/lib",
-        "/lib64",
-        "/proc",
-        "/root",
-        "/sbin",
-        "/sys",
-        "/usr/bin",
-        "/usr/sbin",
-        "/usr/lib",
-        "/usr/lib64",
-    ];
-
-    for system_path in &system_paths {
-        if path.starts_with(system_path) {
-            return true;
-        }
-    }
-
-    false
+pub fn default_protected_paths() -> Vec<PathBuf> {
+    DEFAULT_PROTECTED_PATHS.iter().map(PathBuf::from).collect()
 }
 
-pub fn validate_symlink_target(link: &Path, target: &Path) -> Result<()> {
-    if target.is_absolute() {
-        if is_system_path(target) {
-            tracing::warn!("Symlink points to system path: {} -> {}", 
-                link.display(), target.display());
-        }
+// Checks the path as given and with symlinks resolved, so a link pointing into
+// /etc is caught too. For a path that does not exist yet, the nearest existing
+// ancestor is resolved instead.
+fn is_protected(path: &Path, protected: &[PathBuf]) -> bool {
+    if protected.iter().any(|p| path.starts_with(p)) {
+        return true;
     }
 
-    Ok(())
+    resolve_existing(path).is_some_and(|real| protected.iter().any(|p| real.starts_with(p)))
+}
+
+fn resolve_existing(path: &Path) -> Option<PathBuf> {
+    let mut missing = Vec::new();
+    let mut current = path;
+
+    loop {
+        if let Ok(real) = current.canonicalize() {
+            return Some(missing.iter().rev().fold(real, |acc: PathBuf, name| acc.join(name)));
+        }
+        missing.push(current.file_name()?);
+        current = current.parent()?;
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use mock_authorizer::{AuthCall, MockAuthorizer};
 
     #[test]
     fn test_system_path_detection() {
-        assert!(is_system_path(Path::new("/bin/ls")));
-        assert!(is_system_path(Path::new("/etc/passwd")));
-        assert!(!is_system_path(Path::new("/home/user/file.txt")));
-        assert!(!is_system_path(Path::new("/tmp/test")));
+        let security = Security::with_authorizer(Box::new(MockAuthorizer::granting()));
+
+        assert!(security.is_protected_path(Path::new("/bin/ls")));
+        assert!(security.is_protected_path(Path::new("/etc/passwd")));
+        assert!(!security.is_protected_path(Path::new("/home/user/file.txt")));
+        assert!(!security.is_protected_path(Path::new("/tmp/test")));
+    }
+
+    #[test]
+    fn test_custom_protected_paths() {
+        let security = Security::with_authorizer(Box::new(MockAuthorizer::granting()))
+            .with_protected_paths(vec![PathBuf::from("/nix/store"), PathBuf::from("/opt")]);
+
+        assert!(security.is_protected_path(Path::new("/nix/store/abc-hello/bin/hello")));
+        assert!(security.is_protected_path(Path::new("/opt/app")));
+        assert!(!security.is_protected_path(Path::new("/etc/passwd")));
+        assert!(!security.is_protected_path(Path::new("/nix/var")));
+    }
+
+    #[test]
+    fn test_configured_paths_reach_symlink_checks() {
+        let config = SecurityConfig { protected_paths: vec![PathBuf::from("/srv/data")] };
+        let security = Security::with_authorizer(Box::new(MockAuthorizer::granting()))
+            .with_protected_paths(config.protected_paths.clone());
+
+        assert!(security.is_protected_path(Path::new("/srv/data/db")));
+        assert!(!security.is_protected_path(Path::new("/etc/passwd")));
+        assert!(security.validate_symlink_target(Path::new("/tmp/link"), Path::new("/srv/data/db")).is_ok());
+    }
+
+    #[test]
+    fn test_symlinks_into_protected_paths() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let protected = temp_dir.path().join("protected");
+        std::fs::create_dir(&protected).unwrap();
+        std::os::unix::fs::symlink(&protected, temp_dir.path().join("link")).unwrap();
+        std::os::unix::fs::symlink("/etc", temp_dir.path().join("etc-link")).unwrap();
+
+        let security = Security::with_authorizer(Box::new(MockAuthorizer::granting()))
+            .with_protected_paths(vec![protected.canonicalize().unwrap()]);
+
+        assert!(security.is_protected_path(&temp_dir.path().join("link/file.txt")));
+        assert!(security.is_protected_path(&temp_dir.path().join("link/new/dir")));
+        assert!(!security.is_protected_path(&temp_dir.path().join("elsewhere")));
+        assert!(!security.is_protected_path(&temp_dir.path().join("etc-link/passwd")));
+
+        let defaults = Security::with_authorizer(Box::new(MockAuthorizer::granting()));
+        assert!(defaults.is_protected_path(&temp_dir.path().join("etc-link/passwd")));
+    }
+
+    #[test]
+    fn test_decision_require() {
+        assert!(AuthorizationDecision::Authorized.require().is_ok());
+        assert!(matches!(AuthorizationDecision::Denied.require(), Err(Error::PolkitDenied(_))));
+        assert!(matches!(AuthorizationDecision::Dismissed.require(), Err(Error::PolkitDenied(_))));
+    }
+
+    #[tokio::test]
+    async fn test_security_caches_grants() {
+        let authorizer = MockAuthorizer::granting();
+        let security = Security::with_authorizer(Box::new(authorizer.clone()));
+
+        assert!(security.check_permission(polkit::ACTION_MODIFY).await.unwrap());
+        assert!(security.request_authorization(polkit::ACTION_MODIFY).await.unwrap());
+        assert_eq!(authorizer.calls(), vec![AuthCall::Check(polkit::ACTION_MODIFY.to_string())]);
+
+        security.invalidate_auth_cache();
+        assert!(security.request_authorization(polkit::ACTION_MODIFY).await.unwrap());
+        assert_eq!(authorizer.calls().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_sensitive_operations_are_audited() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let security = Security::with_authorizer(Box::new(MockAuthorizer::denying()))
+            .with_protected_paths(vec![PathBuf::from("/srv")])
+            .with_audit_log(AuditLog::new(temp_dir.path().join("audit.log")));
+
+        assert!(security.validate_safe_operation(Path::new("/srv/data")).is_err());
+        assert!(matches!(security.request_authorization(polkit::ACTION_MODIFY).await, Err(Error::PolkitDenied(_))));
+
+        let events: Vec<(String, PathBuf, bool)> = security.read_audit_log()
+            .unwrap()
+            .into_iter()
+            .map(|event| (event.action, event.path, event.success))
+            .collect();
+        assert_eq!(events, vec![
+            ("validate_safe_operation".to_string(), PathBuf::from("/srv/data"), false),
+            (polkit::ACTION_MODIFY.to_string(), PathBuf::new(), false),
+        ]);
+
+        let unaudited = Security::with_authorizer(Box::new(MockAuthorizer::granting()));
+        unaudited.audit_log(AuditEvent::new("delete", "/tmp/a", true)).unwrap();
+        assert!(unaudited.read_audit_log().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_security_does_not_cache_challenges() {
+        let authorizer = MockAuthorizer::challenging();
+        let security = Security::with_authorizer(Box::new(authorizer.clone()));
+        let paths = vec![PathBuf::from("/srv/data")];
+
+        for _ in 0..2 {
+            let decision = security.authorize_operation(polkit::ACTION_DELETE, &paths).await.unwrap();
+            assert_eq!(decision, AuthorizationDecision::Dismissed);
+        }
+        assert_eq!(authorizer.calls().len(), 2);
+
+        let decision = security.authorize_operation(polkit::ACTION_DELETE, &[]).await.unwrap();
+        assert_eq!(decision, AuthorizationDecision::Authorized);
+        assert_eq!(authorizer.calls().len(), 2);
+    }
+
+    fn result(is_authorized: bool, is_challenge: bool) -> polkit::AuthorizationResult {
+        polkit::AuthorizationResult::new(is_authorized, is_challenge)
+    }
+
+    #[test]
+    fn test_auth_cache_hit_and_expiry() {
+        let mut cache = AuthCache::new(Duration::from_secs(60));
+        let start = Instant::now();
+
+        assert!(!cache.is_granted(polkit::ACTION_DELETE, start));
+        cache.record(polkit::ACTION_DELETE, &result(true, false), start);
+
+        assert!(cache.is_granted(polkit::ACTION_DELETE, start + Duration::from_secs(59)));
+        assert!(!cache.is_granted(polkit::ACTION_MODIFY, start));
+        assert!(!cache.is_granted(polkit::ACTION_DELETE, start + Duration::from_secs(60)));
+        assert!(cache.granted.is_empty());
+    }
+
+    #[test]
+    fn test_auth_cache_skips_challenges_and_denials() {
+        let mut cache = AuthCache::new(Duration::from_secs(60));
+        let now = Instant::now();
+
+        cache.record(polkit::ACTION_MOUNT, &result(false, true), now);
+        cache.record(polkit::ACTION_UNMOUNT, &result(false, false), now);
+        assert!(!cache.is_granted(polkit::ACTION_MOUNT, now));
+        assert!(!cache.is_granted(polkit::ACTION_UNMOUNT, now));
+
+        // A later denial revokes an earlier grant.
+        cache.record(polkit::ACTION_DELETE, &result(true, false), now);
+        cache.record(polkit::ACTION_DELETE, &result(false, true), now);
+        assert!(!cache.is_granted(polkit::ACTION_DELETE, now));
+    }
+
+    #[test]
+    fn test_auth_cache_clear() {
+        let mut cache = AuthCache::new(Duration::from_secs(60));
+        let now = Instant::now();
+
+        cache.record(polkit::ACTION_DELETE, &result(true, false), now);
+        cache.record(polkit::ACTION_MODIFY, &result(true, false), now);
+        cache.clear();
+
+        assert!(!cache.is_granted(polkit::ACTION_DELETE, now));
+        assert!(!cache.is_granted(polkit::ACTION_MODIFY, now));
     }
 }
@@ -1,4 +1,6 @@
+use super::{AuthFuture, Authorizer};
 use crate::{Error, Result};
+use crate::error::context;
 use zbus::{Connection, proxy};
 use std::collections::HashMap;
 
@@ -34,6 +36,31 @@ pub struct AuthorizationResult {
     details: HashMap<String, String>,
 }
 
+impl AuthorizationResult {
+    #[cfg(test)]
+    pub(crate) fn new(is_authorized: bool, is_challenge: bool) -> Self {
+        Self { is_authorized, is_challenge, details: HashMap::new() }
+    }
+
+    pub fn is_authorized(&self) -> bool {
+        self.is_authorized
+    }
+
+    pub fn is_challenge(&self) -> bool {
+        self.is_challenge
+    }
+
+    pub fn granted(&self) -> Result<bool> {
+        if self.is_authorized {
+            Ok(true)
+        } else if self.is_challenge {
+            Err(Error::PolkitDenied("User cancelled authentication".to_string()))
+        } else {
+            Err(Error::PolkitDenied("Authorization denied".to_string()))
+        }
+    }
+}
+
 pub struct PolkitClient {
     connection: Connection,
 }
@@ -42,47 +69,45 @@ impl PolkitClient {
     pub fn new() -> Result<Self> {
         Ok(Self {
             connection: Connection::system()
-                .map_err(|e| Error::DBus(format!("Failed to connect to system bus: {}", e)))?,
+                .map_err(|e| Error::DBus(context("Failed to connect to system bus", e)))?,
         })
     }
 
     pub async fn check_authorization(&self, action: &str) -> Result<bool> {
+        Ok(self.query_authorization(action).await?.is_authorized)
+    }
+
+    pub async fn query_authorization(&self, action: &str) -> Result<AuthorizationResult> {
         let proxy = PolkitAuthorityProxy::new(&self.connection)
             .await
-            .map_err(|e| Error::DBus(format!("Failed to create proxy: {}", e)))?;
+            .map_err(|e| Error::DBus(context("Failed to create proxy", e)))?;
 
         let subject = self.get_current_subject()?;
         let details = HashMap::new();
 
-        let result = proxy
+        proxy
             .check_authorization(subject, action, details, 0, "")
             .await
-            .map_err(|e| Error::DBus(format!("Authorization check failed: {}", e)))?;
-
-        Ok(result.is_authorized)
+            .map_err(|e| Error::DBus(context("Authorization check failed", e)))
     }
 
     pub async fn request_authorization(&self, action: &str) -> Result<bool> {
+        self.request_authorization_result(action).await?.granted()
+    }
+
+    pub async fn request_authorization_result(&self, action: &str) -> Result<AuthorizationResult> {
         let proxy = PolkitAuthorityProxy::new(&self.connection)
             .await
-            .map_err(|e| Error::DBus(format!("Failed to create proxy: {}", e)))?;
+            .map_err(|e| Error::DBus(context("Failed to create proxy", e)))?;
 
         let subject = self.get_current_subject()?;
         let details = HashMap::new();
         let flags = 1;
 
-        let result = proxy
+        proxy
             .check_authorization(subject, action, details, flags, "")
             .await
-            .map_err(|e| Error::PolkitDenied(format!("Authorization request failed: {}", e)))?;
-
-        if result.is_authorized {
-            Ok(true)
-        } else if result.is_challenge {
-            Err(Error::PolkitDenied("User cancelled authentication".to_string()))
-        } else {
-            Err(Error::PolkitDenied("Authorization denied".to_string()))
-        }
+            .map_err(|e| Error::PolkitDenied(format!("Authorization request failed: {}", e)))
     }
 
     fn get_current_subject(&self) -> Result<Subject> {
@@ -109,11 +134,21 @@ impl PolkitClient {
 
         #[cfg(not(unix))]
         {
-            Err(Error::DBus("Polkit not supported on this platform".to_string()))
+            Err(Error::DBus("Polkit not supported on this platform".into()))
         }
     }
 }
 
+impl Authorizer for PolkitClient {
+    fn check_permission<'a>(&'a self, action: &'a str) -> AuthFuture<'a> {
+        Box::pin(self.query_authorization(action))
+    }
+
+    fn request_authorization<'a>(&'a self, action: &'a str) -> AuthFuture<'a> {
+        Box::pin(self.request_authorization_result(action))
+    }
+}
+
 pub const ACTION_DELETE: &str = "org.ratos.cheese.delete";
 pub const ACTION_MODIFY: &str = "org.ratos.cheese.modify";
 pub const ACTION_MOUNT: &str = "org.ratos.cheese.mount";
@@ -1,22 +1,45 @@
 use crate::{Error, Result};
+use crate::error::context;
+use crate::blocking;
+use crate::config::SortOrder;
+use crate::fs::ops::{ConflictResolution, CopyOptions, LocalFileOps, OperationProgress, SelinuxLabeling};
+use crate::fs::watcher::{WatchEvent, Watcher};
 use std::path::{Path, PathBuf};
 use std::fs;
-use std::time::SystemTime;
-use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
 use xdg::BaseDirectories;
 
+const REMOVAL_BATCH_WINDOW: Duration = Duration::from_millis(200);
+const INFO_READ_RETRIES: u32 = 5;
+const INFO_READ_RETRY_DELAY: Duration = Duration::from_millis(20);
+// Octal mode of the trashed item's parent directory, recorded in .trashinfo.
+const PARENT_MODE_KEY: &str = "X-Cheese-ParentMode";
+
+#[derive(Clone)]
 pub struct Trash {
     trash_dir: PathBuf,
     files_dir: PathBuf,
     info_dir: PathBuf,
+    // Bumped by each `empty_trash`, so `watch` can tell an emptied trash from
+    // items that were deleted one by one.
+    empties: Arc<AtomicU64>,
 }
 
 impl Trash {
     pub fn new() -> Result<Self> {
         let xdg_dirs = BaseDirectories::new()
-            .map_err(|e| Error::TrashError(format!("Failed to get XDG directories: {}", e)))?;
+            .map_err(|e| Error::TrashError(context("Failed to get XDG directories", e)))?;
         
-        let trash_dir = xdg_dirs.get_data_home().join("Trash");
+        Self::with_dir(xdg_dirs.get_data_home().join("Trash"))
+    }
+
+    pub fn with_dir(trash_dir: PathBuf) -> Result<Self> {
         let files_dir = trash_dir.join("files");
         let info_dir = trash_dir.join("info");
 
@@ -27,11 +50,12 @@ impl Trash {
             trash_dir,
             files_dir,
             info_dir,
+            empties: Arc::new(AtomicU64::new(0)),
         })
     }
 
     pub fn send_to_trash(&self, path: &Path) -> Result<()> {
-        if !path.exists() {
+        if path.symlink_metadata().is_err() {
             return Err(Error::NotFound { path: path.to_path_buf() });
         }
 
@@ -44,44 +68,148 @@ impl Trash {
         let trash_file_path = self.files_dir.join(&unique_name);
         let trash_info_path = self.info_dir.join(format!("{}.trashinfo", unique_name));
 
-        let original_path = path.canonicalize()?;
+        let original_path = original_path(path)?;
         let deletion_date = SystemTime::now();
 
         self.create_trash_info(&trash_info_path, &original_path, deletion_date)?;
 
         fs::rename(path, &trash_file_path).map_err(|e| {
             let _ = fs::remove_file(&trash_info_path);
-            Error::TrashError(format!("Failed to move file to trash: {}", e))
+            Error::TrashError(context("Failed to move file to trash", e))
         })?;
 
         Ok(())
     }
 
+    pub async fn move_to_trash_async(
+        &self,
+        path: &Path,
+        cancel: CancellationToken,
+        progress: mpsc::Sender<OperationProgress>,
+    ) -> Result<()> {
+        if cancel.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+
+        if self.is_same_trash_filesystem(path).await? {
+            let trash = self.clone();
+            let path = path.to_path_buf();
+            return blocking::spawn_on(None, move || trash.send_to_trash(&path)).await?;
+        }
+
+        self.copy_to_trash(path, cancel, progress).await
+    }
+
+    pub async fn is_same_trash_filesystem(&self, path: &Path) -> Result<bool> {
+        use std::os::unix::fs::MetadataExt;
+
+        let metadata = tokio::fs::symlink_metadata(path).await.map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => Error::NotFound { path: path.to_path_buf() },
+            _ => Error::Io(e),
+        })?;
+        Ok(metadata.dev() == tokio::fs::metadata(&self.files_dir).await?.dev())
+    }
+
+    // Cross-filesystem fallback: copy into a staging directory next to the
+    // trash so the final rename into files/ stays atomic, then delete the source.
+    async fn copy_to_trash(
+        &self,
+        path: &Path,
+        cancel: CancellationToken,
+        progress: mpsc::Sender<OperationProgress>,
+    ) -> Result<()> {
+        let file_name = path.file_name()
+            .ok_or_else(|| Error::InvalidPath { path: path.to_path_buf() })?
+            .to_os_string();
+
+        let trash = self.clone();
+        let source = path.to_path_buf();
+        let staged_name = file_name.clone();
+        let (trash_file_path, trash_info_path, original_path, staging_dir) = blocking::spawn_on(None, move || {
+            let unique_name = trash.find_unique_trash_name(&staged_name.to_string_lossy())?;
+            let staging_dir = trash.trash_dir.join(format!(".staging-{}", unique_name));
+            fs::create_dir_all(&staging_dir)?;
+
+            Ok::<_, Error>((
+                trash.files_dir.join(&unique_name),
+                trash.info_dir.join(format!("{}.trashinfo", unique_name)),
+                original_path(&source)?,
+                staging_dir,
+            ))
+        }).await??;
+
+        let ops = LocalFileOps::new(1);
+        let copied = ops.copy_files(
+            vec![path.to_path_buf()],
+            staging_dir.clone(),
+            ConflictResolution::Overwrite,
+            CopyOptions {
+                preserve_xattrs: true,
+                selinux: SelinuxLabeling::PreserveSource,
+                ..CopyOptions::default()
+            },
+            progress.clone(),
+            cancel.clone(),
+        ).await;
+
+        let trash = self.clone();
+        let staged = blocking::spawn_on(None, move || {
+            let staged = copied.and_then(|_| {
+                trash.create_trash_info(&trash_info_path, &original_path, SystemTime::now())?;
+                fs::rename(staging_dir.join(file_name), &trash_file_path).map_err(|e| {
+                    let _ = fs::remove_file(&trash_info_path);
+                    Error::TrashError(context("Failed to move file to trash", e))
+                })
+            });
+            let _ = fs::remove_dir_all(&staging_dir);
+            staged
+        }).await?;
+        staged?;
+
+        ops.delete_files(vec![path.to_path_buf()], None, progress, cancel).await
+    }
+
+    /// Moves a trashed file or directory tree back to its original path.
+    /// Missing parent directories are recreated; the direct parent gets back
+    /// the mode it had when the item was trashed.
     pub fn restore(&self, trash_name: &str) -> Result<PathBuf> {
-        let trash_file_path = self.files_dir.join(trash_name);
-        let trash_info_path = self.info_dir.join(format!("{}.trashinfo", trash_name));
+        let (trash_file_path, trash_info_path) = self.trashed_paths(trash_name)?;
 
-        if !trash_file_path.exists() {
+        if trash_file_path.symlink_metadata().is_err() {
             return Err(Error::NotFound { path: trash_file_path });
         }
 
         let original_path = self.read_trash_info(&trash_info_path)?;
 
-        if original_path.exists() {
+        if original_path.symlink_metadata().is_ok() {
             return Err(Error::AlreadyExists { path: original_path });
         }
 
-        if let Some(parent) = original_path.parent() {
-            fs::create_dir_all(parent)?;
-        }
+        let recreated_parent = match original_path.parent() {
+            Some(parent) if !parent.exists() => {
+                fs::create_dir_all(parent)?;
+                Some(parent)
+            }
+            _ => None,
+        };
+        let parent_mode = self.read_parent_mode(&trash_info_path);
 
         fs::rename(&trash_file_path, &original_path)?;
         fs::remove_file(&trash_info_path)?;
 
+        // Applied after the rename, since the original mode may not allow
+        // writing into the directory.
+        if let (Some(parent), Some(mode)) = (recreated_parent, parent_mode) {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(parent, fs::Permissions::from_mode(mode))?;
+        }
+
         Ok(original_path)
     }
 
     pub fn empty_trash(&self) -> Result<()> {
+        self.empties.fetch_add(1, Ordering::SeqCst);
+
         for entry in fs::read_dir(&self.files_dir)? {
             let entry = entry?;
             let path = entry.path();
@@ -102,48 +230,195 @@ impl Trash {
     }
 
     pub fn list_trash_items(&self) -> Result<Vec<TrashItem>> {
-        let mut items = Vec::new();
+        self.info_paths()?
+            .iter()
+            .map(|path| self.read_trash_item(path))
+            .collect()
+    }
 
-        for entry in fs::read_dir(&self.info_dir)? {
-            let entry = entry?;
-            let path = entry.path();
+    // Sizes are only computed for the returned page, unless the listing is sorted by
+    // size; items outside the page never have their trashed contents walked.
+    pub fn list_trash_items_page(&self, offset: usize, limit: usize, sort: TrashSort) -> Result<Vec<TrashItem>> {
+        let by_size = sort.key == TrashSortKey::Size;
 
-            if path.extension().and_then(|e| e.to_str()) != Some("trashinfo") {
-                continue;
+        let mut items = self.info_paths()?
+            .iter()
+            .map(|path| self.read_trash_item_with_size(path, by_size))
+            .collect::<Result<Vec<_>>>()?;
+        sort_trash_items(&mut items, sort);
+
+        let mut page: Vec<TrashItem> = items.into_iter().skip(offset).take(limit).collect();
+        if sort.include_sizes && !by_size {
+            for item in &mut page {
+                item.size = self.trashed_size(&item.trash_name)?;
             }
+        }
 
-            let trash_name = path.file_stem()
-                .and_then(|s| s.to_str())
-                .ok_or_else(|| Error::TrashError("Invalid trash info file".to_string()))?
-                .to_string();
+        Ok(page)
+    }
+
+    fn info_paths(&self) -> Result<Vec<PathBuf>> {
+        let mut paths = Vec::new();
+
+        for entry in fs::read_dir(&self.info_dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) == Some("trashinfo") {
+                paths.push(path);
+            }
+        }
+
+        Ok(paths)
+    }
+
+    pub async fn watch(&self, sender: mpsc::Sender<TrashEvent>, cancel: CancellationToken) -> Result<()> {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let watcher = Watcher::new(Duration::ZERO);
+        watcher.start(tx)?;
+        watcher.watch(&self.info_dir)?;
 
-            let original_path = self.read_trash_info(&path)?;
-            let deletion_date = self.read_deletion_date(&path)?;
-            let trash_file_path = self.files_dir.join(&trash_name);
+        let mut known: HashMap<String, TrashItem> = self.list_trash_items()?
+            .into_iter()
+            .map(|item| (item.trash_name.clone(), item))
+            .collect();
+        let mut removed = Vec::new();
+        let mut empties_seen = self.empties.load(Ordering::SeqCst);
 
-            let size = if trash_file_path.exists() {
-                self.get_size_recursive(&trash_file_path)?
+        loop {
+            let event = if removed.is_empty() {
+                tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    event = rx.recv() => event,
+                }
             } else {
-                0
+                tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    event = tokio::time::timeout(REMOVAL_BATCH_WINDOW, rx.recv()) => match event {
+                        Ok(event) => event,
+                        Err(_) => {
+                            // Removals only read as one Emptied event when
+                            // empty_trash ran and left nothing behind.
+                            let empties = self.empties.load(Ordering::SeqCst);
+                            let emptied = known.is_empty() && empties != empties_seen;
+                            if emptied {
+                                empties_seen = empties;
+                            }
+
+                            Self::flush_removed(&mut removed, emptied, &sender).await?;
+                            continue;
+                        }
+                    },
+                }
             };
 
-            items.push(TrashItem {
-                trash_name,
-                original_path,
-                deletion_date,
-                size,
-            });
+            let Some(event) = event else {
+                break;
+            };
+
+            let changes = match event {
+                WatchEvent::Created(path) | WatchEvent::Modified(path) => vec![(true, path)],
+                WatchEvent::Deleted(path) => vec![(false, path)],
+                WatchEvent::Renamed { from, to } => vec![(false, from), (true, to)],
+                WatchEvent::AttributesChanged(_) => vec![],
+            };
+
+            for (added, path) in changes {
+                let Some(trash_name) = trash_name_from_info(&path) else {
+                    continue;
+                };
+
+                if added {
+                    if known.contains_key(&trash_name) {
+                        continue;
+                    }
+
+                    match self.read_trash_item_with_retry(&path).await {
+                        Ok(item) => {
+                            known.insert(trash_name, item.clone());
+                            sender.send(TrashEvent {
+                                kind: TrashEventKind::ItemAdded,
+                                item: Some(item),
+                            }).await.map_err(|_| Error::Cancelled)?;
+                        }
+                        Err(e) => {
+                            tracing::warn!("Failed to read trash info {:?}: {}", path, e);
+                        }
+                    }
+                } else if let Some(item) = known.remove(&trash_name) {
+                    removed.push(item);
+                }
+            }
         }
 
-        Ok(items)
+        watcher.stop();
+        Ok(())
+    }
+
+    async fn flush_removed(
+        removed: &mut Vec<TrashItem>,
+        emptied: bool,
+        sender: &mpsc::Sender<TrashEvent>,
+    ) -> Result<()> {
+        let batch = std::mem::take(removed);
+
+        if emptied {
+            return sender.send(TrashEvent {
+                kind: TrashEventKind::Emptied,
+                item: None,
+            }).await.map_err(|_| Error::Cancelled);
+        }
+
+        for item in batch {
+            sender.send(TrashEvent {
+                kind: TrashEventKind::ItemRemoved,
+                item: Some(item),
+            }).await.map_err(|_| Error::Cancelled)?;
+        }
+
+        Ok(())
+    }
+
+    async fn read_trash_item_with_retry(&self, info_path: &Path) -> Result<TrashItem> {
+        let mut attempt = 0;
+
+        loop {
+            match self.read_trash_item(info_path) {
+                Ok(item) => return Ok(item),
+                Err(e) if attempt >= INFO_READ_RETRIES => return Err(e),
+                Err(_) => {
+                    attempt += 1;
+                    tokio::time::sleep(INFO_READ_RETRY_DELAY).await;
+                }
+            }
+        }
+    }
+
+    fn read_trash_item(&self, info_path: &Path) -> Result<TrashItem> {
+        self.read_trash_item_with_size(info_path, true)
+    }
+
+    fn read_trash_item_with_size(&self, info_path: &Path, with_size: bool) -> Result<TrashItem> {
+        let trash_name = trash_name_from_info(info_path)
+            .ok_or_else(|| Error::TrashError("Invalid trash info file".into()))?;
+
+        let original_path = self.read_trash_info(info_path)?;
+        let deletion_date = self.read_deletion_date(info_path)?;
+        let size = if with_size { self.trashed_size(&trash_name)? } else { 0 };
+
+        Ok(TrashItem {
+            trash_name,
+            original_path,
+            deletion_date,
+            size,
+        })
     }
 
     pub fn permanently_delete(&self, trash_name: &str) -> Result<()> {
-        let trash_file_path = self.files_dir.join(trash_name);
-        let trash_info_path = self.info_dir.join(format!("{}.trashinfo", trash_name));
+        let (trash_file_path, trash_info_path) = self.trashed_paths(trash_name)?;
 
-        if trash_file_path.exists() {
-            if trash_file_path.is_dir() {
+        // Symlinks, dangling ones included, are removed themselves and never
+        // followed.
+        if let Ok(metadata) = trash_file_path.symlink_metadata() {
+            if metadata.is_dir() {
                 fs::remove_dir_all(&trash_file_path)?;
             } else {
                 fs::remove_file(&trash_file_path)?;
@@ -157,15 +432,102 @@ impl Trash {
         Ok(())
     }
 
+    pub fn permanently_delete_many(&self, names: &[String]) -> Result<Vec<(String, Error)>> {
+        Ok(self.for_each_trashed(names, |name| self.permanently_delete(name)))
+    }
+
+    pub fn restore_many(&self, names: &[String]) -> Result<Vec<(String, Error)>> {
+        Ok(self.for_each_trashed(names, |name| self.restore(name).map(|_| ())))
+    }
+
+    fn for_each_trashed(&self, names: &[String], action: impl Fn(&str) -> Result<()>) -> Vec<(String, Error)> {
+        let mut failures = Vec::new();
+
+        for name in names {
+            if let Err(e) = self.check_trashed(name).and_then(|_| action(name)) {
+                tracing::warn!("Trash operation failed for {}: {}", name, e);
+                failures.push((name.clone(), e));
+            }
+        }
+
+        failures
+    }
+
+    fn check_trashed(&self, trash_name: &str) -> Result<()> {
+        let (trash_file_path, trash_info_path) = self.trashed_paths(trash_name)?;
+        if trash_file_path.symlink_metadata().is_err() && !trash_info_path.exists() {
+            return Err(Error::NotFound { path: trash_file_path });
+        }
+
+        Ok(())
+    }
+
+    // The file and .trashinfo paths of `trash_name`, which must be a single
+    // name inside the trash so callers can never reach outside it.
+    fn trashed_paths(&self, trash_name: &str) -> Result<(PathBuf, PathBuf)> {
+        if trash_name.is_empty() || trash_name.contains('/') || trash_name == "." || trash_name == ".." {
+            return Err(Error::TrashError(format!("Invalid trash name: {}", trash_name).into()));
+        }
+
+        Ok((
+            self.files_dir.join(trash_name),
+            self.info_dir.join(format!("{}.trashinfo", trash_name)),
+        ))
+    }
+
+    pub fn delete_matching(&self, predicate: impl Fn(&TrashItem) -> bool + Send + Sync) -> Result<DeletionReport> {
+        let mut report = DeletionReport::default();
+
+        for item in self.list_trash_items()? {
+            if !predicate(&item) {
+                continue;
+            }
+
+            match self.permanently_delete(&item.trash_name) {
+                Ok(()) => {
+                    report.deleted += 1;
+                    report.freed_bytes += item.size;
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to delete {} from trash: {}", item.trash_name, e);
+                    report.errors.push((item.trash_name, e));
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    pub fn delete_larger_than(&self, bytes: u64) -> Result<DeletionReport> {
+        self.delete_matching(|item| item.size > bytes)
+    }
+
+    pub fn delete_by_extension(&self, ext: &str) -> Result<DeletionReport> {
+        let ext = ext.trim_start_matches('.').to_lowercase();
+
+        self.delete_matching(|item| {
+            item.original_path
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| e.to_lowercase() == ext)
+        })
+    }
+
     fn create_trash_info(&self, info_path: &Path, original_path: &Path, deletion_date: SystemTime) -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
         let datetime: DateTime<Utc> = deletion_date.into();
         let formatted_date = datetime.format("%Y-%m-%dT%H:%M:%S").to_string();
 
-        let content = format!(
+        let mut content = format!(
             "[Trash Info]\nPath={}\nDeletionDate={}\n",
             original_path.display(),
             formatted_date
         );
+        // Not part of the trash spec; other implementations ignore it.
+        if let Some(parent) = original_path.parent().and_then(|p| fs::metadata(p).ok()) {
+            content.push_str(&format!("{}={:o}\n", PARENT_MODE_KEY, parent.permissions().mode() & 0o7777));
+        }
 
         fs::write(info_path, content)?;
         Ok(())
@@ -180,7 +542,14 @@ impl Trash {
             }
         }
 
-        Err(Error::TrashError("Invalid trash info format".to_string()))
+        Err(Error::TrashError("Invalid trash info format".into()))
+    }
+
+    fn read_parent_mode(&self, info_path: &Path) -> Option<u32> {
+        let content = fs::read_to_string(info_path).ok()?;
+        content.lines()
+            .find_map(|line| line.strip_prefix(PARENT_MODE_KEY)?.strip_prefix('='))
+            .and_then(|mode| u32::from_str_radix(mode, 8).ok())
     }
 
     fn read_deletion_date(&self, info_path: &Path) -> Result<SystemTime> {
@@ -188,9 +557,10 @@ impl Trash {
 
         for line in content.lines() {
             if let Some(date_str) = line.strip_prefix("DeletionDate=") {
-                let datetime = DateTime::parse_from_str(date_str, "%Y-%m-%dT%H:%M:%S")
-                    .or_else(|_| DateTime::parse_from_rfc3339(date_str))
-                    .map_err(|e| Error::TrashError(format!("Invalid date format: {}", e)))?;
+                let datetime = NaiveDateTime::parse_from_str(date_str, "%Y-%m-%dT%H:%M:%S")
+                    .map(|naive| naive.and_utc())
+                    .or_else(|_| DateTime::parse_from_rfc3339(date_str).map(|dt| dt.with_timezone(&Utc)))
+                    .map_err(|e| Error::TrashError(context("Invalid date format", e)))?;
                 return Ok(datetime.into());
             }
         }
@@ -213,31 +583,48 @@ impl Trash {
             counter += 1;
 
             if counter > 9999 {
-                return Err(Error::TrashError("Too many files with same name in trash".to_string()));
+                return Err(Error::TrashError("Too many files with same name in trash".into()));
             }
         }
 
         Ok(name)
     }
 
-    fn get_size_recursive(&self, path: &Path) -> Result<u64> {
-        let metadata = fs::metadata(path)?;
+    fn trashed_size(&self, trash_name: &str) -> Result<u64> {
+        let trash_file_path = self.files_dir.join(trash_name);
+
+        if trash_file_path.symlink_metadata().is_ok() {
+            self.get_size_recursive(&trash_file_path, &mut HashSet::new())
+        } else {
+            Ok(0)
+        }
+    }
+
+    // Symlinks count as themselves and are not followed. Files with several
+    // hard links are counted once, tracked by (device, inode) in `seen`.
+    fn get_size_recursive(&self, path: &Path, seen: &mut HashSet<(u64, u64)>) -> Result<u64> {
+        use std::os::unix::fs::MetadataExt;
 
-        if metadata.is_file() {
+        let metadata = fs::symlink_metadata(path)?;
+
+        if !metadata.is_dir() {
+            if metadata.nlink() > 1 && !seen.insert((metadata.dev(), metadata.ino())) {
+                return Ok(0);
+            }
             return Ok(metadata.len());
         }
 
         let mut total = 0u64;
         for entry in fs::read_dir(path)? {
             let entry = entry?;
-            total += self.get_size_recursive(&entry.path())?;
+            total += self.get_size_recursive(&entry.path(), seen)?;
         }
 
         Ok(total)
     }
 
     pub fn trash_size(&self) -> Result<u64> {
-        self.get_size_recursive(&self.files_dir)
+        self.get_size_recursive(&self.files_dir, &mut HashSet::new())
     }
 }
 
@@ -247,6 +634,34 @@ impl Default for Trash {
     }
 }
 
+// Ties are broken by trash name, which is unique, so pages never overlap or shift.
+fn sort_trash_items(items: &mut [TrashItem], sort: TrashSort) {
+    items.sort_by(|a, b| {
+        let ordering = match sort.key {
+            TrashSortKey::DeletionDate => a.deletion_date.cmp(&b.deletion_date),
+            TrashSortKey::Size => a.size.cmp(&b.size),
+            TrashSortKey::Name => a.display_name().to_lowercase().cmp(&b.display_name().to_lowercase()),
+        };
+
+        let ordering = match sort.order {
+            SortOrder::Ascending => ordering,
+            SortOrder::Descending => ordering.reverse(),
+        };
+
+        ordering.then_with(|| a.trash_name.cmp(&b.trash_name))
+    });
+}
+
+fn trash_name_from_info(info_path: &Path) -> Option<String> {
+    if info_path.extension().and_then(|e| e.to_str()) != Some("trashinfo") {
+        return None;
+    }
+
+    info_path.file_stem()
+        .and_then(|s| s.to_str())
+        .map(String::from)
+}
+
 #[derive(Debug, Clone)]
 pub struct TrashItem {
     pub trash_name: String,
@@ -254,3 +669,582 @@ pub struct TrashItem {
     pub deletion_date: SystemTime,
     pub size: u64,
 }
+
+impl TrashItem {
+    pub fn display_name(&self) -> String {
+        self.original_path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.trash_name.clone())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrashSortKey {
+    #[default]
+    DeletionDate,
+    Size,
+    Name,
+}
+
+// Without include_sizes, items are returned with a size of 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrashSort {
+    pub key: TrashSortKey,
+    pub order: SortOrder,
+    pub include_sizes: bool,
+}
+
+impl Default for TrashSort {
+    fn default() -> Self {
+        Self {
+            key: TrashSortKey::DeletionDate,
+            order: SortOrder::Descending,
+            include_sizes: false,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DeletionReport {
+    pub deleted: usize,
+    pub freed_bytes: u64,
+    pub errors: Vec<(String, Error)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrashEventKind {
+    ItemAdded,
+    ItemRemoved,
+    Emptied,
+}
+
+#[derive(Debug, Clone)]
+pub struct TrashEvent {
+    pub kind: TrashEventKind,
+    pub item: Option<TrashItem>,
+}
+
+// The absolute path recorded in .trashinfo. Only the parent directory is
+// resolved, so a trashed symlink keeps its own path rather than its target's.
+fn original_path(path: &Path) -> Result<PathBuf> {
+    let file_name = path.file_name()
+        .ok_or_else(|| Error::InvalidPath { path: path.to_path_buf() })?;
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    Ok(parent.canonicalize()?.join(file_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    async fn next_event(rx: &mut mpsc::Receiver<TrashEvent>) -> TrashEvent {
+        tokio::time::timeout(Duration::from_secs(2), rx.recv())
+            .await
+            .expect("Timed out waiting for trash event")
+            .expect("Trash watch channel closed")
+    }
+
+    #[tokio::test]
+    async fn test_trash_watch_add_remove() {
+        let temp_dir = TempDir::new().unwrap();
+        let trash = Arc::new(Trash::with_dir(temp_dir.path().join("Trash")).unwrap());
+        let (tx, mut rx) = mpsc::channel(16);
+        let cancel = CancellationToken::new();
+
+        let watch_trash = Arc::clone(&trash);
+        let watch_cancel = cancel.clone();
+        let handle = tokio::spawn(async move {
+            watch_trash.watch(tx, watch_cancel).await
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let file = temp_dir.path().join("doomed.txt");
+        fs::write(&file, "data").unwrap();
+        trash.send_to_trash(&file).unwrap();
+
+        let event = next_event(&mut rx).await;
+        assert_eq!(event.kind, TrashEventKind::ItemAdded);
+        assert_eq!(event.item.unwrap().trash_name, "doomed.txt");
+
+        trash.permanently_delete("doomed.txt").unwrap();
+
+        let event = next_event(&mut rx).await;
+        assert_eq!(event.kind, TrashEventKind::ItemRemoved);
+        assert_eq!(event.item.unwrap().trash_name, "doomed.txt");
+
+        cancel.cancel();
+        handle.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_trash_watch_emptied_depends_on_empty_trash() {
+        let temp_dir = TempDir::new().unwrap();
+        let trash = Arc::new(Trash::with_dir(temp_dir.path().join("Trash")).unwrap());
+
+        for name in ["a.txt", "b.txt"] {
+            let file = temp_dir.path().join(name);
+            fs::write(&file, name).unwrap();
+            trash.send_to_trash(&file).unwrap();
+        }
+
+        let (tx, mut rx) = mpsc::channel(16);
+        let cancel = CancellationToken::new();
+        let watch_trash = Arc::clone(&trash);
+        let watch_cancel = cancel.clone();
+        let handle = tokio::spawn(async move {
+            watch_trash.watch(tx, watch_cancel).await
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // Deleting every item by hand is not emptying the trash.
+        let names = vec!["a.txt".to_string(), "b.txt".to_string()];
+        assert!(trash.permanently_delete_many(&names).unwrap().is_empty());
+        let mut removed = Vec::new();
+        for _ in 0..2 {
+            let event = next_event(&mut rx).await;
+            assert_eq!(event.kind, TrashEventKind::ItemRemoved);
+            removed.push(event.item.unwrap().trash_name);
+        }
+        removed.sort();
+        assert_eq!(removed, names);
+
+        // Emptying a trash with a single item is.
+        let file = temp_dir.path().join("c.txt");
+        fs::write(&file, "c").unwrap();
+        trash.send_to_trash(&file).unwrap();
+        assert_eq!(next_event(&mut rx).await.kind, TrashEventKind::ItemAdded);
+
+        trash.empty_trash().unwrap();
+        let event = next_event(&mut rx).await;
+        assert_eq!(event.kind, TrashEventKind::Emptied);
+        assert!(event.item.is_none());
+
+        cancel.cancel();
+        handle.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_trash_watch_emptied() {
+        let temp_dir = TempDir::new().unwrap();
+        let trash = Arc::new(Trash::with_dir(temp_dir.path().join("Trash")).unwrap());
+
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            let file = temp_dir.path().join(name);
+            fs::write(&file, name).unwrap();
+            trash.send_to_trash(&file).unwrap();
+        }
+
+        let (tx, mut rx) = mpsc::channel(16);
+        let cancel = CancellationToken::new();
+
+        let watch_trash = Arc::clone(&trash);
+        let watch_cancel = cancel.clone();
+        let handle = tokio::spawn(async move {
+            watch_trash.watch(tx, watch_cancel).await
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        trash.empty_trash().unwrap();
+
+        let event = next_event(&mut rx).await;
+        assert_eq!(event.kind, TrashEventKind::Emptied);
+        assert!(event.item.is_none());
+
+        cancel.cancel();
+        handle.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_move_to_trash_async_same_filesystem() {
+        let temp_dir = TempDir::new().unwrap();
+        let trash = Trash::with_dir(temp_dir.path().join("Trash")).unwrap();
+        let file = temp_dir.path().join("report.txt");
+        fs::write(&file, "data").unwrap();
+        let original = file.canonicalize().unwrap();
+
+        assert!(trash.is_same_trash_filesystem(&file).await.unwrap());
+
+        let (tx, _rx) = mpsc::channel(16);
+        trash.move_to_trash_async(&file, CancellationToken::new(), tx).await.unwrap();
+
+        assert!(!file.exists());
+        let items = trash.list_trash_items().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].trash_name, "report.txt");
+        assert_eq!(items[0].original_path, original);
+    }
+
+    #[tokio::test]
+    async fn test_copy_to_trash_fallback() {
+        let temp_dir = TempDir::new().unwrap();
+        let trash = Trash::with_dir(temp_dir.path().join("Trash")).unwrap();
+
+        let existing = temp_dir.path().join("notes.txt");
+        fs::write(&existing, "old").unwrap();
+        trash.send_to_trash(&existing).unwrap();
+
+        let dir = temp_dir.path().join("notes.txt");
+        fs::create_dir(&dir).unwrap();
+        fs::write(dir.join("inner.txt"), "new").unwrap();
+        let original = dir.canonicalize().unwrap();
+
+        let (tx, mut rx) = mpsc::channel(64);
+        trash.copy_to_trash(&dir, CancellationToken::new(), tx).await.unwrap();
+
+        assert!(!dir.exists());
+        assert!(rx.try_recv().is_ok());
+        assert_eq!(fs::read_to_string(trash.files_dir.join("notes.1.txt/inner.txt")).unwrap(), "new");
+        assert_eq!(trash.read_trash_info(&trash.info_dir.join("notes.1.txt.trashinfo")).unwrap(), original);
+        assert_eq!(remaining(&trash), vec!["notes.1.txt", "notes.txt"]);
+        assert!(!fs::read_dir(&trash.trash_dir).unwrap().any(|entry| {
+            entry.unwrap().file_name().to_string_lossy().starts_with(".staging-")
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_move_to_trash_async_cancelled() {
+        let temp_dir = TempDir::new().unwrap();
+        let trash = Trash::with_dir(temp_dir.path().join("Trash")).unwrap();
+        let file = temp_dir.path().join("keep.txt");
+        fs::write(&file, "data").unwrap();
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let (tx, _rx) = mpsc::channel(16);
+        let result = trash.move_to_trash_async(&file, cancel, tx).await;
+
+        assert!(matches!(result, Err(Error::Cancelled)));
+        assert!(file.exists());
+        assert!(trash.list_trash_items().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_is_same_trash_filesystem_missing_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let trash = Trash::with_dir(temp_dir.path().join("Trash")).unwrap();
+
+        let result = trash.is_same_trash_filesystem(&temp_dir.path().join("missing")).await;
+        assert!(matches!(result, Err(Error::NotFound { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_trashed_symlinks_keep_their_own_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let trash = Trash::with_dir(temp_dir.path().join("Trash")).unwrap();
+        let target = temp_dir.path().join("target.txt");
+        fs::write(&target, "data").unwrap();
+        let link = temp_dir.path().join("link.txt");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        let dangling = temp_dir.path().join("dangling");
+        std::os::unix::fs::symlink(temp_dir.path().join("missing"), &dangling).unwrap();
+
+        let (tx, _rx) = mpsc::channel(16);
+        trash.move_to_trash_async(&link, CancellationToken::new(), tx.clone()).await.unwrap();
+        trash.move_to_trash_async(&dangling, CancellationToken::new(), tx).await.unwrap();
+
+        let root = temp_dir.path().canonicalize().unwrap();
+        assert_eq!(trash.read_trash_info(&trash.info_dir.join("link.txt.trashinfo")).unwrap(), root.join("link.txt"));
+        assert_eq!(trash.read_trash_info(&trash.info_dir.join("dangling.trashinfo")).unwrap(), root.join("dangling"));
+        assert!(trash.files_dir.join("link.txt").symlink_metadata().unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "data");
+
+        assert_eq!(trash.restore("link.txt").unwrap(), root.join("link.txt"));
+        assert_eq!(fs::read_link(&link).unwrap(), target);
+    }
+
+    fn trash_with_files(temp_dir: &TempDir, files: &[(&str, usize)]) -> Trash {
+        let trash = Trash::with_dir(temp_dir.path().join("Trash")).unwrap();
+
+        for (name, size) in files {
+            let file = temp_dir.path().join(name);
+            fs::write(&file, vec![b'x'; *size]).unwrap();
+            trash.send_to_trash(&file).unwrap();
+        }
+
+        trash
+    }
+
+    fn remaining(trash: &Trash) -> Vec<String> {
+        let mut names: Vec<_> = trash.list_trash_items().unwrap()
+            .into_iter()
+            .map(|item| item.trash_name)
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_delete_matching() {
+        let temp_dir = TempDir::new().unwrap();
+        let trash = trash_with_files(&temp_dir, &[("keep.txt", 10), ("drop-1.txt", 20), ("drop-2.txt", 30)]);
+
+        let report = trash.delete_matching(|item| item.trash_name.starts_with("drop")).unwrap();
+
+        assert_eq!(report.deleted, 2);
+        assert_eq!(report.freed_bytes, 50);
+        assert!(report.errors.is_empty());
+        assert_eq!(remaining(&trash), vec!["keep.txt"]);
+    }
+
+    #[test]
+    fn test_delete_larger_than() {
+        let temp_dir = TempDir::new().unwrap();
+        let trash = trash_with_files(&temp_dir, &[("small.bin", 100), ("exact.bin", 1000), ("large.bin", 5000)]);
+
+        let report = trash.delete_larger_than(1000).unwrap();
+
+        assert_eq!(report.deleted, 1);
+        assert_eq!(report.freed_bytes, 5000);
+        assert_eq!(remaining(&trash), vec!["exact.bin", "small.bin"]);
+    }
+
+    #[test]
+    fn test_delete_by_extension() {
+        let temp_dir = TempDir::new().unwrap();
+        let trash = trash_with_files(&temp_dir, &[("a.log", 1), ("B.LOG", 2), ("c.txt", 3), ("log", 4)]);
+
+        let report = trash.delete_by_extension(".log").unwrap();
+
+        assert_eq!(report.deleted, 2);
+        assert_eq!(report.freed_bytes, 3);
+        assert_eq!(remaining(&trash), vec!["c.txt", "log"]);
+    }
+
+    #[test]
+    fn test_delete_matching_continues_after_failure() {
+        let temp_dir = TempDir::new().unwrap();
+        let trash = Trash::with_dir(temp_dir.path().join("Trash")).unwrap();
+
+        let stuck = temp_dir.path().join("stuck.txt");
+        fs::write(&stuck, "x").unwrap();
+        trash.send_to_trash(&stuck).unwrap();
+        let file = temp_dir.path().join("free.txt");
+        fs::write(&file, "data").unwrap();
+        trash.send_to_trash(&file).unwrap();
+
+        // A directory in place of the .trashinfo cannot be removed as a file,
+        // even by root, so this delete fails however the tests are run.
+        let report = trash.delete_matching(|item| {
+            if item.trash_name == "stuck.txt" {
+                let info = temp_dir.path().join("Trash/info/stuck.txt.trashinfo");
+                fs::remove_file(&info).unwrap();
+                fs::create_dir(&info).unwrap();
+            }
+            true
+        }).unwrap();
+
+        assert_eq!(report.deleted, 1);
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].0, "stuck.txt");
+    }
+
+    fn set_deletion_date(trash: &Trash, trash_name: &str, date: &str) {
+        let info_path = trash.info_dir.join(format!("{}.trashinfo", trash_name));
+        let original = trash.read_trash_info(&info_path).unwrap();
+        fs::write(
+            &info_path,
+            format!("[Trash Info]\nPath={}\nDeletionDate={}\n", original.display(), date),
+        )
+        .unwrap();
+    }
+
+    fn names(items: &[TrashItem]) -> Vec<&str> {
+        items.iter().map(|item| item.trash_name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_list_page_sorting() {
+        let temp_dir = TempDir::new().unwrap();
+        let trash = trash_with_files(
+            &temp_dir,
+            &[("b.txt", 300), ("A.txt", 100), ("c.txt", 200), ("d.txt", 200), ("e.txt", 50)],
+        );
+        set_deletion_date(&trash, "b.txt", "2024-01-01T10:00:00");
+        set_deletion_date(&trash, "A.txt", "2024-03-01T10:00:00");
+        set_deletion_date(&trash, "c.txt", "2024-02-01T10:00:00");
+        set_deletion_date(&trash, "d.txt", "2024-02-01T10:00:00");
+        set_deletion_date(&trash, "e.txt", "2023-12-01T10:00:00");
+
+        let newest_first = trash.list_trash_items_page(0, 10, TrashSort::default()).unwrap();
+        assert_eq!(names(&newest_first), vec!["A.txt", "c.txt", "d.txt", "b.txt", "e.txt"]);
+        assert!(newest_first.iter().all(|item| item.size == 0));
+
+        let by_name = TrashSort { key: TrashSortKey::Name, order: SortOrder::Ascending, include_sizes: true };
+        let items = trash.list_trash_items_page(0, 10, by_name).unwrap();
+        assert_eq!(names(&items), vec!["A.txt", "b.txt", "c.txt", "d.txt", "e.txt"]);
+        assert_eq!(items[0].size, 100);
+
+        let by_size = TrashSort { key: TrashSortKey::Size, order: SortOrder::Descending, include_sizes: false };
+        let items = trash.list_trash_items_page(0, 10, by_size).unwrap();
+        assert_eq!(names(&items), vec!["b.txt", "c.txt", "d.txt", "A.txt", "e.txt"]);
+        assert_eq!(items[0].size, 300);
+    }
+
+    #[test]
+    fn test_list_page_is_stable() {
+        let temp_dir = TempDir::new().unwrap();
+        let files: Vec<(String, usize)> = (0..25).map(|i| (format!("file-{:02}.txt", i), i % 3)).collect();
+        let files: Vec<(&str, usize)> = files.iter().map(|(n, s)| (n.as_str(), *s)).collect();
+        let trash = trash_with_files(&temp_dir, &files);
+        for (name, _) in &files {
+            set_deletion_date(&trash, name, "2024-05-05T05:05:05");
+        }
+
+        let sort = TrashSort { key: TrashSortKey::Size, order: SortOrder::Ascending, include_sizes: true };
+        let all = trash.list_trash_items_page(0, usize::MAX, sort).unwrap();
+        let mut paged = Vec::new();
+        for offset in (0..30).step_by(7) {
+            paged.extend(trash.list_trash_items_page(offset, 7, sort).unwrap());
+        }
+
+        assert_eq!(names(&paged), names(&all));
+        assert_eq!(all.len(), 25);
+        assert!(all.windows(2).all(|w| (w[0].size, &w[0].trash_name) < (w[1].size, &w[1].trash_name)));
+        assert!(trash.list_trash_items_page(25, 10, sort).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_permanently_delete_many() {
+        let temp_dir = TempDir::new().unwrap();
+        let trash = trash_with_files(&temp_dir, &[("one.txt", 1), ("two.txt", 2), ("three.txt", 3)]);
+
+        let names: Vec<String> = ["one.txt", "missing.txt", "three.txt", "../escape"]
+            .iter()
+            .map(|n| n.to_string())
+            .collect();
+        let failures = trash.permanently_delete_many(&names).unwrap();
+
+        let failed: Vec<&str> = failures.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(failed, vec!["missing.txt", "../escape"]);
+        assert!(matches!(failures[0].1, Error::NotFound { .. }));
+        assert!(matches!(failures[1].1, Error::TrashError(_)));
+        assert_eq!(remaining(&trash), vec!["two.txt"]);
+    }
+
+    #[test]
+    fn test_permanently_delete_rejects_names_outside_trash() {
+        let temp_dir = TempDir::new().unwrap();
+        let trash = trash_with_files(&temp_dir, &[("kept.txt", 1)]);
+        let outside = temp_dir.path().join("outside.txt");
+        fs::write(&outside, "not trashed").unwrap();
+
+        for name in ["../../outside.txt", "..", ".", "", "sub/kept.txt"] {
+            assert!(matches!(trash.permanently_delete(name), Err(Error::TrashError(_))), "{:?}", name);
+        }
+
+        assert!(outside.exists());
+        assert_eq!(remaining(&trash), vec!["kept.txt"]);
+    }
+
+    #[test]
+    fn test_restore_rejects_names_outside_trash() {
+        let temp_dir = TempDir::new().unwrap();
+        let trash = trash_with_files(&temp_dir, &[("kept.txt", 1)]);
+        let outside = temp_dir.path().join("outside.txt");
+        fs::write(&outside, "not trashed").unwrap();
+        fs::write(
+            temp_dir.path().join("outside.txt.trashinfo"),
+            format!("[Trash Info]\nPath={}\nDeletionDate=2024-01-01T00:00:00\n", temp_dir.path().join("moved.txt").display()),
+        ).unwrap();
+
+        for name in ["../../outside.txt", "..", ".", "", "sub/kept.txt"] {
+            assert!(matches!(trash.restore(name), Err(Error::TrashError(_))), "{:?}", name);
+        }
+
+        assert!(outside.exists());
+        assert!(!temp_dir.path().join("moved.txt").exists());
+        assert_eq!(remaining(&trash), vec!["kept.txt"]);
+    }
+
+    #[test]
+    fn test_restore_many() {
+        let temp_dir = TempDir::new().unwrap();
+        let trash = trash_with_files(&temp_dir, &[("keep.txt", 4), ("blocked.txt", 5), ("back.txt", 6)]);
+        fs::write(temp_dir.path().join("blocked.txt"), "new file in the way").unwrap();
+
+        let names: Vec<String> = ["back.txt", "gone.txt", "blocked.txt"]
+            .iter()
+            .map(|n| n.to_string())
+            .collect();
+        let failures = trash.restore_many(&names).unwrap();
+
+        assert_eq!(failures.len(), 2);
+        assert_eq!(failures[0].0, "gone.txt");
+        assert!(matches!(failures[0].1, Error::NotFound { .. }));
+        assert_eq!(failures[1].0, "blocked.txt");
+        assert!(matches!(failures[1].1, Error::AlreadyExists { .. }));
+
+        assert_eq!(fs::read(temp_dir.path().join("back.txt")).unwrap().len(), 6);
+        assert_eq!(remaining(&trash), vec!["blocked.txt", "keep.txt"]);
+    }
+
+    #[test]
+    fn test_restore_directory_tree() {
+        let temp_dir = TempDir::new().unwrap();
+        let trash = Trash::with_dir(temp_dir.path().join("Trash")).unwrap();
+        let project = temp_dir.path().join("project");
+        fs::create_dir_all(project.join("src/nested")).unwrap();
+        fs::write(project.join("README"), "readme").unwrap();
+        fs::write(project.join("src/nested/main.rs"), "fn main() {}").unwrap();
+        std::os::unix::fs::symlink("README", project.join("link")).unwrap();
+
+        trash.send_to_trash(&project).unwrap();
+        assert!(!project.exists());
+        assert_eq!(remaining(&trash), vec!["project"]);
+
+        let restored = trash.restore("project").unwrap();
+
+        assert_eq!(restored, project.canonicalize().unwrap());
+        assert_eq!(fs::read_to_string(project.join("README")).unwrap(), "readme");
+        assert_eq!(fs::read_to_string(project.join("src/nested/main.rs")).unwrap(), "fn main() {}");
+        assert_eq!(fs::read_link(project.join("link")).unwrap(), PathBuf::from("README"));
+        assert!(remaining(&trash).is_empty());
+    }
+
+    #[test]
+    fn test_restore_recreates_parent_with_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let trash = Trash::with_dir(temp_dir.path().join("Trash")).unwrap();
+        let parent = temp_dir.path().join("a/private");
+        fs::create_dir_all(&parent).unwrap();
+        fs::set_permissions(&parent, fs::Permissions::from_mode(0o700)).unwrap();
+        fs::write(parent.join("notes.txt"), "notes").unwrap();
+
+        trash.send_to_trash(&parent.join("notes.txt")).unwrap();
+        fs::remove_dir_all(temp_dir.path().join("a")).unwrap();
+
+        trash.restore("notes.txt").unwrap();
+
+        assert_eq!(fs::read_to_string(parent.join("notes.txt")).unwrap(), "notes");
+        assert_eq!(fs::metadata(&parent).unwrap().permissions().mode() & 0o7777, 0o700);
+    }
+
+    #[test]
+    fn test_trashed_size_counts_hard_links_once() {
+        let temp_dir = TempDir::new().unwrap();
+        let trash = Trash::with_dir(temp_dir.path().join("Trash")).unwrap();
+        let dir = temp_dir.path().join("linked");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("data.bin"), vec![0u8; 100]).unwrap();
+        fs::hard_link(dir.join("data.bin"), dir.join("alias.bin")).unwrap();
+        std::os::unix::fs::symlink("/", dir.join("root")).unwrap();
+
+        trash.send_to_trash(&dir).unwrap();
+
+        let item = trash.list_trash_items().unwrap().pop().unwrap();
+        assert_eq!(item.size, 100 + fs::symlink_metadata(temp_dir.path().join("Trash/files/linked/root")).unwrap().len());
+
+        trash.permanently_delete("linked").unwrap();
+        assert!(remaining(&trash).is_empty());
+        assert_eq!(trash.trash_size().unwrap(), 0);
+    }
+}
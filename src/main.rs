@@ -38,13 +38,6 @@ fn main() -> glib::ExitCode {
 }
 
 fn build_ui(app: &Application) {
-    let runtime = tokio::runtime::Builder::new_multi_thread()
-        .worker_threads(4)
-        .thread_name("cheese-worker")
-        .enable_all()
-        .build()
-        .expect("Failed to create tokio runtime");
-
     let core = match cheese_core::CheeseCore::new() {
         Ok(core) => core,
         Err(e) => {
@@ -53,9 +46,29 @@ fn build_ui(app: &Application) {
         }
     };
 
+    let worker_threads = core.config().read().performance.runtime_worker_threads();
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(worker_threads)
+        .thread_name("cheese-worker")
+        .enable_all()
+        .build()
+        .expect("Failed to create tokio runtime");
+
     let app_state = state::AppState::new(core, runtime);
-    
-    let window = ui::window::CheeseWindow::new(app, app_state);
+
+    let session = if app_state.restore_session_enabled() {
+        match app_state.restore_session() {
+            Ok(session) => Some(session),
+            Err(e) => {
+                tracing::warn!("Failed to restore session: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let window = ui::window::CheeseWindow::new(app, app_state, session);
     window.present();
 }
 
@@ -0,0 +1,236 @@
+mod scan;
+mod session;
+
+pub use scan::spawn_sorted_scan;
+pub use session::{SessionState, SessionTab};
+
+use cheese_core::{CheeseCore, Result};
+use cheese_core::config::SortConfig;
+use cheese_core::fs::operation_manager::{OperationId, OperationRequest};
+use cheese_core::fs::scanner::ScanResult;
+use cheese_core::search::RecentQueries;
+use cheese_core::trash::{Trash, TrashEvent};
+use parking_lot::Mutex;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::runtime::Runtime;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+const TRASH_EVENT_BUFFER: usize = 64;
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone)]
+pub struct TabState {
+    pub path: PathBuf,
+    pub scroll_position: f64,
+    pub pane: usize,
+    pub sort: SortConfig,
+}
+
+pub struct AppState {
+    core: CheeseCore,
+    runtime: Runtime,
+    tabs: Mutex<Vec<TabState>>,
+    active_tab: AtomicUsize,
+    active_pane: AtomicUsize,
+    trash_events: Mutex<Option<mpsc::Receiver<TrashEvent>>>,
+    recent_queries: Mutex<RecentQueries>,
+    shutdown: CancellationToken,
+}
+
+impl AppState {
+    pub fn new(core: CheeseCore, runtime: Runtime) -> Arc<Self> {
+        let recent_queries = Self::load_recent_queries(core.config().read().ui.search_history_size);
+
+        let state = Arc::new(Self {
+            core,
+            runtime,
+            tabs: Mutex::new(Vec::new()),
+            active_tab: AtomicUsize::new(0),
+            active_pane: AtomicUsize::new(0),
+            trash_events: Mutex::new(None),
+            recent_queries: Mutex::new(recent_queries),
+            shutdown: CancellationToken::new(),
+        });
+
+        state.watch_trash();
+        state
+    }
+
+    fn watch_trash(&self) {
+        let trash = match Trash::new() {
+            Ok(trash) => trash,
+            Err(e) => {
+                tracing::warn!("Trash monitoring disabled: {}", e);
+                return;
+            }
+        };
+
+        let (tx, rx) = mpsc::channel(TRASH_EVENT_BUFFER);
+        *self.trash_events.lock() = Some(rx);
+
+        let cancel = self.shutdown.child_token();
+        self.runtime.spawn(async move {
+            if let Err(e) = trash.watch(tx, cancel).await {
+                tracing::warn!("Trash watch stopped: {}", e);
+            }
+        });
+    }
+
+    fn load_recent_queries(max_size: usize) -> RecentQueries {
+        RecentQueries::history_path()
+            .and_then(|path| RecentQueries::load(&path, max_size))
+            .unwrap_or_else(|e| {
+                tracing::warn!("Search history unavailable: {}", e);
+                RecentQueries::new(max_size)
+            })
+    }
+
+    pub fn record_search(&self, query: &str) {
+        let mut recent = self.recent_queries.lock();
+        recent.record(query);
+
+        if let Err(e) = RecentQueries::history_path().and_then(|path| recent.save(&path)) {
+            tracing::warn!("Failed to save search history: {}", e);
+        }
+    }
+
+    pub fn search_suggestions(&self, prefix: &str) -> Vec<String> {
+        self.recent_queries.lock().suggest(prefix)
+    }
+
+    pub fn take_trash_events(&self) -> Option<mpsc::Receiver<TrashEvent>> {
+        self.trash_events.lock().take()
+    }
+
+    pub fn core(&self) -> &CheeseCore {
+        &self.core
+    }
+
+    pub fn runtime(&self) -> &Runtime {
+        &self.runtime
+    }
+
+    /// Runs a copy, move or delete on the core's operation manager, so it
+    /// shows up in the transfer list and is drained by `shutdown`.
+    pub fn submit_operation(&self, request: OperationRequest) -> OperationId {
+        self.core.operations().submit(request)
+    }
+
+    pub fn add_tab(&self, path: PathBuf) {
+        self.add_tab_with_state(path, self.default_sort(), 0.0);
+    }
+
+    pub fn add_tab_with_state(&self, path: PathBuf, sort: SortConfig, scroll_position: f64) -> usize {
+        let mut tabs = self.tabs.lock();
+        tabs.push(TabState {
+            path,
+            scroll_position,
+            pane: 0,
+            sort,
+        });
+        tabs.len() - 1
+    }
+
+    /// Forgets the tab at `index`, moving the active tab along with the tabs
+    /// after it.
+    pub fn remove_tab(&self, index: usize) -> Option<TabState> {
+        let mut tabs = self.tabs.lock();
+        if index >= tabs.len() {
+            return None;
+        }
+
+        let removed = tabs.remove(index);
+        let active = self.active_tab();
+        if active > index || active >= tabs.len() {
+            self.set_active_tab(active.saturating_sub(1));
+        }
+        Some(removed)
+    }
+
+    /// Records how far the tab at `index` is scrolled, as a fraction of its
+    /// scrollable range.
+    pub fn set_tab_scroll(&self, index: usize, scroll_position: f64) {
+        if let Some(tab) = self.tabs.lock().get_mut(index) {
+            tab.scroll_position = scroll_position;
+        }
+    }
+
+    pub fn set_tab_pane(&self, index: usize, pane: usize) {
+        if let Some(tab) = self.tabs.lock().get_mut(index) {
+            tab.pane = pane;
+        }
+    }
+
+    pub fn default_sort(&self) -> SortConfig {
+        SortConfig::from(&self.core.config().read().navigation)
+    }
+
+    pub fn scan_tab(&self, path: PathBuf, sort: SortConfig) -> mpsc::Receiver<ScanResult> {
+        spawn_sorted_scan(self.runtime.handle(), self.core.scanner(), path, sort, self.shutdown.child_token())
+    }
+
+    pub fn tabs(&self) -> Vec<TabState> {
+        self.tabs.lock().clone()
+    }
+
+    pub fn set_active_tab(&self, index: usize) {
+        self.active_tab.store(index, Ordering::Relaxed);
+    }
+
+    pub fn active_tab(&self) -> usize {
+        self.active_tab.load(Ordering::Relaxed)
+    }
+
+    pub fn restore_session_enabled(&self) -> bool {
+        self.core.config().read().ui.restore_session
+    }
+
+    pub fn session(&self) -> SessionState {
+        let tabs = self
+            .tabs
+            .lock()
+            .iter()
+            .map(|tab| SessionTab {
+                path: tab.path.clone(),
+                scroll_position: tab.scroll_position,
+                pane: tab.pane,
+                sort: tab.sort,
+            })
+            .collect();
+
+        SessionState {
+            tabs,
+            active_tab: self.active_tab(),
+            active_pane: self.active_pane.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn save_session(&self) -> Result<()> {
+        self.session().save(&SessionState::session_path()?)
+    }
+
+    /// Stops background tasks and lets in-flight file operations clean up
+    /// before the window goes away.
+    pub fn shutdown(&self) {
+        self.shutdown.cancel();
+        if let Err(e) = self.core.shutdown(SHUTDOWN_TIMEOUT) {
+            tracing::warn!("Shutdown incomplete: {}", e);
+        }
+    }
+
+    pub fn restore_session(&self) -> Result<SessionState> {
+        let session = SessionState::load(&SessionState::session_path()?)?;
+        self.active_pane.store(session.active_pane, Ordering::Relaxed);
+        Ok(session)
+    }
+}
+
+impl Drop for AppState {
+    fn drop(&mut self) {
+        self.shutdown.cancel();
+    }
+}
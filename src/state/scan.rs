@@ -0,0 +1,77 @@
+use cheese_core::config::SortConfig;
+use cheese_core::fs::scanner::{ScanResult, Scanner};
+use std::path::PathBuf;
+use tokio::runtime::Handle;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+const SCAN_BUFFER: usize = 16;
+
+// Scans on the runtime so the receiver can be drained from the GTK main loop.
+// Batches arrive already sorted by `sort`.
+pub fn spawn_sorted_scan(
+    runtime: &Handle,
+    scanner: Scanner,
+    path: PathBuf,
+    sort: SortConfig,
+    cancel: CancellationToken,
+) -> mpsc::Receiver<ScanResult> {
+    let (tx, rx) = mpsc::channel(SCAN_BUFFER);
+
+    runtime.spawn(async move {
+        if let Err(e) = scanner.scan_directory_sorted(path.clone(), sort, tx, cancel).await {
+            tracing::warn!("Failed to scan {}: {}", path.display(), e);
+        }
+    });
+
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cheese_core::config::{SortBy, SortOrder};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_first_batch_uses_tab_sort() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        for (name, size) in [("a.txt", 10), ("b.txt", 30), ("c.txt", 20)] {
+            std::fs::write(temp_dir.path().join(name), vec![0u8; size]).unwrap();
+        }
+        std::fs::create_dir(temp_dir.path().join("z-dir")).unwrap();
+
+        let sort = SortConfig {
+            sort_by: SortBy::Size,
+            sort_order: SortOrder::Descending,
+            directories_first: false,
+        };
+        let mut results = spawn_sorted_scan(
+            runtime.handle(),
+            Scanner::new(false, 10, false),
+            temp_dir.path().to_path_buf(),
+            sort,
+            CancellationToken::new(),
+        );
+
+        let first = runtime.block_on(results.recv()).unwrap();
+        let names: Vec<&str> = first.entries.iter().filter(|e| !e.is_dir).map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["b.txt", "c.txt", "a.txt"]);
+        assert_eq!(first.total_count, 4);
+    }
+
+    #[test]
+    fn test_missing_directory_closes_channel() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let mut results = spawn_sorted_scan(
+            runtime.handle(),
+            Scanner::new(false, 10, false),
+            PathBuf::from("/nonexistent/cheese-test"),
+            SortConfig::default(),
+            CancellationToken::new(),
+        );
+
+        assert!(runtime.block_on(results.recv()).is_none());
+    }
+}
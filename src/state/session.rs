@@ -0,0 +1,244 @@
+use cheese_core::config::SortConfig;
+use cheese_core::fs::ops::LocalFileOps;
+use cheese_core::error::context;
+use cheese_core::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use xdg::BaseDirectories;
+
+const SESSION_FILE: &str = "session.json";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionState {
+    pub tabs: Vec<SessionTab>,
+    pub active_tab: usize,
+    pub active_pane: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionTab {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub scroll_position: f64,
+    #[serde(default)]
+    pub pane: usize,
+    #[serde(default)]
+    pub sort: SortConfig,
+}
+
+impl SessionState {
+    pub fn session_path() -> Result<PathBuf> {
+        let xdg_dirs = BaseDirectories::with_prefix("cheese")
+            .map_err(|e| Error::Config(context("Failed to get XDG directories", e)))?;
+        Ok(xdg_dirs.get_data_home().join(SESSION_FILE))
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        let mut session = self.clone();
+        session.normalize();
+
+        serde_json::to_string_pretty(&session)
+            .map_err(|e| Error::Config(context("Failed to serialize session", e)))
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        let mut session: Self = serde_json::from_str(json)
+            .map_err(|e| Error::Config(context("Failed to parse session", e)))?;
+        session.normalize();
+        Ok(session)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(json) => Self::from_json(&json),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = self.to_json()?;
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        LocalFileOps::atomic_write(path, json.as_bytes())
+    }
+
+    // JSON has no representation for NaN/infinity or non-UTF-8 paths, so those are
+    // dropped before saving; indices from an older session are clamped on load.
+    // Dropping a tab before the active one shifts the active index down with it.
+    fn normalize(&mut self) {
+        let mut index = 0;
+        let active = self.active_tab;
+        self.tabs.retain(|tab| {
+            let keep = tab.path.to_str().is_some();
+            if !keep && index < active {
+                self.active_tab -= 1;
+            }
+            index += 1;
+            keep
+        });
+
+        for tab in &mut self.tabs {
+            if !tab.scroll_position.is_finite() || tab.scroll_position < 0.0 {
+                tab.scroll_position = 0.0;
+            }
+        }
+
+        if self.active_tab >= self.tabs.len() {
+            self.active_tab = self.tabs.len().saturating_sub(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn tab(path: &str, scroll_position: f64, pane: usize) -> SessionTab {
+        SessionTab {
+            path: PathBuf::from(path),
+            scroll_position,
+            pane,
+            sort: SortConfig::default(),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let session = SessionState {
+            tabs: vec![tab("/home/user", 120.5, 0), tab("/tmp/with space/ünïcode", 0.0, 1)],
+            active_tab: 1,
+            active_pane: 1,
+        };
+
+        let restored = SessionState::from_json(&session.to_json().unwrap()).unwrap();
+        assert_eq!(restored, session);
+    }
+
+    #[test]
+    fn test_sort_round_trip() {
+        use cheese_core::config::{SortBy, SortOrder};
+
+        let mut sorted = tab("/var/log", 0.5, 0);
+        sorted.sort = SortConfig {
+            sort_by: SortBy::Modified,
+            sort_order: SortOrder::Descending,
+            directories_first: false,
+        };
+        let session = SessionState {
+            tabs: vec![sorted],
+            active_tab: 0,
+            active_pane: 0,
+        };
+
+        let restored = SessionState::from_json(&session.to_json().unwrap()).unwrap();
+        assert_eq!(restored, session);
+    }
+
+    #[test]
+    fn test_non_finite_scroll_positions() {
+        let session = SessionState {
+            tabs: vec![tab("/a", f64::NAN, 0), tab("/b", f64::INFINITY, 0), tab("/c", -4.0, 0)],
+            active_tab: 0,
+            active_pane: 0,
+        };
+
+        let restored = SessionState::from_json(&session.to_json().unwrap()).unwrap();
+        assert!(restored.tabs.iter().all(|t| t.scroll_position == 0.0));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_paths_are_dropped() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let session = SessionState {
+            tabs: vec![
+                SessionTab {
+                    path: PathBuf::from(OsStr::from_bytes(b"/tmp/\xff\xfe")),
+                    scroll_position: 0.0,
+                    pane: 0,
+                    sort: SortConfig::default(),
+                },
+                tab("/tmp", 0.0, 0),
+            ],
+            active_tab: 1,
+            active_pane: 0,
+        };
+
+        let restored = SessionState::from_json(&session.to_json().unwrap()).unwrap();
+        assert_eq!(restored.tabs, vec![tab("/tmp", 0.0, 0)]);
+        assert_eq!(restored.active_tab, 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_active_tab_follows_dropped_tabs() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let invalid = SessionTab {
+            path: PathBuf::from(OsStr::from_bytes(b"/tmp/\xff")),
+            ..tab("/", 0.0, 0)
+        };
+        let session = SessionState {
+            tabs: vec![tab("/a", 0.0, 0), invalid.clone(), tab("/b", 0.0, 0), tab("/c", 0.0, 0)],
+            active_tab: 2,
+            active_pane: 0,
+        };
+        let restored = SessionState::from_json(&session.to_json().unwrap()).unwrap();
+        assert_eq!(restored.tabs[restored.active_tab], tab("/b", 0.0, 0));
+
+        let session = SessionState {
+            tabs: vec![tab("/a", 0.0, 0), tab("/b", 0.0, 0), invalid],
+            active_tab: 2,
+            active_pane: 0,
+        };
+        let restored = SessionState::from_json(&session.to_json().unwrap()).unwrap();
+        assert_eq!(restored.active_tab, 1);
+    }
+
+    #[test]
+    fn test_missing_fields_and_out_of_range_index() {
+        let restored = SessionState::from_json(r#"{ "tabs": [{ "path": "/srv" }], "active_tab": 7 }"#).unwrap();
+        assert_eq!(restored.tabs, vec![tab("/srv", 0.0, 0)]);
+        assert_eq!(restored.active_tab, 0);
+        assert_eq!(restored.active_pane, 0);
+
+        let empty = SessionState::from_json("{}").unwrap();
+        assert_eq!(empty, SessionState::default());
+    }
+
+    #[test]
+    fn test_corrupt_session_is_an_error() {
+        assert!(SessionState::from_json("{ \"tabs\": [").is_err());
+        assert!(SessionState::from_json(r#"{ "tabs": [{ "scroll_position": 1.0 }] }"#).is_err());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("nested/session.json");
+
+        assert_eq!(SessionState::load(&path).unwrap(), SessionState::default());
+
+        let session = SessionState {
+            tabs: vec![tab("/var/log", 33.0, 0)],
+            active_tab: 0,
+            active_pane: 0,
+        };
+        session.save(&path).unwrap();
+
+        assert_eq!(SessionState::load(&path).unwrap(), session);
+        let leftovers: Vec<_> = std::fs::read_dir(path.parent().unwrap()).unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(leftovers, vec![std::ffi::OsString::from("session.json")]);
+    }
+}
@@ -0,0 +1,230 @@
+use crate::state::AppState;
+use fuzzy_matcher::FuzzyMatcher;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use gtk4::prelude::*;
+use gtk4::{glib, ApplicationWindow, Dialog, Label, ListBox, Orientation, ScrolledWindow, SearchEntry, SelectionMode};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+
+pub struct CommandEntry {
+    pub id: String,
+    pub label: String,
+    pub shortcut: Option<String>,
+    pub action: Box<dyn Fn(&AppState)>,
+}
+
+impl CommandEntry {
+    pub fn new(id: impl Into<String>, label: impl Into<String>, action: impl Fn(&AppState) + 'static) -> Self {
+        Self {
+            id: id.into(),
+            label: label.into(),
+            shortcut: None,
+            action: Box::new(action),
+        }
+    }
+
+    pub fn with_shortcut(mut self, shortcut: impl Into<String>) -> Self {
+        self.shortcut = Some(shortcut.into());
+        self
+    }
+}
+
+/// Searchable list of commands shown on Ctrl+P. Cheap to clone; clones share
+/// the registered commands.
+#[derive(Clone)]
+pub struct CommandPalette {
+    window: ApplicationWindow,
+    app_state: Arc<AppState>,
+    // Entries are reference counted so an action may register further
+    // commands while it runs.
+    entries: Rc<RefCell<Vec<Rc<CommandEntry>>>>,
+}
+
+impl CommandPalette {
+    pub fn new(window: &ApplicationWindow, app_state: Arc<AppState>) -> Self {
+        Self {
+            window: window.clone(),
+            app_state,
+            entries: Rc::new(RefCell::new(Vec::new())),
+        }
+    }
+
+    /// Adds `entry`, replacing any command already registered under its id.
+    pub fn register(&self, entry: CommandEntry) {
+        let mut entries = self.entries.borrow_mut();
+        match entries.iter().position(|existing| existing.id == entry.id) {
+            Some(index) => entries[index] = Rc::new(entry),
+            None => entries.push(Rc::new(entry)),
+        }
+    }
+
+    pub fn show(&self) {
+        let dialog = Dialog::builder()
+            .transient_for(&self.window)
+            .modal(true)
+            .title("Command Palette")
+            .default_width(480)
+            .default_height(360)
+            .build();
+
+        let search = SearchEntry::new();
+        let list = ListBox::new();
+        list.set_selection_mode(SelectionMode::Browse);
+        let scrolled = ScrolledWindow::builder()
+            .child(&list)
+            .vexpand(true)
+            .build();
+
+        let content = dialog.content_area();
+        content.append(&search);
+        content.append(&scrolled);
+
+        // Entry indices in the order their rows appear in the list.
+        let visible: Rc<RefCell<Vec<usize>>> = Rc::new(RefCell::new(Vec::new()));
+        self.populate(&list, &visible, "");
+
+        let palette = self.clone();
+        let (filter_list, filter_visible) = (list.clone(), Rc::clone(&visible));
+        search.connect_search_changed(move |search| {
+            palette.populate(&filter_list, &filter_visible, &search.text());
+        });
+
+        let palette = self.clone();
+        let (enter_list, enter_visible, enter_dialog) = (list.clone(), Rc::clone(&visible), dialog.clone());
+        search.connect_activate(move |_| {
+            if let Some(row) = enter_list.selected_row() {
+                palette.activate(&enter_visible, row.index(), &enter_dialog);
+            }
+        });
+
+        let palette = self.clone();
+        let (row_visible, row_dialog) = (Rc::clone(&visible), dialog.clone());
+        list.connect_row_activated(move |_, row| {
+            palette.activate(&row_visible, row.index(), &row_dialog);
+        });
+
+        // Keep focus in the search entry while arrow keys move the highlight.
+        let controller = gtk4::EventControllerKey::new();
+        let nav_list = list.clone();
+        controller.connect_key_pressed(move |_, key, _, _| {
+            use gtk4::gdk::Key;
+
+            let step = match key {
+                Key::Down => 1,
+                Key::Up => -1,
+                _ => return glib::Propagation::Proceed,
+            };
+            let current = nav_list.selected_row().map_or(-1, |row| row.index());
+            if let Some(row) = nav_list.row_at_index((current + step).max(0)) {
+                nav_list.select_row(Some(&row));
+            }
+            glib::Propagation::Stop
+        });
+        search.add_controller(controller);
+
+        dialog.present();
+        search.grab_focus();
+    }
+
+    fn populate(&self, list: &ListBox, visible: &Rc<RefCell<Vec<usize>>>, query: &str) {
+        while let Some(child) = list.first_child() {
+            list.remove(&child);
+        }
+
+        let entries = self.entries.borrow();
+        let matches = filter_commands(&entries, query);
+
+        for &index in &matches {
+            let entry = &entries[index];
+            let row = gtk4::Box::new(Orientation::Horizontal, 12);
+            row.append(&Label::builder().label(entry.label.as_str()).xalign(0.0).hexpand(true).build());
+            if let Some(shortcut) = &entry.shortcut {
+                let shortcut_label = Label::new(Some(shortcut.as_str()));
+                shortcut_label.add_css_class("dim-label");
+                row.append(&shortcut_label);
+            }
+            list.append(&row);
+        }
+
+        list.select_row(list.row_at_index(0).as_ref());
+        *visible.borrow_mut() = matches;
+    }
+
+    fn activate(&self, visible: &Rc<RefCell<Vec<usize>>>, row: i32, dialog: &Dialog) {
+        let entry = usize::try_from(row)
+            .ok()
+            .and_then(|row| visible.borrow().get(row).copied())
+            .and_then(|index| self.entries.borrow().get(index).cloned());
+
+        dialog.close();
+        if let Some(entry) = entry {
+            tracing::debug!("Running command {}", entry.id);
+            (entry.action)(&self.app_state);
+        }
+    }
+}
+
+/// Indices of the entries matching `query`, best match first. Labels and ids
+/// are both searched; an empty query lists every entry in registration order.
+pub fn filter_commands(entries: &[Rc<CommandEntry>], query: &str) -> Vec<usize> {
+    let query = query.trim();
+    if query.is_empty() {
+        return (0..entries.len()).collect();
+    }
+
+    let matcher = SkimMatcherV2::default();
+    let mut scored: Vec<(i64, usize)> = entries
+        .iter()
+        .enumerate()
+        .filter_map(|(index, entry)| {
+            let label = matcher.fuzzy_match(&entry.label, query);
+            let id = matcher.fuzzy_match(&entry.id, query);
+            label.max(id).map(|score| (score, index))
+        })
+        .collect();
+
+    // Stable sort keeps registration order among equal scores.
+    scored.sort_by(|a, b| b.0.cmp(&a.0));
+    scored.into_iter().map(|(_, index)| index).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(labels: &[&str]) -> Vec<Rc<CommandEntry>> {
+        labels
+            .iter()
+            .enumerate()
+            .map(|(i, label)| Rc::new(CommandEntry::new(format!("test.command{}", i), *label, |_| {})))
+            .collect()
+    }
+
+    #[test]
+    fn test_empty_query_keeps_registration_order() {
+        let entries = entries(&["Save session", "Toggle hidden files", "Close tab"]);
+        assert_eq!(filter_commands(&entries, ""), vec![0, 1, 2]);
+        assert_eq!(filter_commands(&entries, "   "), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_filter_drops_non_matches() {
+        let entries = entries(&["Save session", "Toggle hidden files", "Open history"]);
+        assert_eq!(filter_commands(&entries, "hid"), vec![1]);
+        assert!(filter_commands(&entries, "xyz").is_empty());
+    }
+
+    #[test]
+    fn test_contiguous_match_ranks_first() {
+        let entries = entries(&["Close all tabs", "Close tab", "New window"]);
+        assert_eq!(filter_commands(&entries, "close tab"), vec![1, 0]);
+    }
+
+    #[test]
+    fn test_filter_matches_ids() {
+        let entry = CommandEntry::new("operations.clear_finished", "Tidy transfer list", |_| {});
+        let entries = vec![Rc::new(entry)];
+        assert_eq!(filter_commands(&entries, "clear"), vec![0]);
+    }
+}
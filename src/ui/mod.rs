@@ -1,6 +1,15 @@
+mod command_palette;
+mod search_bar;
+mod shortcuts_window;
+mod status_bar;
+
+pub use command_palette::{CommandEntry, CommandPalette};
+pub use status_bar::StatusBar;
+
 use gtk4::prelude::*;
-use gtk4::{glib, Application, ApplicationWindow, Box, Orientation, Notebook};
-use crate::state::AppState;
+use gtk4::{glib, Application, ApplicationWindow, Box, Orientation, Notebook, ScrolledWindow};
+use crate::state::{AppState, SessionState};
+use cheese_core::config::SortConfig;
 use std::sync::Arc;
 use std::path::PathBuf;
 
@@ -8,10 +17,12 @@ pub struct CheeseWindow {
     window: ApplicationWindow,
     notebook: Notebook,
     app_state: Arc<AppState>,
+    command_palette: CommandPalette,
+    status_bar: StatusBar,
 }
 
 impl CheeseWindow {
-    pub fn new(app: &Application, app_state: Arc<AppState>) -> Self {
+    pub fn new(app: &Application, app_state: Arc<AppState>, session: Option<SessionState>) -> Self {
         let window = ApplicationWindow::builder()
             .application(app)
             .title("Cheese")
@@ -29,13 +40,24 @@ impl CheeseWindow {
 
         main_box.append(&notebook);
 
+        let status_bar = StatusBar::new(Arc::clone(&app_state));
+        main_box.append(status_bar.widget());
+
+        let command_palette = CommandPalette::new(&window, Arc::clone(&app_state));
+
         let mut cheese_window = Self {
             window,
             notebook,
             app_state,
+            command_palette,
+            status_bar,
         };
 
-        cheese_window.create_initial_tab();
+        match session {
+            Some(session) if !session.tabs.is_empty() => cheese_window.restore_tabs(&session),
+            _ => cheese_window.create_initial_tab(),
+        }
+        cheese_window.register_commands();
         cheese_window.setup_keyboard_shortcuts();
         cheese_window.setup_signals();
 
@@ -50,13 +72,45 @@ impl CheeseWindow {
         self.add_tab(home);
     }
 
+    fn restore_tabs(&mut self, session: &SessionState) {
+        for tab in &session.tabs {
+            let index = self.add_tab_with_state(tab.path.clone(), tab.sort, tab.scroll_position);
+            self.app_state.set_tab_pane(index, tab.pane);
+        }
+
+        self.notebook.set_current_page(Some(session.active_tab as u32));
+        self.app_state.set_active_tab(session.active_tab);
+        if let Some(tab) = session.tabs.get(session.active_tab) {
+            self.status_bar.show_directory(&tab.path);
+        }
+    }
+
     fn add_tab(&mut self, path: PathBuf) {
-        let tab_label = gtk4::Label::new(Some(&self.get_tab_name(&path)));
+        let sort = self.app_state.default_sort();
+        self.add_tab_with_state(path, sort, 0.0);
+    }
+
+    pub fn add_tab_with_state(&mut self, path: PathBuf, sort: SortConfig, scroll: f64) -> usize {
+        self.append_page(&path, sort, scroll);
+        self.status_bar.show_directory(&path);
+        self.app_state.add_tab_with_state(path, sort, scroll)
+    }
+
+    fn append_page(&mut self, path: &PathBuf, sort: SortConfig, scroll: f64) {
+        let tab_label = gtk4::Label::new(Some(&self.get_tab_name(path)));
         
         let tab_content = Box::new(Orientation::Vertical, 0);
         let path_label = gtk4::Label::new(Some(&format!("Path: {}", path.display())));
         tab_content.append(&path_label);
 
+        let list = gtk4::ListBox::new();
+        let scrolled = ScrolledWindow::builder()
+            .child(&list)
+            .vexpand(true)
+            .build();
+        tab_content.append(&scrolled);
+        self.load_entries(path.clone(), sort, list, scrolled.clone(), scroll);
+
         let close_button = gtk4::Button::with_label("×");
         close_button.set_has_frame(false);
         
@@ -67,14 +121,47 @@ impl CheeseWindow {
         let page_num = self.notebook.append_page(&tab_content, Some(&tab_box));
         self.notebook.set_current_page(Some(page_num));
 
+        // Pages shift as tabs close, so handlers look their page up when
+        // they run rather than keeping `page_num`.
         let notebook = self.notebook.clone();
-        close_button.connect_clicked(move |_| {
-            if let Some(page) = notebook.current_page() {
-                notebook.remove_page(Some(page));
+        let app_state = Arc::clone(&self.app_state);
+        let page = tab_content.downgrade();
+        scrolled.vadjustment().connect_value_changed(move |adjustment| {
+            if let Some(index) = page.upgrade().and_then(|page| notebook.page_num(&page)) {
+                app_state.set_tab_scroll(index as usize, scroll_fraction(adjustment));
             }
         });
 
-        self.app_state.add_tab(path);
+        let notebook = self.notebook.clone();
+        let app_state = Arc::clone(&self.app_state);
+        let page = tab_content.downgrade();
+        close_button.connect_clicked(move |_| {
+            let Some(index) = page.upgrade().and_then(|page| notebook.page_num(&page)) else {
+                return;
+            };
+            // Removing the page switches to another one, whose handler reads
+            // the tab list, so the closed tab's state has to go first.
+            app_state.remove_tab(index as usize);
+            notebook.remove_page(Some(index));
+        });
+    }
+
+    fn load_entries(&self, path: PathBuf, sort: SortConfig, list: gtk4::ListBox, scrolled: ScrolledWindow, scroll: f64) {
+        let mut results = self.app_state.scan_tab(path, sort);
+
+        glib::MainContext::default().spawn_local(async move {
+            while let Some(result) = results.recv().await {
+                for entry in &result.entries {
+                    let row = gtk4::Label::builder().label(entry.name.as_str()).xalign(0.0).build();
+                    list.append(&row);
+                }
+
+                if result.is_complete {
+                    scroll_to_fraction(&scrolled, scroll);
+                    break;
+                }
+            }
+        });
     }
 
     fn get_tab_name(&self, path: &PathBuf) -> String {
@@ -84,10 +171,42 @@ impl CheeseWindow {
             .to_string()
     }
 
+    pub fn command_palette(&self) -> &CommandPalette {
+        &self.command_palette
+    }
+
+    pub fn show_keyboard_shortcuts(&self) {
+        shortcuts_window::show(&self.window, &self.app_state);
+    }
+
+    fn register_commands(&self) {
+        let palette = &self.command_palette;
+
+        palette.register(
+            CommandEntry::new("view.toggle_hidden", "Toggle hidden files", |app_state| {
+                let config = app_state.core().config();
+                let mut config = config.write();
+                config.ui.show_hidden = !config.ui.show_hidden;
+            })
+            .with_shortcut("Ctrl+H"),
+        );
+        palette.register(CommandEntry::new("session.save", "Save session", |app_state| {
+            if let Err(e) = app_state.save_session() {
+                tracing::warn!("Failed to save session: {}", e);
+            }
+        }));
+        palette.register(CommandEntry::new(
+            "operations.clear_finished",
+            "Clear finished operations",
+            |app_state| app_state.core().operations().clear_finished(),
+        ));
+    }
+
     fn setup_keyboard_shortcuts(&self) {
         let controller = gtk4::EventControllerKey::new();
         let app_state = Arc::clone(&self.app_state);
         let window_ref = self.window.clone();
+        let command_palette = self.command_palette.clone();
 
         controller.connect_key_pressed(move |_, key, _, modifiers| {
             use gtk4::gdk::ModifierType;
@@ -114,11 +233,15 @@ impl CheeseWindow {
                     glib::Propagation::Stop
                 }
                 (Key::f, true, false) => {
-                    tracing::info!("Fuzzy search");
+                    search_bar::show(&window_ref, Arc::clone(&app_state));
                     glib::Propagation::Stop
                 }
                 (Key::p, true, false) => {
-                    tracing::info!("Command palette");
+                    command_palette.show();
+                    glib::Propagation::Stop
+                }
+                (Key::question, true, true) => {
+                    shortcuts_window::show(&window_ref, &app_state);
                     glib::Propagation::Stop
                 }
                 _ => glib::Propagation::Proceed,
@@ -130,13 +253,24 @@ impl CheeseWindow {
 
     fn setup_signals(&self) {
         let app_state = Arc::clone(&self.app_state);
+        let status_bar = self.status_bar.clone();
         
         self.notebook.connect_switch_page(move |_, _, page_num| {
             app_state.set_active_tab(page_num as usize);
+            if let Some(tab) = app_state.tabs().get(page_num as usize) {
+                status_bar.show_directory(&tab.path);
+            }
         });
 
+        let app_state = Arc::clone(&self.app_state);
         self.window.connect_close_request(move |_| {
             tracing::info!("Window closing");
+            if app_state.restore_session_enabled() {
+                if let Err(e) = app_state.save_session() {
+                    tracing::warn!("Failed to save session: {}", e);
+                }
+            }
+            app_state.shutdown();
             glib::Propagation::Proceed
         });
     }
@@ -145,3 +279,24 @@ impl CheeseWindow {
         self.window.present();
     }
 }
+
+// The new rows are only measured on the next layout pass, so the adjustment's
+// range is read from an idle callback rather than right after appending them.
+fn scroll_to_fraction(scrolled: &ScrolledWindow, fraction: f64) {
+    let adjustment = scrolled.vadjustment();
+    let fraction = fraction.clamp(0.0, 1.0);
+
+    glib::idle_add_local_once(move || {
+        let range = (adjustment.upper() - adjustment.page_size()).max(0.0);
+        adjustment.set_value(adjustment.lower() + fraction * range);
+    });
+}
+
+// How far down `adjustment` is scrolled, as restored by scroll_to_fraction.
+fn scroll_fraction(adjustment: &gtk4::Adjustment) -> f64 {
+    let range = adjustment.upper() - adjustment.page_size() - adjustment.lower();
+    if range <= 0.0 {
+        return 0.0;
+    }
+    ((adjustment.value() - adjustment.lower()) / range).clamp(0.0, 1.0)
+}
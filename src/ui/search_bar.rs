@@ -0,0 +1,71 @@
+use crate::state::AppState;
+use gtk4::prelude::*;
+use gtk4::{ApplicationWindow, Dialog, Label, ListBox, ScrolledWindow, SearchEntry, SelectionMode};
+use std::sync::Arc;
+
+/// Opens the fuzzy search prompt on Ctrl+F. Recent queries matching what has
+/// been typed are listed below the entry; a query is added to the history
+/// when it is submitted.
+pub fn show(window: &ApplicationWindow, app_state: Arc<AppState>) {
+    let dialog = Dialog::builder()
+        .transient_for(window)
+        .modal(true)
+        .title("Search")
+        .default_width(480)
+        .default_height(300)
+        .build();
+
+    let search = SearchEntry::new();
+    let suggestions = ListBox::new();
+    suggestions.set_selection_mode(SelectionMode::Browse);
+    let scrolled = ScrolledWindow::builder()
+        .child(&suggestions)
+        .vexpand(true)
+        .build();
+
+    let content = dialog.content_area();
+    content.append(&search);
+    content.append(&scrolled);
+
+    populate(&suggestions, &app_state, "");
+
+    let (filter_list, filter_state) = (suggestions.clone(), Arc::clone(&app_state));
+    search.connect_search_changed(move |search| {
+        populate(&filter_list, &filter_state, &search.text());
+    });
+
+    let entry = search.clone();
+    suggestions.connect_row_activated(move |_, row| {
+        if let Some(label) = row.child().and_downcast::<Label>() {
+            entry.set_text(&label.text());
+            entry.set_position(-1);
+            entry.grab_focus();
+        }
+    });
+
+    let submit_dialog = dialog.clone();
+    search.connect_activate(move |search| {
+        let query = search.text();
+        let query = query.trim();
+        if !query.is_empty() {
+            app_state.record_search(query);
+            tracing::info!("Fuzzy search for {:?}", query);
+        }
+        submit_dialog.close();
+    });
+
+    dialog.present();
+    search.grab_focus();
+}
+
+fn populate(list: &ListBox, app_state: &AppState, prefix: &str) {
+    while let Some(child) = list.first_child() {
+        list.remove(&child);
+    }
+
+    for query in app_state.search_suggestions(prefix) {
+        let label = Label::new(Some(&query));
+        label.set_xalign(0.0);
+        list.append(&label);
+    }
+}
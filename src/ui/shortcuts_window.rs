@@ -0,0 +1,95 @@
+use crate::state::AppState;
+use gtk4::prelude::*;
+use gtk4::{ApplicationWindow, ShortcutsGroup, ShortcutsSection, ShortcutsShortcut, ShortcutsWindow};
+use std::collections::BTreeMap;
+
+/// Opens the shortcuts overview: configured shortcuts in one section and
+/// plugin shortcuts, grouped by plugin, in another.
+pub fn show(window: &ApplicationWindow, app_state: &AppState) {
+    let shortcuts = app_state.core().config().read().keyboard_shortcuts();
+    let plugin_bindings = app_state.core().plugins().registered_key_bindings();
+
+    let shortcuts_window = ShortcutsWindow::builder()
+        .transient_for(window)
+        .modal(true)
+        .build();
+
+    let section = ShortcutsSection::builder()
+        .section_name("application")
+        .title("Cheese")
+        .build();
+    let group = ShortcutsGroup::builder().title("General").build();
+    for (label, accelerator) in &shortcuts {
+        group.add_shortcut(&shortcut(label, accelerator));
+    }
+    section.add_group(&group);
+    shortcuts_window.add_section(&section);
+
+    if !plugin_bindings.is_empty() {
+        let mut by_plugin: BTreeMap<String, Vec<_>> = BTreeMap::new();
+        for (plugin, binding) in plugin_bindings {
+            by_plugin.entry(plugin).or_default().push(binding);
+        }
+
+        let section = ShortcutsSection::builder()
+            .section_name("plugins")
+            .title("Plugins")
+            .build();
+        for (plugin, bindings) in by_plugin {
+            let group = ShortcutsGroup::builder().title(plugin.as_str()).build();
+            for binding in &bindings {
+                group.add_shortcut(&shortcut(&binding.label, &binding.accelerator));
+            }
+            section.add_group(&group);
+        }
+        shortcuts_window.add_section(&section);
+    }
+
+    shortcuts_window.present();
+}
+
+fn shortcut(label: &str, accelerator: &str) -> ShortcutsShortcut {
+    ShortcutsShortcut::builder()
+        .title(label)
+        .accelerator(gtk_accelerator(accelerator).as_str())
+        .build()
+}
+
+/// Converts the config notation ("Ctrl+Shift+P") into the GTK accelerator
+/// syntax ("<Control><Shift>p").
+pub fn gtk_accelerator(shortcut: &str) -> String {
+    let mut parts: Vec<&str> = shortcut.split('+').map(str::trim).collect();
+    let key = parts.pop().unwrap_or_default();
+
+    let mut accelerator: String = parts
+        .into_iter()
+        .map(|modifier| match modifier.to_lowercase().as_str() {
+            "ctrl" | "control" => "<Control>".to_string(),
+            "shift" => "<Shift>".to_string(),
+            "alt" => "<Alt>".to_string(),
+            "super" | "meta" => "<Super>".to_string(),
+            other => format!("<{}>", other),
+        })
+        .collect();
+
+    if key.chars().count() == 1 {
+        accelerator.push_str(&key.to_lowercase());
+    } else {
+        accelerator.push_str(key);
+    }
+    accelerator
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gtk_accelerator() {
+        assert_eq!(gtk_accelerator("Ctrl+P"), "<Control>p");
+        assert_eq!(gtk_accelerator("Ctrl+Shift+T"), "<Control><Shift>t");
+        assert_eq!(gtk_accelerator("Shift+Delete"), "<Shift>Delete");
+        assert_eq!(gtk_accelerator("Delete"), "Delete");
+        assert_eq!(gtk_accelerator("Alt + F4"), "<Alt>F4");
+    }
+}
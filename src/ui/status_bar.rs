@@ -0,0 +1,158 @@
+use crate::state::AppState;
+use cheese_core::fs::watcher::{WatchEvent, Watcher};
+use gtk4::prelude::*;
+use gtk4::{glib, Label};
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// Line at the bottom of the window showing what plugins with the
+/// `status_bar` capability report for the current directory. Cheap to clone;
+/// clones share the label and watcher.
+#[derive(Clone)]
+pub struct StatusBar {
+    label: Label,
+    app_state: Arc<AppState>,
+    watcher: Arc<Watcher>,
+    current_dir: Rc<RefCell<Option<PathBuf>>>,
+}
+
+impl StatusBar {
+    pub fn new(app_state: Arc<AppState>) -> Self {
+        let label = Label::builder()
+            .xalign(0.0)
+            .margin_start(6)
+            .margin_end(6)
+            .margin_top(2)
+            .margin_bottom(2)
+            .build();
+
+        let status_bar = Self {
+            label,
+            app_state,
+            watcher: Arc::new(Watcher::new(WATCH_DEBOUNCE)),
+            current_dir: Rc::new(RefCell::new(None)),
+        };
+        status_bar.listen();
+        status_bar
+    }
+
+    pub fn widget(&self) -> &Label {
+        &self.label
+    }
+
+    /// Switches the status bar to `dir`, watching it for changes instead of
+    /// the previous directory.
+    pub fn show_directory(&self, dir: &Path) {
+        let previous = self.current_dir.replace(Some(dir.to_path_buf()));
+        if previous.as_deref() != Some(dir) {
+            if let Some(previous) = previous {
+                let _ = self.watcher.unwatch(&previous);
+            }
+            if let Err(e) = self.watcher.watch(dir) {
+                tracing::warn!("Failed to watch {} for status bar: {}", dir.display(), e);
+            }
+        }
+
+        self.refresh();
+    }
+
+    pub fn refresh(&self) {
+        let Some(dir) = self.current_dir.borrow().clone() else {
+            self.label.set_text("");
+            return;
+        };
+
+        let plugins = self.app_state.core().plugins();
+        let task_dir = dir.clone();
+        let task = self.app_state.runtime().spawn(async move {
+            plugins.collect_status_bar_text(&task_dir).await
+        });
+
+        let label = self.label.clone();
+        let current_dir = Rc::clone(&self.current_dir);
+        glib::MainContext::default().spawn_local(async move {
+            let Ok(contributions) = task.await else {
+                return;
+            };
+            // A slow plugin may answer after the user moved on.
+            if current_dir.borrow().as_deref() == Some(dir.as_path()) {
+                label.set_text(&format_contributions(&contributions));
+            }
+        });
+    }
+
+    fn listen(&self) {
+        let (sender, mut events) = mpsc::unbounded_channel();
+        if let Err(e) = self.watcher.start(sender) {
+            tracing::warn!("Status bar will not refresh on changes: {}", e);
+            return;
+        }
+
+        let status_bar = self.clone();
+        glib::MainContext::default().spawn_local(async move {
+            while let Some(event) = events.recv().await {
+                let affected = status_bar.current_dir.borrow()
+                    .as_deref()
+                    .is_some_and(|dir| event_in_dir(&event, dir));
+                if affected {
+                    status_bar.refresh();
+                }
+            }
+        });
+    }
+}
+
+/// Joins the non-empty plugin texts with `|`.
+pub fn format_contributions(contributions: &[(String, String)]) -> String {
+    contributions
+        .iter()
+        .map(|(_, text)| text.trim())
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+fn event_in_dir(event: &WatchEvent, dir: &Path) -> bool {
+    let in_dir = |path: &Path| path == dir || path.parent() == Some(dir);
+
+    match event {
+        WatchEvent::Created(path)
+        | WatchEvent::Modified(path)
+        | WatchEvent::Deleted(path)
+        | WatchEvent::AttributesChanged(path) => in_dir(path),
+        WatchEvent::Renamed { from, to } => in_dir(from) || in_dir(to),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_contributions() {
+        let contributions = vec![
+            ("git".to_string(), "3 changes".to_string()),
+            ("empty".to_string(), "  ".to_string()),
+            ("du".to_string(), "1.2 GB".to_string()),
+        ];
+        assert_eq!(format_contributions(&contributions), "3 changes | 1.2 GB");
+        assert_eq!(format_contributions(&[]), "");
+    }
+
+    #[test]
+    fn test_event_in_dir() {
+        let dir = Path::new("/home/user/project");
+        assert!(event_in_dir(&WatchEvent::Created(dir.join("a.txt")), dir));
+        assert!(!event_in_dir(&WatchEvent::Modified(PathBuf::from("/home/user/other/a.txt")), dir));
+        assert!(event_in_dir(&WatchEvent::Renamed {
+            from: PathBuf::from("/tmp/a.txt"),
+            to: dir.join("a.txt"),
+        }, dir));
+    }
+}